@@ -0,0 +1,94 @@
+//! Run JsonLogic rules in a wasmtime sandbox
+//!
+//! Build the WASI command and then run this example with the path to it:
+//!
+//! ```sh
+//! cargo build --release --target wasm32-wasip1 --features wasi --bin jsonlogic-wasi
+//! cd examples/wasmtime-host
+//! cargo run -- ../../target/wasm32-wasip1/release/jsonlogic-wasi.wasm \
+//!     '{"<": [{"var": "age"}, 18]}' '{"age": 12}'
+//! ```
+//!
+//! The module has no access to the host filesystem, network, or clock
+//! beyond what the WASI context below grants, and each evaluation is
+//! limited by a fuel budget, so a hostile rule cannot run indefinitely.
+
+use std::env;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use wasmtime::{Config, Engine, Linker, Module, Store};
+use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::{I32Exit, WasiCtxBuilder};
+
+/// Instructions each evaluation may execute before being aborted
+const FUEL: u64 = 100_000_000;
+/// Maximum size of the module's output
+const MAX_OUTPUT: usize = 1 << 20;
+
+struct Sandbox {
+    engine: Engine,
+    module: Module,
+    linker: Linker<WasiP1Ctx>,
+}
+impl Sandbox {
+    fn new(path: &str) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        // Compile once; each evaluation gets a fresh, isolated instance.
+        let module = Module::from_file(&engine, path)
+            .with_context(|| format!("Could not load module {}", path))?;
+        let mut linker = Linker::new(&engine);
+        preview1::add_to_linker_sync(&mut linker, |ctx| ctx)?;
+        Ok(Self {
+            engine,
+            module,
+            linker,
+        })
+    }
+
+    fn apply(&self, logic: &Value, data: &Value) -> Result<Value> {
+        let input = json!({"logic": logic, "data": data}).to_string();
+        let stdout = MemoryOutputPipe::new(MAX_OUTPUT);
+        let wasi = WasiCtxBuilder::new()
+            .stdin(MemoryInputPipe::new(input))
+            .stdout(stdout.clone())
+            .build_p1();
+
+        let mut store = Store::new(&self.engine, wasi);
+        store.set_fuel(FUEL)?;
+        let instance = self.linker.instantiate(&mut store, &self.module)?;
+        let start = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+
+        // The command exits non-zero on failure; the reason is on stdout.
+        if let Err(err) = start.call(&mut store, ()) {
+            if err.downcast_ref::<I32Exit>().is_none() {
+                return Err(err.context("Evaluation aborted"));
+            }
+        }
+
+        let output: Value = serde_json::from_slice(&stdout.contents())?;
+        match output.get("result") {
+            Some(result) => Ok(result.clone()),
+            None => Err(anyhow!("{}", output["error"]["message"])),
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        return Err(anyhow!("usage: {} <module.wasm> <logic> [data]", args[0]));
+    }
+    let logic: Value = serde_json::from_str(&args[2]).context("Invalid logic")?;
+    let data: Value = match args.get(3) {
+        Some(data) => serde_json::from_str(data).context("Invalid data")?,
+        None => Value::Null,
+    };
+
+    let sandbox = Sandbox::new(&args[1])?;
+    println!("{}", sandbox.apply(&logic, &data)?);
+    Ok(())
+}
@@ -0,0 +1,26 @@
+//! Check that `apply` never panics, whatever the rule and data
+//!
+//! The input is parsed as a JSON array of `[rule, data]`. Inputs that are
+//! not valid JSON are skipped, since they can never reach `apply`.
+
+#![no_main]
+
+use jsonlogic_rs::{DivisionByZero, Engine};
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+fuzz_target!(|input: &[u8]| {
+    let (rule, data) = match serde_json::from_slice::<Value>(input) {
+        Ok(Value::Array(mut items)) if items.len() == 2 => {
+            let data = items.pop().unwrap_or(Value::Null);
+            (items.pop().unwrap_or(Value::Null), data)
+        }
+        Ok(rule) => (rule, Value::Null),
+        Err(_) => return,
+    };
+
+    let _ = jsonlogic_rs::apply(&rule, &data);
+    let _ = Engine::new()
+        .division_by_zero(DivisionByZero::Null)
+        .apply(&rule, &data);
+});
@@ -0,0 +1,324 @@
+//! Compile-time JsonLogic rules
+//!
+//! The [`jsonlogic!`] macro turns a rule known at compile time into a Rust
+//! closure, so that the rule is checked when the crate is built and never
+//! has to be parsed at runtime.
+
+use jsonlogic_rs::Engine;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use serde_json::Value;
+use syn::{parse_macro_input, LitStr};
+
+/// Compile a JsonLogic rule into a closure
+///
+/// The rule is given as a string literal of JSON, and the macro expands to
+/// a closure of type `Fn(&serde_json::Value) -> Result<serde_json::Value,
+/// jsonlogic_rs::Error>`, which gives the same results as
+/// `jsonlogic_rs::apply()` with the rule. The crate using the macro must
+/// depend on `jsonlogic-rs` and `serde_json`.
+///
+/// ```rust
+/// use jsonlogic_macros::jsonlogic;
+/// use serde_json::json;
+///
+/// let is_adult = jsonlogic!(r#"{">=": [{"var": "age"}, 18]}"#);
+/// assert_eq!(is_adult(&json!({"age": 30})).unwrap(), json!(true));
+/// assert_eq!(is_adult(&json!({"age": 12})).unwrap(), json!(false));
+/// ```
+///
+/// Invalid JSON, invalid rules, and objects with a single key that isn't a
+/// known operator are compile errors, so a misspelled operator is caught
+/// at build time rather than being treated as an object literal:
+///
+/// ```compile_fail
+/// use jsonlogic_macros::jsonlogic;
+///
+/// let rule = jsonlogic!(r#"{"and": [{"var": "a"}, {"nto": {"var": "b"}}]}"#);
+/// ```
+///
+/// Variable lookups, comparisons, `!`, `!!`, `and`, `or`, and `if` are
+/// compiled to Rust code. Other operations are built as JSON values once,
+/// on first use, and evaluated with `jsonlogic_rs::apply()`. Only the
+/// operators available with `jsonlogic-rs`'s default features are known.
+#[proc_macro]
+pub fn jsonlogic(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let error = |message: String| {
+        syn::Error::new(literal.span(), message)
+            .to_compile_error()
+            .into()
+    };
+    let rule: Value = match serde_json::from_str(&literal.value()) {
+        Ok(rule) => rule,
+        Err(err) => return error(format!("Invalid JSON in rule: {}", err)),
+    };
+    let compiler = Compiler {
+        engine: Engine::new(),
+    };
+    if let Err(message) = compiler.check(&rule) {
+        return error(message);
+    }
+    let body = compiler.expr(&rule);
+    quote!(
+        |data: &::serde_json::Value| -> ::std::result::Result<
+            ::serde_json::Value,
+            ::jsonlogic_rs::Error,
+        > { ::std::result::Result::Ok(#body) }
+    )
+    .into()
+}
+
+/// Split a key on unescaped dots, as `var` does
+fn split_with_escape(input: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut slice = String::new();
+    let mut escape = false;
+
+    for c in input.chars() {
+        if escape {
+            slice.push(c);
+            escape = false;
+        } else if c == '\\' {
+            escape = true;
+        } else if c == '.' {
+            result.push(slice.clone());
+            slice.clear();
+        } else {
+            slice.push(c);
+        }
+    }
+
+    if !slice.is_empty() {
+        result.push(slice);
+    }
+
+    result
+}
+
+/// Generate code that builds a value
+fn literal(value: &Value) -> TokenStream2 {
+    match value {
+        Value::Null => quote!(::serde_json::Value::Null),
+        Value::Bool(b) => quote!(::serde_json::Value::Bool(#b)),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                quote!(::serde_json::Value::from(#i))
+            } else if let Some(u) = n.as_u64() {
+                quote!(::serde_json::Value::from(#u))
+            } else {
+                let f = n.as_f64().unwrap_or(f64::NAN);
+                quote!(::serde_json::Value::from(#f))
+            }
+        }
+        Value::String(s) => {
+            quote!(::serde_json::Value::String(::std::string::String::from(#s)))
+        }
+        Value::Array(items) => {
+            let items = items.iter().map(literal);
+            quote!(::serde_json::Value::Array(::std::vec![#(#items),*]))
+        }
+        Value::Object(map) => {
+            let keys = map.keys();
+            let values = map.values().map(literal);
+            quote!({
+                let mut map = ::serde_json::Map::new();
+                #(map.insert(::std::string::String::from(#keys), #values);)*
+                ::serde_json::Value::Object(map)
+            })
+        }
+    }
+}
+
+struct Compiler {
+    engine: Engine,
+}
+impl Compiler {
+    /// Split an operation into its operator and arguments
+    fn split<'v>(&self, rule: &'v Value) -> Option<(&'v str, Vec<&'v Value>)> {
+        match rule {
+            Value::Object(map) if map.len() == 1 => {
+                let (key, args) = map.iter().next()?;
+                if !self.engine.has_operator(key) {
+                    return None;
+                }
+                let args = match args {
+                    Value::Array(items) => items.iter().collect(),
+                    _ => vec![args],
+                };
+                Some((key.as_str(), args))
+            }
+            _ => None,
+        }
+    }
+
+    /// Check that a rule is valid and uses only known operators
+    fn check(&self, rule: &Value) -> Result<(), String> {
+        fn unknown_operators<'v>(
+            engine: &Engine,
+            value: &'v Value,
+            found: &mut Vec<&'v str>,
+        ) {
+            match value {
+                Value::Object(map) if map.len() == 1 => {
+                    let (key, args) = map.iter().next().unwrap();
+                    if !engine.has_operator(key) {
+                        found.push(key);
+                    }
+                    unknown_operators(engine, args, found);
+                }
+                Value::Array(items) => items
+                    .iter()
+                    .for_each(|item| unknown_operators(engine, item, found)),
+                _ => {}
+            }
+        }
+        let mut found = Vec::new();
+        unknown_operators(&self.engine, rule, &mut found);
+        if let Some(key) = found.first() {
+            return Err(format!("Unknown operator {:?}", key));
+        }
+        self.engine
+            .validate(rule)
+            .map_err(|err| format!("Invalid rule: {}", err))
+    }
+
+    /// Generate code that evaluates a rule against `data`
+    fn expr(&self, rule: &Value) -> TokenStream2 {
+        let (key, args) = match self.split(rule) {
+            Some(split) => split,
+            None => return literal(rule),
+        };
+        let compiled = match key {
+            "var" => self.var(&args),
+            "==" => self.binary(&args, quote!(abstract_eq)),
+            "!=" => self.binary(&args, quote!(abstract_ne)),
+            "===" => self.binary(&args, quote!(strict_eq)),
+            "!==" => self.binary(&args, quote!(strict_ne)),
+            "<" => self.chained(&args, quote!(abstract_lt)),
+            "<=" => self.chained(&args, quote!(abstract_lte)),
+            ">" => self.chained(&args, quote!(abstract_gt)),
+            ">=" => self.chained(&args, quote!(abstract_gte)),
+            "!" | "!!" => match args.as_slice() {
+                [arg] => {
+                    let arg = self.expr(arg);
+                    let negate = match key {
+                        "!" => quote!(!),
+                        _ => quote!(),
+                    };
+                    Some(quote!(::serde_json::Value::Bool(
+                        #negate ::jsonlogic_rs::js_op::truthy(&#arg)
+                    )))
+                }
+                _ => None,
+            },
+            "and" | "or" => self.short_circuit(key == "and", &args),
+            "if" => Some(self.conditional(&args)),
+            _ => None,
+        };
+        compiled.unwrap_or_else(|| {
+            let rule = literal(rule);
+            quote!({
+                static RULE: ::std::sync::OnceLock<::serde_json::Value> =
+                    ::std::sync::OnceLock::new();
+                ::jsonlogic_rs::apply(RULE.get_or_init(|| #rule), data)?
+            })
+        })
+    }
+
+    /// Compile a `var` with a constant key
+    fn var(&self, args: &[&Value]) -> Option<TokenStream2> {
+        let (key, default) = match args {
+            [key] => (key, None),
+            [key, default] => (key, Some(default)),
+            _ => return None,
+        };
+        let segments = match key {
+            Value::Null => Vec::new(),
+            Value::String(s) => split_with_escape(s),
+            Value::Number(n) if n.is_i64() || n.is_u64() => vec![n.to_string()],
+            _ => return None,
+        };
+        let default = match default {
+            Some(default) => self.expr(default),
+            None => quote!(::serde_json::Value::Null),
+        };
+        Some(quote!(
+            match ::jsonlogic_rs::DataSource::get_path(data, &[#(#segments),*]) {
+                ::std::option::Option::Some(value) => value.into_owned(),
+                ::std::option::Option::None => #default,
+            }
+        ))
+    }
+
+    fn binary(&self, args: &[&Value], func: TokenStream2) -> Option<TokenStream2> {
+        match args {
+            [a, b] => {
+                let (a, b) = (self.expr(a), self.expr(b));
+                Some(quote!(::serde_json::Value::Bool(
+                    ::jsonlogic_rs::js_op::#func(&#a, &#b)
+                )))
+            }
+            _ => None,
+        }
+    }
+
+    /// Compile a comparison of each adjacent pair of arguments
+    fn chained(&self, args: &[&Value], func: TokenStream2) -> Option<TokenStream2> {
+        if args.len() < 2 {
+            return None;
+        }
+        let args = args.iter().map(|arg| self.expr(arg));
+        Some(quote!({
+            let items = [#(#args),*];
+            ::serde_json::Value::Bool(
+                items
+                    .windows(2)
+                    .all(|pair| ::jsonlogic_rs::js_op::#func(&pair[0], &pair[1])),
+            )
+        }))
+    }
+
+    /// Compile `and` or `or`, which return the first falsy or truthy
+    /// argument respectively, or the last one
+    fn short_circuit(&self, is_and: bool, args: &[&Value]) -> Option<TokenStream2> {
+        let (last, rest) = args.split_last()?;
+        let last = self.expr(last);
+        Some(rest.iter().rev().fold(last, |acc, arg| {
+            let arg = self.expr(arg);
+            let negate = match is_and {
+                true => quote!(!),
+                false => quote!(),
+            };
+            quote!({
+                let value = #arg;
+                if #negate ::jsonlogic_rs::js_op::truthy(&value) {
+                    value
+                } else {
+                    #acc
+                }
+            })
+        }))
+    }
+
+    /// Compile `if`, with each condition followed by its result and an
+    /// optional final result if no condition holds
+    fn conditional(&self, args: &[&Value]) -> TokenStream2 {
+        match args {
+            [] => quote!(::serde_json::Value::Null),
+            [otherwise] => self.expr(otherwise),
+            [condition, then, rest @ ..] => {
+                let (condition, then) = (self.expr(condition), self.expr(then));
+                let rest = self.conditional(rest);
+                quote!(
+                    if ::jsonlogic_rs::js_op::truthy(&#condition) {
+                        #then
+                    } else {
+                        #rest
+                    }
+                )
+            }
+        }
+    }
+}
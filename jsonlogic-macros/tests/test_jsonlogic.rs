@@ -0,0 +1,78 @@
+//! Tests that compiled rules give the same results as the interpreter
+
+use jsonlogic_macros::jsonlogic;
+use serde_json::{json, Value};
+
+/// Check a compiled rule against `jsonlogic_rs::apply()` for each datum
+macro_rules! assert_matches_apply {
+    ($rule:literal, $data:expr) => {{
+        let compiled = jsonlogic!($rule);
+        let rule: Value = serde_json::from_str($rule).unwrap();
+        for data in $data {
+            assert_eq!(
+                compiled(&data).ok(),
+                jsonlogic_rs::apply(&rule, &data).ok(),
+                "{} with {}",
+                $rule,
+                data
+            );
+        }
+    }};
+}
+
+fn people() -> Vec<Value> {
+    vec![
+        json!({"name": "Ada", "age": 36, "tags": ["admin"], "address": {"country": "UK"}}),
+        json!({"name": "Bo", "age": "17", "tags": []}),
+        json!({"name": "", "age": null, "address": {"country": "US"}}),
+        json!({}),
+        json!(null),
+    ]
+}
+
+#[test]
+fn test_compiled_operators() {
+    assert_matches_apply!(r#"{"var": "age"}"#, people());
+    assert_matches_apply!(r#"{"var": ["address.country", "unknown"]}"#, people());
+    assert_matches_apply!(r#"{"var": "tags.0"}"#, people());
+    assert_matches_apply!(r#"{"var": ""}"#, people());
+    assert_matches_apply!(r#"{"==": [{"var": "age"}, 17]}"#, people());
+    assert_matches_apply!(r#"{"===": [{"var": "age"}, 17]}"#, people());
+    assert_matches_apply!(r#"{"!=": [{"var": "name"}, "Bo"]}"#, people());
+    assert_matches_apply!(r#"{"!==": [{"var": "name"}, null]}"#, people());
+    assert_matches_apply!(r#"{"<": [16, {"var": "age"}, 40]}"#, people());
+    assert_matches_apply!(r#"{">=": [{"var": "age"}, 18]}"#, people());
+    assert_matches_apply!(r#"{"!": {"var": "name"}}"#, people());
+    assert_matches_apply!(r#"{"!!": [{"var": "tags"}]}"#, people());
+    assert_matches_apply!(
+        r#"{"and": [{"var": "name"}, {"var": "age"}, {"var": "tags"}]}"#,
+        people()
+    );
+    assert_matches_apply!(
+        r#"{"or": [{"var": "tags.0"}, {"var": "name"}, 0]}"#,
+        people()
+    );
+    assert_matches_apply!(
+        r#"{"if": [{"<": [{"var": "age"}, 18]}, "minor", {"var": "name"}, "adult"]}"#,
+        people()
+    );
+    assert_matches_apply!(
+        r#"{"if": [{"<": [{"var": "age"}, 18]}, "minor", {"var": "name"}, "adult", "?"]}"#,
+        people()
+    );
+}
+
+#[test]
+fn test_interpreted_operators() {
+    assert_matches_apply!(
+        r#"{"cat": ["Hello, ", {"var": ["name", "stranger"]}, "!"]}"#,
+        people()
+    );
+    assert_matches_apply!(
+        r#"{"and": [{"in": ["admin", {"var": "tags"}]}, {"+": [{"var": "age"}, 1]}]}"#,
+        people()
+    );
+    assert_matches_apply!(r#"{"/": [{"var": "age"}, 0]}"#, people());
+    assert_matches_apply!(r#"{"missing": ["name", "age"]}"#, people());
+    assert_matches_apply!(r#"{"a": 1, "b": [1, 2.5, {"c": null}]}"#, people());
+}
@@ -1,15 +1,24 @@
 """Python JSONLogic with a Rust Backend."""
 
 __all__ = (
+    "Engine",
+    "JsonLogicError",
     "apply",
+    "apply_file",
     "apply_serialized",
+    "fingerprint",
 )
 
 import json as _json
+import os as _os
 import sys as _sys
 
 try:
-    from .jsonlogic import apply as _apply
+    from .jsonlogic import (
+        Engine as _Engine,
+        apply as _apply,
+        fingerprint as _fingerprint,
+    )
 except ImportError:
     # See https://docs.python.org/3/library/os.html#os.add_dll_directory
     # for why this is here.
@@ -18,20 +27,140 @@ except ImportError:
         from pathlib import Path
         if hasattr(os, "add_dll_directory"):
             os.add_dll_directory(str(Path(__file__).parent))
-        from .jsonlogic import apply as _apply
+        from .jsonlogic import (
+            Engine as _Engine,
+            apply as _apply,
+            fingerprint as _fingerprint,
+        )
     else:
         raise
 
 
+class JsonLogicError(ValueError):
+    """An error raised while parsing or evaluating a rule.
+
+    ``code`` is a stable, machine-readable identifier for the kind of error,
+    and ``details`` is a dict of information specific to it.
+    """
+
+    def __init__(self, message: str, code: str, details: dict):
+        super().__init__(message)
+        self.code = code
+        self.details = details
+
+    def to_json(self) -> dict:
+        """Return the error in the same shape as Rust's ``Error::to_json``."""
+        return {"code": self.code, "message": str(self), "details": self.details}
+
+
+def _apply_checked(value: str, data: str, apply_fn=None) -> str:
+    """Call the Rust backend, converting its errors to JsonLogicError."""
+    try:
+        return (apply_fn if apply_fn is not None else _apply)(value, data)
+    except ValueError as exc:
+        try:
+            err = _json.loads(str(exc))
+        except ValueError:
+            raise exc from None
+        raise JsonLogicError(
+            err["message"], err["code"], err["details"]
+        ) from None
+
+
 def apply(value, data=None, serializer=None, deserializer=None):
     """Run JSONLogic on a value and some data."""
     serializer = serializer if serializer is not None else _json.dumps
     deserializer = deserializer if deserializer is not None else _json.loads
-    res = _apply(serializer(value), serializer(data))
+    res = _apply_checked(serializer(value), serializer(data))
     return deserializer(res)
 
 
 def apply_serialized(value: str, data: str = None, deserializer=None):
     """Run JSONLogic on some already serialized value and optional data."""
-    res = _apply(value, data if data is not None else "null")
+    deserializer = deserializer if deserializer is not None else _json.loads
+    res = _apply_checked(value, data if data is not None else "null")
+    return deserializer(res)
+
+
+def fingerprint(value, serializer=None) -> str:
+    """Return a stable hash identifying a rule.
+
+    Rules that differ only in how their operations are written, like
+    ``{"var": "a"}`` and ``{"var": ["a"]}``, have the same fingerprint.
+    """
+    serializer = serializer if serializer is not None else _json.dumps
+    return _fingerprint(serializer(value))
+
+
+class Engine:
+    """A JSONLogic evaluator with custom operations.
+
+    Operations are registered with ``add_operation()``, and rules using them
+    are run with ``apply()``, which works like the ``apply()`` function::
+
+        engine = Engine()
+        engine.add_operation("between", lambda x, lo, hi: lo <= x <= hi)
+        engine.apply({"between": [{"var": "age"}, 18, 65]}, {"age": 30})
+
+    ``serializer`` and ``deserializer`` convert rules, data, and the
+    arguments and results of operations to and from JSON, and default to
+    ``json.dumps`` and ``json.loads``.
+    """
+
+    def __init__(self, serializer=None, deserializer=None):
+        self._engine = _Engine()
+        self._serializer = serializer if serializer is not None else _json.dumps
+        self._deserializer = (
+            deserializer if deserializer is not None else _json.loads
+        )
+
+    def add_operation(self, name: str, func) -> "Engine":
+        """Register a custom operation.
+
+        ``func`` is called with the operation's evaluated arguments, and
+        returns its result. Exceptions it raises are reported as a
+        ``JsonLogicError`` with the ``plugin_error`` code. Built-in
+        operations take precedence over custom ones of the same name.
+        """
+
+        def call(args: str) -> str:
+            try:
+                result = func(*self._deserializer(args))
+                return self._serializer({"result": result})
+            except Exception as exc:
+                return _json.dumps({"error": f"{type(exc).__name__}: {exc}"})
+
+        self._engine.add_operation(name, call)
+        return self
+
+    def apply(self, value, data=None):
+        """Run JSONLogic on a value and some data."""
+        res = _apply_checked(
+            self._serializer(value), self._serializer(data), self._engine.apply
+        )
+        return self._deserializer(res)
+
+
+def _read(source) -> str:
+    """Read a path, path-like object, or file-like object to a string."""
+    if hasattr(source, "read"):
+        contents = source.read()
+    else:
+        with open(_os.fspath(source), "rb") as f:
+            contents = f.read()
+    if isinstance(contents, bytes):
+        contents = contents.decode("utf-8")
+    return contents
+
+
+def apply_file(rule, data=None, deserializer=None):
+    """Run JSONLogic from a file of rules and an optional file of data.
+
+    ``rule`` and ``data`` may each be a path (``str`` or ``pathlib.Path``)
+    or an open file object, in text or binary mode. Files must contain
+    JSON.
+    """
+    deserializer = deserializer if deserializer is not None else _json.loads
+    data_str = _read(data) if data is not None else "null"
+    res = _apply_checked(_read(rule), data_str)
     return deserializer(res)
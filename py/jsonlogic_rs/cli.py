@@ -0,0 +1,74 @@
+"""The ``jsonlogic`` console script.
+
+This mirrors the commandline interface of the Rust ``jsonlogic`` binary,
+for environments where the Python package can be installed but a separate
+binary cannot.
+"""
+
+import argparse
+import json
+import sys
+import typing as t
+
+from . import apply_serialized
+
+EPILOG = """\
+examples:
+    jsonlogic '{"===": [{"var": "a"}, "foo"]}' '{"a": "foo"}'
+    jsonlogic '{"===": [1, 1]}' null
+    echo '{"a": "foo"}' | jsonlogic '{"===": [{"var": "a"}, "foo"]}'
+
+Inspired by and conformant with the original JsonLogic (jsonlogic.com).
+
+Report bugs to github.com/Bestowinc/json-logic-rs.
+"""
+
+
+def parse_args(argv: t.Optional[t.List[str]]) -> argparse.Namespace:
+    """Parse commandline arguments."""
+    parser = argparse.ArgumentParser(
+        prog="jsonlogic",
+        description=(
+            "Parse JSON data with a JsonLogic rule.\n\n"
+            "When no <data> or <data> is -, read from stdin.\n\n"
+            "The result is written to stdout as JSON, so multiple calls\n"
+            "can be chained together if desired."
+        ),
+        epilog=EPILOG,
+        formatter_class=argparse.RawDescriptionHelpFormatter,
+    )
+    parser.add_argument("logic", help="A JSON logic string")
+    parser.add_argument(
+        "data",
+        nargs="?",
+        default="-",
+        help="A string of JSON data to parse. May be provided as stdin.",
+    )
+    return parser.parse_args(argv)
+
+
+def main(argv: t.Optional[t.List[str]] = None) -> int:
+    """Run the commandline interface, returning an exit code."""
+    args = parse_args(argv)
+    data = sys.stdin.read() if args.data == "-" else args.data
+
+    for name, value in (("logic", args.logic), ("data", data)):
+        try:
+            json.loads(value)
+        except ValueError as exc:
+            msg = f"Error: Could not parse {name} as JSON: {exc}"
+            print(msg, file=sys.stderr)
+            return 1
+
+    try:
+        result = apply_serialized(args.logic, data, deserializer=str)
+    except ValueError as exc:
+        print(f"Error: Could not execute logic: {exc}", file=sys.stderr)
+        return 1
+
+    print(result)
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -82,6 +82,7 @@ setup(
         )
     ],
     packages=["jsonlogic_rs"],
+    entry_points={"console_scripts": ["jsonlogic = jsonlogic_rs.cli:main"]},
     package_dir={"": "py"},
     include_package_data=True,
     setup_requires=SETUP_REQUIRES,
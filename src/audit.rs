@@ -0,0 +1,312 @@
+//! Audit records of rule evaluation
+//!
+//! An [`AuditRecord`] captures what happened when a rule was applied: the
+//! rule and data it was applied to, identified by hashes rather than copied
+//! so that records stay small and don't retain sensitive data, along with
+//! the result and [`Stats`]. With the `serde` feature, records and the other
+//! results of evaluation (stats, lint warnings, failure explanations, and
+//! rule set results) implement `Serialize`, so they can be stored directly
+//! in e.g. a database or log index.
+//!
+//! The hashes are stable: they won't change without a new major version, so
+//! stored hashes can be compared with those computed by later releases, or
+//! by other processes. They're computed over [`canonical_json()`], which can
+//! also be used directly, e.g. to key a cache of compiled rules.
+
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+
+use crate::context::Stats;
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::op;
+
+/// A record of a rule being applied to some data
+#[derive(Debug)]
+pub struct AuditRecord {
+    /// The [`rule_hash()`] of the rule
+    pub rule_hash: String,
+    /// The [`data_hash()`] of the data
+    pub data_hash: String,
+    /// The result, or the error applying the rule
+    pub result: Result<Value, Error>,
+    pub stats: Stats,
+}
+
+/// Serialized as an object with either a `result` or an `error`
+#[cfg(feature = "serde")]
+impl Serialize for AuditRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("rule_hash", &self.rule_hash)?;
+        map.serialize_entry("data_hash", &self.data_hash)?;
+        serialize_result(&mut map, &self.result)?;
+        map.serialize_entry("stats", &self.stats)?;
+        map.end()
+    }
+}
+
+/// Serialize a result as a `result` entry, or an `error` entry if it failed
+#[cfg(feature = "serde")]
+pub(crate) fn serialize_result<M: SerializeMap>(
+    map: &mut M,
+    result: &Result<Value, Error>,
+) -> Result<(), M::Error> {
+    match result {
+        Ok(value) => map.serialize_entry("result", value),
+        Err(err) => map.serialize_entry("error", err),
+    }
+}
+
+/// A stable hash identifying a rule
+///
+/// The hash is the xxHash64 (with a seed of 0) of the rule's
+/// [`canonical_json()`], written as 16 lowercase hexadecimal digits. This
+/// scheme is part of the public API, and won't change without a new major
+/// version.
+///
+/// ```rust
+/// use jsonlogic_rs::rule_hash;
+/// use serde_json::json;
+///
+/// assert_eq!(
+///     rule_hash(&json!({"in": [{"var": "a"}, [1, 2]]})),
+///     rule_hash(&serde_json::from_str(r#"{ "in": [ {"var":"a"}, [1,2] ] }"#).unwrap()),
+/// );
+/// ```
+pub fn rule_hash(rule: &Value) -> String {
+    hash(rule)
+}
+
+/// A stable hash identifying some data
+///
+/// This is the same scheme as [`rule_hash()`], so e.g. a rule used as data
+/// has the same hash either way.
+pub fn data_hash(data: &Value) -> String {
+    hash(data)
+}
+
+/// A stable hash identifying a rule up to how its operations are written,
+/// recognizing the engine's custom operators
+///
+/// See [`fingerprint()`](crate::fingerprint).
+pub(crate) fn fingerprint(engine: &Engine, rule: &Value) -> String {
+    hash(&normalize(engine, rule))
+}
+
+/// Write a rule's operations in one form: with their arguments in an array,
+/// and with aliases replaced by the operators they stand for, unless they
+/// change the result
+fn normalize(engine: &Engine, value: &Value) -> Value {
+    if let Some((key, args)) = op::split_operation(engine, value) {
+        let key = op::equivalent_alias_target(key).unwrap_or(key);
+        let args = args.into_iter().map(|arg| normalize(engine, arg)).collect();
+        let mut operation = serde_json::Map::with_capacity(1);
+        operation.insert(key.into(), Value::Array(args));
+        return Value::Object(operation);
+    }
+    match value {
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| normalize(engine, item)).collect())
+        }
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(key, val)| (key.clone(), normalize(engine, val)))
+                .collect(),
+        ),
+        scalar => scalar.clone(),
+    }
+}
+
+fn hash(value: &Value) -> String {
+    format!(
+        "{:016x}",
+        xxhash_rust::xxh64::xxh64(canonical_json(value).as_bytes(), 0)
+    )
+}
+
+/// A value's canonical JSON serialization
+///
+/// The canonical serialization has no whitespace, sorts object keys by code
+/// point, and writes strings as `serde_json` does. Numbers are written in
+/// their shortest form that reads back as the same number, with integers
+/// written as integers and other numbers as `serde_json` writes an `f64`,
+/// so e.g. `1.50` is written as `1.5`. The result doesn't depend on the
+/// order keys were given in, or on which of `serde_json`'s features are
+/// enabled, so values that are the same serialize the same on any platform.
+/// This is what [`rule_hash()`] and [`data_hash()`] hash.
+///
+/// ```rust
+/// use jsonlogic_rs::canonical_json;
+/// use serde_json::json;
+///
+/// let rule = serde_json::from_str(r#"{"if": [ {"var": "a"}, {"y": 1.50, "x": 2} ]}"#).unwrap();
+/// assert_eq!(canonical_json(&rule), r#"{"if":[{"var":"a"},{"x":2,"y":1.5}]}"#);
+/// ```
+pub fn canonical_json(value: &Value) -> String {
+    let mut canonical = String::new();
+    write_canonical(value, &mut canonical);
+    canonical
+}
+
+/// Write a value's canonical JSON serialization
+///
+/// Maps are sorted already unless serde_json's `preserve_order` feature is
+/// enabled by another crate, so keys are sorted explicitly. Likewise,
+/// numbers keep the text they were parsed from if its `arbitrary_precision`
+/// feature is enabled, so they're rewritten from their values.
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Array(items) => {
+            out.push('[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(obj) => {
+            let mut entries: Vec<(&String, &Value)> = obj.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (idx, (key, val)) in entries.into_iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(val, out);
+            }
+            out.push('}');
+        }
+        Value::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(int), _, _) => out.push_str(&int.to_string()),
+            (_, Some(int), _) => out.push_str(&int.to_string()),
+            (_, _, Some(float)) => out.push_str(&Value::from(float).to_string()),
+            _ => out.push_str(&n.to_string()),
+        },
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test_audit {
+    use super::*;
+    use crate::Engine;
+    use serde_json::json;
+
+    #[test]
+    fn test_hash() {
+        let rule = json!({"if": [{"var": "a"}, "é\n", [1, 2.5, null]]});
+        let canonical = canonical_json(&rule);
+        assert_eq!(canonical, rule.to_string());
+        assert_eq!(canonical, r#"{"if":[{"var":"a"},"é\n",[1,2.5,null]]}"#);
+
+        // Known value, which must not change between releases
+        assert_eq!(rule_hash(&json!({"var": "a"})), "5f63c2d297b60e38");
+        assert_eq!(rule_hash(&json!(1)).len(), 16);
+        assert_eq!(rule_hash(&rule), data_hash(&rule));
+        assert_ne!(rule_hash(&json!(1)), rule_hash(&json!("1")));
+
+        // Numbers are written from their values
+        let parsed: Value = serde_json::from_str("[1.50, 1e2, 7]").unwrap();
+        assert_eq!(canonical_json(&parsed), "[1.5,100.0,7]");
+        assert_eq!(canonical_json(&json!(u64::MAX)), "18446744073709551615");
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let engine = Engine::new();
+        let normalized = |rule: Value| fingerprint(&engine, &rule);
+        assert_eq!(
+            normalized(json!({"var": "a"})),
+            rule_hash(&json!({"var": ["a"]}))
+        );
+        assert_eq!(
+            normalized(json!({"?:": [{"!": {"var": "a"}}, 1, 2]})),
+            normalized(json!({"if": [{"!": [{"var": ["a"]}]}, 1, 2]}))
+        );
+        assert_eq!(
+            normalized(json!({"x": {"var": "a"}, "y": [{"var": "b"}]})),
+            normalized(json!({"y": [{"var": ["b"]}], "x": {"var": ["a"]}}))
+        );
+        // Aliases that change the result are kept
+        assert_ne!(
+            normalized(json!({"not_in": ["a", ["b"]]})),
+            normalized(json!({"in": ["a", ["b"]]}))
+        );
+        // Only operators are normalized
+        assert_ne!(
+            normalized(json!({"unknown": "a"})),
+            normalized(json!({"unknown": ["a"]}))
+        );
+        let engine =
+            Engine::new().add_operator("double", |args: &[&Value]| Ok(args[0].clone()));
+        assert_eq!(
+            fingerprint(&engine, &json!({"double": 1})),
+            fingerprint(&engine, &json!({"double": [1]}))
+        );
+    }
+
+    #[test]
+    fn test_audit() {
+        let rule = json!({"<": [{"var": "a"}, 2]});
+        let record = Engine::new().audit(&rule, &json!({"a": 1}));
+        assert_eq!(record.rule_hash, rule_hash(&rule));
+        assert_eq!(record.data_hash, data_hash(&json!({"a": 1})));
+        assert_eq!(record.result.unwrap(), json!(true));
+        assert_eq!(record.stats.data_lookups, 1);
+
+        let record = Engine::new().audit(&json!({"==": [1]}), &json!(null));
+        assert!(record.result.is_err());
+    }
+
+    #[test]
+    fn test_audit_redacted() {
+        let engine = Engine::new().redact("ssn");
+        let data = json!({"ssn": "123-45-6789"});
+
+        let record = engine.audit(&json!({"cat": ["id:", {"var": "ssn"}]}), &data);
+        assert_eq!(record.data_hash, data_hash(&data));
+        assert_eq!(record.result.unwrap(), json!("id:[REDACTED]"));
+
+        let record = engine.audit(&json!({"throw": {"var": "ssn"}}), &data);
+        assert!(!record.result.unwrap_err().to_string().contains("6789"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        let record = Engine::new().audit(&json!({"var": "a"}), &json!({"a": 1}));
+        let serialized = serde_json::to_value(&record).unwrap();
+        assert_eq!(serialized["rule_hash"], json!(record.rule_hash));
+        assert_eq!(serialized["result"], json!(1));
+        assert_eq!(serialized["stats"]["operations"], json!(1));
+
+        let record = Engine::new().audit(&json!({"==": [1]}), &json!(null));
+        let serialized = serde_json::to_value(&record).unwrap();
+        assert_eq!(serialized["error"]["code"], json!("wrong_argument_count"));
+        assert!(serialized.get("result").is_none());
+
+        let warnings = Engine::new().lint(&json!({"<": [1, 2]}));
+        assert_eq!(
+            serde_json::to_value(&warnings[0]).unwrap()["kind"],
+            json!("constant_comparison")
+        );
+
+        let results = crate::RuleSet::new()
+            .add("adult", json!({">=": [{"var": "age"}, 18]}))
+            .evaluate(&Engine::new(), &json!({"age": 12}));
+        assert_eq!(
+            serde_json::to_value(&results).unwrap(),
+            json!({"outcomes": {"adult": {
+                "severity": "error",
+                "passed": false,
+                "result": false,
+            }}})
+        );
+    }
+}
@@ -1,12 +1,37 @@
+use std::fs;
 use std::io;
 use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use clap::{App, Arg};
-use serde_json;
-use serde_json::Value;
+use anyhow::{bail, Context, Result};
+use clap::{App, AppSettings, Arg, ArgMatches, Shell, SubCommand};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde_json::{json, Value};
 
-use jsonlogic_rs;
+use jsonlogic_rs::conformance::{self, Divergence};
+use jsonlogic_rs::graph::{render_graph, Format};
+use jsonlogic_rs::snapshot;
+use jsonlogic_rs::{format_rule, Engine, FormatStyle};
+
+fn logic_arg<'a, 'b>(required: bool) -> Arg<'a, 'b> {
+    let help = match required {
+        true => "A JSON logic string",
+        false => "A JSON logic string. May be provided as stdin.",
+    };
+    Arg::with_name("logic")
+        .help(help)
+        .required(required)
+        .takes_value(true)
+}
+
+fn data_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("data")
+        .help("A string of JSON data to parse. May be provided as stdin.")
+        .required(false)
+        .takes_value(true)
+}
 
 fn configure_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
     app.version(env!("CARGO_PKG_VERSION"))
@@ -17,25 +42,172 @@ fn configure_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
             When no <data> or <data> is -, read from stdin.
             \n\
             The result is written to stdout as JSON, so multiple calls \n\
-            can be chained together if desired.",
+            can be chained together if desired.\n\
+            \n\
+            `jsonlogic <logic> [data]` is short for `jsonlogic eval`.",
+        )
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .arg(logic_arg(true))
+        .arg(data_arg())
+        .subcommand(
+            SubCommand::with_name("eval")
+                .about("Apply a JsonLogic rule to JSON data")
+                .arg(logic_arg(true))
+                .arg(data_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about(
+                    "Check that a JsonLogic rule is valid, printing any lint \
+                    warnings to stderr",
+                )
+                .arg(logic_arg(false))
+                .arg(
+                    Arg::with_name("strict")
+                        .long("strict")
+                        .help("Treat lint warnings as errors"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Pretty-print a JsonLogic rule")
+                .arg(logic_arg(false))
+                .arg(
+                    Arg::with_name("indent")
+                        .long("indent")
+                        .help("The number of spaces per level of indentation")
+                        .default_value("2")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("width")
+                        .long("width")
+                        .help("The line width above which values are broken up")
+                        .default_value("80")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("explain")
+                .about("Render the structure of a JsonLogic rule as a graph")
+                .arg(logic_arg(false))
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .help("The graph description language to use")
+                        .possible_values(&["dot", "mermaid"])
+                        .default_value("mermaid")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("vars")
+                .about(
+                    "List the data paths a JsonLogic rule reads, with their \
+                    inferred types",
+                )
+                .arg(logic_arg(false))
+                .arg(
+                    Arg::with_name("schema")
+                        .long("schema")
+                        .help("Print the inferred JSON Schema instead"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("snapshot")
+                .about(
+                    "Apply a JsonLogic rule to JSON data, printing a snapshot of \
+                    the evaluation to replay",
+                )
+                .arg(logic_arg(true))
+                .arg(data_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about(
+                    "Re-run the evaluation in a snapshot, failing if its outcome \
+                    isn't reproduced",
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .help("A file containing a snapshot. May be provided as stdin.")
+                        .takes_value(true),
+                ),
         )
-        .arg(
-            Arg::with_name("logic")
-                .help("A JSON logic string")
-                .required(true)
-                .takes_value(true),
+        .subcommand(
+            SubCommand::with_name("shrink")
+                .about(
+                    "Shrink a JsonLogic rule and JSON data to the smallest rule \
+                    and data with the same result or error",
+                )
+                .arg(logic_arg(true))
+                .arg(data_arg()),
         )
-        .arg(
-            Arg::with_name("data")
-                .help("A string of JSON data to parse. May be provided as stdin.")
-                .required(false)
-                .takes_value(true),
+        .subcommand(
+            SubCommand::with_name("watch")
+                .about(
+                    "Apply a JsonLogic rule to JSON data from files, again \
+                    whenever either file changes",
+                )
+                .arg(
+                    Arg::with_name("logic-file")
+                        .long("logic-file")
+                        .help("A file containing a JSON logic string")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("data-file")
+                        .long("data-file")
+                        .help("A file containing JSON data. Defaults to null data.")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("test")
+                .about(
+                    "Run a file of [logic, data, expected] tests, in the format of \
+                    the official JsonLogic tests",
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .help(
+                            "A JSON file containing an array of tests. Defaults to \
+                            the official tests, which are bundled with jsonlogic.",
+                        )
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Print a JSON report of the failed tests"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("Generate a shell completion script, written to stdout")
+                .arg(
+                    Arg::with_name("shell")
+                        .help("The shell to generate completions for")
+                        .possible_values(&Shell::variants())
+                        .required(true)
+                        .takes_value(true),
+                ),
         )
         .after_help(
             r#"EXAMPLES:
     jsonlogic '{"===": [{"var": "a"}, "foo"]}' '{"a": "foo"}'
     jsonlogic '{"===": [1, 1]}' null
     echo '{"a": "foo"}' | jsonlogic '{"===": [{"var": "a"}, "foo"]}'
+    jsonlogic fmt --width 40 < rule.json
+    jsonlogic vars '{"<": [{"var": "person.age"}, 18]}'
+    jsonlogic snapshot '{"/": [{"var": "a"}, 0]}' '{"a": 1}' > bug.json
+    jsonlogic replay bug.json
+    jsonlogic shrink "$(cat rule.json)" < data.json
+    jsonlogic watch --logic-file rule.json --data-file data.json
+    jsonlogic test tests.json
+    jsonlogic completions bash > /etc/bash_completion.d/jsonlogic
 
 Inspired by and conformant with the original JsonLogic (jsonlogic.com).
 
@@ -43,31 +215,341 @@ Report bugs to github.com/Bestowinc/json-logic-rs."#,
         )
 }
 
-fn main() -> Result<()> {
-    let app = configure_args(App::new("jsonlogic"));
-    let matches = app.get_matches();
+/// Get a value from its argument, or from stdin if it is missing or `-`
+fn read_arg(matches: &ArgMatches, name: &str) -> Result<String> {
+    match matches.value_of(name).unwrap_or("-") {
+        "-" => {
+            let mut input = String::new();
+            io::stdin().lock().read_to_string(&mut input)?;
+            Ok(input)
+        }
+        arg => Ok(arg.to_string()),
+    }
+}
+
+fn read_logic(matches: &ArgMatches) -> Result<Value> {
+    let logic = read_arg(matches, "logic")?;
+    serde_json::from_str(&logic).context("Could not parse logic as JSON")
+}
 
+fn eval(matches: &ArgMatches) -> Result<()> {
     let logic = matches.value_of("logic").expect("logic arg expected");
     let json_logic: Value =
         serde_json::from_str(logic).context("Could not parse logic as JSON")?;
 
-    // let mut data: String;
-    let data_arg = matches.value_of("data").unwrap_or("-");
-
-    let mut data: String;
-    if data_arg != "-" {
-        data = data_arg.to_string();
-    } else {
-        data = String::new();
-        io::stdin().lock().read_to_string(&mut data)?;
-    }
+    let data = read_arg(matches, "data")?;
     let json_data: Value =
         serde_json::from_str(&data).context("Could not parse data as JSON")?;
 
     let result = jsonlogic_rs::apply(&json_logic, &json_data)
         .context("Could not execute logic")?;
 
-    println!("{}", result.to_string());
+    println!("{}", result);
+    Ok(())
+}
+
+fn validate(matches: &ArgMatches) -> Result<()> {
+    let json_logic = read_logic(matches)?;
+    let engine = Engine::new();
+    engine.validate(&json_logic).context("Invalid logic")?;
+
+    let warnings = engine.lint(&json_logic);
+    for warning in &warnings {
+        eprintln!("warning: {}", warning);
+    }
+    if matches.is_present("strict") && !warnings.is_empty() {
+        bail!("Found {} lint warning(s)", warnings.len());
+    }
+    Ok(())
+}
+
+fn fmt(matches: &ArgMatches) -> Result<()> {
+    let json_logic = read_logic(matches)?;
+    let style = FormatStyle {
+        indent: matches
+            .value_of("indent")
+            .unwrap_or("2")
+            .parse()
+            .context("Could not parse indent as a number")?,
+        max_width: matches
+            .value_of("width")
+            .unwrap_or("80")
+            .parse()
+            .context("Could not parse width as a number")?,
+    };
+    println!("{}", format_rule(&json_logic, style));
+    Ok(())
+}
+
+fn explain(matches: &ArgMatches) -> Result<()> {
+    let json_logic = read_logic(matches)?;
+    let format = match matches.value_of("format") {
+        Some("dot") => Format::Dot,
+        _ => Format::Mermaid,
+    };
+    print!("{}", render_graph(&json_logic, format));
+    Ok(())
+}
+
+fn snapshot(matches: &ArgMatches) -> Result<()> {
+    let json_logic = read_logic(matches)?;
+    let data = read_arg(matches, "data")?;
+    let json_data: Value =
+        serde_json::from_str(&data).context("Could not parse data as JSON")?;
+
+    let snapshot = Engine::new().snapshot(&json_logic, &json_data);
+    println!("{}", serde_json::to_string_pretty(&snapshot)?);
+    Ok(())
+}
+
+fn replay(matches: &ArgMatches) -> Result<()> {
+    let contents = match matches.value_of("file").unwrap_or("-") {
+        "-" => read_arg(matches, "file")?,
+        path => fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path))?,
+    };
+    let json_snapshot: Value =
+        serde_json::from_str(&contents).context("Could not parse snapshot as JSON")?;
+
+    let replayed = snapshot::replay(&json_snapshot).context("Invalid snapshot")?;
+    match &replayed.result {
+        Ok(result) => println!("{}", result),
+        Err(err) => eprintln!("Error: {}", err),
+    }
+    if !replayed.reproduced() {
+        bail!("Outcome not reproduced; recorded {}", replayed.recorded);
+    }
+    Ok(())
+}
+
+fn shrink(matches: &ArgMatches) -> Result<()> {
+    let json_logic = read_logic(matches)?;
+    let data = read_arg(matches, "data")?;
+    let json_data: Value =
+        serde_json::from_str(&data).context("Could not parse data as JSON")?;
+
+    let shrunk = Engine::new().shrink(&json_logic, &json_data);
+    println!("{}", json!({"logic": shrunk.rule, "data": shrunk.data}));
+    Ok(())
+}
+
+/// Print the paths in an inferred schema, one per line with their types
+///
+/// Array items are shown as `*`.
+fn print_paths(schema: &Value, path: &mut Vec<String>) {
+    let mut has_children = false;
+    if let Some(Value::Object(properties)) = schema.get("properties") {
+        for (key, property) in properties {
+            has_children = true;
+            path.push(key.clone());
+            print_paths(property, path);
+            path.pop();
+        }
+    }
+    if let Some(items) = schema.get("items") {
+        has_children = true;
+        path.push("*".into());
+        print_paths(items, path);
+        path.pop();
+    }
+    if !has_children && !path.is_empty() {
+        let types = match schema.get("type") {
+            Some(Value::String(t)) => t.clone(),
+            Some(Value::Array(types)) => types
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<&str>>()
+                .join(" | "),
+            _ => "any".into(),
+        };
+        println!("{}\t{}", path.join("."), types);
+    }
+}
 
+fn vars(matches: &ArgMatches) -> Result<()> {
+    let json_logic = read_logic(matches)?;
+    let schema = jsonlogic_rs::infer_schema(&json_logic);
+    if matches.is_present("schema") {
+        println!("{}", schema);
+    } else {
+        print_paths(&schema, &mut Vec::new());
+    }
     Ok(())
 }
+
+/// Apply the logic in a file to the data in another, if given
+fn eval_files(logic_file: &Path, data_file: Option<&Path>) -> Result<Value> {
+    let logic = fs::read_to_string(logic_file)
+        .with_context(|| format!("Could not read {}", logic_file.display()))?;
+    let json_logic: Value =
+        serde_json::from_str(&logic).context("Could not parse logic as JSON")?;
+    let json_data: Value = match data_file {
+        Some(data_file) => {
+            let data = fs::read_to_string(data_file)
+                .with_context(|| format!("Could not read {}", data_file.display()))?;
+            serde_json::from_str(&data).context("Could not parse data as JSON")?
+        }
+        None => Value::Null,
+    };
+    jsonlogic_rs::apply(&json_logic, &json_data).context("Could not execute logic")
+}
+
+fn watch(matches: &ArgMatches) -> Result<()> {
+    let canonical = |name: &str| -> Result<Option<PathBuf>> {
+        matches
+            .value_of(name)
+            .map(|path| {
+                fs::canonicalize(path)
+                    .with_context(|| format!("Could not find {}", path))
+            })
+            .transpose()
+    };
+    let logic_file = canonical("logic-file")?.expect("logic-file arg expected");
+    let data_file = canonical("data-file")?;
+    let files: Vec<&PathBuf> = std::iter::once(&logic_file).chain(&data_file).collect();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    // Editors often save by replacing a file, which would end a watch on
+    // the file itself, so the directories containing the files are watched
+    for file in &files {
+        let dir = file.parent().unwrap_or_else(|| Path::new("/"));
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+
+    let evaluate = || match eval_files(&logic_file, data_file.as_deref()) {
+        Ok(result) => println!("{}", result),
+        Err(err) => eprintln!("Error: {:#}", err),
+    };
+    evaluate();
+    while let Ok(event) = rx.recv() {
+        let event = event?;
+        let relevant = !matches!(event.kind, EventKind::Access(_))
+            && event.paths.iter().any(|path| files.contains(&path));
+        if !relevant {
+            continue;
+        }
+        // Wait for a save to finish, since it may produce several events
+        while rx.recv_timeout(Duration::from_millis(50)).is_ok() {}
+        evaluate();
+    }
+    Ok(())
+}
+
+/// Diff two blocks of text line by line, marking lines only in `old` with
+/// `-` and lines only in `new` with `+`
+fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // The length of the longest common subsequence of each pair of suffixes
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+fn test(matches: &ArgMatches) -> Result<()> {
+    let cases = match matches.value_of("file") {
+        Some(path) => {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Could not read {}", path))?;
+            let tests: Value = serde_json::from_str(&contents)
+                .context("Could not parse tests as JSON")?;
+            conformance::parse_cases(&tests).context("Invalid tests")?
+        }
+        None => conformance::official_cases(),
+    };
+    let report = conformance::run_cases(&Engine::new(), &cases);
+
+    if matches.is_present("json") {
+        println!("{}", report.to_json());
+    } else {
+        let style = FormatStyle::default();
+        for (case, divergence) in &report.divergences {
+            match &case.section {
+                Some(section) => println!("FAILED: item {} ({})", case.index, section),
+                None => println!("FAILED: item {}", case.index),
+            }
+            println!("  logic: {}", case.logic);
+            println!("  data: {}", case.data);
+            match divergence {
+                Divergence::Result(actual) => {
+                    let diff = diff_lines(
+                        &format_rule(&case.expected, style),
+                        &format_rule(actual, style),
+                    );
+                    diff.iter().for_each(|line| println!("  {}", line));
+                }
+                Divergence::Error { message, .. } => {
+                    println!("  expected: {}", case.expected);
+                    println!("  error: {}", message);
+                }
+            }
+            println!();
+        }
+        println!(
+            "{} passed, {} failed",
+            report.passed,
+            report.divergences.len()
+        );
+    }
+    if !report.is_conformant() {
+        bail!("{} test(s) failed", report.divergences.len());
+    }
+    Ok(())
+}
+
+fn completions(matches: &ArgMatches) -> Result<()> {
+    let shell: Shell = matches
+        .value_of("shell")
+        .expect("shell arg expected")
+        .parse()
+        .map_err(anyhow::Error::msg)?;
+    configure_args(App::new("jsonlogic")).gen_completions_to(
+        "jsonlogic",
+        shell,
+        &mut io::stdout(),
+    );
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let app = configure_args(App::new("jsonlogic"));
+    let matches = app.get_matches();
+
+    match matches.subcommand() {
+        ("eval", Some(matches)) => eval(matches),
+        ("validate", Some(matches)) => validate(matches),
+        ("fmt", Some(matches)) => fmt(matches),
+        ("explain", Some(matches)) => explain(matches),
+        ("vars", Some(matches)) => vars(matches),
+        ("snapshot", Some(matches)) => snapshot(matches),
+        ("replay", Some(matches)) => replay(matches),
+        ("shrink", Some(matches)) => shrink(matches),
+        ("watch", Some(matches)) => watch(matches),
+        ("test", Some(matches)) => test(matches),
+        ("completions", Some(matches)) => completions(matches),
+        _ => eval(&matches),
+    }
+}
@@ -0,0 +1,200 @@
+//! Presets of engine options
+//!
+//! Most of an engine's options exist to match the behavior of some other
+//! implementation, or to go beyond what the specification defines. Rather
+//! than choosing each of them, an [`EngineBuilder`] starts from a preset
+//! bundling a consistent set of them, which can then be adjusted.
+
+use crate::compare::Comparator;
+use crate::engine::{Compatibility, DivisionByZero, Engine, NumericKeys};
+use crate::policy::Policy;
+
+/// The operators defined by the [JsonLogic specification](http://jsonlogic.com/operations.html)
+pub(crate) const SPEC_OPERATORS: &[&str] = &[
+    "var",
+    "missing",
+    "missing_some",
+    "if",
+    "?:",
+    "==",
+    "===",
+    "!=",
+    "!==",
+    "!",
+    "!!",
+    "or",
+    "and",
+    ">",
+    ">=",
+    "<",
+    "<=",
+    "max",
+    "min",
+    "+",
+    "-",
+    "*",
+    "/",
+    "%",
+    "map",
+    "reduce",
+    "filter",
+    "all",
+    "none",
+    "some",
+    "merge",
+    "in",
+    "cat",
+    "substr",
+    "log",
+];
+
+/// A preset of engine options, to build an engine from
+///
+/// ```rust
+/// use jsonlogic_rs::EngineBuilder;
+/// use serde_json::json;
+///
+/// let engine = EngineBuilder::spec_strict().build();
+/// assert!(engine.apply(&json!({"+": [1, 1]}), &json!(null)).is_ok());
+/// assert!(engine.apply(&json!({"startsWith": ["ab", "a"]}), &json!(null)).is_err());
+///
+/// // Presets can be adjusted with the engine's own options
+/// let engine = EngineBuilder::js_compat()
+///     .configure(|engine| engine.max_result_size(1024))
+///     .build();
+/// assert_eq!(engine.apply(&json!({"/": [1, 0]}), &json!(null)).unwrap(), json!(null));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EngineBuilder {
+    engine: Engine,
+}
+impl EngineBuilder {
+    /// Start from the default options, as with [`Engine::new()`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only the operators defined by the specification, with the default
+    /// options
+    ///
+    /// Rules using the crate's extension operators, like `startsWith` or
+    /// `bucket`, or custom operators, fail with a
+    /// [`PolicyViolation`](crate::Error::PolicyViolation), so that rules
+    /// accepted by this engine can be evaluated by any other conformant
+    /// implementation.
+    pub fn spec_strict() -> Self {
+        let policy = SPEC_OPERATORS
+            .iter()
+            .fold(Policy::default(), |policy, operator| policy.allow(operator));
+        Self::new().configure(|engine| engine.policy(policy))
+    }
+
+    /// Match the behavior of the reference implementation,
+    /// [json-logic-js](https://github.com/jwadhams/json-logic-js), where it
+    /// differs from the specification
+    ///
+    /// Division by zero evaluates to `null`, as `Infinity` and `NaN` are
+    /// serialized by JavaScript, and the operators the reference
+    /// implementation is more lenient with accept the same arguments, as
+    /// described by [`Compatibility::Reference`].
+    pub fn js_compat() -> Self {
+        Self::new().configure(|engine| {
+            engine
+                .division_by_zero(DivisionByZero::Null)
+                .compatibility(Compatibility::Reference)
+        })
+    }
+
+    /// Every extension to the specification
+    ///
+    /// The values of objects that aren't operations are evaluated, arrays
+    /// and objects are compared by value, and numeric path segments find
+    /// object keys written as JSON would write the number, as well as keys
+    /// written as they are in the path.
+    pub fn extended() -> Self {
+        Self::new().configure(|engine| {
+            engine
+                .evaluate_objects(true)
+                .comparator(Comparator::default().deep(true))
+                .numeric_keys(NumericKeys::ExactFirst)
+        })
+    }
+
+    /// Adjust the options of the engine being built
+    pub fn configure<F>(mut self, configure: F) -> Self
+    where
+        F: FnOnce(Engine) -> Engine,
+    {
+        self.engine = configure(self.engine);
+        self
+    }
+
+    /// Build the engine
+    pub fn build(self) -> Engine {
+        self.engine
+    }
+}
+impl From<EngineBuilder> for Engine {
+    fn from(builder: EngineBuilder) -> Self {
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod test_builder {
+    use super::*;
+    use crate::conformance;
+    use crate::op;
+    use serde_json::json;
+
+    #[test]
+    fn test_spec_operators_exist() {
+        let engine = Engine::new();
+        for operator in SPEC_OPERATORS {
+            assert!(op::is_operator(&engine, operator), "{}", operator);
+        }
+    }
+
+    #[test]
+    fn test_conformant() {
+        for builder in [EngineBuilder::spec_strict(), EngineBuilder::js_compat()] {
+            let report = conformance::run_cases(
+                &builder.build(),
+                &conformance::official_cases(),
+            );
+            assert!(report.is_conformant(), "{:?}", report.divergences);
+        }
+    }
+
+    #[test]
+    fn test_presets() {
+        let rule = json!({"==": [[1, 2], {"var": "ids"}]});
+        let data = json!({"ids": [1, 2]});
+        let apply = |builder: EngineBuilder| builder.build().apply(&rule, &data);
+        assert_eq!(apply(EngineBuilder::new()).unwrap(), json!(false));
+        assert_eq!(apply(EngineBuilder::extended()).unwrap(), json!(true));
+        let rule = json!({"!": [0, 1]});
+        assert!(EngineBuilder::new()
+            .build()
+            .apply(&rule, &json!(null))
+            .is_err());
+        assert_eq!(
+            EngineBuilder::js_compat()
+                .build()
+                .apply(&rule, &json!(null))
+                .unwrap(),
+            json!(true)
+        );
+
+        let engine: Engine = EngineBuilder::spec_strict()
+            .configure(|engine| engine.division_by_zero(DivisionByZero::Null))
+            .into();
+        assert_eq!(
+            engine.apply(&json!({"%": [1, 0]}), &json!(null)).unwrap(),
+            json!(null)
+        );
+        assert!(engine
+            .apply(&json!({"bucket": ["a", 2]}), &json!(null))
+            .is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Column-wise evaluation over Arrow record batches
+//!
+//! Rules used as filters are mostly comparisons of columns with literals,
+//! combined with `and`, `or`, and `!`. Those are evaluated here with Arrow's
+//! compute kernels over whole columns at once. Anything else is evaluated
+//! row by row, with each row converted to a JSON object, so every rule gives
+//! the same result as it would with `Engine::apply()`.
+
+use std::cell::OnceCell;
+
+use arrow_arith::boolean;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type,
+    UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, BooleanArray, Datum, Float64Array, RecordBatch, StringArray};
+use arrow_ord::cmp;
+use arrow_schema::{ArrowError, DataType};
+use serde_json::{Map, Number, Value};
+
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::op::{split_operation, truthy};
+
+fn arrow_error(err: ArrowError) -> Error {
+    Error::UnexpectedError(format!("Arrow compute failed: {}", err))
+}
+
+/// A comparison argument that can be evaluated column-wise
+enum Operand {
+    Numbers(Float64Array),
+    Strings(StringArray),
+    Bools(BooleanArray),
+    Number(f64),
+    String(String),
+    Bool(bool),
+}
+impl Operand {
+    fn is_column(&self) -> bool {
+        matches!(self, Self::Numbers(_) | Self::Strings(_) | Self::Bools(_))
+    }
+
+    fn datum(&self) -> Box<dyn Datum> {
+        match self {
+            Self::Numbers(arr) => Box::new(arr.clone()),
+            Self::Strings(arr) => Box::new(arr.clone()),
+            Self::Bools(arr) => Box::new(arr.clone()),
+            Self::Number(n) => Box::new(Float64Array::new_scalar(*n)),
+            Self::String(s) => Box::new(StringArray::new_scalar(s)),
+            Self::Bool(b) => Box::new(BooleanArray::new_scalar(*b)),
+        }
+    }
+}
+
+/// The kinds of operands that compare alike
+#[derive(PartialEq)]
+enum Kind {
+    Number,
+    String,
+    Bool,
+}
+impl From<&Operand> for Kind {
+    fn from(operand: &Operand) -> Self {
+        match operand {
+            Operand::Numbers(_) | Operand::Number(_) => Self::Number,
+            Operand::Strings(_) | Operand::String(_) => Self::String,
+            Operand::Bools(_) | Operand::Bool(_) => Self::Bool,
+        }
+    }
+}
+
+/// Convert a numeric column to floats, as JsonLogic compares numbers
+fn to_f64(array: &dyn Array) -> Option<Float64Array> {
+    macro_rules! convert {
+        ($t:ty) => {
+            array.as_primitive::<$t>().unary(|v| v as f64)
+        };
+    }
+    Some(match array.data_type() {
+        DataType::Int8 => convert!(Int8Type),
+        DataType::Int16 => convert!(Int16Type),
+        DataType::Int32 => convert!(Int32Type),
+        DataType::Int64 => convert!(Int64Type),
+        DataType::UInt8 => convert!(UInt8Type),
+        DataType::UInt16 => convert!(UInt16Type),
+        DataType::UInt32 => convert!(UInt32Type),
+        DataType::UInt64 => convert!(UInt64Type),
+        DataType::Float32 => convert!(Float32Type),
+        DataType::Float64 => array.as_primitive::<Float64Type>().clone(),
+        _ => return None,
+    })
+}
+
+/// Get the JSON value of a cell, if the column type is supported
+fn cell(array: &dyn Array, row: usize) -> Option<Value> {
+    if array.is_null(row) {
+        return Some(Value::Null);
+    }
+    let value = match array.data_type() {
+        DataType::Boolean => Value::Bool(array.as_boolean().value(row)),
+        DataType::Utf8 => Value::String(array.as_string::<i32>().value(row).into()),
+        DataType::LargeUtf8 => {
+            Value::String(array.as_string::<i64>().value(row).into())
+        }
+        DataType::Int64 => Value::from(array.as_primitive::<Int64Type>().value(row)),
+        DataType::UInt64 => Value::from(array.as_primitive::<UInt64Type>().value(row)),
+        _ => {
+            let num = to_f64(array)?.value(row);
+            if num.fract() == 0.0 && num.abs() < i64::MAX as f64 {
+                Value::from(num as i64)
+            } else {
+                Number::from_f64(num).map_or(Value::Null, Value::Number)
+            }
+        }
+    };
+    Some(value)
+}
+
+struct BatchEvaluator<'a> {
+    engine: &'a Engine,
+    batch: &'a RecordBatch,
+    rows: OnceCell<Vec<Value>>,
+}
+impl<'a> BatchEvaluator<'a> {
+    /// The rows of the batch as JSON objects, built on first use
+    fn rows(&self) -> &[Value] {
+        self.rows.get_or_init(|| {
+            let schema = self.batch.schema();
+            (0..self.batch.num_rows())
+                .map(|row| {
+                    let map: Map<String, Value> = schema
+                        .fields()
+                        .iter()
+                        .zip(self.batch.columns())
+                        .filter_map(|(field, column)| {
+                            cell(column.as_ref(), row)
+                                .map(|v| (field.name().clone(), v))
+                        })
+                        .collect();
+                    Value::Object(map)
+                })
+                .collect()
+        })
+    }
+
+    /// Evaluate a rule for each row, as `Engine::apply()` would
+    fn row_wise(&self, rule: &Value) -> Result<BooleanArray, Error> {
+        self.rows()
+            .iter()
+            .map(|row| self.engine.apply(rule, row).map(|v| Some(truthy(&v))))
+            .collect()
+    }
+
+    /// Get a column or literal that can be compared column-wise
+    ///
+    /// Columns with nulls are excluded, because JsonLogic's comparisons
+    /// with null (e.g. `null < 1` is true) don't match Arrow's.
+    fn operand(&self, value: &Value) -> Option<Operand> {
+        match value {
+            Value::Number(n) => n.as_f64().map(Operand::Number),
+            Value::String(s) => Some(Operand::String(s.clone())),
+            Value::Bool(b) => Some(Operand::Bool(*b)),
+            Value::Object(_) => {
+                let (key, args) = split_operation(self.engine, value)?;
+                let name = match (key, args.as_slice()) {
+                    ("var", [Value::String(name)]) if !name.contains(['.', '\\']) => {
+                        name
+                    }
+                    _ => return None,
+                };
+                let column = self.batch.column_by_name(name)?;
+                if column.null_count() > 0 {
+                    return None;
+                }
+                match column.data_type() {
+                    DataType::Utf8 => {
+                        Some(Operand::Strings(column.as_string().clone()))
+                    }
+                    DataType::Boolean => {
+                        Some(Operand::Bools(column.as_boolean().clone()))
+                    }
+                    _ => to_f64(column.as_ref()).map(Operand::Numbers),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Compare two operands column-wise, if the operator and types allow
+    fn compare(
+        &self,
+        key: &str,
+        left: &Operand,
+        right: &Operand,
+    ) -> Option<Result<BooleanArray, Error>> {
+        if !(left.is_column() || right.is_column())
+            || Kind::from(left) != Kind::from(right)
+        {
+            return None;
+        }
+        let ordered = Kind::from(left) == Kind::Number;
+        let kernel = match key {
+            "==" | "===" => cmp::eq,
+            "!=" | "!==" => cmp::neq,
+            "<" if ordered => cmp::lt,
+            "<=" if ordered => cmp::lt_eq,
+            ">" if ordered => cmp::gt,
+            ">=" if ordered => cmp::gt_eq,
+            _ => return None,
+        };
+        Some(kernel(left.datum().as_ref(), right.datum().as_ref()).map_err(arrow_error))
+    }
+
+    /// Evaluate the truthiness of a rule for every row
+    fn truth(&self, rule: &Value) -> Result<BooleanArray, Error> {
+        let num_rows = self.batch.num_rows();
+        if let Some(Operand::Bool(b)) = self.operand(rule) {
+            return Ok(BooleanArray::from(vec![b; num_rows]));
+        }
+        if let Some(Operand::Bools(column)) = self.operand(rule) {
+            return Ok(column);
+        }
+        let (key, args) = match split_operation(self.engine, rule) {
+            Some(split) => split,
+            None => return self.row_wise(rule),
+        };
+        match (key, args.as_slice()) {
+            ("and", [first, rest @ ..]) => {
+                rest.iter().try_fold(self.truth(first)?, |acc, arg| {
+                    boolean::and(&acc, &self.truth(arg)?).map_err(arrow_error)
+                })
+            }
+            ("or", [first, rest @ ..]) => {
+                rest.iter().try_fold(self.truth(first)?, |acc, arg| {
+                    boolean::or(&acc, &self.truth(arg)?).map_err(arrow_error)
+                })
+            }
+            ("!", [arg]) => boolean::not(&self.truth(arg)?).map_err(arrow_error),
+            ("!!", [arg]) => self.truth(arg),
+            (_, [left, right]) => {
+                let compared = self
+                    .operand(left)
+                    .zip(self.operand(right))
+                    .and_then(|(left, right)| self.compare(key, &left, &right));
+                match compared {
+                    Some(result) => result,
+                    None => self.row_wise(rule),
+                }
+            }
+            _ => self.row_wise(rule),
+        }
+    }
+}
+
+impl Engine {
+    /// Evaluate a rule against each row of an Arrow record batch
+    ///
+    /// Returns whether the rule's result is truthy for each row, suitable
+    /// for filtering the batch. Comparisons of columns with literals or
+    /// other columns, combined with `and`, `or`, `!`, and `!!`, are
+    /// evaluated column-wise. Other operations are evaluated row by row,
+    /// with each row converted to a JSON object keyed by column name, in
+    /// which columns of unsupported types (anything but booleans, numbers,
+    /// and strings) are left out.
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use arrow_array::{Int64Array, RecordBatch, StringArray};
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::json;
+    ///
+    /// let batch = RecordBatch::try_from_iter(vec![
+    ///     ("age", Arc::new(Int64Array::from(vec![17, 30, 45])) as _),
+    ///     ("country", Arc::new(StringArray::from(vec!["US", "US", "CA"])) as _),
+    /// ])
+    /// .unwrap();
+    /// let rule = json!({"and": [
+    ///     {">=": [{"var": "age"}, 18]},
+    ///     {"==": [{"var": "country"}, "US"]},
+    /// ]});
+    /// let mask = Engine::new().evaluate_batch(&rule, &batch).unwrap();
+    /// assert_eq!(mask.values().iter().collect::<Vec<bool>>(), vec![false, true, false]);
+    /// ```
+    pub fn evaluate_batch(
+        &self,
+        rule: &Value,
+        batch: &RecordBatch,
+    ) -> Result<BooleanArray, Error> {
+        // Validate the rule as `apply()` would, since column-wise
+        // evaluation skips the operators' own argument checks
+        self.parse(rule)?;
+        BatchEvaluator {
+            engine: self,
+            batch,
+            rows: OnceCell::new(),
+        }
+        .truth(rule)
+    }
+}
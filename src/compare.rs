@@ -0,0 +1,488 @@
+//! Comparing values
+//!
+//! Every operator that compares values does so with the engine's
+//! [`Comparator`], so that a change to how values compare, like ignoring
+//! the case of strings, applies to all of them consistently. The default
+//! comparator follows JavaScript:
+//!
+//! - `==` and `!=` use abstract equality, converting between types
+//! - `===` and `!==` use strict equality, without conversion
+//! - `<`, `<=`, `>`, `>=`, and `is_sorted` use JS' relational comparison
+//! - Membership, as checked by `in`, `all_distinct`, `contains_all`, and
+//!   `contains_any`, uses strict equality, except that arrays and objects
+//!   are compared by value. This is also how `missing_some` recognizes
+//!   repeated keys.
+//!
+//! `==i`, `!=i`, and `in_i` use the engine's comparator with
+//! [`Comparator::case_insensitive()`] set.
+//!
+//! Like JS, comparisons convert numbers to 64-bit floats, which can't
+//! represent every integer beyond 2^53. With the `bigint` feature,
+//! `Comparator::big_integers()` compares such integers exactly instead.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use serde_json::{Number, Value};
+
+use crate::js_op;
+
+/// How an engine compares values
+///
+/// ```rust
+/// use jsonlogic_rs::{Comparator, Engine};
+/// use serde_json::json;
+///
+/// let engine = Engine::new().comparator(Comparator::default().case_insensitive(true));
+/// let apply = |rule| engine.apply(&rule, &json!({"plan": "Pro"})).unwrap();
+/// assert_eq!(apply(json!({"==": [{"var": "plan"}, "pro"]})), json!(true));
+/// assert_eq!(apply(json!({"in": [{"var": "plan"}, ["free", "PRO"]]})), json!(true));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Comparator {
+    strict: bool,
+    deep: bool,
+    case_insensitive: bool,
+    #[cfg(feature = "bigint")]
+    big_integers: bool,
+}
+impl Comparator {
+    /// Compare with `==` as with `===`, never converting between types
+    ///
+    /// The relational operators then only order numbers with numbers and
+    /// strings with strings, and are false for any other pair of values.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Compare arrays and objects by value with `==` and `===`
+    ///
+    /// By default, as in JS, `==` compares an array or object with a
+    /// primitive by converting it to a string, and two arrays or objects
+    /// are never equal, since they aren't the same reference.
+    pub fn deep(mut self, deep: bool) -> Self {
+        self.deep = deep;
+        self
+    }
+
+    /// Compare strings without regard to case
+    ///
+    /// Strings, including those in arrays and objects, are lowercased
+    /// according to the Unicode `Lowercase` property before they are
+    /// compared. Note that this is lowercasing rather than full case
+    /// folding, so e.g. "ß" and "ss" are not considered equal.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Compare integers beyond 2^53 exactly
+    ///
+    /// When an integer given as a JSON number is compared with another, or
+    /// with a string of an integer like `"12345678901234567891"`, and either
+    /// is too large to be represented exactly as a 64-bit float, they are
+    /// compared as arbitrary-precision integers rather than converted to
+    /// floats. This applies to equality, including membership, and to
+    /// ordering, and follows the comparator's other options, so e.g. a
+    /// strict comparator still never considers a string equal to a number.
+    ///
+    /// Two strings are compared as strings whatever their size, as they
+    /// are in JavaScript, so `"100000000000000000000"` is less than `"9"`.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{Comparator, Engine};
+    /// use serde_json::json;
+    ///
+    /// let rule = json!({"==": [{"var": "id"}, "9007199254740993"]});
+    /// let data = json!({"id": 9007199254740992_u64});
+    /// // Both round to the same float
+    /// assert_eq!(Engine::new().apply(&rule, &data).unwrap(), json!(true));
+    ///
+    /// let engine = Engine::new().comparator(Comparator::default().big_integers(true));
+    /// assert_eq!(engine.apply(&rule, &data).unwrap(), json!(false));
+    /// ```
+    #[cfg(feature = "bigint")]
+    pub fn big_integers(mut self, big_integers: bool) -> Self {
+        self.big_integers = big_integers;
+        self
+    }
+
+    /// Compare values as arbitrary-precision integers, if they should be
+    #[cfg(feature = "bigint")]
+    fn compare_big(&self, first: &Value, second: &Value) -> Option<Ordering> {
+        // Strings are only read as integers to compare them with numbers,
+        // so that two strings compare the same way at any magnitude
+        if !self.big_integers || (first.is_string() && second.is_string()) {
+            return None;
+        }
+        let (first, second) = (big::integer(first)?, big::integer(second)?);
+        match big::is_safe(&first) && big::is_safe(&second) {
+            true => None,
+            false => Some(first.cmp(&second)),
+        }
+    }
+
+    #[cfg(not(feature = "bigint"))]
+    fn compare_big(&self, _first: &Value, _second: &Value) -> Option<Ordering> {
+        None
+    }
+
+    /// Compare numbers as arbitrary-precision integers, if they should be
+    fn compare_big_numbers(&self, first: &Value, second: &Value) -> Option<Ordering> {
+        match (first, second) {
+            (Value::Number(_), Value::Number(_)) => self.compare_big(first, second),
+            _ => None,
+        }
+    }
+
+    /// The comparator's options, for a snapshot
+    pub(crate) fn to_json(self) -> Value {
+        #[allow(unused_mut)]
+        let mut options = serde_json::json!({
+            "strict": self.strict,
+            "deep": self.deep,
+            "case_insensitive": self.case_insensitive,
+        });
+        #[cfg(feature = "bigint")]
+        {
+            options["big_integers"] = Value::Bool(self.big_integers);
+        }
+        options
+    }
+
+    /// Read the comparator's options from a snapshot, ignoring those of
+    /// features that aren't enabled
+    pub(crate) fn from_json(options: &Value) -> Self {
+        let flag =
+            |name: &str| options.get(name).and_then(Value::as_bool).unwrap_or(false);
+        Self {
+            strict: flag("strict"),
+            deep: flag("deep"),
+            case_insensitive: flag("case_insensitive"),
+            #[cfg(feature = "bigint")]
+            big_integers: flag("big_integers"),
+        }
+    }
+
+    /// Whether values are equal, as by `==`
+    pub fn equal(&self, first: &Value, second: &Value) -> bool {
+        if self.strict {
+            return self.strict_equal(first, second);
+        }
+        if let Some(ordering) = self.compare_big(first, second) {
+            return ordering == Ordering::Equal;
+        }
+        let (first, second) = (self.fold(first), self.fold(second));
+        items_equal(&first, &second, self.deep, js_op::abstract_eq)
+    }
+
+    /// Whether values are equal, as by `===`
+    pub fn strict_equal(&self, first: &Value, second: &Value) -> bool {
+        if let Some(ordering) = self.compare_big_numbers(first, second) {
+            return ordering == Ordering::Equal;
+        }
+        let (first, second) = (self.fold(first), self.fold(second));
+        items_equal(&first, &second, self.deep, js_op::strict_eq)
+    }
+
+    /// Whether an item is the same as another, as by `in`
+    pub fn same(&self, first: &Value, second: &Value) -> bool {
+        if let Some(ordering) = self.compare_big_numbers(first, second) {
+            return ordering == Ordering::Equal;
+        }
+        let (first, second) = (self.fold(first), self.fold(second));
+        items_equal(&first, &second, true, js_op::strict_eq)
+    }
+
+    /// How values are ordered, or None if they can't be ordered, e.g.
+    /// because one can't be converted to a number
+    pub fn compare(&self, first: &Value, second: &Value) -> Option<Ordering> {
+        let (first, second) = (self.fold(first), self.fold(second));
+        if self.strict {
+            match (&*first, &*second) {
+                (Value::Number(_), Value::Number(_))
+                | (Value::String(_), Value::String(_)) => {}
+                _ => return None,
+            }
+        }
+        if let Some(ordering) = self.compare_big(&first, &second) {
+            return Some(ordering);
+        }
+        match (
+            js_op::abstract_rel(&first, &second),
+            js_op::abstract_rel(&second, &first),
+        ) {
+            (Some(true), _) => Some(Ordering::Less),
+            (_, Some(true)) => Some(Ordering::Greater),
+            (Some(false), Some(false)) => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+
+    /// Whether the first value is less than the second, as by `<`
+    pub fn lt(&self, first: &Value, second: &Value) -> bool {
+        self.compare(first, second) == Some(Ordering::Less)
+    }
+
+    /// Whether the first value is at most the second, as by `<=`
+    pub fn lte(&self, first: &Value, second: &Value) -> bool {
+        matches!(
+            self.compare(first, second),
+            Some(Ordering::Less | Ordering::Equal)
+        )
+    }
+
+    /// Whether the first value is greater than the second, as by `>`
+    pub fn gt(&self, first: &Value, second: &Value) -> bool {
+        self.compare(first, second) == Some(Ordering::Greater)
+    }
+
+    /// Whether the first value is at least the second, as by `>=`
+    pub fn gte(&self, first: &Value, second: &Value) -> bool {
+        matches!(
+            self.compare(first, second),
+            Some(Ordering::Greater | Ordering::Equal)
+        )
+    }
+
+    /// Whether a string contains a substring
+    pub fn contains_str(&self, haystack: &str, needle: &str) -> bool {
+        match self.case_insensitive {
+            true => haystack.to_lowercase().contains(&needle.to_lowercase()),
+            false => haystack.contains(needle),
+        }
+    }
+
+    /// A key identifying a value, such that values are the [`same()`] if
+    /// and only if their keys are equal
+    ///
+    /// This allows membership to be checked with a set.
+    ///
+    /// [`same()`]: Comparator::same
+    pub(crate) fn key(&self, value: &Value) -> String {
+        // Integers that floats can't represent are keyed by their digits,
+        // which can't be mistaken for a float or a quoted string
+        #[cfg(feature = "bigint")]
+        if let (true, Value::Number(_)) = (self.big_integers, value) {
+            if let Some(int) = big::integer(value).filter(|int| !big::is_safe(int)) {
+                return int.to_string();
+            }
+        }
+        normalize_numbers(&self.fold(value)).to_string()
+    }
+
+    /// Lowercase any strings, if comparing without regard to case
+    fn fold<'v>(&self, value: &'v Value) -> Cow<'v, Value> {
+        if !self.case_insensitive {
+            return Cow::Borrowed(value);
+        }
+        Cow::Owned(lowercase(value))
+    }
+}
+
+#[cfg(feature = "bigint")]
+mod big {
+    use num_bigint::BigInt;
+    use serde_json::Value;
+
+    /// The largest integer a 64-bit float represents along with its
+    /// neighbors, i.e. JS' `Number.MAX_SAFE_INTEGER`
+    const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+    /// A value as an integer, if it's an integer number, or a string of
+    /// only an integer, optionally signed and surrounded by whitespace
+    pub(super) fn integer(value: &Value) -> Option<BigInt> {
+        match value {
+            Value::Number(num) => match (num.as_i64(), num.as_u64()) {
+                (Some(int), _) => Some(BigInt::from(int)),
+                (_, Some(int)) => Some(BigInt::from(int)),
+                _ => None,
+            },
+            Value::String(s) => {
+                let s = s.trim();
+                let digits = s.strip_prefix(['+', '-']).unwrap_or(s);
+                match !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                    true => s.parse().ok(),
+                    false => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub(super) fn is_safe(int: &BigInt) -> bool {
+        *int <= BigInt::from(MAX_SAFE_INTEGER)
+            && *int >= BigInt::from(-MAX_SAFE_INTEGER)
+    }
+}
+
+fn lowercase(value: &Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(s.to_lowercase()),
+        Value::Array(items) => Value::Array(items.iter().map(lowercase).collect()),
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(key, val)| (key.clone(), lowercase(val)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// Compare values with `eq`, or item by item if they are both arrays or
+/// both objects and `deep` is set
+fn items_equal(
+    first: &Value,
+    second: &Value,
+    deep: bool,
+    eq: fn(&Value, &Value) -> bool,
+) -> bool {
+    match (first, second) {
+        (Value::Array(first), Value::Array(second)) if deep => {
+            first.len() == second.len()
+                && first
+                    .iter()
+                    .zip(second)
+                    .all(|(f, s)| items_equal(f, s, deep, eq))
+        }
+        (Value::Object(first), Value::Object(second)) if deep => {
+            first.len() == second.len()
+                && first.iter().all(|(key, f)| {
+                    second.get(key).is_some_and(|s| items_equal(f, s, deep, eq))
+                })
+        }
+        _ => eq(first, second),
+    }
+}
+
+/// Represent every number as a float, as strict equality compares them
+fn normalize_numbers(value: &Value) -> Value {
+    match value {
+        Value::Number(num) => {
+            // Positive and negative zero are equal
+            let num = num.as_f64().map(|num| if num == 0.0 { 0.0 } else { num });
+            num.and_then(Number::from_f64)
+                .map_or(Value::Null, Value::Number)
+        }
+        Value::Array(items) => {
+            Value::Array(items.iter().map(normalize_numbers).collect())
+        }
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(key, val)| (key.clone(), normalize_numbers(val)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test_compare {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_default() {
+        let cmp = Comparator::default();
+        assert!(cmp.equal(&json!(1), &json!("1")));
+        assert!(cmp.equal(&json!([1]), &json!(1)));
+        assert!(!cmp.equal(&json!([1]), &json!([1])));
+        assert!(!cmp.strict_equal(&json!(1), &json!("1")));
+        assert!(cmp.strict_equal(&json!(1), &json!(1.0)));
+        assert!(cmp.same(&json!([1, {"a": 2}]), &json!([1.0, {"a": 2}])));
+        assert!(!cmp.same(&json!(1), &json!("1")));
+        assert!(cmp.lte(&json!(null), &json!(0)));
+        assert!(cmp.lte(&json!({}), &json!({})));
+        assert!(!cmp.lte(&json!("a"), &json!(0)));
+        assert!(!cmp.gte(&json!("a"), &json!(0)));
+        assert_eq!(
+            cmp.compare(&json!("b"), &json!("a")),
+            Some(Ordering::Greater)
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let strict = Comparator::default().strict(true);
+        assert!(!strict.equal(&json!(1), &json!("1")));
+        assert!(!strict.lt(&json!(1), &json!("2")));
+        assert!(strict.lt(&json!("a"), &json!("b")));
+
+        let deep = Comparator::default().deep(true);
+        assert!(deep.equal(&json!([1, [2]]), &json!(["1", [2]])));
+        assert!(!deep.strict_equal(&json!([1, [2]]), &json!(["1", [2]])));
+        assert!(deep.strict_equal(&json!({"a": [1]}), &json!({"a": [1]})));
+        assert!(!deep.strict_equal(&json!({"a": [1]}), &json!({"a": [1], "b": 2})));
+
+        let insensitive = Comparator::default().case_insensitive(true);
+        assert!(insensitive.equal(&json!("ABC"), &json!("abc")));
+        assert!(insensitive.same(&json!(["A", {"b": "C"}]), &json!(["a", {"b": "c"}])));
+        assert!(!insensitive.lte(&json!("B"), &json!("a")));
+        assert!(insensitive.contains_str("Hello", "LL"));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_big_integers() {
+        let cmp = Comparator::default().big_integers(true);
+        let big = json!(9007199254740993_u64);
+        assert!(cmp.equal(&big, &json!("9007199254740993")));
+        assert!(!cmp.equal(&big, &json!(9007199254740992_u64)));
+        assert!(!cmp.equal(&big, &json!("9007199254740992")));
+        assert!(cmp.strict_equal(&big, &json!(9007199254740993_u64)));
+        assert!(!cmp.strict_equal(&big, &json!("9007199254740993")));
+        assert!(cmp.lt(
+            &json!("-99999999999999999999"),
+            &json!(-9007199254740993_i64)
+        ));
+        assert!(cmp.gt(&json!(" 123456789012345678901234567890 "), &json!(9)));
+        assert!(!cmp.strict(true).equal(&big, &json!("9007199254740993")));
+        assert_ne!(cmp.key(&big), cmp.key(&json!(9007199254740992_u64)));
+
+        // Integers that floats represent exactly compare as usual
+        assert!(!cmp.equal(&json!("1e3"), &json!("1000")));
+
+        // Strings compare with each other as strings on either side of 2^53,
+        // and with numbers as integers
+        let small = "12";
+        let large = "12345678901234567890";
+        for digits in [small, large] {
+            let padded = json!(format!("0{}", digits));
+            let number: Value = serde_json::from_str(digits).unwrap();
+            assert!(!cmp.equal(&padded, &json!(digits)), "{}", digits);
+            assert!(cmp.equal(&padded, &number), "{}", digits);
+            assert!(
+                cmp.lt(&json!(format!("{}0", digits)), &json!("9")),
+                "{}",
+                digits
+            );
+            assert!(
+                cmp.gt(&json!(format!("{}0", digits)), &json!(9)),
+                "{}",
+                digits
+            );
+        }
+
+        let cmp = Comparator::default();
+        assert!(cmp.equal(&big, &json!(9007199254740992_u64)));
+    }
+
+    #[test]
+    fn test_key() {
+        let cmp = Comparator::default();
+        let pairs = vec![
+            (json!(1), json!(1.0), true),
+            (json!(0), json!(-0.0), true),
+            (json!([1, {"a": 2}]), json!([1.0, {"a": 2}]), true),
+            (json!(1), json!("1"), false),
+            (json!("a"), json!("A"), false),
+        ];
+        for (first, second, same) in pairs {
+            assert_eq!(cmp.same(&first, &second), same);
+            assert_eq!(cmp.key(&first) == cmp.key(&second), same);
+        }
+        let insensitive = cmp.case_insensitive(true);
+        assert_eq!(insensitive.key(&json!("a")), insensitive.key(&json!("A")));
+    }
+}
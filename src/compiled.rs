@@ -0,0 +1,212 @@
+//! Rules parsed once and evaluated many times
+//!
+//! Applying a rule parses it before evaluating it. When the same rule is
+//! evaluated against many documents, [`Engine::compile()`] does the parsing
+//! (and any policy check) once up front, producing a [`CompiledRule`].
+//!
+//! A compiled rule borrows the JSON it was compiled from, to avoid copying
+//! its literals, but not the data it is evaluated against or the engine
+//! that compiled it. Compiled rules are `Send` and `Sync`, so a rule
+//! compiled from a `'static` value can be cached in a `static` and shared
+//! between threads.
+//!
+//! Rules loaded at runtime, e.g. from a request or a database, can be
+//! compiled with [`Engine::compile_owned()`] instead, or detached from the
+//! JSON they borrow with [`CompiledRule::into_owned()`]. Their literals are
+//! copied into a `CompiledRule<'static>`, which can be cached for longer
+//! than the JSON lives.
+
+use std::borrow::Cow;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::context::Context;
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::telemetry;
+use crate::value::{Parsed, ParsedOwned};
+
+/// A parsed rule, ready to be evaluated
+///
+/// ```rust
+/// use std::sync::OnceLock;
+///
+/// use jsonlogic_rs::{CompiledRule, Engine};
+/// use serde_json::{json, Value};
+///
+/// fn eligibility() -> &'static CompiledRule<'static> {
+///     static RULE: OnceLock<CompiledRule<'static>> = OnceLock::new();
+///     RULE.get_or_init(|| {
+///         // Leaked once, so the rule can borrow it for the life of the
+///         // program
+///         let rule: &'static Value =
+///             Box::leak(Box::new(json!({">=": [{"var": "age"}, 18]})));
+///         Engine::new().compile(rule).unwrap()
+///     })
+/// }
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|age| std::thread::spawn(move || {
+///         eligibility().apply(&json!({"age": age * 10})).unwrap()
+///     }))
+///     .collect();
+/// let results: Vec<Value> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+/// assert_eq!(results, vec![json!(false), json!(false), json!(true), json!(true)]);
+/// ```
+#[derive(Debug)]
+pub struct CompiledRule<'a> {
+    engine: Engine,
+    value: Cow<'a, Value>,
+    parsed: Parsed<'a>,
+}
+impl<'a> CompiledRule<'a> {
+    pub(crate) fn new(engine: &Engine, value: &'a Value) -> Result<Self, Error> {
+        let parsed = {
+            let _span = telemetry::Span::parse();
+            engine.parse(value)
+        }
+        .inspect_err(telemetry::record_error)?;
+        Ok(Self {
+            engine: engine.clone(),
+            value: Cow::Borrowed(value),
+            parsed,
+        })
+    }
+
+    pub(crate) fn new_owned(
+        engine: &Engine,
+        value: Value,
+    ) -> Result<CompiledRule<'static>, Error> {
+        let parsed = CompiledRule::new(engine, &value)?.parsed.into_owned();
+        Ok(CompiledRule::from_parts(
+            engine.clone(),
+            Cow::Owned(value),
+            parsed,
+        ))
+    }
+
+    fn from_parts(
+        engine: Engine,
+        value: Cow<'static, Value>,
+        parsed: ParsedOwned,
+    ) -> CompiledRule<'static> {
+        CompiledRule {
+            engine,
+            value,
+            parsed,
+        }
+    }
+
+    /// The JSON the rule was compiled from
+    pub fn rule(&self) -> &Value {
+        &self.value
+    }
+
+    /// Copy the JSON the rule was compiled from, so that the compiled rule
+    /// no longer borrows it
+    pub fn into_owned(self) -> CompiledRule<'static> {
+        CompiledRule::from_parts(
+            self.engine,
+            Cow::Owned(self.value.into_owned()),
+            self.parsed.into_owned(),
+        )
+    }
+
+    /// Evaluate the rule against some data
+    pub fn apply(&self, data: &Value) -> Result<Value, Error> {
+        let _span = telemetry::Span::apply(&self.value);
+        let ctx = Context::new(&self.engine);
+        self.engine
+            .evaluate_in(&ctx, &self.parsed, data)
+            .inspect_err(telemetry::record_error)
+    }
+
+    /// Evaluate the rule against any serializable data
+    ///
+    /// See [`Engine::apply_serde()`].
+    pub fn apply_serde<T>(&self, data: &T) -> Result<Value, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let data = serde_json::to_value(data).map_err(|err| Error::InvalidData {
+            value: Value::Null,
+            reason: format!("Could not serialize data: {}", err),
+        })?;
+        self.apply(&data)
+    }
+}
+
+#[cfg(test)]
+mod test_compiled {
+    use super::*;
+    use crate::value::Evaluated;
+    use crate::Policy;
+    use serde_json::json;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<Engine>();
+        assert_send_sync::<Parsed<'static>>();
+        assert_send_sync::<Evaluated<'static>>();
+        assert_send_sync::<CompiledRule<'static>>();
+    }
+
+    #[test]
+    fn test_compile() {
+        let rule = json!({"if": [
+            {"<": [{"var": "n"}, 0]}, "negative",
+            {"double": {"var": "n"}},
+        ]});
+        let engine = Engine::new().add_operator("double", |args: &[&Value]| {
+            Ok(json!(args[0].as_f64().unwrap_or(0.0) * 2.0))
+        });
+        let compiled = engine.compile(&rule).unwrap();
+        // The rule doesn't borrow the engine
+        drop(engine);
+
+        assert_eq!(compiled.rule(), &rule);
+        assert_eq!(
+            compiled.apply(&json!({"n": -1})).unwrap(),
+            json!("negative")
+        );
+        assert_eq!(compiled.apply(&json!({"n": 2})).unwrap(), json!(4.0));
+        assert_eq!(
+            compiled
+                .apply_serde(&std::collections::HashMap::from([("n", 3)]))
+                .unwrap(),
+            json!(6.0)
+        );
+    }
+
+    #[test]
+    fn test_compile_owned() {
+        let engine = Engine::new();
+        let compiled: CompiledRule<'static> = {
+            let rule = json!({"in": [{"var": "x"}, ["a", "b"]]});
+            engine.compile(&rule).unwrap().into_owned()
+        };
+        assert_eq!(compiled.apply(&json!({"x": "b"})).unwrap(), json!(true));
+        assert_eq!(compiled.apply(&json!({"x": "c"})).unwrap(), json!(false));
+
+        let compiled = engine
+            .compile_owned(json!({"cat": ["v", {"var": ""}, {"merge": [[1]]}]}))
+            .unwrap();
+        let handle = std::thread::spawn(move || compiled.apply(&json!(2)).unwrap());
+        assert_eq!(handle.join().unwrap(), json!("v21"));
+
+        assert!(engine.compile_owned(json!({"==": [1]})).is_err());
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert!(Engine::new().compile(&json!({"==": [1]})).is_err());
+        let engine = Engine::new().policy(Policy::default().deny("log"));
+        assert!(matches!(
+            engine.compile(&json!({"log": 1})),
+            Err(Error::PolicyViolation { .. })
+        ));
+    }
+}
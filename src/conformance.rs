@@ -0,0 +1,256 @@
+//! Conformance with the official JsonLogic tests
+//!
+//! The [official tests](http://jsonlogic.com/tests.json) are bundled with
+//! the crate, so an [`Engine`] can be checked against them without network
+//! access. Engines with default options pass every test; options and custom
+//! operators that change the behavior of standard operators show up as
+//! divergences, which makes it easy to see exactly which behaviors a
+//! configuration changes.
+
+use serde_json::{json, Value};
+
+use crate::engine::Engine;
+use crate::error::Error;
+
+const OFFICIAL_TESTS: &str = include_str!("../tests/data/tests.json");
+
+/// A test of a rule's result for some data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    /// The index of the test in the array of tests and comments it came from
+    pub index: usize,
+    /// The comment preceding the test, which names the section it's in
+    pub section: Option<String>,
+    pub logic: Value,
+    pub data: Value,
+    pub expected: Value,
+}
+
+/// Parse tests in the format of the official tests
+///
+/// Tests are given as a JSON array of `[logic, data, expected]` triples.
+/// Strings in the array are comments, each naming the section of tests
+/// after it.
+pub fn parse_cases(tests: &Value) -> Result<Vec<Case>, Error> {
+    let items = tests.as_array().ok_or_else(|| Error::InvalidData {
+        value: tests.clone(),
+        reason: "Tests must be an array".into(),
+    })?;
+    let mut section = None;
+    let mut cases = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        match item {
+            Value::String(comment) => {
+                section = Some(comment.trim_start_matches('#').trim().to_string());
+            }
+            Value::Array(triple) if triple.len() == 3 => cases.push(Case {
+                index,
+                section: section.clone(),
+                logic: triple[0].clone(),
+                data: triple[1].clone(),
+                expected: triple[2].clone(),
+            }),
+            _ => {
+                return Err(Error::InvalidData {
+                    value: item.clone(),
+                    reason: format!(
+                    "Item {} is neither a comment nor a [logic, data, expected] test",
+                    index
+                ),
+                })
+            }
+        }
+    }
+    Ok(cases)
+}
+
+/// The bundled official tests
+pub fn official_cases() -> Vec<Case> {
+    let tests = serde_json::from_str(OFFICIAL_TESTS).expect("Bundled tests are JSON");
+    parse_cases(&tests).expect("Bundled tests are valid")
+}
+
+/// How an engine's result for a test differed from the expected one
+#[derive(Debug, Clone, PartialEq)]
+pub enum Divergence {
+    /// The rule evaluated to a different value
+    Result(Value),
+    /// The rule failed to evaluate, with the error's code and message
+    Error { code: String, message: String },
+}
+
+/// The results of running tests against an engine
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// The number of tests that passed
+    pub passed: usize,
+    /// The tests that failed, with how the engine's results differed
+    pub divergences: Vec<(Case, Divergence)>,
+}
+impl Report {
+    /// Whether every test passed
+    pub fn is_conformant(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    /// The report as JSON
+    ///
+    /// ```json
+    /// {
+    ///   "passed": 274,
+    ///   "failed": 1,
+    ///   "divergences": [{
+    ///     "index": 12,
+    ///     "section": "Single operator tests",
+    ///     "logic": {"==": [1, "1"]},
+    ///     "data": {},
+    ///     "expected": true,
+    ///     "actual": false
+    ///   }]
+    /// }
+    /// ```
+    ///
+    /// Tests that failed with an error have an `"error"` object with its
+    /// `"code"` and `"message"` in place of `"actual"`.
+    pub fn to_json(&self) -> Value {
+        let divergences: Vec<Value> = self
+            .divergences
+            .iter()
+            .map(|(case, divergence)| {
+                let mut rv = json!({
+                    "index": case.index,
+                    "section": case.section,
+                    "logic": case.logic,
+                    "data": case.data,
+                    "expected": case.expected,
+                });
+                match divergence {
+                    Divergence::Result(actual) => rv["actual"] = actual.clone(),
+                    Divergence::Error { code, message } => {
+                        rv["error"] = json!({"code": code, "message": message})
+                    }
+                }
+                rv
+            })
+            .collect();
+        json!({
+            "passed": self.passed,
+            "failed": self.divergences.len(),
+            "divergences": divergences,
+        })
+    }
+}
+
+/// Run tests against an engine
+pub fn run_cases(engine: &Engine, cases: &[Case]) -> Report {
+    let mut report = Report {
+        passed: 0,
+        divergences: Vec::new(),
+    };
+    for case in cases {
+        let divergence = match engine.apply(&case.logic, &case.data) {
+            Ok(actual) if actual == case.expected => {
+                report.passed += 1;
+                continue;
+            }
+            Ok(actual) => Divergence::Result(actual),
+            Err(err) => Divergence::Error {
+                code: err.code().into(),
+                message: err.to_string(),
+            },
+        };
+        report.divergences.push((case.clone(), divergence));
+    }
+    report
+}
+
+/// Run the bundled official tests against an engine
+///
+/// ```rust
+/// use jsonlogic_rs::conformance::{run, Divergence};
+/// use jsonlogic_rs::{Engine, Policy};
+///
+/// assert!(run(&Engine::new()).is_conformant());
+///
+/// // Denying an operator changes the results of the tests that use it
+/// let engine = Engine::new().policy(Policy::default().deny("substr"));
+/// let report = run(&engine);
+/// assert!(!report.is_conformant());
+/// for (case, divergence) in &report.divergences {
+///     assert!(case.logic.get("substr").is_some());
+///     assert!(matches!(divergence, Divergence::Error { code, .. } if code == "policy_violation"));
+/// }
+/// ```
+pub fn run(engine: &Engine) -> Report {
+    run_cases(engine, &official_cases())
+}
+
+#[cfg(test)]
+mod test_conformance {
+    use super::*;
+    use crate::DivisionByZero;
+
+    #[test]
+    fn test_parse_cases() {
+        let cases = parse_cases(&json!([
+            [{"var": "a"}, {"a": 1}, 1],
+            "# Logic",
+            [{"!": true}, null, false],
+        ]))
+        .unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].section, None);
+        assert_eq!(cases[1].index, 2);
+        assert_eq!(cases[1].section.as_deref(), Some("Logic"));
+
+        assert!(parse_cases(&json!({})).is_err());
+        assert!(parse_cases(&json!([[1, 2]])).is_err());
+        assert!(parse_cases(&json!([3])).is_err());
+    }
+
+    #[test]
+    fn test_official() {
+        let report = run(&Engine::new());
+        assert_eq!(report.passed, official_cases().len());
+        assert!(report.is_conformant());
+        assert_eq!(report.to_json()["failed"], json!(0));
+    }
+
+    #[test]
+    fn test_divergences() {
+        let cases = parse_cases(&json!([
+            "# Arithmetic",
+            [{"/": [1, 0]}, null, null],
+            [{"+": [1, 1]}, null, 2],
+            [{"+": [1, 1]}, null, 3],
+        ]))
+        .unwrap();
+        let engine = Engine::new().division_by_zero(DivisionByZero::Error);
+        let report = run_cases(&engine, &cases);
+        assert_eq!(report.passed, 1);
+        assert_eq!(
+            report.to_json()["divergences"],
+            json!([
+                {
+                    "index": 1,
+                    "section": "Arithmetic",
+                    "logic": {"/": [1, 0]},
+                    "data": null,
+                    "expected": null,
+                    "error": {
+                        "code": "invalid_argument",
+                        "message": report.to_json()["divergences"][0]["error"]["message"],
+                    },
+                },
+                {
+                    "index": 3,
+                    "section": "Arithmetic",
+                    "logic": {"+": [1, 1]},
+                    "data": null,
+                    "expected": 3,
+                    "actual": 2,
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Per-evaluation state
+//!
+//! A context is created for each call to `Engine::apply()` and threaded
+//! through evaluation. It carries the engine's options along with counters
+//! that are surfaced as [`Stats`].
+
+use std::cell::Cell;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::engine::Engine;
+use crate::source::DataSource;
+
+/// Statistics collected while evaluating a rule
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Stats {
+    /// The number of operators invoked, including those invoked repeatedly
+    /// by operators like `map` or `filter`
+    pub operations: u64,
+    /// The deepest nesting of operators reached during evaluation
+    pub max_depth: usize,
+    /// The number of keys looked up in the data by `var`, `missing`, and
+    /// `missing_some`
+    pub data_lookups: u64,
+    /// Wall-clock time spent parsing and evaluating the rule
+    pub elapsed: Duration,
+}
+
+pub struct Context<'e> {
+    pub(crate) engine: &'e Engine,
+    /// A source to read the data at the root from, in place of the data
+    /// passed to operators
+    ///
+    /// Evaluation always needs a `Value` to pass around as the data, so a
+    /// placeholder is passed at the root, and data operators reading from
+    /// it are redirected to the source. Operators like `map` pass other
+    /// values as the data, which are read directly.
+    source: Option<&'e dyn DataSource>,
+    /// The number of expressions being evaluated against something other
+    /// than the data at the root, like the items of a `map`
+    item_scopes: Cell<usize>,
+    /// The number of arrays and templates being evaluated
+    containers: Cell<usize>,
+    operations: Cell<u64>,
+    depth: Cell<usize>,
+    max_depth: Cell<usize>,
+    data_lookups: Cell<u64>,
+    /// The argument of the top-level operation that determined the result
+    branch: Cell<Option<usize>>,
+}
+impl<'e> Context<'e> {
+    pub fn new(engine: &'e Engine) -> Self {
+        Self {
+            engine,
+            source: None,
+            item_scopes: Cell::new(0),
+            containers: Cell::new(0),
+            operations: Cell::new(0),
+            depth: Cell::new(0),
+            max_depth: Cell::new(0),
+            data_lookups: Cell::new(0),
+            branch: Cell::new(None),
+        }
+    }
+
+    /// Read the data at the root from a source rather than a value
+    ///
+    /// Rules must then be evaluated with a placeholder, like `null`, as
+    /// their data.
+    pub fn with_source(engine: &'e Engine, source: &'e dyn DataSource) -> Self {
+        Self {
+            source: Some(source),
+            ..Self::new(engine)
+        }
+    }
+
+    /// The source to read from, given the data an operator was passed
+    pub fn source<'d>(&'d self, data: &'d Value) -> &'d dyn DataSource {
+        match self.source {
+            Some(source) if self.is_root() => source,
+            _ => data,
+        }
+    }
+
+    /// Evaluate an expression against something other than the data at the
+    /// root, like an item of a `map` or the accumulator of a `reduce`
+    pub fn in_item<T>(&self, evaluate: impl FnOnce() -> T) -> T {
+        self.item_scopes.set(self.item_scopes.get() + 1);
+        let rv = evaluate();
+        self.item_scopes.set(self.item_scopes.get() - 1);
+        rv
+    }
+
+    /// Whether operators are being passed the data at the root
+    pub fn is_root(&self) -> bool {
+        self.item_scopes.get() == 0
+    }
+
+    /// Evaluate the items of an array or the values of a template
+    ///
+    /// Operators in a container aren't at the top level of the rule, even
+    /// though no operator encloses them.
+    pub fn in_container<T>(&self, evaluate: impl FnOnce() -> T) -> T {
+        self.containers.set(self.containers.get() + 1);
+        let rv = evaluate();
+        self.containers.set(self.containers.get() - 1);
+        rv
+    }
+
+    /// Record the start of an operator's evaluation
+    pub fn enter(&self) {
+        let depth = self.depth.get() + 1;
+        self.depth.set(depth);
+        self.max_depth.set(self.max_depth.get().max(depth));
+        self.operations.set(self.operations.get() + 1);
+    }
+
+    /// Record the end of an operator's evaluation
+    pub fn exit(&self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+
+    /// Record a lookup of a key in the data
+    pub fn record_lookup(&self) {
+        self.data_lookups.set(self.data_lookups.get() + 1);
+    }
+
+    /// Record the argument that determined the result of an operator like
+    /// `if`, if the operator is at the top level of the rule
+    pub fn record_branch(&self, index: usize) {
+        if self.depth.get() == 1 && self.containers.get() == 0 {
+            self.branch.set(Some(index));
+        }
+    }
+
+    /// The argument that determined the result of the rule, if its
+    /// top-level operator records one
+    pub fn branch(&self) -> Option<usize> {
+        self.branch.get()
+    }
+
+    pub fn stats(&self, elapsed: Duration) -> Stats {
+        Stats {
+            operations: self.operations.get(),
+            max_depth: self.max_depth.get(),
+            data_lookups: self.data_lookups.get(),
+            elapsed,
+        }
+    }
+}
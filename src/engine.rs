@@ -0,0 +1,1318 @@
+//! Engine configuration
+//!
+//! The engine holds the options that govern how rules are evaluated. The
+//! free `apply()` function uses the [`default_engine()`], which has default
+//! options, matching the historical behavior of the crate, unless another
+//! engine was set with [`set_default_engine()`].
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::audit::{self, data_hash, rule_hash, AuditRecord};
+use crate::compare::Comparator;
+use crate::compiled::CompiledRule;
+use crate::conformance::{self, Report};
+use crate::context::{Context, Stats};
+use crate::error::Error;
+use crate::explain::{self, Failure};
+use crate::lint::{self, LintWarning};
+#[cfg(feature = "money")]
+use crate::op::ExchangeRates;
+use crate::op::{self, CustomOperator, Stream};
+use crate::packs::{self, OperatorPack, Packs};
+#[cfg(feature = "native-plugins")]
+use crate::plugins::native::NativePlugin;
+#[cfg(feature = "script-plugins")]
+use crate::plugins::script::ScriptPlugin;
+#[cfg(feature = "wasm-plugins")]
+use crate::plugins::wasm::WasmPlugin;
+use crate::policy::Policy;
+use crate::redact::Redaction;
+use crate::shrink::{self, Shrunk};
+use crate::snapshot;
+use crate::source::DataSource;
+use crate::telemetry;
+use crate::value::{to_float_value, to_number_value, Parsed};
+
+/// Behavior of `/` and `%` when the divisor is zero, or the result is
+/// otherwise too large to represent, as for `{"/": [1e308, 1e-308]}`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DivisionByZero {
+    /// Return an `InvalidArgument` error (the default)
+    #[default]
+    Error,
+    /// Evaluate to `null`.
+    ///
+    /// JavaScript produces `Infinity` or `NaN` for these operations, which
+    /// cannot be represented in JSON and which `JSON.stringify()` renders
+    /// as `null`, so this is the closest analogue to the reference
+    /// implementation.
+    Null,
+    /// Evaluate to `null` if the result would be infinite, as for
+    /// `{"/": [1, 0]}` or `{"/": [1e308, 1e-308]}`, and return an `InvalidArgument` error if it would
+    /// be `NaN`, as for `{"/": [0, 0]}` or any `%` by zero.
+    ///
+    /// This keeps the `Infinity` of the reference implementation as `null`,
+    /// as `JSON.stringify()` would, while still treating results that
+    /// aren't numbers at all as errors.
+    InfinityAsNull,
+}
+
+/// The decimal separator used by numbers written as strings in the data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecimalSeparator {
+    /// A decimal point, as in "1234.56" (the default)
+    #[default]
+    Point,
+    /// A decimal comma, as in "1.234,56" or "1234,56"
+    ///
+    /// This is the convention in much of continental Europe. Any `.` in a
+    /// number string is then treated as a thousands separator and ignored,
+    /// so e.g. "1.5" is read as 15.
+    Comma,
+}
+
+/// How whole-number results of numeric operators are represented
+///
+/// JSON doesn't distinguish `2` from `2.0`, but many consumers of it do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberOutput {
+    /// Represent whole numbers as integers where possible, so that e.g.
+    /// `{"/": [4.0, 2]}` is `2` (the default)
+    #[default]
+    Integer,
+    /// Always represent results as floats, so that e.g. `{"+": [1, 1]}` is
+    /// `2.0`
+    Float,
+    /// Represent whole numbers as integers only if all of the operator's
+    /// arguments were integers, so that e.g. `{"+": [1, 1]}` is `2`, but
+    /// `{"+": [1.5, 0.5]}` is `2.0`. Non-numeric arguments, like numbers
+    /// written as strings, produce floats.
+    Preserve,
+}
+
+/// How `var` and the other data operators match path segments that are
+/// numbers against the keys of objects
+///
+/// Objects keyed by IDs may have been written with numbers formatted
+/// differently from the paths used to look them up, as with a key of `"7"`
+/// and a path of `"ids.07"`. Array indices are unaffected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumericKeys {
+    /// Only look up keys exactly as they're written in the path (the
+    /// default)
+    #[default]
+    Exact,
+    /// Look up keys as they're written, and if there's no value there,
+    /// with numbers written as JSON would write them, so that `"07"`,
+    /// `"7.0"`, and `"7e0"` find the key `"7"`
+    ExactFirst,
+    /// Look up keys with numbers written as JSON would write them, and if
+    /// there's no value there, as they're written in the path
+    NumberFirst,
+}
+
+/// Which behavior to follow where the reference implementation,
+/// [json-logic-js](https://github.com/jwadhams/json-logic-js), deliberately
+/// diverges from the specification
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compatibility {
+    /// Follow the specification (the default)
+    #[default]
+    Spec,
+    /// Accept the rules json-logic-js accepts, evaluating them as it does
+    ///
+    /// `!` and `!!` may be given any number of arguments, rather than
+    /// exactly one. They evaluate all of them, but only the first is
+    /// negated or converted to a boolean, and no arguments at all are
+    /// treated like `null`, so that `{"!": [0, 1]}` and `{"!": []}` are
+    /// both `true`.
+    ///
+    /// `if` and its alias `?:` accept a single argument without wrapping it
+    /// in an array, as in `{"if": {"var": "flag"}}`, in either mode, as the
+    /// reference implementation does.
+    Reference,
+}
+
+/// How `cat` converts objects and arrays to strings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CatCoercion {
+    /// Convert them as JavaScript does, so that objects are
+    /// `"[object Object]"` and arrays are their items joined with commas,
+    /// as in `"1,2"` (the default)
+    #[default]
+    Js,
+    /// Serialize them as JSON, so that `{"a": 1}` is `"{\"a\":1}"` and
+    /// `[1, 2]` is `"[1,2]"`
+    Json,
+    /// Fail with an `InvalidArgument` error
+    Error,
+}
+
+/// Whether a numeric operator's argument was an integer, or an array of them
+fn is_integral(value: &Value) -> bool {
+    match value {
+        Value::Number(n) => n.is_i64() || n.is_u64(),
+        Value::Array(items) => items.iter().all(is_integral),
+        _ => false,
+    }
+}
+
+/// The size of a value, as limited by [`Engine::max_result_size()`]
+pub(crate) fn result_size(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.len(),
+        Value::Array(items) => {
+            items.len() + items.iter().map(result_size).sum::<usize>()
+        }
+        Value::Object(obj) => {
+            obj.len()
+                + obj
+                    .iter()
+                    .map(|(key, val)| key.len() + result_size(val))
+                    .sum::<usize>()
+        }
+        _ => 0,
+    }
+}
+
+/// The default key marking rules embedded in documents, for
+/// [`Engine::apply_all_paths()`]
+pub const LOGIC_MARKER: &str = "$logic";
+
+/// The engine used by the free functions, like [`apply()`](crate::apply)
+static DEFAULT_ENGINE: OnceLock<Engine> = OnceLock::new();
+
+/// The engine used by the free functions, like [`apply()`](crate::apply)
+///
+/// This has default options unless [`set_default_engine()`] was called
+/// before it was first used.
+pub fn default_engine() -> &'static Engine {
+    DEFAULT_ENGINE.get_or_init(Engine::new)
+}
+
+/// Set the engine used by the free functions, like [`apply()`](crate::apply)
+///
+/// This lets an application configure options and custom operators once,
+/// at startup, and keep using the free functions everywhere else. The
+/// default engine can only be set once, and only before it's first used,
+/// whether by a free function or by [`default_engine()`], so that every
+/// evaluation in the process sees the same engine. Otherwise, the engine is
+/// returned as an error.
+///
+/// ```rust
+/// use jsonlogic_rs::{set_default_engine, DivisionByZero, Engine};
+/// use serde_json::json;
+///
+/// set_default_engine(Engine::new().division_by_zero(DivisionByZero::Null)).unwrap();
+/// assert_eq!(
+///     jsonlogic_rs::apply(&json!({"/": [1, 0]}), &json!(null)).unwrap(),
+///     json!(null)
+/// );
+/// // It can't be replaced once it's in use
+/// assert!(set_default_engine(Engine::new()).is_err());
+/// ```
+#[allow(clippy::result_large_err)]
+pub fn set_default_engine(engine: Engine) -> Result<(), Engine> {
+    DEFAULT_ENGINE.set(engine)
+}
+
+/// A configured JsonLogic evaluator
+///
+/// ```rust
+/// use jsonlogic_rs::{DivisionByZero, Engine};
+/// use serde_json::json;
+///
+/// let engine = Engine::new().division_by_zero(DivisionByZero::Null);
+/// assert_eq!(
+///     engine.apply(&json!({"/": [1, 0]}), &json!(null)).unwrap(),
+///     json!(null)
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Engine {
+    pub(crate) division_by_zero: DivisionByZero,
+    pub(crate) decimal_separator: DecimalSeparator,
+    pub(crate) pure_only: bool,
+    pub(crate) policy: Option<Policy>,
+    pub(crate) max_result_size: Option<usize>,
+    pub(crate) number_output: NumberOutput,
+    pub(crate) evaluate_objects: bool,
+    pub(crate) numeric_keys: NumericKeys,
+    pub(crate) compatibility: Compatibility,
+    pub(crate) cat_coercion: CatCoercion,
+    pub(crate) comparator: Comparator,
+    #[cfg(feature = "money")]
+    pub(crate) exchange_rates: Option<ExchangeRates>,
+    pub(crate) custom_operators: HashMap<String, Arc<CustomOperator>>,
+    /// Namespaces of operators, like `acme` in `acme:discount_rate`
+    pub(crate) namespaces: BTreeSet<String>,
+    /// Deprecated operators, with advice on what to use instead
+    pub(crate) deprecated_operators: HashMap<String, String>,
+    pub(crate) redaction: Redaction,
+    /// The key marking rules embedded in documents, if not `$logic`
+    pub(crate) logic_marker: Option<String>,
+    /// Operator packs added with `add_pack()`
+    pub(crate) packs: Packs,
+}
+impl Engine {
+    /// Create an engine with default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the behavior of `/` and `%` when dividing by zero
+    pub fn division_by_zero(mut self, behavior: DivisionByZero) -> Self {
+        self.division_by_zero = behavior;
+        self
+    }
+
+    /// Set the decimal separator used when converting strings to numbers
+    ///
+    /// This applies to the arithmetic and comparison operators, `max`,
+    /// `min`, `max_by`, `min_by`, and `toNumber`, so that e.g. with
+    /// `DecimalSeparator::Comma`, `{"+": ["1.234,5", 1]}` is `1235.5`. It
+    /// also applies to `==` and `!=` where they convert a string to compare
+    /// it with a number, but not to strings compared with one another.
+    pub fn decimal_separator(mut self, separator: DecimalSeparator) -> Self {
+        self.decimal_separator = separator;
+        self
+    }
+
+    /// Set how whole-number results of numeric operators are represented
+    ///
+    /// This applies to the arithmetic operators, `max`, `min`, `toNumber`,
+    /// and `measure`.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{Engine, NumberOutput};
+    /// use serde_json::json;
+    ///
+    /// let rule = json!({"*": [{"var": "price"}, 2]});
+    /// let data = json!({"price": 1.5});
+    /// assert_eq!(Engine::new().apply(&rule, &data).unwrap(), json!(3));
+    ///
+    /// let engine = Engine::new().number_output(NumberOutput::Preserve);
+    /// assert_eq!(engine.apply(&rule, &data).unwrap(), json!(3.0));
+    /// ```
+    pub fn number_output(mut self, output: NumberOutput) -> Self {
+        self.number_output = output;
+        self
+    }
+
+    /// Evaluate the values of objects that aren't operations
+    ///
+    /// By default, an object in a rule that isn't an operation, like one
+    /// with multiple keys, is used as it is. With this set, each of its
+    /// values is evaluated, so that an object can serve as a template for
+    /// the result.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::json;
+    ///
+    /// let rule = json!({
+    ///     "name": {"var": "user.name"},
+    ///     "adult": {">=": [{"var": "user.age"}, 18]},
+    ///     "source": "signup",
+    /// });
+    /// let data = json!({"user": {"name": "Ada", "age": 36}});
+    /// assert_eq!(
+    ///     Engine::new().evaluate_objects(true).apply(&rule, &data).unwrap(),
+    ///     json!({"name": "Ada", "adult": true, "source": "signup"})
+    /// );
+    /// ```
+    pub fn evaluate_objects(mut self, evaluate: bool) -> Self {
+        self.evaluate_objects = evaluate;
+        self
+    }
+
+    /// Set the key marking rules embedded in documents for
+    /// [`apply_all_paths()`](Self::apply_all_paths), instead of `$logic`
+    pub fn logic_marker(mut self, marker: &str) -> Self {
+        self.logic_marker = Some(marker.into());
+        self
+    }
+
+    /// Set how path segments that are numbers are matched against object
+    /// keys
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{Engine, NumericKeys};
+    /// use serde_json::json;
+    ///
+    /// let rule = json!({"var": "users.007.name"});
+    /// let data = json!({"users": {"7": {"name": "Ada"}}});
+    /// assert_eq!(Engine::new().apply(&rule, &data).unwrap(), json!(null));
+    ///
+    /// let engine = Engine::new().numeric_keys(NumericKeys::ExactFirst);
+    /// assert_eq!(engine.apply(&rule, &data).unwrap(), json!("Ada"));
+    /// ```
+    pub fn numeric_keys(mut self, numeric_keys: NumericKeys) -> Self {
+        self.numeric_keys = numeric_keys;
+        self
+    }
+
+    /// Represent a numeric operator's result per the engine's number output
+    ///
+    /// Results that aren't finite, like those of operations overflowing,
+    /// can't be represented in JSON, and are an `InvalidArgument` error.
+    pub(crate) fn number_value(
+        &self,
+        operation: &str,
+        number: f64,
+        args: &[&Value],
+    ) -> Result<Value, Error> {
+        if !number.is_finite() {
+            return Err(Error::InvalidArgument {
+                value: Value::Array(args.iter().map(|arg| (*arg).clone()).collect()),
+                operation: operation.into(),
+                reason: format!("Result {} is not a finite number", number),
+            });
+        }
+        match self.number_output {
+            NumberOutput::Integer => to_number_value(number),
+            NumberOutput::Float => to_float_value(number),
+            NumberOutput::Preserve if args.iter().all(|arg| is_integral(arg)) => {
+                to_number_value(number)
+            }
+            NumberOutput::Preserve => to_float_value(number),
+        }
+    }
+
+    /// Follow the reference implementation, rather than the specification,
+    /// where the two differ
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{Compatibility, Engine};
+    /// use serde_json::json;
+    ///
+    /// let rule = json!({"!": [{"var": "a"}, {"var": "b"}]});
+    /// assert!(Engine::new().apply(&rule, &json!({"a": 0})).is_err());
+    ///
+    /// let engine = Engine::new().compatibility(Compatibility::Reference);
+    /// assert_eq!(engine.apply(&rule, &json!({"a": 0})).unwrap(), json!(true));
+    /// ```
+    pub fn compatibility(mut self, compatibility: Compatibility) -> Self {
+        self.compatibility = compatibility;
+        self
+    }
+
+    /// Set how `cat` converts objects and arrays to strings
+    ///
+    /// Other values are converted as JavaScript does with any of these, so
+    /// e.g. `1.5` is `"1.5"` and `null` is `"null"`.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{CatCoercion, Engine};
+    /// use serde_json::json;
+    ///
+    /// let rule = json!({"cat": ["user: ", {"var": "user"}]});
+    /// let data = json!({"user": {"id": 7}});
+    /// assert_eq!(
+    ///     Engine::new().apply(&rule, &data).unwrap(),
+    ///     json!("user: [object Object]")
+    /// );
+    ///
+    /// let engine = Engine::new().cat_coercion(CatCoercion::Json);
+    /// assert_eq!(engine.apply(&rule, &data).unwrap(), json!(r#"user: {"id":7}"#));
+    /// ```
+    pub fn cat_coercion(mut self, coercion: CatCoercion) -> Self {
+        self.cat_coercion = coercion;
+        self
+    }
+
+    /// Reject rules that use operators with side effects, like `log`
+    ///
+    /// With this set, evaluation is deterministic and free of side effects,
+    /// which makes it suitable for running untrusted rules. Rules using
+    /// impure operators fail with an `InvalidOperation` error.
+    /// Custom operators are not checked, so they must be pure as well for
+    /// this guarantee to hold.
+    pub fn pure_only(mut self, pure_only: bool) -> Self {
+        self.pure_only = pure_only;
+        self
+    }
+
+    /// Compare values with a [`Comparator`] other than the default, which
+    /// follows JavaScript
+    ///
+    /// The comparator is used by every operator that compares values, from
+    /// `==` and `<` to `in` and `all_distinct`.
+    pub fn comparator(mut self, comparator: Comparator) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Compare and combine amounts of money in different currencies, with
+    /// the `money` feature's operators
+    ///
+    /// Without exchange rates, these operators fail with an
+    /// `InvalidArgument` error for amounts in different currencies.
+    #[cfg(feature = "money")]
+    pub fn exchange_rates(mut self, rates: ExchangeRates) -> Self {
+        self.exchange_rates = Some(rates);
+        self
+    }
+
+    /// Restrict the rules this engine will run
+    ///
+    /// This is also where limits on a rule's size and nesting depth are
+    /// configured, so that hostile rules are rejected cheaply.
+    ///
+    /// Rules are checked against the policy in full before being parsed,
+    /// and violations are reported as `PolicyViolation` errors identifying
+    /// the offending operator and its location in the rule as a JSON
+    /// Pointer.
+    pub fn policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Limit the size of the strings and arrays built by `cat`, `merge`,
+    /// and `map`
+    ///
+    /// A string's size is its length in bytes. An array's size is its
+    /// number of items plus the sizes of any strings, arrays, and objects
+    /// in it, and an object's is its number of entries plus the lengths of
+    /// its keys and the sizes of its values. Operators stop building their
+    /// result as soon as it exceeds the maximum, failing with a
+    /// `ResultTooLarge` error, so a rule can't exhaust memory by e.g.
+    /// concatenating strings for every item of a large array.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{Engine, Error};
+    /// use serde_json::json;
+    ///
+    /// let engine = Engine::new().max_result_size(10);
+    /// let rule = json!({"map": [{"var": "names"}, {"cat": ["Hello, ", {"var": ""}]}]});
+    /// assert_eq!(
+    ///     engine.apply(&rule, &json!({"names": ["Al"]})).unwrap(),
+    ///     json!(["Hello, Al"])
+    /// );
+    /// assert!(matches!(
+    ///     engine.apply(&rule, &json!({"names": ["Al", "Bo"]})),
+    ///     Err(Error::ResultTooLarge { .. })
+    /// ));
+    /// ```
+    pub fn max_result_size(mut self, size: usize) -> Self {
+        self.max_result_size = Some(size);
+        self
+    }
+
+    /// Check the size of an operator's result against the maximum
+    pub(crate) fn check_result_size(
+        &self,
+        operator: &str,
+        size: usize,
+    ) -> Result<(), Error> {
+        match self.max_result_size {
+            Some(max) if size > max => Err(Error::ResultTooLarge {
+                operator: operator.into(),
+                size,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Register a custom operator
+    ///
+    /// The operator's arguments are evaluated before it is called, and it
+    /// may accept any number of them, except those with an implementation
+    /// registered by [`add_operator_arity()`](Self::add_operator_arity).
+    /// Built-in operators take precedence, so
+    /// registering an operator with the same name as one has no effect.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{Engine, Error};
+    /// use serde_json::{json, Value};
+    ///
+    /// let engine = Engine::new().add_operator("double", |args: &[&Value]| {
+    ///     args.first()
+    ///         .and_then(|v| v.as_f64())
+    ///         .map(|n| json!(n * 2.0))
+    ///         .ok_or_else(|| Error::InvalidArgument {
+    ///             value: json!(args),
+    ///             operation: "double".into(),
+    ///             reason: "double takes a number".into(),
+    ///         })
+    /// });
+    /// assert_eq!(
+    ///     engine.apply(&json!({"double": {"var": "a"}}), &json!({"a": 2})).unwrap(),
+    ///     json!(4.0)
+    /// );
+    /// ```
+    pub fn add_operator<F>(self, name: &str, operator: F) -> Self
+    where
+        F: Fn(&[&Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.update_operator(name, |op| op.set_fallback(Arc::new(operator)))
+    }
+
+    /// Register a custom operator taking exactly `num_args` arguments
+    ///
+    /// The same operator can be registered with several numbers of
+    /// arguments, and is dispatched to the implementation matching the
+    /// number it is given. If it is also registered with
+    /// [`add_operator()`](Self::add_operator), that implementation handles
+    /// any other number of arguments. Otherwise, rules giving it a number
+    /// without an implementation fail to parse with a
+    /// [`WrongArgumentCount`](Error::WrongArgumentCount) error.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::{json, Value};
+    ///
+    /// fn between(value: &Value, min: &Value, max: &Value, inclusive: bool) -> Value {
+    ///     let (value, min, max) = (value.as_f64(), min.as_f64(), max.as_f64());
+    ///     json!(match inclusive {
+    ///         true => min <= value && value <= max,
+    ///         false => min < value && value < max,
+    ///     })
+    /// }
+    ///
+    /// let engine = Engine::new()
+    ///     .add_operator_arity("between", 3, |args: &[&Value]| {
+    ///         Ok(between(args[0], args[1], args[2], true))
+    ///     })
+    ///     .add_operator_arity("between", 4, |args: &[&Value]| {
+    ///         Ok(between(args[0], args[1], args[2], args[3] == "inclusive"))
+    ///     });
+    ///
+    /// let apply = |rule| engine.apply(&rule, &json!(null));
+    /// assert_eq!(apply(json!({"between": [5, 1, 5]})).unwrap(), json!(true));
+    /// assert_eq!(apply(json!({"between": [5, 1, 5, "exclusive"]})).unwrap(), json!(false));
+    /// assert!(apply(json!({"between": [5, 1]})).is_err());
+    /// ```
+    pub fn add_operator_arity<F>(self, name: &str, num_args: usize, operator: F) -> Self
+    where
+        F: Fn(&[&Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.update_operator(name, |op| op.add_overload(num_args, Arc::new(operator)))
+    }
+
+    /// Modify a custom operator, registering it if needed
+    fn update_operator(
+        mut self,
+        name: &str,
+        update: impl FnOnce(&mut CustomOperator),
+    ) -> Self {
+        if let Some((namespace, _)) = op::split_namespace(name) {
+            self.namespaces.insert(namespace.into());
+        }
+        let operator = self
+            .custom_operators
+            .entry(name.into())
+            .or_insert_with(|| Arc::new(CustomOperator::new(name)));
+        update(Arc::make_mut(operator));
+        self
+    }
+
+    /// Register a namespace for operators, like `acme` in `acme:discount_rate`
+    ///
+    /// Custom operators from third parties can be named within a namespace,
+    /// so that they won't collide with each other or with operators added
+    /// to the specification later. Adding an operator with a namespaced name
+    /// registers its namespace, so this is only needed for namespaces whose
+    /// operators aren't all known to the engine, as when rules are checked
+    /// with an engine that doesn't implement them.
+    ///
+    /// Once any namespace is registered, rules using a namespaced operator
+    /// the engine doesn't have fail to parse, with an
+    /// [`InvalidOperation`](Error::InvalidOperation) error saying whether
+    /// the namespace or the operator within it is unknown. Otherwise, as
+    /// with any unknown operator, they would be used as literal objects.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{Engine, Error};
+    /// use serde_json::{json, Value};
+    ///
+    /// let engine = Engine::new()
+    ///     .add_operator("acme:discount_rate", |_: &[&Value]| Ok(json!(0.1)));
+    /// assert!(engine.validate(&json!({"acme:discount_rate": []})).is_ok());
+    ///
+    /// match engine.validate(&json!({"acne:discount_rate": []})) {
+    ///     Err(Error::InvalidOperation { key, reason }) => {
+    ///         assert_eq!(key, "acne:discount_rate");
+    ///         assert_eq!(reason, r#"Unknown operator namespace "acne""#);
+    ///     }
+    ///     other => panic!("expected an unknown namespace, got {:?}", other),
+    /// }
+    /// ```
+    pub fn register_namespace(mut self, namespace: &str) -> Self {
+        self.namespaces.insert(namespace.into());
+        self
+    }
+
+    /// Add a pack of operators
+    ///
+    /// The pack registers its operators on the engine, and is listed by
+    /// [`packs()`](Self::packs) and tested by
+    /// [`verify_packs()`](Self::verify_packs). Adding a pack with the same
+    /// name as one added earlier replaces it in the list, though operators
+    /// registered by the earlier pack alone are kept.
+    pub fn add_pack<P>(self, pack: P) -> Self
+    where
+        P: OperatorPack + 'static,
+    {
+        let mut engine = pack.register(self);
+        engine.packs.add(Arc::new(pack));
+        engine
+    }
+
+    /// The names of the engine's operator packs
+    ///
+    /// These are the packs of the optional features the crate was built
+    /// with, followed by those added with [`add_pack()`](Self::add_pack).
+    pub fn packs(&self) -> Vec<&str> {
+        op::FEATURE_OPERATOR_MAPS
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(self.packs.iter().map(OperatorPack::name))
+            .collect()
+    }
+
+    /// Run the tests of each of the engine's operator packs
+    ///
+    /// Returns each pack's name with the report of its tests, in the order
+    /// of [`packs()`](Self::packs). A pack may fail its tests on an engine
+    /// whose options change the results of its operators, like
+    /// [`number_output()`](Self::number_output), or where a policy denies
+    /// them.
+    pub fn verify_packs(&self) -> Vec<(String, Report)> {
+        let feature_packs = packs::feature_packs();
+        feature_packs
+            .iter()
+            .map(|pack| pack as &dyn OperatorPack)
+            .chain(self.packs.iter())
+            .map(|pack| {
+                let report = conformance::run_cases(self, &pack.tests());
+                (pack.name().to_string(), report)
+            })
+            .collect()
+    }
+
+    /// Mark an operator as deprecated
+    ///
+    /// Rules using the operator are still valid and evaluate as before, but
+    /// [`lint()`](Self::lint) reports each use as a
+    /// [`LintKind::DeprecatedOperator`](crate::LintKind::DeprecatedOperator)
+    /// warning including `note`, e.g. to say what to use instead. Both
+    /// built-in and custom operators can be deprecated.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{Engine, LintKind};
+    /// use serde_json::{json, Value};
+    ///
+    /// let engine = Engine::new()
+    ///     .add_operator("legacy_score", |_: &[&Value]| Ok(json!(1)))
+    ///     .deprecate_operator("legacy_score", "use `score` instead");
+    /// let rule = json!({">": [{"legacy_score": []}, 0]});
+    ///
+    /// assert_eq!(engine.apply(&rule, &json!(null)).unwrap(), json!(true));
+    /// let warnings = engine.lint(&rule);
+    /// assert_eq!(warnings[0].kind, LintKind::DeprecatedOperator);
+    /// assert_eq!(warnings[0].path, "/>/0");
+    /// assert_eq!(
+    ///     warnings[0].message,
+    ///     "`legacy_score` is deprecated: use `score` instead"
+    /// );
+    /// ```
+    pub fn deprecate_operator(mut self, name: &str, note: &str) -> Self {
+        self.deprecated_operators.insert(name.into(), note.into());
+        self
+    }
+
+    /// Redact the value at a path in the data from audit records and
+    /// failure explanations
+    ///
+    /// Paths are written as for `var`, and `*` matches any key or array
+    /// index, as in `"applicants.*.ssn"`. Redacted values are still used
+    /// to evaluate rules, but are replaced with `[REDACTED]` wherever they
+    /// appear in the results of [`audit()`](Self::audit) and
+    /// [`explain_failure()`](Self::explain_failure), including within
+    /// strings derived from them and in error messages.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::json;
+    ///
+    /// let engine = Engine::new().redact("user.ssn");
+    /// let rule = json!({"==": [{"var": "user.ssn"}, "000-00-0000"]});
+    /// let data = json!({"user": {"ssn": "123-45-6789"}});
+    ///
+    /// let failures = engine.explain_failure(&rule, &data).unwrap();
+    /// assert_eq!(
+    ///     failures[0].description,
+    ///     r#"user.ssn "[REDACTED]" !== "000-00-0000""#
+    /// );
+    /// ```
+    pub fn redact(mut self, path: &str) -> Self {
+        self.redaction.add_path(path);
+        self
+    }
+
+    /// Redact values in the data chosen by a function, from audit records
+    /// and failure explanations
+    ///
+    /// The function is given the path to each value in the data, as object
+    /// keys and array indices, along with the value. Values it chooses are
+    /// redacted as with [`redact()`](Self::redact), and aren't looked into
+    /// further.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::{json, Value};
+    ///
+    /// let engine = Engine::new().redact_with(|path: &[String], _: &Value| {
+    ///     path.last().is_some_and(|key| key.ends_with("token"))
+    /// });
+    /// let data = json!({"session": {"auth_token": "abc123"}});
+    /// let record = engine.audit(&json!({"var": "session.auth_token"}), &data);
+    /// assert_eq!(record.result.unwrap(), json!("[REDACTED]"));
+    /// ```
+    pub fn redact_with<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&[String], &Value) -> bool + Send + Sync + 'static,
+    {
+        self.redaction.set_predicate(Arc::new(predicate));
+        self
+    }
+
+    /// Register the operators provided by a WebAssembly plugin
+    ///
+    /// As with [`add_operator()`](Self::add_operator), built-in operators
+    /// take precedence over the plugin's. Operations are run one at a time
+    /// per plugin, so engines sharing a plugin, or threads sharing an
+    /// engine, wait for each other's operations to finish.
+    #[cfg(feature = "wasm-plugins")]
+    pub fn add_wasm_plugin(self, plugin: &WasmPlugin) -> Self {
+        plugin
+            .operator_fns()
+            .fold(self, |engine, (name, operator)| {
+                engine.add_operator(name, operator)
+            })
+    }
+
+    /// Register the operators provided by a native plugin
+    ///
+    /// As with [`add_operator()`](Self::add_operator), built-in operators
+    /// take precedence over the plugin's. The plugin's library stays
+    /// loaded for as long as any engine uses its operators.
+    #[cfg(feature = "native-plugins")]
+    pub fn add_native_plugin(self, plugin: &NativePlugin) -> Self {
+        plugin
+            .operator_fns()
+            .fold(self, |engine, (name, operator)| {
+                engine.add_operator(name, operator)
+            })
+    }
+
+    /// Register the operators provided by a script plugin
+    ///
+    /// As with [`add_operator()`](Self::add_operator), built-in operators
+    /// take precedence over the plugin's.
+    #[cfg(feature = "script-plugins")]
+    pub fn add_script_plugin(self, plugin: &ScriptPlugin) -> Self {
+        plugin
+            .operator_fns()
+            .fold(self, |engine, (name, operator)| {
+                engine.add_operator(name, operator)
+            })
+    }
+
+    /// Run JSONLogic for the given operation and data.
+    pub fn apply(&self, value: &Value, data: &Value) -> Result<Value, Error> {
+        let ctx = Context::new(self);
+        self.apply_in(&ctx, value, data)
+    }
+
+    /// Parse a rule once, to be evaluated many times
+    ///
+    /// The rule is checked against the engine's policy, if any, when it is
+    /// compiled. The compiled rule keeps a copy of the engine's options, so
+    /// it can outlive the engine.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::json;
+    ///
+    /// let rule = json!({"in": [{"var": "country"}, ["US", "CA"]]});
+    /// let compiled = Engine::new().compile(&rule).unwrap();
+    /// assert_eq!(compiled.apply(&json!({"country": "CA"})).unwrap(), json!(true));
+    /// assert_eq!(compiled.apply(&json!({"country": "DE"})).unwrap(), json!(false));
+    /// ```
+    pub fn compile<'a>(&self, value: &'a Value) -> Result<CompiledRule<'a>, Error> {
+        CompiledRule::new(self, value)
+    }
+
+    /// Parse a rule once, copying it so the compiled rule borrows nothing
+    ///
+    /// This is [`Engine::compile()`] for rules that don't outlive the
+    /// request that loaded them, but whose compiled form needs to, e.g. to
+    /// be cached.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{CompiledRule, Engine};
+    /// use serde_json::{json, Value};
+    ///
+    /// let rule: Value = serde_json::from_str(r#"{"<": [{"var": "n"}, 10]}"#).unwrap();
+    /// let compiled: CompiledRule<'static> = Engine::new().compile_owned(rule).unwrap();
+    /// assert_eq!(compiled.apply(&json!({"n": 3})).unwrap(), json!(true));
+    /// ```
+    pub fn compile_owned(&self, value: Value) -> Result<CompiledRule<'static>, Error> {
+        CompiledRule::new_owned(self, value)
+    }
+
+    /// Run JSONLogic, reading data from a [`DataSource`]
+    ///
+    /// Only the paths the rule reads are requested from the source, so
+    /// there is no need to build a JSON document of all the data.
+    pub fn apply_source(
+        &self,
+        value: &Value,
+        source: &dyn DataSource,
+    ) -> Result<Value, Error> {
+        let placeholder = Value::Null;
+        let ctx = Context::with_source(self, source);
+        self.apply_in(&ctx, value, &placeholder)
+    }
+
+    /// Run JSONLogic against any serializable data, like a Rust struct
+    ///
+    /// The data is serialized to JSON before evaluation, so fields are
+    /// named as they would be by `serde_json`, including any renames.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde::Serialize;
+    /// use serde_json::json;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Order {
+    ///     total: f64,
+    ///     country: &'static str,
+    /// }
+    ///
+    /// let order = Order { total: 120.0, country: "US" };
+    /// let rule = json!({"and": [
+    ///     {">": [{"var": "total"}, 100]},
+    ///     {"==": [{"var": "country"}, "US"]},
+    /// ]});
+    /// assert_eq!(Engine::new().apply_serde(&rule, &order).unwrap(), json!(true));
+    /// ```
+    pub fn apply_serde<T>(&self, value: &Value, data: &T) -> Result<Value, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let data = serde_json::to_value(data).map_err(|err| Error::InvalidData {
+            value: Value::Null,
+            reason: format!("Could not serialize data: {}", err),
+        })?;
+        self.apply(value, &data)
+    }
+
+    /// Evaluate the rules embedded in a document, leaving the rest of it as
+    /// it is
+    ///
+    /// Rules are marked by wrapping them in an object whose only key is
+    /// `$logic`, or the engine's [`logic_marker()`](Self::logic_marker),
+    /// which may appear anywhere in the document. Each is replaced with its
+    /// result for the data, so that e.g. a configuration file can mix
+    /// static settings with settings computed from the data. An object with
+    /// the marker alongside other keys is an `InvalidData` error.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::json;
+    ///
+    /// let config = json!({
+    ///     "region": "eu-west-1",
+    ///     "limits": {
+    ///         "requests": {"$logic": {"if": [{"var": "premium"}, 1000, 100]}},
+    ///         "burst": 10,
+    ///     },
+    /// });
+    /// assert_eq!(
+    ///     Engine::new().apply_all_paths(&config, &json!({"premium": true})).unwrap(),
+    ///     json!({"region": "eu-west-1", "limits": {"requests": 1000, "burst": 10}})
+    /// );
+    /// ```
+    pub fn apply_all_paths(
+        &self,
+        document: &Value,
+        data: &Value,
+    ) -> Result<Value, Error> {
+        let marker = self.logic_marker.as_deref().unwrap_or(LOGIC_MARKER);
+        match document {
+            Value::Object(obj) => match obj.get(marker) {
+                Some(rule) if obj.len() == 1 => self.apply(rule, data),
+                Some(_) => Err(Error::InvalidData {
+                    value: document.clone(),
+                    reason: format!(
+                        "{:?} must be the only key of an object marking a rule",
+                        marker
+                    ),
+                }),
+                None => obj
+                    .iter()
+                    .map(|(key, val)| {
+                        Ok((key.clone(), self.apply_all_paths(val, data)?))
+                    })
+                    .collect::<Result<_, Error>>()
+                    .map(Value::Object),
+            },
+            Value::Array(items) => items
+                .iter()
+                .map(|item| self.apply_all_paths(item, data))
+                .collect::<Result<_, Error>>()
+                .map(Value::Array),
+            other => Ok(other.clone()),
+        }
+    }
+
+    /// Run JSONLogic for a rule that evaluates to an array, producing its
+    /// items one at a time
+    ///
+    /// When the rule is a `map` or a `filter`, its expression is evaluated
+    /// for each item only as the iterator is advanced, so a pipeline that
+    /// needs only the first few results doesn't pay for the rest. Other
+    /// rules are evaluated in full before their items are produced.
+    ///
+    /// Errors in checking the rule, or in evaluating the array that a `map`
+    /// or `filter` iterates over, are returned immediately. Errors in
+    /// evaluating individual items are produced by the iterator, which then
+    /// ends. Rules that don't evaluate to an array are an `InvalidData`
+    /// error.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::json;
+    ///
+    /// let engine = Engine::new();
+    /// let rule = json!({"filter": [{"var": "orders"}, {">": [{"var": "total"}, 100]}]});
+    /// let data = json!({"orders": [
+    ///     {"id": 1, "total": 150},
+    ///     {"id": 2, "total": 50},
+    ///     {"id": 3, "total": 120},
+    ///     {"id": 4, "total": "not a number"},
+    /// ]});
+    /// let first: Vec<_> = engine
+    ///     .apply_iter(&rule, &data)
+    ///     .unwrap()
+    ///     .take(2)
+    ///     .collect::<Result<_, _>>()
+    ///     .unwrap();
+    /// assert_eq!(first, vec![json!({"id": 1, "total": 150}), json!({"id": 3, "total": 120})]);
+    /// ```
+    pub fn apply_iter<'a>(
+        &'a self,
+        value: &'a Value,
+        data: &'a Value,
+    ) -> Result<ApplyIter<'a>, Error> {
+        let ctx = Context::new(self);
+        let parsed = self.parse(value)?;
+        let args = value.as_object().and_then(|obj| obj.values().next());
+        let stream = match (parsed.symbol(), args) {
+            (Some(symbol @ ("map" | "filter")), Some(Value::Array(args))) => {
+                telemetry::record_operation(symbol);
+                // The operator is entered as when it's evaluated, so that its
+                // items are evaluated a level below it, and it's left along
+                // with the context once the iterator is dropped
+                ctx.enter();
+                Stream::new(&ctx, data, symbol, args)
+                    .inspect_err(telemetry::record_error)?
+            }
+            _ => None,
+        };
+        let results = match stream {
+            Some(stream) => Results::Stream(stream),
+            None => match Value::from(
+                parsed
+                    .evaluate(data, &ctx)
+                    .inspect_err(telemetry::record_error)?,
+            ) {
+                Value::Array(items) => Results::Evaluated(items.into_iter()),
+                other => {
+                    return Err(Error::InvalidData {
+                        reason: format!(
+                            "Rules must evaluate to an array to be iterated, got {}",
+                            other
+                        ),
+                        value: other,
+                    })
+                }
+            },
+        };
+        Ok(ApplyIter { ctx, data, results })
+    }
+
+    /// Run JSONLogic, returning statistics about the evaluation alongside
+    /// the result.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::json;
+    ///
+    /// let (res, stats) = Engine::new()
+    ///     .apply_with_stats(&json!({"+": [1, {"var": "a"}]}), &json!({"a": 2}))
+    ///     .unwrap();
+    /// assert_eq!(res, json!(3));
+    /// assert_eq!(stats.operations, 2);
+    /// assert_eq!(stats.max_depth, 2);
+    /// assert_eq!(stats.data_lookups, 1);
+    /// ```
+    pub fn apply_with_stats(
+        &self,
+        value: &Value,
+        data: &Value,
+    ) -> Result<(Value, Stats), Error> {
+        let start = Instant::now();
+        let ctx = Context::new(self);
+        let result = self.apply_in(&ctx, value, data)?;
+        Ok((result, ctx.stats(start.elapsed())))
+    }
+
+    /// Run JSONLogic, returning the argument of the top-level operator that
+    /// determined the result alongside it
+    ///
+    /// For `if`, this is the index of the argument that was returned: the
+    /// result following the first condition that held, or the final "else"
+    /// result. For `or`, it's the index of the first truthy argument, and
+    /// for `and`, the first falsy one, or in either case the last argument
+    /// if there's no such argument. This lets e.g. experiments record which
+    /// arm of a rule was taken without explaining the whole evaluation.
+    ///
+    /// It's `None` if the rule isn't one of these operators, or if no
+    /// argument was returned, as for an `if` with no conditions that held
+    /// and no "else" result.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::json;
+    ///
+    /// let rule = json!({"if": [
+    ///     {"<": [{"var": "bucket"}, 50]}, "control",
+    ///     {"<": [{"var": "bucket"}, 75]}, "variant_a",
+    ///     "variant_b",
+    /// ]});
+    /// let (res, branch) = Engine::new()
+    ///     .apply_with_branch(&rule, &json!({"bucket": 60}))
+    ///     .unwrap();
+    /// assert_eq!(res, json!("variant_a"));
+    /// assert_eq!(branch, Some(3));
+    /// ```
+    pub fn apply_with_branch(
+        &self,
+        value: &Value,
+        data: &Value,
+    ) -> Result<(Value, Option<usize>), Error> {
+        let ctx = Context::new(self);
+        let result = self.apply_in(&ctx, value, data)?;
+        Ok((result, ctx.branch()))
+    }
+
+    /// Apply a rule, recording hashes of the rule and data, the result, and
+    /// statistics about the evaluation
+    ///
+    /// See [`AuditRecord`] for details, and the `serde` feature to serialize
+    /// records.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{rule_hash, Engine};
+    /// use serde_json::json;
+    ///
+    /// let rule = json!({">=": [{"var": "age"}, 18]});
+    /// let record = Engine::new().audit(&rule, &json!({"age": 21}));
+    /// assert_eq!(record.rule_hash, rule_hash(&rule));
+    /// assert_eq!(record.result.unwrap(), json!(true));
+    /// ```
+    pub fn audit(&self, value: &Value, data: &Value) -> AuditRecord {
+        let start = Instant::now();
+        let ctx = Context::new(self);
+        let result = self.apply_in(&ctx, value, data);
+        AuditRecord {
+            rule_hash: rule_hash(value),
+            data_hash: data_hash(data),
+            result: self.redaction.sensitive(data).result(result),
+            stats: ctx.stats(start.elapsed()),
+        }
+    }
+
+    /// A stable hash identifying a rule up to how its operations are written
+    ///
+    /// This works like the free [`fingerprint()`](crate::fingerprint)
+    /// function, but recognizes this engine's custom operators rather than
+    /// those of the [`default_engine()`].
+    pub fn fingerprint(&self, value: &Value) -> String {
+        audit::fingerprint(self, value)
+    }
+
+    /// Evaluate a rule, recording the rule, data, options, and outcome as
+    /// a snapshot that can be replayed later
+    ///
+    /// See [`snapshot`](crate::snapshot) for the format.
+    pub fn snapshot(&self, value: &Value, data: &Value) -> Value {
+        snapshot::snapshot(self, value, data)
+    }
+
+    /// Shrink a rule and data that produce an error or unexpected result to
+    /// a minimal reproduction
+    ///
+    /// Operations are replaced by operations among their arguments, and
+    /// arguments, array items, and keys of the data are removed, for as
+    /// long as the result stays the same. Errors count as the same if they
+    /// have the same code and operator, since their messages often include
+    /// values from the data. See [`shrink_by()`](Self::shrink_by) to choose
+    /// which results to keep instead.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::json;
+    ///
+    /// let rule = json!({"and": [
+    ///     {"==": [{"var": "plan"}, "gold"]},
+    ///     {">": [{"/": [{"var": "price"}, {"var": "qty"}]}, 100]},
+    /// ]});
+    /// let data = json!({"plan": "gold", "price": 500, "qty": 0});
+    /// let shrunk = Engine::new().shrink(&rule, &data);
+    /// assert_eq!(shrunk.rule, json!({"/": [{"var": "price"}, {"var": "qty"}]}));
+    /// assert_eq!(shrunk.data, json!({}));
+    /// ```
+    pub fn shrink(&self, value: &Value, data: &Value) -> Shrunk {
+        let expected = self.apply(value, data);
+        shrink::shrink(self, value, data, |result| {
+            shrink::same_outcome(&expected, result)
+        })
+    }
+
+    /// Shrink a rule and data to a minimal reproduction of a result, given
+    /// a function deciding whether results are still of interest
+    ///
+    /// This works like [`shrink()`](Self::shrink), but keeps any smaller
+    /// rule and data whose result `interesting` accepts. If the original
+    /// result isn't accepted, the rule and data are returned as they are.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::Engine;
+    /// use serde_json::json;
+    ///
+    /// // What makes this order's total over the limit?
+    /// let rule = json!({"+": [
+    ///     {"*": [{"var": "price"}, {"var": "qty"}]},
+    ///     {"var": "shipping"},
+    /// ]});
+    /// let data = json!({"price": 30, "qty": 4, "shipping": 5, "coupon": null});
+    /// let shrunk = Engine::new().shrink_by(&rule, &data, |result| {
+    ///     matches!(result, Ok(total) if total.as_f64() > Some(100.0))
+    /// });
+    /// assert_eq!(shrunk.rule, json!({"*": [{"var": "price"}, {"var": "qty"}]}));
+    /// assert_eq!(shrunk.data, json!({"price": 30, "qty": 4}));
+    /// ```
+    pub fn shrink_by<F>(&self, value: &Value, data: &Value, interesting: F) -> Shrunk
+    where
+        F: FnMut(&Result<Value, Error>) -> bool,
+    {
+        shrink::shrink(self, value, data, interesting)
+    }
+
+    /// Find suspicious constructs in a rule
+    ///
+    /// This works like the free [`lint()`](crate::lint) function, but
+    /// recognizes this engine's custom operators rather than those of the
+    /// [`default_engine()`].
+    pub fn lint(&self, value: &Value) -> Vec<LintWarning> {
+        lint::lint(self, value)
+    }
+
+    /// Explain why a rule evaluates to a falsy value for some data
+    ///
+    /// This works like the free [`explain_failure()`](crate::explain_failure)
+    /// function, but evaluates with this engine's options and operators
+    /// rather than those of the [`default_engine()`].
+    pub fn explain_failure(
+        &self,
+        value: &Value,
+        data: &Value,
+    ) -> Result<Vec<Failure>, Error> {
+        explain::explain_failure(self, value, data)
+    }
+
+    /// Check that a rule is valid for this engine without evaluating it
+    pub fn validate(&self, value: &Value) -> Result<(), Error> {
+        self.parse(value).map(|_| ())
+    }
+
+    /// Whether a key names an operator, alias, or custom operator known to
+    /// this engine
+    pub fn has_operator(&self, key: &str) -> bool {
+        op::is_operator(self, key)
+    }
+
+    /// Check a rule against the engine's restrictions and parse it
+    pub(crate) fn parse<'a>(&self, value: &'a Value) -> Result<Parsed<'a>, Error> {
+        if let Some(policy) = &self.policy {
+            policy.check(self, value)?;
+        }
+        op::check_namespaces(self, value)?;
+        Parsed::from_value(value, self)
+    }
+
+    pub(crate) fn apply_in(
+        &self,
+        ctx: &Context,
+        value: &Value,
+        data: &Value,
+    ) -> Result<Value, Error> {
+        let _span = telemetry::Span::apply(value);
+        let parsed = {
+            let _span = telemetry::Span::parse();
+            self.parse(value)
+        };
+        parsed
+            .and_then(|parsed| self.evaluate_in(ctx, &parsed, data))
+            .inspect_err(telemetry::record_error)
+    }
+
+    /// Evaluate a parsed rule
+    pub(crate) fn evaluate_in(
+        &self,
+        ctx: &Context,
+        parsed: &Parsed,
+        data: &Value,
+    ) -> Result<Value, Error> {
+        let span = telemetry::Span::evaluate();
+        let result = parsed.evaluate(data, ctx).map(Value::from);
+        span.record_counts(ctx);
+        result
+    }
+}
+
+enum Results<'a> {
+    Stream(Stream<'a>),
+    Evaluated(std::vec::IntoIter<Value>),
+}
+
+/// The items of a rule's result, produced by [`Engine::apply_iter()`]
+pub struct ApplyIter<'a> {
+    ctx: Context<'a>,
+    data: &'a Value,
+    results: Results<'a>,
+}
+impl Iterator for ApplyIter<'_> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.results {
+            Results::Stream(stream) => stream
+                .next(&self.ctx, self.data)
+                .map(|result| result.inspect_err(telemetry::record_error)),
+            Results::Evaluated(items) => items.next().map(Ok),
+        }
+    }
+}
@@ -1,12 +1,17 @@
 //! Error handling
 //!
-use serde_json::Value;
-use thiserror;
+use serde::ser::{Serialize, Serializer};
+use serde_json::{json, Value};
 
 use crate::op::NumParams;
 
 /// Public error enumeration
+///
+/// Variants may be added in minor releases, so matches on it need a
+/// wildcard arm.
+#[allow(clippy::enum_variant_names)]
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     #[error("Invalid data - value: {value:?}, reason: {reason:?}")]
     InvalidData { value: Value, reason: String },
@@ -27,12 +32,179 @@ pub enum Error {
         reason: String,
     },
 
+    /// An argument, or an item of one, that isn't structured as the
+    /// operator expects
+    #[error(
+        "Malformed argument {} for '{operation}' - '{value:?}', expected: {expected}",
+        position(*.index, *.item)
+    )]
+    MalformedArgument {
+        operation: String,
+        /// The position of the argument, from 0
+        index: usize,
+        /// For an argument that is a list, the position of the offending
+        /// item in it
+        item: Option<usize>,
+        value: Value,
+        /// The structure expected, like "an array of keys"
+        expected: String,
+    },
+
     #[error("Invalid variable mapping - {0} is not an object.")]
     InvalidVarMap(Value),
 
     #[error("Encountered an unexpected error. Please raise an issue on GitHub and include the following error message: {0}")]
     UnexpectedError(String),
 
-    #[error("Wrong argument count - expected: {expected:?}, actual: {actual:?}")]
-    WrongArgumentCount { expected: NumParams, actual: usize },
+    #[error("Policy violation at '{path}' - operator: {operator:?}, reason: {reason}")]
+    PolicyViolation {
+        operator: Option<String>,
+        path: String,
+        reason: String,
+    },
+
+    #[error("Wrong argument count for '{operator}' - expected: {expected}, actual: {actual}, arguments: {arguments}")]
+    WrongArgumentCount {
+        operator: String,
+        expected: NumParams,
+        actual: usize,
+        /// The arguments as JSON, truncated if long
+        arguments: String,
+    },
+
+    #[error("Result of '{operator}' too large - size: {size}, maximum: {max}")]
+    ResultTooLarge {
+        operator: String,
+        /// The size of the result when it exceeded the maximum, as measured
+        /// by `Engine::max_result_size()`
+        size: usize,
+        max: usize,
+    },
+
+    #[error("Plugin error - operator: {operator:?}, reason: {reason}")]
+    PluginError {
+        /// The operator being evaluated, or `None` if the plugin failed to
+        /// load
+        operator: Option<String>,
+        reason: String,
+    },
+
+    /// An error raised by the rule itself, with `throw` or `assert`
+    #[error("Rule raised an error - operator: '{operator}', message: {message}")]
+    RuleRaised { operator: String, message: String },
+}
+impl Error {
+    /// A stable, machine-readable identifier for the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidData { .. } => "invalid_data",
+            Self::InvalidOperation { .. } => "invalid_operation",
+            Self::InvalidVariable { .. } => "invalid_variable",
+            Self::InvalidVariableKey { .. } => "invalid_variable_key",
+            Self::InvalidArgument { .. } => "invalid_argument",
+            Self::MalformedArgument { .. } => "malformed_argument",
+            Self::InvalidVarMap(_) => "invalid_var_map",
+            Self::UnexpectedError(_) => "unexpected_error",
+            Self::PolicyViolation { .. } => "policy_violation",
+            Self::WrongArgumentCount { .. } => "wrong_argument_count",
+            Self::ResultTooLarge { .. } => "result_too_large",
+            Self::PluginError { .. } => "plugin_error",
+            Self::RuleRaised { .. } => "rule_raised",
+        }
+    }
+
+    /// Render the error as JSON
+    ///
+    /// The result is an object with the error's `code`, its human-readable
+    /// `message`, and a `details` object holding the variant's fields.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::apply;
+    /// use serde_json::json;
+    ///
+    /// let err = apply(&json!({"==": [1]}), &json!(null)).unwrap_err();
+    /// let rendered = err.to_json();
+    /// assert_eq!(rendered["code"], "wrong_argument_count");
+    /// assert_eq!(rendered["details"]["operator"], "==");
+    /// // `Error` implements `Serialize` with the same representation
+    /// assert_eq!(serde_json::to_value(&err).unwrap(), rendered);
+    /// ```
+    pub fn to_json(&self) -> Value {
+        let details = match self {
+            Self::InvalidData { value, reason }
+            | Self::InvalidVariable { value, reason }
+            | Self::InvalidVariableKey { value, reason } => {
+                json!({"value": value, "reason": reason})
+            }
+            Self::InvalidOperation { key, reason } => {
+                json!({"operator": key, "reason": reason})
+            }
+            Self::InvalidArgument {
+                value,
+                operation,
+                reason,
+            } => json!({"value": value, "operator": operation, "reason": reason}),
+            Self::MalformedArgument {
+                operation,
+                index,
+                item,
+                value,
+                expected,
+            } => json!({
+                "operator": operation,
+                "index": index,
+                "item": item,
+                "value": value,
+                "expected": expected,
+            }),
+            Self::InvalidVarMap(value) => json!({ "value": value }),
+            Self::UnexpectedError(reason) => json!({ "reason": reason }),
+            Self::PolicyViolation {
+                operator,
+                path,
+                reason,
+            } => json!({"operator": operator, "path": path, "reason": reason}),
+            Self::WrongArgumentCount {
+                operator,
+                expected,
+                actual,
+                arguments,
+            } => json!({
+                "operator": operator,
+                "expected": expected.to_string(),
+                "actual": actual,
+                "arguments": arguments,
+            }),
+            Self::ResultTooLarge {
+                operator,
+                size,
+                max,
+            } => json!({"operator": operator, "size": size, "max": max}),
+            Self::PluginError { operator, reason } => {
+                json!({"operator": operator, "reason": reason})
+            }
+            Self::RuleRaised { operator, message } => {
+                json!({"operator": operator, "message": message})
+            }
+        };
+        json!({
+            "code": self.code(),
+            "message": self.to_string(),
+            "details": details,
+        })
+    }
+}
+
+/// An argument's position, like "1" or, for an item of it, "1[2]"
+fn position(index: usize, item: Option<usize>) -> String {
+    match item {
+        Some(item) => format!("{}[{}]", index, item),
+        None => index.to_string(),
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
 }
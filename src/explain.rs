@@ -0,0 +1,342 @@
+//! Explaining why rules fail
+//!
+//! A rule that evaluates to `false` says nothing about why. Explanation
+//! re-evaluates the parts of a falsy rule to find the conditions that made
+//! it falsy: the first falsy argument of an `and`, every argument of an
+//! `or`, the first item an `all` rejects, and the branch an `if` took.
+//! Other operations are leaves, reported with the values their operands
+//! evaluated to, so that e.g. `{">=": [{"var": "age"}, 18]}` fails with
+//! "age 16 !>= 18".
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::js_op::truthy;
+use crate::op;
+use crate::policy::Walk;
+
+/// Operators whose failures are described as a failed comparison
+const COMPARISONS: &[&str] =
+    &["==", "===", "!=", "!==", "<", "<=", ">", ">=", "==i", "!=i"];
+
+/// A condition that caused a rule to fail
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Failure {
+    /// A JSON Pointer to the condition in the rule
+    pub path: String,
+    /// The condition itself
+    pub condition: Value,
+    /// What failed, with the values of the condition's operands
+    pub description: String,
+}
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.description)
+    }
+}
+
+pub(crate) fn explain_failure(
+    engine: &Engine,
+    rule: &Value,
+    data: &Value,
+) -> Result<Vec<Failure>, Error> {
+    if truthy(&engine.apply(rule, data)?) {
+        return Ok(Vec::new());
+    }
+    let mut explainer = Explainer {
+        engine,
+        failures: Vec::new(),
+    };
+    explainer.explain(rule, data, &mut Walk::new())?;
+    let sensitive = engine.redaction.sensitive(data);
+    Ok(explainer
+        .failures
+        .into_iter()
+        .map(|failure| Failure {
+            condition: sensitive.value(failure.condition),
+            description: sensitive.text(&failure.description),
+            ..failure
+        })
+        .collect())
+}
+
+struct Explainer<'e> {
+    engine: &'e Engine,
+    failures: Vec<Failure>,
+}
+impl Explainer<'_> {
+    fn is_truthy(&self, value: &Value, data: &Value) -> Result<bool, Error> {
+        Ok(truthy(&self.engine.apply(value, data)?))
+    }
+
+    /// Explain a value known to be falsy for the data
+    fn explain(
+        &mut self,
+        value: &Value,
+        data: &Value,
+        walk: &mut Walk,
+    ) -> Result<(), Error> {
+        let (key, args) = match op::split_operation(self.engine, value) {
+            Some(split) => split,
+            None => return self.leaf(value, data, walk),
+        };
+        let unary = !matches!(value.get(key), Some(Value::Array(_)));
+        let arg = |explainer: &mut Self, idx: usize, data: &Value, walk: &mut Walk| {
+            walk.descend(key, |walk| {
+                if unary {
+                    explainer.explain(args[idx], data, walk)
+                } else {
+                    walk.descend(&idx.to_string(), |walk| {
+                        explainer.explain(args[idx], data, walk)
+                    })
+                }
+            })
+        };
+
+        match key {
+            "and" => {
+                for (idx, item) in args.iter().enumerate() {
+                    if !self.is_truthy(item, data)? {
+                        return arg(self, idx, data, walk);
+                    }
+                }
+                self.leaf(value, data, walk)
+            }
+            "or" if !args.is_empty() => {
+                for idx in 0..args.len() {
+                    arg(self, idx, data, walk)?;
+                }
+                Ok(())
+            }
+            "!!" if args.len() == 1 => arg(self, 0, data, walk),
+            "if" | "?:" => {
+                let mut idx = 0;
+                while idx + 1 < args.len() {
+                    if self.is_truthy(args[idx], data)? {
+                        break;
+                    }
+                    idx += 2;
+                }
+                let taken = match idx {
+                    // A condition held, selecting the result after it
+                    idx if idx + 1 < args.len() => idx + 1,
+                    // The final result, if no condition held
+                    idx if idx < args.len() => idx,
+                    _ => return self.leaf(value, data, walk),
+                };
+                if op::split_operation(self.engine, args[taken]).is_some() {
+                    arg(self, taken, data, walk)
+                } else {
+                    self.leaf(value, data, walk)
+                }
+            }
+            "all" if args.len() == 2 => {
+                let items = match self.engine.apply(args[0], data)? {
+                    Value::Array(items) => items,
+                    Value::String(s) => {
+                        s.chars().map(|c| Value::from(c.to_string())).collect()
+                    }
+                    _ => Vec::new(),
+                };
+                for (item_idx, item) in items.iter().enumerate() {
+                    if !self.is_truthy(args[1], item)? {
+                        let start = self.failures.len();
+                        arg(self, 1, item, walk)?;
+                        for failure in &mut self.failures[start..] {
+                            failure.description =
+                                format!("item {}: {}", item_idx, failure.description);
+                        }
+                        return Ok(());
+                    }
+                }
+                self.leaf(value, data, walk)
+            }
+            _ => self.leaf(value, data, walk),
+        }
+    }
+
+    /// Record a condition that can't be broken down any further
+    fn leaf(&mut self, value: &Value, data: &Value, walk: &Walk) -> Result<(), Error> {
+        let description = self.describe(value, data)?;
+        self.failures.push(Failure {
+            path: if walk.path.is_empty() {
+                "/".into()
+            } else {
+                walk.path.clone()
+            },
+            condition: value.clone(),
+            description,
+        });
+        Ok(())
+    }
+
+    /// Describe why a falsy condition is falsy
+    fn describe(&self, value: &Value, data: &Value) -> Result<String, Error> {
+        let (key, args) = match op::split_operation(self.engine, value) {
+            Some(split) => split,
+            None => return Ok(format!("{} is falsy", value)),
+        };
+        let operands = args
+            .iter()
+            .map(|arg| self.describe_operand(arg, data))
+            .collect::<Result<Vec<String>, Error>>()?;
+        Ok(match key {
+            _ if COMPARISONS.contains(&key) && operands.len() >= 2 => {
+                operands.join(&format!(" !{} ", key))
+            }
+            "in" | "in_i" if operands.len() == 2 => {
+                format!("{} not in {}", operands[0], operands[1])
+            }
+            "not_in" | "not_in_i" if operands.len() == 2 => {
+                format!("{} in {}", operands[0], operands[1])
+            }
+            "!" if operands.len() == 1 => format!("{} is truthy", operands[0]),
+            _ if is_var(key) => {
+                format!("{} is falsy", self.describe_operand(value, data)?)
+            }
+            _ => format!(
+                "{}({}) is {}",
+                key,
+                operands.join(", "),
+                self.engine.apply(value, data)?
+            ),
+        })
+    }
+
+    /// Describe an operand by its value, and its name if it's a variable
+    fn describe_operand(&self, value: &Value, data: &Value) -> Result<String, Error> {
+        let evaluated = self.engine.apply(value, data)?;
+        Ok(match op::split_operation(self.engine, value) {
+            Some((key, args)) if is_var(key) => match args.first() {
+                Some(Value::String(name)) if !name.is_empty() => {
+                    format!("{} {}", name, evaluated)
+                }
+                Some(Value::Number(idx)) => format!("{} {}", idx, evaluated),
+                _ => evaluated.to_string(),
+            },
+            _ => evaluated.to_string(),
+        })
+    }
+}
+
+fn is_var(key: &str) -> bool {
+    matches!(key, "var" | "var_str" | "var_num" | "var_bool")
+}
+
+#[cfg(test)]
+mod test_explain {
+    use super::*;
+    use serde_json::json;
+
+    fn explain(rule: Value, data: Value) -> Vec<String> {
+        explain_failure(&Engine::new(), &rule, &data)
+            .unwrap()
+            .iter()
+            .map(|failure| failure.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_truthy() {
+        assert!(explain(json!({"==": [1, 1]}), json!(null)).is_empty());
+    }
+
+    #[test]
+    fn test_leaves() {
+        assert_eq!(
+            explain(json!({">=": [{"var": "age"}, 18]}), json!({"age": 16})),
+            vec!["/: age 16 !>= 18"]
+        );
+        assert_eq!(
+            explain(json!({"<": [0, {"var": "x"}, 10]}), json!({"x": 12})),
+            vec!["/: 0 !< x 12 !< 10"]
+        );
+        assert_eq!(
+            explain(
+                json!({"not_in": ["a", {"var": "tags"}]}),
+                json!({"tags": ["a"]})
+            ),
+            vec![r#"/: "a" in tags ["a"]"#]
+        );
+        assert_eq!(
+            explain(json!({"!": {"var": "banned"}}), json!({"banned": true})),
+            vec!["/: banned true is truthy"]
+        );
+        assert_eq!(
+            explain(
+                json!({"startsWith": [{"var": "name"}, "A"]}),
+                json!({"name": "Bob"})
+            ),
+            vec![r#"/: startsWith(name "Bob", "A") is false"#]
+        );
+        assert_eq!(explain(json!(0), json!(null)), vec!["/: 0 is falsy"]);
+    }
+
+    #[test]
+    fn test_logic() {
+        let rule = json!({"and": [
+            {"var": "active"},
+            {"!!": {"or": [{"==": [{"var": "role"}, "admin"]}, {"var": "override"}]}},
+            {"var": "unreached"},
+        ]});
+        assert_eq!(
+            explain(rule, json!({"active": true, "role": "user"})),
+            vec![
+                r#"/and/1/!!/or/0: role "user" !== "admin""#,
+                "/and/1/!!/or/1: override null is falsy",
+            ]
+        );
+        let rule = json!({"if": [
+            {"var": "minor"}, {">=": [{"var": "consent"}, 1]},
+            {">=": [{"var": "age"}, 18]},
+        ]});
+        assert_eq!(
+            explain(rule.clone(), json!({"minor": true, "consent": 0})),
+            vec!["/if/1: consent 0 !>= 1"]
+        );
+        assert_eq!(
+            explain(rule, json!({"minor": false, "age": 16})),
+            vec!["/if/2: age 16 !>= 18"]
+        );
+        assert_eq!(
+            explain(json!({"if": [{"var": "x"}, false, true]}), json!({"x": 1})),
+            vec![r#"/: if(x 1, false, true) is false"#]
+        );
+    }
+
+    #[test]
+    fn test_all() {
+        let rule = json!({"all": [{"var": "people"}, {">=": [{"var": "age"}, 18]}]});
+        assert_eq!(
+            explain(
+                rule,
+                json!({"people": [{"age": 30}, {"age": 16}, {"age": 12}]})
+            ),
+            vec!["/all/1: item 1: age 16 !>= 18"]
+        );
+        assert_eq!(
+            explain(json!({"all": [[], true]}), json!(null)),
+            vec!["/: all([], true) is false"]
+        );
+    }
+
+    #[test]
+    fn test_redacted() {
+        let engine = Engine::new().redact("people.*.age");
+        let rule = json!({"all": [{"var": "people"}, {">=": [{"var": "age"}, 18]}]});
+        let failures = explain_failure(
+            &engine,
+            &rule,
+            &json!({"people": [{"age": 30}, {"age": 16}]}),
+        )
+        .unwrap();
+        assert_eq!(
+            failures[0].to_string(),
+            "/all/1: item 1: age [REDACTED] !>= 18"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Rule formatting
+//!
+//! Rules are formatted so that short values stay on one line and long ones
+//! are broken up, one item per line. An operation keeps its operator on the
+//! opening line, so that `{"and": [` reads as a heading for the conditions
+//! below it.
+
+use serde_json::Value;
+
+/// Options for [`format_rule()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatStyle {
+    /// The number of spaces per level of indentation
+    pub indent: usize,
+    /// The line width above which values are broken up, where possible
+    pub max_width: usize,
+}
+impl Default for FormatStyle {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            max_width: 80,
+        }
+    }
+}
+
+/// Pretty-print a rule as JSON
+///
+/// Values that fit in the remaining width of their line are written on a
+/// single line, with a space after each `,` and `:`. Longer arrays and
+/// objects are written with each item on its own line, except that the
+/// operator of an operation stays on the line that opens it. The output
+/// parses back to the same rule.
+///
+/// ```rust
+/// use jsonlogic_rs::{format_rule, FormatStyle};
+/// use serde_json::json;
+///
+/// let rule = json!({"and": [
+///     {">=": [{"var": "applicant.age"}, 18]},
+///     {"in": [{"var": "applicant.state"}, ["CA", "NY", "TX"]]}
+/// ]});
+/// let style = FormatStyle { max_width: 50, ..FormatStyle::default() };
+/// assert_eq!(
+///     format_rule(&rule, style),
+///     r#"{"and": [
+///   {">=": [{"var": "applicant.age"}, 18]},
+///   {"in": [
+///     {"var": "applicant.state"},
+///     ["CA", "NY", "TX"]
+///   ]}
+/// ]}"#
+/// );
+/// ```
+pub fn format_rule(rule: &Value, style: FormatStyle) -> String {
+    let mut out = String::new();
+    Formatter {
+        style,
+        out: &mut out,
+    }
+    .value(rule, 0, 0);
+    out
+}
+
+/// Render a value on a single line
+fn inline(value: &Value) -> String {
+    match value {
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(inline).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Object(obj) => {
+            let entries: Vec<String> = obj
+                .iter()
+                .map(|(key, val)| {
+                    format!("{}: {}", Value::from(key.as_str()), inline(val))
+                })
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        _ => value.to_string(),
+    }
+}
+
+struct Formatter<'a> {
+    style: FormatStyle,
+    out: &'a mut String,
+}
+impl Formatter<'_> {
+    fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        self.out
+            .extend(std::iter::repeat_n(' ', depth * self.style.indent));
+    }
+
+    /// Write items one per line, between `open` and `close`
+    fn block<'v, I>(&mut self, open: &str, close: &str, items: I, depth: usize)
+    where
+        I: ExactSizeIterator<Item = (Option<&'v String>, &'v Value)>,
+    {
+        self.out.push_str(open);
+        let len = items.len();
+        for (idx, (key, item)) in items.enumerate() {
+            self.newline(depth + 1);
+            let mut prefix_len = 0;
+            if let Some(key) = key {
+                let key = format!("{}: ", Value::from(key.as_str()));
+                prefix_len = key.len();
+                self.out.push_str(&key);
+            }
+            let trailer = if idx + 1 < len { "," } else { "" };
+            self.value(item, depth + 1, prefix_len + trailer.len());
+            self.out.push_str(trailer);
+        }
+        self.newline(depth);
+        self.out.push_str(close);
+    }
+
+    /// Write a value at the current position, on a line indented to
+    /// `depth`, with `reserved` characters of the line already used or
+    /// still to come
+    fn value(&mut self, value: &Value, depth: usize, reserved: usize) {
+        let compact = inline(value);
+        let used = depth * self.style.indent + reserved;
+        if used + compact.len() <= self.style.max_width {
+            self.out.push_str(&compact);
+            return;
+        }
+        match value {
+            Value::Object(obj) if obj.len() == 1 => {
+                let (key, args) = obj.iter().next().unwrap();
+                let open = format!("{{{}: ", Value::from(key.as_str()));
+                match args {
+                    Value::Array(items) if !items.is_empty() => {
+                        self.block(
+                            &format!("{}[", open),
+                            "]}",
+                            items.iter().map(|item| (None, item)),
+                            depth,
+                        );
+                    }
+                    _ => {
+                        self.out.push_str(&open);
+                        self.value(args, depth, reserved + open.len() + 1);
+                        self.out.push('}');
+                    }
+                }
+            }
+            Value::Object(obj) if !obj.is_empty() => {
+                self.block("{", "}", obj.iter().map(|(k, v)| (Some(k), v)), depth)
+            }
+            Value::Array(items) if !items.is_empty() => {
+                self.block("[", "]", items.iter().map(|item| (None, item)), depth)
+            }
+            _ => self.out.push_str(&compact),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_format {
+    use super::*;
+    use serde_json::json;
+
+    fn narrow(rule: &Value) -> String {
+        format_rule(
+            rule,
+            FormatStyle {
+                indent: 4,
+                max_width: 30,
+            },
+        )
+    }
+
+    #[test]
+    fn test_inline() {
+        let rule = json!({"if": [{"<": [{"var": "a"}, 1]}, "low", "high"]});
+        assert_eq!(
+            format_rule(&rule, FormatStyle::default()),
+            r#"{"if": [{"<": [{"var": "a"}, 1]}, "low", "high"]}"#
+        );
+    }
+
+    #[test]
+    fn test_breaking() {
+        let rule = json!({"!": {"or": [{"var": "is_deleted"}, {"var": "is_hidden"}]}});
+        assert_eq!(
+            narrow(&rule),
+            r#"{"!": {"or": [
+    {"var": "is_deleted"},
+    {"var": "is_hidden"}
+]}}"#
+        );
+        let rule = json!({"merge": [{"first": "a", "second": "b"}, ["c", "d", "e", "f", "g"]]});
+        assert_eq!(
+            narrow(&rule),
+            r#"{"merge": [
+    {
+        "first": "a",
+        "second": "b"
+    },
+    ["c", "d", "e", "f", "g"]
+]}"#
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rules = vec![
+            json!({"and": [{"var": "a.b"}, {"missing_some": [1, ["a", "b", "c", "d"]]}]}),
+            json!([{"a\"quoted\\key": "line\nbreak"}, [], {}, null, 1.5, -3]),
+            json!({"cat": ["a very long string that does not fit on one line at all"]}),
+        ];
+        for rule in rules {
+            let formatted = narrow(&rule);
+            let parsed: Value = serde_json::from_str(&formatted).unwrap();
+            assert_eq!(parsed, rule, "{}", formatted);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Rendering rules as graphs
+//!
+//! Rules are rendered as trees, with an operator at each internal node and
+//! literal values at the leaves. Operators that read from the data, like
+//! `var`, are drawn as leaves labeled with the data they reference, so
+//! that the inputs to a rule stand out.
+
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use crate::engine::Engine;
+use crate::op;
+
+/// A graph description language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Graphviz DOT
+    Dot,
+    /// A Mermaid flowchart
+    Mermaid,
+}
+
+/// The kind of a node, which determines its shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Operator,
+    DataReference,
+    Literal,
+}
+
+struct Node {
+    kind: NodeKind,
+    label: String,
+}
+
+/// Render a rule's structure as a graph
+///
+/// ```rust
+/// use jsonlogic_rs::graph::{render_graph, Format};
+/// use serde_json::json;
+///
+/// let dot = render_graph(&json!({"<": [{"var": "age"}, 18]}), Format::Dot);
+/// assert!(dot.contains(r#"n1 [label="var age", shape=parallelogram];"#));
+/// ```
+pub fn render_graph(rule: &Value, format: Format) -> String {
+    let mut graph = Graph {
+        engine: Engine::new(),
+        nodes: Vec::new(),
+        edges: Vec::new(),
+    };
+    graph.add(rule);
+    match format {
+        Format::Dot => graph.to_dot(),
+        Format::Mermaid => graph.to_mermaid(),
+    }
+}
+
+struct Graph {
+    engine: Engine,
+    nodes: Vec<Node>,
+    /// Edges from parent to child, by node index
+    edges: Vec<(usize, usize)>,
+}
+impl Graph {
+    /// Whether a value contains no operations
+    ///
+    /// Objects that are not operations are not evaluated, so they are
+    /// literals regardless of their contents.
+    fn is_literal(&self, value: &Value) -> bool {
+        match value {
+            Value::Array(items) => items.iter().all(|item| self.is_literal(item)),
+            Value::Object(_) => op::split_operation(&self.engine, value).is_none(),
+            _ => true,
+        }
+    }
+
+    /// Add a value and its children, returning the value's node index
+    fn add(&mut self, value: &Value) -> usize {
+        let (kind, label, children) = match op::split_operation(&self.engine, value) {
+            Some((key, args))
+                if op::is_data_operator(key)
+                    && args.iter().all(|arg| self.is_literal(arg)) =>
+            {
+                let label = std::iter::once(key.to_string())
+                    .chain(args.iter().map(|arg| match arg {
+                        Value::String(s) => s.clone(),
+                        _ => arg.to_string(),
+                    }))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                (NodeKind::DataReference, label, Vec::new())
+            }
+            Some((key, args)) => (NodeKind::Operator, key.to_string(), args),
+            None => match value {
+                // Arrays of literals are drawn as a single value, but arrays
+                // containing operations are broken out
+                Value::Array(items) if !self.is_literal(value) => {
+                    (NodeKind::Operator, "[ ]".into(), items.iter().collect())
+                }
+                _ => (NodeKind::Literal, value.to_string(), Vec::new()),
+            },
+        };
+        let idx = self.nodes.len();
+        self.nodes.push(Node { kind, label });
+        for child in children {
+            let child_idx = self.add(child);
+            self.edges.push((idx, child_idx));
+        }
+        idx
+    }
+
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph rule {\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let shape = match node.kind {
+                NodeKind::Operator => "ellipse",
+                NodeKind::DataReference => "parallelogram",
+                NodeKind::Literal => "box",
+            };
+            let label = node.label.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(out, "    n{} [label=\"{}\", shape={}];", idx, label, shape)
+                .unwrap();
+        }
+        for (parent, child) in &self.edges {
+            writeln!(out, "    n{} -> n{};", parent, child).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart TD\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let (open, close) = match node.kind {
+                NodeKind::Operator => ("(", ")"),
+                NodeKind::DataReference => ("[/", "/]"),
+                NodeKind::Literal => ("[", "]"),
+            };
+            let label = node.label.replace('"', "#quot;");
+            writeln!(out, "    n{}{}\"{}\"{}", idx, open, label, close).unwrap();
+        }
+        for (parent, child) in &self.edges {
+            writeln!(out, "    n{} --> n{}", parent, child).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test_graph {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_dot() {
+        let rule =
+            json!({"if": [{"==": [{"var": "a"}, "x"]}, [1, 2], {"var": ["b", 0]}]});
+        assert_eq!(
+            render_graph(&rule, Format::Dot),
+            r#"digraph rule {
+    n0 [label="if", shape=ellipse];
+    n1 [label="==", shape=ellipse];
+    n2 [label="var a", shape=parallelogram];
+    n3 [label="\"x\"", shape=box];
+    n4 [label="[1,2]", shape=box];
+    n5 [label="var b 0", shape=parallelogram];
+    n1 -> n2;
+    n1 -> n3;
+    n0 -> n1;
+    n0 -> n4;
+    n0 -> n5;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_mermaid() {
+        let rule = json!({"in": [{"var": {"cat": ["a", "b"]}}, ["x", {"var": "y"}]]});
+        assert_eq!(
+            render_graph(&rule, Format::Mermaid),
+            r##"flowchart TD
+    n0("in")
+    n1("var")
+    n2("cat")
+    n3["#quot;a#quot;"]
+    n4["#quot;b#quot;"]
+    n5("[ ]")
+    n6["#quot;x#quot;"]
+    n7[/"var y"/]
+    n2 --> n3
+    n2 --> n4
+    n1 --> n2
+    n0 --> n1
+    n5 --> n6
+    n5 --> n7
+    n0 --> n5
+"##
+        );
+    }
+}
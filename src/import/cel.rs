@@ -0,0 +1,620 @@
+//! Converting CEL expressions into rules
+//!
+//! [CEL](https://github.com/google/cel-spec) is the Common Expression
+//! Language. The supported subset covers what rules are usually written
+//! with:
+//!
+//! - `null`, boolean, number, and string literals, and lists of values
+//! - variables, with field access (`user.age`) and constant indexes
+//!   (`items[0]`, `labels["app"]`)
+//! - `!`, `-`, `&&`, `||`, and the ternary `? :`
+//! - `==`, `!=`, `<`, `<=`, `>`, `>=`, and `in`
+//! - `+`, `-`, `*`, `/`, and `%`
+//! - the `startsWith()`, `endsWith()`, and `contains()` string methods
+//! - the `double()` and `string()` conversions
+//! - the `all()`, `exists()`, `filter()`, and `map()` macros
+//!
+//! CEL is strictly typed, so `==` and `!=` become `===` and `!==`. Since
+//! rules can't refer to the data outside of the item an array operation is
+//! evaluating, the body of a macro may only refer to the macro's variable.
+//! `+` becomes `cat` when either side is a string literal, and is otherwise
+//! numeric addition.
+
+use serde_json::{json, Number, Value};
+
+use super::invalid;
+use crate::error::Error;
+
+const RESERVED: &[&str] = &[
+    "as",
+    "break",
+    "const",
+    "continue",
+    "else",
+    "false",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "let",
+    "loop",
+    "package",
+    "namespace",
+    "null",
+    "return",
+    "true",
+    "var",
+    "void",
+    "while",
+];
+
+/// Whether a string can be written as a CEL identifier
+pub(crate) fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RESERVED.contains(&s)
+}
+
+/// Convert a CEL expression into an equivalent rule
+///
+/// ```rust
+/// use jsonlogic_rs::import::cel::from_cel;
+/// use serde_json::json;
+///
+/// let rule = from_cel(r#"user.age >= 18 && user.country in ["US", "CA"]"#).unwrap();
+/// assert_eq!(
+///     rule,
+///     json!({"and": [
+///         {">=": [{"var": "user.age"}, 18]},
+///         {"in": [{"var": "user.country"}, ["US", "CA"]]},
+///     ]})
+/// );
+/// ```
+pub fn from_cel(expression: &str) -> Result<Value, Error> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser {
+        source: expression,
+        tokens,
+        pos: 0,
+        scopes: Vec::new(),
+    };
+    let rule = parser.expr()?;
+    match parser.peek() {
+        Token::End => Ok(rule),
+        _ => Err(parser.error("Unexpected token")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(Number),
+    Str(String),
+    Punct(&'static str),
+    End,
+}
+
+const PUNCTUATION: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "(", ")", "[", "]", "{", "}", ".", ",", "?",
+    ":", "!", "-", "+", "*", "/", "%", "<", ">",
+];
+
+fn syntax_error(source: &str, offset: usize, reason: &str) -> Error {
+    invalid(
+        &Value::String(source.into()),
+        format!("{} at offset {}", reason, offset),
+    )
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    loop {
+        let trimmed = rest.trim_start();
+        let offset = source.len() - trimmed.len();
+        rest = trimmed;
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => break,
+        };
+        let (token, len) = if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            (Token::Ident(rest[..len].into()), len)
+        } else if c.is_ascii_digit() {
+            lex_number(source, offset, rest)?
+        } else if c == '"' || c == '\'' {
+            lex_string(source, offset, rest, c)?
+        } else if let Some(punct) = PUNCTUATION.iter().find(|p| rest.starts_with(*p)) {
+            (Token::Punct(punct), punct.len())
+        } else {
+            return Err(syntax_error(source, offset, "Unexpected character"));
+        };
+        tokens.push((offset, token));
+        rest = &rest[len..];
+    }
+    tokens.push((source.len(), Token::End));
+    Ok(tokens)
+}
+
+fn lex_number(
+    source: &str,
+    offset: usize,
+    rest: &str,
+) -> Result<(Token, usize), Error> {
+    let error = || syntax_error(source, offset, "Invalid number");
+    if let Some(hex) = rest.strip_prefix("0x") {
+        let len = hex
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(hex.len());
+        let n = u64::from_str_radix(&hex[..len], 16).map_err(|_| error())?;
+        let suffix = hex[len..].starts_with('u') as usize;
+        return Ok((Token::Number(n.into()), 2 + len + suffix));
+    }
+
+    let mut len = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let mut float = false;
+    if rest[len..].starts_with('.')
+        && rest[len + 1..].starts_with(|c: char| c.is_ascii_digit())
+    {
+        float = true;
+        len += 1 + rest[len + 1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len() - len - 1);
+    }
+    if rest[len..].starts_with(['e', 'E']) {
+        let exponent = &rest[len + 1..];
+        let sign = exponent.starts_with(['+', '-']) as usize;
+        let digits = exponent[sign..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(exponent.len() - sign);
+        if digits == 0 {
+            return Err(error());
+        }
+        float = true;
+        len += 1 + sign + digits;
+    }
+
+    let literal = &rest[..len];
+    if float {
+        let n = literal.parse::<f64>().map_err(|_| error())?;
+        let n = Number::from_f64(n).ok_or_else(error)?;
+        return Ok((Token::Number(n), len));
+    }
+    let n = literal.parse::<u64>().map_err(|_| error())?;
+    let suffix = rest[len..].starts_with('u') as usize;
+    Ok((Token::Number(n.into()), len + suffix))
+}
+
+fn lex_string(
+    source: &str,
+    offset: usize,
+    rest: &str,
+    quote: char,
+) -> Result<(Token, usize), Error> {
+    let error = |reason| syntax_error(source, offset, reason);
+    if rest.starts_with(&quote.to_string().repeat(3)) {
+        return Err(error("Triple-quoted strings are not supported"));
+    }
+    let mut s = String::new();
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            _ if c == quote => return Ok((Token::Str(s), idx + 1)),
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 'r')) => '\r',
+                    Some((_, 't')) => '\t',
+                    Some((_, c @ ('\\' | '"' | '\'' | '`' | '?'))) => c,
+                    Some((_, 'u')) => {
+                        let hex: String = (0..4)
+                            .filter_map(|_| chars.next())
+                            .map(|(_, c)| c)
+                            .collect();
+                        u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| error("Invalid unicode escape"))?
+                    }
+                    _ => return Err(error("Unsupported escape sequence")),
+                };
+                s.push(escaped);
+            }
+            '\n' => break,
+            c => s.push(c),
+        }
+    }
+    Err(error("Unterminated string"))
+}
+
+/// The operator a binary CEL operator becomes
+fn binary_operator(token: &Token) -> Option<(&'static str, u8)> {
+    Some(match token {
+        Token::Punct("||") => ("or", 1),
+        Token::Punct("&&") => ("and", 2),
+        Token::Punct("==") => ("===", 3),
+        Token::Punct("!=") => ("!==", 3),
+        Token::Punct("<") => ("<", 3),
+        Token::Punct("<=") => ("<=", 3),
+        Token::Punct(">") => (">", 3),
+        Token::Punct(">=") => (">=", 3),
+        Token::Ident(ident) if ident == "in" => ("in", 3),
+        Token::Punct("+") => ("+", 4),
+        Token::Punct("-") => ("-", 4),
+        Token::Punct("*") => ("*", 5),
+        Token::Punct("/") => ("/", 5),
+        Token::Punct("%") => ("%", 5),
+        _ => return None,
+    })
+}
+
+/// Combine two operands, collecting chains of associative operators into
+/// a single operation
+fn combine(operator: &str, left: Value, right: Value) -> Value {
+    let is_string = |value: &Value| value.is_string() || value.get("cat").is_some();
+    let operator = match operator {
+        "+" if is_string(&left) || is_string(&right) => "cat",
+        operator => operator,
+    };
+    match left {
+        Value::Object(mut obj)
+            if matches!(operator, "and" | "or" | "+" | "*" | "cat")
+                && obj.len() == 1
+                && obj.get(operator).is_some_and(Value::is_array) =>
+        {
+            if let Some(Value::Array(args)) = obj.get_mut(operator) {
+                args.push(right);
+            }
+            Value::Object(obj)
+        }
+        left => json!({ operator: [left, right] }),
+    }
+}
+
+struct Parser<'s> {
+    source: &'s str,
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// The variables of the macros being parsed, innermost last
+    scopes: Vec<String>,
+}
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].1.clone();
+        if token != Token::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        match self.peek() {
+            Token::Punct(p) if *p == punct => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), Error> {
+        match self.eat(punct) {
+            true => Ok(()),
+            false => Err(self.error(&format!("Expected '{}'", punct))),
+        }
+    }
+
+    fn error(&self, reason: &str) -> Error {
+        syntax_error(self.source, self.tokens[self.pos].0, reason)
+    }
+
+    fn expr(&mut self) -> Result<Value, Error> {
+        let condition = self.binary(1)?;
+        if !self.eat("?") {
+            return Ok(condition);
+        }
+        let then = self.binary(1)?;
+        self.expect(":")?;
+        let otherwise = self.expr()?;
+        let mut branches = vec![condition, then];
+        match otherwise {
+            // Flatten chains of ternaries into a single if
+            Value::Object(mut obj) if obj.len() == 1 && obj.contains_key("if") => {
+                if let Some(Value::Array(rest)) = obj.remove("if") {
+                    branches.extend(rest);
+                }
+            }
+            otherwise => branches.push(otherwise),
+        }
+        Ok(json!({ "if": branches }))
+    }
+
+    /// Parse operations of at least the given precedence
+    fn binary(&mut self, min_precedence: u8) -> Result<Value, Error> {
+        let mut left = self.unary()?;
+        while let Some((operator, precedence)) = binary_operator(self.peek()) {
+            if precedence < min_precedence {
+                break;
+            }
+            self.next();
+            let right = self.binary(precedence + 1)?;
+            left = combine(operator, left, right);
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Value, Error> {
+        if self.eat("!") {
+            return Ok(match self.unary()? {
+                Value::Object(mut obj) if obj.len() == 1 && obj.contains_key("!") => {
+                    json!({"!!": obj.remove("!")})
+                }
+                operand => json!({ "!": operand }),
+            });
+        }
+        if self.eat("-") {
+            return Ok(match self.unary()? {
+                Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+                    (Some(i), _) => json!(-i),
+                    (None, Some(f)) => json!(-f),
+                    _ => return Err(self.error("Invalid number")),
+                },
+                operand => json!({ "-": operand }),
+            });
+        }
+        self.member()
+    }
+
+    fn member(&mut self) -> Result<Value, Error> {
+        let mut value = self.primary()?;
+        loop {
+            if self.eat(".") {
+                let name = match self.next() {
+                    Token::Ident(name) => name,
+                    _ => return Err(self.error("Expected a field or method name")),
+                };
+                value = match self.peek() {
+                    Token::Punct("(") => self.method(value, &name)?,
+                    _ => self.field(value, &name)?,
+                };
+            } else if self.eat("[") {
+                let segment = match self.expr()? {
+                    Value::String(s) => s,
+                    Value::Number(n) if n.is_u64() => n.to_string(),
+                    _ => return Err(self.error("Only constant indexes are supported")),
+                };
+                self.expect("]")?;
+                value = self.field(value, &segment)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Access a field of a variable
+    fn field(&self, value: Value, segment: &str) -> Result<Value, Error> {
+        let segment = segment.replace('\\', "\\\\").replace('.', "\\.");
+        match value.get("var") {
+            Some(Value::String(path))
+                if value.as_object().map_or(0, |o| o.len()) == 1 =>
+            {
+                let path = match path.as_str() {
+                    "" => segment,
+                    path => format!("{}.{}", path, segment),
+                };
+                Ok(json!({ "var": path }))
+            }
+            _ => Err(self.error("Fields can only be accessed on variables")),
+        }
+    }
+
+    fn args(&mut self) -> Result<Vec<Value>, Error> {
+        self.expect("(")?;
+        let mut args = Vec::new();
+        if !self.eat(")") {
+            loop {
+                args.push(self.expr()?);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        Ok(args)
+    }
+
+    fn method(&mut self, receiver: Value, name: &str) -> Result<Value, Error> {
+        let operator = match name {
+            "startsWith" | "endsWith" | "contains" => name,
+            "all" | "exists" | "filter" | "map" => {
+                return self.macro_call(receiver, name)
+            }
+            _ => return Err(self.error(&format!("Unsupported method {}", name))),
+        };
+        let mut args = self.args()?;
+        if args.len() != 1 {
+            return Err(self.error(&format!("{} takes one argument", name)));
+        }
+        Ok(json!({ operator: [receiver, args.remove(0)] }))
+    }
+
+    fn macro_call(&mut self, receiver: Value, name: &str) -> Result<Value, Error> {
+        self.expect("(")?;
+        let variable = match self.next() {
+            Token::Ident(variable) if is_identifier(&variable) => variable,
+            _ => return Err(self.error("Expected a variable name")),
+        };
+        self.expect(",")?;
+        self.scopes.push(variable);
+        let body = self.expr();
+        self.scopes.pop();
+        let body = body?;
+        self.expect(")")?;
+        let operator = match name {
+            "exists" => "some",
+            name => name,
+        };
+        Ok(json!({ operator: [receiver, body] }))
+    }
+
+    fn primary(&mut self) -> Result<Value, Error> {
+        match self.next() {
+            Token::Number(n) => Ok(Value::Number(n)),
+            Token::Str(s) => Ok(Value::String(s)),
+            Token::Punct("(") => {
+                let value = self.expr()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Token::Punct("[") => {
+                let mut items = Vec::new();
+                while !self.eat("]") {
+                    items.push(self.expr()?);
+                    if !self.eat(",") {
+                        self.expect("]")?;
+                        break;
+                    }
+                }
+                Ok(Value::Array(items))
+            }
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                "null" => Ok(Value::Null),
+                _ if self.peek() == &Token::Punct("(") => {
+                    let operator = match ident.as_str() {
+                        "double" => "toNumber",
+                        "string" => "toString",
+                        _ => {
+                            return Err(
+                                self.error(&format!("Unsupported function {}", ident))
+                            )
+                        }
+                    };
+                    let mut args = self.args()?;
+                    if args.len() != 1 {
+                        return Err(
+                            self.error(&format!("{} takes one argument", ident))
+                        );
+                    }
+                    Ok(json!({ operator: args.remove(0) }))
+                }
+                _ => match self.scopes.last() {
+                    None => self.field(json!({"var": ""}), &ident),
+                    Some(variable) if *variable == ident => Ok(json!({"var": ""})),
+                    Some(_) => Err(self.error(&format!(
+                        "Only the macro's variable can be used in its body, not {}",
+                        ident
+                    ))),
+                },
+            },
+            Token::Punct("{") => Err(self.error("Map literals are not supported")),
+            _ => {
+                self.pos = self.pos.saturating_sub(1);
+                Err(self.error("Unexpected token"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_cel {
+    use super::*;
+
+    #[test]
+    fn test_from_cel() {
+        let cases = vec![
+            ("null", json!(null)),
+            ("true || false", json!({"or": [true, false]})),
+            ("-1.5e2", json!(-150.0)),
+            ("0x10 + 2u", json!({"+": [16, 2]})),
+            (r#"'it\'s' + "é""#, json!({"cat": ["it's", "é"]})),
+            ("a.b[0]['c.d']", json!({"var": "a.b.0.c\\.d"})),
+            (
+                "a == 1 && b != 'x' && !c",
+                json!({"and": [
+                    {"===": [{"var": "a"}, 1]},
+                    {"!==": [{"var": "b"}, "x"]},
+                    {"!": {"var": "c"}},
+                ]}),
+            ),
+            ("!!a", json!({"!!": {"var": "a"}})),
+            ("-a", json!({"-": {"var": "a"}})),
+            (
+                "1 + 2 * 3 - a % 2",
+                json!({"-": [{"+": [1, {"*": [2, 3]}]}, {"%": [{"var": "a"}, 2]}]}),
+            ),
+            ("(1 + 2) * 3", json!({"*": [{"+": [1, 2]}, 3]})),
+            (
+                "a < b <= c",
+                json!({"<=": [{"<": [{"var": "a"}, {"var": "b"}]}, {"var": "c"}]}),
+            ),
+            ("x in [1, 2,]", json!({"in": [{"var": "x"}, [1, 2]]})),
+            (
+                "a ? 1 : b ? 2 : 3",
+                json!({"if": [{"var": "a"}, 1, {"var": "b"}, 2, 3]}),
+            ),
+            (
+                "name.startsWith('A') || name.contains(\"b\")",
+                json!({"or": [
+                    {"startsWith": [{"var": "name"}, "A"]},
+                    {"contains": [{"var": "name"}, "b"]},
+                ]}),
+            ),
+            (
+                "double(s) + 1",
+                json!({"+": [{"toNumber": {"var": "s"}}, 1]}),
+            ),
+            ("string(n)", json!({"toString": {"var": "n"}})),
+            (
+                "items.all(i, i.price > 0 && i.tags.exists(t, t == 'sale'))",
+                json!({"all": [{"var": "items"}, {"and": [
+                    {">": [{"var": "price"}, 0]},
+                    {"some": [{"var": "tags"}, {"===": [{"var": ""}, "sale"]}]},
+                ]}]}),
+            ),
+            (
+                "xs.filter(x, x > 1).map(x, x * 2)",
+                json!({"map": [
+                    {"filter": [{"var": "xs"}, {">": [{"var": ""}, 1]}]},
+                    {"*": [{"var": ""}, 2]},
+                ]}),
+            ),
+        ];
+        for (expression, expected) in cases {
+            assert_eq!(from_cel(expression).unwrap(), expected, "{}", expression);
+        }
+    }
+
+    #[test]
+    fn test_unsupported() {
+        for expression in &[
+            "",
+            "a +",
+            "(a",
+            "a b",
+            "'abc",
+            "'''abc'''",
+            "a[b]",
+            "{'a': 1}",
+            "size(a)",
+            "a.size()",
+            "(a + b).c",
+            "xs.all(x, x > limit)",
+            "1 # 2",
+        ] {
+            let err = from_cel(expression).unwrap_err();
+            assert_eq!(err.code(), "invalid_data", "{}", expression);
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! Decision tables
+//!
+//! A decision table has a column for each input and one for the outcome.
+//! Each row gives a condition on each input, and the outcome when all of
+//! them hold. Rows are checked in order, and the outcome of the first
+//! matching row is the result, as with a "first" hit policy in DMN.
+//!
+//! Conditions are written in a small expression language, which applies to
+//! the value of the input named by the column:
+//!
+//! | Cell          | Matches                                  |
+//! |---------------|------------------------------------------|
+//! | empty or `-`  | anything                                 |
+//! | `>= 18`       | values compared with `>=`, `>`, `<=`, `<`, `=`, or `!=` |
+//! | `[1..10]`     | values between the bounds, inclusive     |
+//! | `US, CA`      | any of the listed values                 |
+//! | `US`          | the value itself                         |
+//!
+//! Values are read as JSON where possible, so `18` is a number and `"18"`
+//! a string, and are otherwise taken as bare strings. In JSON tables, cells
+//! may also be `null`, matching anything, or non-string values, matching
+//! themselves (or for arrays, any of their items).
+
+use serde_json::{json, Value};
+
+use super::invalid;
+use crate::error::Error;
+
+const COMPARISONS: &[(&str, &str)] = &[
+    (">=", ">="),
+    ("<=", "<="),
+    ("!=", "!="),
+    ("==", "=="),
+    (">", ">"),
+    ("<", "<"),
+    ("=", "=="),
+];
+
+/// A decision table, convertible into an equivalent rule
+///
+/// ```rust
+/// use jsonlogic_rs::import::decision_table::DecisionTable;
+/// use serde_json::json;
+///
+/// let table = DecisionTable::new(vec!["age", "country"])
+///     .row(vec![json!("< 18"), json!("-")], json!("reject"))
+///     .row(vec![json!("-"), json!("US, CA")], json!("approve"))
+///     .default_outcome(json!("review"));
+/// let rule = table.to_rule().unwrap();
+///
+/// let apply = |data| jsonlogic_rs::apply(&rule, &data).unwrap();
+/// assert_eq!(apply(json!({"age": 16, "country": "US"})), json!("reject"));
+/// assert_eq!(apply(json!({"age": 30, "country": "CA"})), json!("approve"));
+/// assert_eq!(apply(json!({"age": 30, "country": "DE"})), json!("review"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DecisionTable {
+    columns: Vec<String>,
+    rows: Vec<(Vec<Value>, Value)>,
+    default: Value,
+}
+impl DecisionTable {
+    /// Create a table with the given input columns, each naming a variable
+    pub fn new<S: Into<String>>(columns: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            columns: columns.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Add a row, with a condition for each column and its outcome
+    pub fn row(mut self, conditions: Vec<Value>, outcome: Value) -> Self {
+        self.rows.push((conditions, outcome));
+        self
+    }
+
+    /// Set the result when no row matches, which is otherwise `null`
+    pub fn default_outcome(mut self, outcome: Value) -> Self {
+        self.default = outcome;
+        self
+    }
+
+    /// Read a table from JSON
+    ///
+    /// The table is an object with the names of its input `columns`, its
+    /// `rows`, each an array of a condition for each column followed by the
+    /// outcome, and optionally a `default` outcome:
+    ///
+    /// ```json
+    /// {
+    ///     "columns": ["age", "country"],
+    ///     "rows": [["< 18", null, "reject"], [null, ["US", "CA"], "approve"]],
+    ///     "default": "review"
+    /// }
+    /// ```
+    pub fn from_json(table: &Value) -> Result<Self, Error> {
+        let columns = match table.get("columns") {
+            Some(Value::Array(columns)) => columns
+                .iter()
+                .map(|column| {
+                    column.as_str().map(String::from).ok_or_else(|| {
+                        invalid(column, "Column names must be strings".into())
+                    })
+                })
+                .collect::<Result<Vec<String>, Error>>()?,
+            _ => {
+                return Err(invalid(
+                    table,
+                    "Decision table must have an array of columns".into(),
+                ))
+            }
+        };
+        let rows = match table.get("rows") {
+            Some(Value::Array(rows)) => rows
+                .iter()
+                .map(
+                    |row| match row.as_array().and_then(|row| row.split_last()) {
+                        Some((outcome, conditions)) => {
+                            Ok((conditions.to_vec(), outcome.clone()))
+                        }
+                        None => Err(invalid(
+                            row,
+                            "Rows must be arrays of conditions and an outcome".into(),
+                        )),
+                    },
+                )
+                .collect::<Result<Vec<(Vec<Value>, Value)>, Error>>()?,
+            _ => {
+                return Err(invalid(
+                    table,
+                    "Decision table must have an array of rows".into(),
+                ))
+            }
+        };
+        Ok(Self {
+            columns,
+            rows,
+            default: table.get("default").cloned().unwrap_or(Value::Null),
+        })
+    }
+
+    /// Read a table from CSV with a header row, given the name of the
+    /// outcome column
+    ///
+    /// Every other column is an input. Outcomes are read as JSON where
+    /// possible, and are otherwise strings.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::import::decision_table::DecisionTable;
+    /// use serde_json::json;
+    ///
+    /// let csv = "\
+    /// age,income,rate
+    /// < 18,-,0
+    /// [18..65],>= 50000,0.05
+    /// -,-,0.1
+    /// ";
+    /// let rule = DecisionTable::from_csv(csv.as_bytes(), "rate")
+    ///     .unwrap()
+    ///     .to_rule()
+    ///     .unwrap();
+    /// let data = json!({"age": 40, "income": 80000});
+    /// assert_eq!(jsonlogic_rs::apply(&rule, &data).unwrap(), json!(0.05));
+    /// ```
+    #[cfg(feature = "csv")]
+    pub fn from_csv<R: std::io::Read>(reader: R, outcome: &str) -> Result<Self, Error> {
+        let error = |err: csv::Error| invalid(&Value::Null, err.to_string());
+        let mut reader = csv::Reader::from_reader(reader);
+        let headers = reader.headers().map_err(error)?.clone();
+        let outcome_idx = headers
+            .iter()
+            .position(|header| header == outcome)
+            .ok_or_else(|| {
+                invalid(
+                    &Value::String(outcome.into()),
+                    "Decision table has no such outcome column".into(),
+                )
+            })?;
+
+        let mut table = Self::new(
+            headers
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| *idx != outcome_idx)
+                .map(|(_, header)| header.trim()),
+        );
+        for record in reader.records() {
+            let record = record.map_err(error)?;
+            let mut conditions = Vec::with_capacity(table.columns.len());
+            let mut outcome = Value::Null;
+            for (idx, cell) in record.iter().enumerate() {
+                if idx == outcome_idx {
+                    outcome = parse_value(cell.trim());
+                } else {
+                    conditions.push(Value::String(cell.into()));
+                }
+            }
+            table.rows.push((conditions, outcome));
+        }
+        Ok(table)
+    }
+
+    /// Convert the table into an equivalent rule
+    ///
+    /// The rule is an `if` with the conditions of each row, combined with
+    /// `and`, followed by the row's outcome. Rows after one that matches
+    /// anything can never match, so are left out.
+    pub fn to_rule(&self) -> Result<Value, Error> {
+        let mut branches = Vec::with_capacity(self.rows.len() * 2 + 1);
+        let mut default = &self.default;
+        for (conditions, outcome) in &self.rows {
+            if conditions.len() != self.columns.len() {
+                return Err(invalid(
+                    &Value::Array(conditions.clone()),
+                    format!(
+                        "Row has {} conditions, but the table has {} columns",
+                        conditions.len(),
+                        self.columns.len()
+                    ),
+                ));
+            }
+            let mut tests = self
+                .columns
+                .iter()
+                .zip(conditions)
+                .filter_map(|(column, cell)| condition(column, cell).transpose())
+                .collect::<Result<Vec<Value>, Error>>()?;
+            match tests.len() {
+                0 => {
+                    default = outcome;
+                    break;
+                }
+                1 => branches.push(tests.remove(0)),
+                _ => branches.push(json!({ "and": tests })),
+            }
+            branches.push(outcome.clone());
+        }
+        if branches.is_empty() {
+            return Ok(default.clone());
+        }
+        branches.push(default.clone());
+        Ok(json!({ "if": branches }))
+    }
+}
+
+/// Convert a cell into a condition on its column's variable
+///
+/// Returns `None` for cells matching anything.
+fn condition(column: &str, cell: &Value) -> Result<Option<Value>, Error> {
+    let var = json!({ "var": column });
+    let s = match cell {
+        Value::Null => return Ok(None),
+        Value::Array(_) => return Ok(Some(json!({"in": [var, cell]}))),
+        Value::String(s) => s.trim(),
+        _ => return Ok(Some(json!({"==": [var, cell]}))),
+    };
+    if s.is_empty() || s == "-" {
+        return Ok(None);
+    }
+
+    if let Some(range) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        if let Some((low, high)) = range.split_once("..") {
+            let (low, high) = (parse_value(low.trim()), parse_value(high.trim()));
+            if !low.is_number() || !high.is_number() {
+                return Err(invalid(cell, "Range bounds must be numbers".into()));
+            }
+            return Ok(Some(json!({"<=": [low, var, high]})));
+        }
+    }
+    for (prefix, operator) in COMPARISONS {
+        if let Some(operand) = s.strip_prefix(prefix) {
+            let operand = operand.trim();
+            if operand.is_empty() {
+                return Err(invalid(cell, format!("Missing value after {}", prefix)));
+            }
+            return Ok(Some(json!({ *operator: [var, parse_value(operand)] })));
+        }
+    }
+
+    let items = split_list(s);
+    if items.len() > 1 {
+        let items: Vec<Value> = items.into_iter().map(parse_value).collect();
+        return Ok(Some(json!({"in": [var, items]})));
+    }
+    Ok(Some(match parse_value(s) {
+        list @ Value::Array(_) => json!({"in": [var, list]}),
+        value => json!({"==": [var, value]}),
+    }))
+}
+
+/// Read a value as JSON, or as a bare string if it isn't valid JSON
+fn parse_value(s: &str) -> Value {
+    serde_json::from_str(s).unwrap_or_else(|_| Value::String(s.into()))
+}
+
+/// Split a list of values on commas outside of quotes and brackets
+fn split_list(s: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let (mut start, mut depth, mut quoted, mut escaped) = (0, 0, false, false);
+    for (idx, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '[' | '{' if !quoted => depth += 1,
+            ']' | '}' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                items.push(s[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(s[start..].trim());
+    items
+}
+
+#[cfg(test)]
+mod test_decision_table {
+    use super::*;
+
+    #[test]
+    fn test_conditions() {
+        let cases = vec![
+            (json!(null), None),
+            (json!(" - "), None),
+            (json!(""), None),
+            (json!(">= 18"), Some(json!({">=": [{"var": "x"}, 18]}))),
+            (json!("<1.5"), Some(json!({"<": [{"var": "x"}, 1.5]}))),
+            (json!("= US"), Some(json!({"==": [{"var": "x"}, "US"]}))),
+            (
+                json!("!= \"18\""),
+                Some(json!({"!=": [{"var": "x"}, "18"]})),
+            ),
+            (json!("[1..10]"), Some(json!({"<=": [1, {"var": "x"}, 10]}))),
+            (
+                json!("US, CA"),
+                Some(json!({"in": [{"var": "x"}, ["US", "CA"]]})),
+            ),
+            (
+                json!(r#""a, b", c"#),
+                Some(json!({"in": [{"var": "x"}, ["a, b", "c"]]})),
+            ),
+            (json!("[1, 2]"), Some(json!({"in": [{"var": "x"}, [1, 2]]}))),
+            (json!("true"), Some(json!({"==": [{"var": "x"}, true]}))),
+            (json!("gold"), Some(json!({"==": [{"var": "x"}, "gold"]}))),
+            (json!(3), Some(json!({"==": [{"var": "x"}, 3]}))),
+            (json!([3, 4]), Some(json!({"in": [{"var": "x"}, [3, 4]]}))),
+        ];
+        for (cell, expected) in cases {
+            assert_eq!(condition("x", &cell).unwrap(), expected, "{}", cell);
+        }
+        assert!(condition("x", &json!(">=")).is_err());
+        assert!(condition("x", &json!("[a..b]")).is_err());
+    }
+
+    #[test]
+    fn test_to_rule() {
+        let table = DecisionTable::new(vec!["a", "b"])
+            .row(vec![json!("> 1"), json!("x")], json!(1))
+            .row(vec![json!("-"), json!("y, z")], json!(2))
+            .row(vec![json!("-"), json!(null)], json!(3))
+            .row(vec![json!("> 100"), json!(null)], json!(4));
+        assert_eq!(
+            table.to_rule().unwrap(),
+            json!({"if": [
+                {"and": [{">": [{"var": "a"}, 1]}, {"==": [{"var": "b"}, "x"]}]}, 1,
+                {"in": [{"var": "b"}, ["y", "z"]]}, 2,
+                3,
+            ]})
+        );
+        assert_eq!(
+            DecisionTable::new(vec!["a"])
+                .default_outcome(json!("none"))
+                .to_rule()
+                .unwrap(),
+            json!("none")
+        );
+        assert!(DecisionTable::new(vec!["a"])
+            .row(vec![], json!(1))
+            .to_rule()
+            .is_err());
+    }
+
+    #[test]
+    fn test_from_json() {
+        let table = DecisionTable::from_json(&json!({
+            "columns": ["tier", "spend"],
+            "rows": [
+                ["gold", null, 0.2],
+                [null, "[100..1000]", 0.1],
+            ],
+            "default": 0,
+        }))
+        .unwrap();
+        let rule = table.to_rule().unwrap();
+        let apply = |data| crate::apply(&rule, &data).unwrap();
+        assert_eq!(apply(json!({"tier": "gold", "spend": 5})), json!(0.2));
+        assert_eq!(apply(json!({"tier": "silver", "spend": 500})), json!(0.1));
+        assert_eq!(apply(json!({"tier": "silver", "spend": 5})), json!(0));
+
+        assert!(DecisionTable::from_json(&json!({"rows": []})).is_err());
+        assert!(
+            DecisionTable::from_json(&json!({"columns": [1], "rows": []})).is_err()
+        );
+        assert!(
+            DecisionTable::from_json(&json!({"columns": [], "rows": [[]]})).is_err()
+        );
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_from_csv() {
+        let csv = "outcome,country,age\napprove,\"US, CA\",>= 18\nreject,-,-\n";
+        let rule = DecisionTable::from_csv(csv.as_bytes(), "outcome")
+            .unwrap()
+            .to_rule()
+            .unwrap();
+        assert_eq!(
+            rule,
+            json!({"if": [
+                {"and": [
+                    {"in": [{"var": "country"}, ["US", "CA"]]},
+                    {">=": [{"var": "age"}, 18]},
+                ]},
+                "approve",
+                "reject",
+            ]})
+        );
+        assert!(DecisionTable::from_csv(csv.as_bytes(), "missing").is_err());
+    }
+}
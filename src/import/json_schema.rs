@@ -0,0 +1,277 @@
+//! Converting JSON Schema constraints into rules
+//!
+//! Schemas already describing which documents are acceptable can be reused
+//! as eligibility rules. The supported keywords are:
+//!
+//! - `properties` and `required`
+//! - `enum` and `const`
+//! - `minimum`, `maximum`, `exclusiveMinimum`, and `exclusiveMaximum`
+//! - `if`, `then`, and `else`
+//! - `allOf`, `anyOf`, and `not`
+//!
+//! Annotations like `title` and `description` are ignored, as is `type`,
+//! since JsonLogic's comparisons coerce their operands. Other keywords are
+//! rejected with an error.
+//!
+//! Note that JSON Schema ignores constraints on properties that are absent,
+//! while the rules they're converted into don't, so that e.g. a `minimum`
+//! fails if the property is missing. This is usually what eligibility rules
+//! need, and makes `required` unnecessary in most cases.
+
+use serde_json::{json, Value};
+
+use super::invalid;
+use crate::error::Error;
+
+/// Keywords that don't constrain the data
+const ANNOTATIONS: &[&str] = &[
+    "$comment",
+    "$id",
+    "$schema",
+    "default",
+    "deprecated",
+    "description",
+    "examples",
+    "readOnly",
+    "title",
+    "type",
+    "writeOnly",
+];
+
+/// Convert a JSON Schema into a rule that is truthy for data it accepts
+///
+/// ```rust
+/// use jsonlogic_rs::import::json_schema::from_schema;
+/// use serde_json::json;
+///
+/// let schema = json!({
+///     "properties": {"country": {"enum": ["US", "CA"]}},
+///     "if": {"properties": {"country": {"const": "US"}}},
+///     "then": {"properties": {"age": {"minimum": 21}}},
+///     "else": {"properties": {"age": {"minimum": 18}}},
+/// });
+/// let rule = from_schema(&schema).unwrap();
+///
+/// let apply = |data| jsonlogic_rs::apply(&rule, &data).unwrap();
+/// assert_eq!(apply(json!({"country": "US", "age": 20})), json!(false));
+/// assert_eq!(apply(json!({"country": "CA", "age": 20})), json!(true));
+/// assert_eq!(apply(json!({"country": "DE", "age": 30})), json!(false));
+/// ```
+pub fn from_schema(schema: &Value) -> Result<Value, Error> {
+    constraints(schema, "")
+}
+
+/// Escape a property name for use as a segment of a `var` path
+fn join(path: &str, name: &str) -> String {
+    let name = name.replace('\\', "\\\\").replace('.', "\\.");
+    match path {
+        "" => name,
+        path => format!("{}.{}", path, name),
+    }
+}
+
+fn all(mut conditions: Vec<Value>) -> Value {
+    match conditions.len() {
+        0 => Value::Bool(true),
+        1 => conditions.remove(0),
+        _ => json!({ "and": conditions }),
+    }
+}
+
+/// Convert the constraints of a schema on the value at a path
+fn constraints(schema: &Value, path: &str) -> Result<Value, Error> {
+    let keywords = match schema {
+        Value::Bool(accept) => return Ok(Value::Bool(*accept)),
+        Value::Object(keywords) => keywords,
+        _ => {
+            return Err(invalid(
+                schema,
+                "Schemas must be objects or booleans".into(),
+            ))
+        }
+    };
+    let var = || json!({ "var": path });
+    let number = |keyword: &str, value: &Value| match value {
+        Value::Number(_) => Ok(value.clone()),
+        _ => Err(invalid(value, format!("{} must be a number", keyword))),
+    };
+    let subschemas = |keyword: &str, value: &Value| match value {
+        Value::Array(schemas) if !schemas.is_empty() => schemas
+            .iter()
+            .map(|schema| constraints(schema, path))
+            .collect::<Result<Vec<Value>, Error>>(),
+        _ => Err(invalid(
+            value,
+            format!("{} must be a non-empty array of schemas", keyword),
+        )),
+    };
+
+    let mut conditions = Vec::new();
+    for (keyword, value) in keywords {
+        match keyword.as_str() {
+            "properties" => {
+                let properties = value.as_object().ok_or_else(|| {
+                    invalid(value, "properties must be an object".into())
+                })?;
+                for (name, schema) in properties {
+                    match constraints(schema, &join(path, name))? {
+                        Value::Bool(true) => {}
+                        condition => conditions.push(condition),
+                    }
+                }
+            }
+            "required" => {
+                let names = value
+                    .as_array()
+                    .and_then(|names| {
+                        names
+                            .iter()
+                            .map(|name| name.as_str().map(|name| join(path, name)))
+                            .collect::<Option<Vec<String>>>()
+                    })
+                    .ok_or_else(|| {
+                        invalid(value, "required must be an array of strings".into())
+                    })?;
+                if !names.is_empty() {
+                    conditions.push(json!({"!": {"missing": names}}));
+                }
+            }
+            "enum" => match value {
+                Value::Array(_) => conditions.push(json!({"in": [var(), value]})),
+                _ => return Err(invalid(value, "enum must be an array".into())),
+            },
+            "const" => conditions.push(json!({"===": [var(), value]})),
+            "minimum" => {
+                conditions.push(json!({">=": [var(), number(keyword, value)?]}))
+            }
+            "maximum" => {
+                conditions.push(json!({"<=": [var(), number(keyword, value)?]}))
+            }
+            "exclusiveMinimum" => {
+                conditions.push(json!({">": [var(), number(keyword, value)?]}))
+            }
+            "exclusiveMaximum" => {
+                conditions.push(json!({"<": [var(), number(keyword, value)?]}))
+            }
+            "if" => {
+                let branch = |keyword: &str| {
+                    keywords
+                        .get(keyword)
+                        .map_or(Ok(Value::Bool(true)), |schema| {
+                            constraints(schema, path)
+                        })
+                };
+                conditions.push(json!({"if": [
+                    constraints(value, path)?,
+                    branch("then")?,
+                    branch("else")?,
+                ]}));
+            }
+            // Without an if, these have no effect
+            "then" | "else" => {}
+            "allOf" => conditions.extend(subschemas(keyword, value)?),
+            "anyOf" => conditions.push(json!({"or": subschemas(keyword, value)?})),
+            "not" => conditions.push(json!({"!": constraints(value, path)?})),
+            keyword if ANNOTATIONS.contains(&keyword) => {}
+            keyword => {
+                return Err(invalid(
+                    schema,
+                    format!("The {} keyword is not supported", keyword),
+                ))
+            }
+        }
+    }
+    Ok(all(conditions))
+}
+
+#[cfg(test)]
+mod test_json_schema {
+    use super::*;
+
+    #[test]
+    fn test_from_schema() {
+        let cases = vec![
+            (json!(true), json!(true)),
+            (json!({}), json!(true)),
+            (json!({"title": "Anything", "type": "object"}), json!(true)),
+            (json!({"minimum": 1}), json!({">=": [{"var": ""}, 1]})),
+            (
+                json!({"properties": {"a": {
+                    "exclusiveMinimum": 0,
+                    "exclusiveMaximum": 10,
+                }}}),
+                json!({"and": [
+                    {"<": [{"var": "a"}, 10]},
+                    {">": [{"var": "a"}, 0]},
+                ]}),
+            ),
+            (
+                json!({"properties": {"user": {"properties": {
+                    "plan": {"enum": ["pro", "team"]},
+                    "v1.0": {"const": true},
+                }}}}),
+                json!({"and": [
+                    {"in": [{"var": "user.plan"}, ["pro", "team"]]},
+                    {"===": [{"var": "user.v1\\.0"}, true]},
+                ]}),
+            ),
+            (
+                json!({"required": ["a", "b"]}),
+                json!({"!": {"missing": ["a", "b"]}}),
+            ),
+            (
+                json!({"anyOf": [{"properties": {"a": {"const": 1}}}, false]}),
+                json!({"or": [{"===": [{"var": "a"}, 1]}, false]}),
+            ),
+            (
+                json!({"allOf": [{"maximum": 5}, {"not": {"const": 3}}]}),
+                json!({"and": [
+                    {"<=": [{"var": ""}, 5]},
+                    {"!": {"===": [{"var": ""}, 3]}},
+                ]}),
+            ),
+            (
+                json!({"if": {"const": 1}, "then": {"maximum": 0}}),
+                json!({"if": [{"===": [{"var": ""}, 1]}, {"<=": [{"var": ""}, 0]}, true]}),
+            ),
+            (json!({"then": {"maximum": 0}}), json!(true)),
+        ];
+        for (schema, expected) in cases {
+            assert_eq!(from_schema(&schema).unwrap(), expected, "{}", schema);
+        }
+    }
+
+    #[test]
+    fn test_unsupported() {
+        for schema in &[
+            json!(1),
+            json!({"pattern": "^a"}),
+            json!({"properties": {"a": {"oneOf": [true]}}}),
+            json!({"minimum": "1"}),
+            json!({"enum": "a"}),
+            json!({"required": [1]}),
+            json!({"anyOf": []}),
+            json!({"properties": []}),
+        ] {
+            let err = from_schema(schema).unwrap_err();
+            assert_eq!(err.code(), "invalid_data", "{}", schema);
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let rule = from_schema(&json!({
+            "required": ["income"],
+            "properties": {
+                "income": {"minimum": 30000},
+                "state": {"not": {"enum": ["NY", "TX"]}},
+            },
+        }))
+        .unwrap();
+        let apply = |data| crate::apply(&rule, &data).unwrap();
+        assert_eq!(apply(json!({"income": 40000, "state": "CA"})), json!(true));
+        assert_eq!(apply(json!({"income": 40000, "state": "NY"})), json!(false));
+        assert_eq!(apply(json!({"income": 20000, "state": "CA"})), json!(false));
+        assert_eq!(apply(json!({"state": "CA"})), json!(false));
+    }
+}
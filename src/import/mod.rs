@@ -0,0 +1,23 @@
+//! Translating other rule formats into rules
+//!
+//! This is the inverse of [`transpile`](crate::transpile): rules authored in
+//! other formats are converted into equivalent JsonLogic, so they can be
+//! evaluated by the same engine as any other rule. Input that can't be
+//! converted is rejected with an error, rather than converted into a rule
+//! that might behave differently.
+
+use serde_json::Value;
+
+use crate::error::Error;
+
+#[cfg(feature = "cel")]
+pub mod cel;
+pub mod decision_table;
+pub mod json_schema;
+
+fn invalid(value: &Value, reason: String) -> Error {
+    Error::InvalidData {
+        value: value.clone(),
+        reason,
+    }
+}
@@ -1,13 +1,16 @@
 //! Implementations of JavaScript operators for JSON Values
 
 use serde_json::{Number, Value};
+use std::borrow::Cow;
 use std::f64;
 use std::str::FromStr;
 
+use crate::engine::DecimalSeparator;
 use crate::error::Error;
+pub use crate::op::truthy;
 
 // numeric characters according to parseFloat
-const NUMERICS: &'static [char] = &[
+const NUMERICS: &[char] = &[
     '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '.', '-', '+', 'e', 'E',
 ];
 
@@ -56,12 +59,113 @@ fn to_primitive_number(value: &Value) -> Option<f64> {
     }
 }
 
+/// Convert a string to a number as JS' `Number()` would
+///
+/// Returns None where `Number()` would return `NaN`. As in JS, surrounding
+/// whitespace is ignored, so that an empty or blank string is 0, and hex,
+/// octal, and binary integers like "0x1F" are accepted, as are "Infinity"
+/// and "-Infinity". Spellings that Rust accepts but JS does not, like "inf"
+/// and "NaN", are not.
+///
+/// ```rust
+/// use jsonlogic_rs::js_op::str_to_number;
+///
+/// assert_eq!(str_to_number(" 1.5\n"), Some(1.5));
+/// assert_eq!(str_to_number(""), Some(0.0));
+/// assert_eq!(str_to_number("0x1F"), Some(31.0));
+/// assert_eq!(str_to_number("-Infinity"), Some(f64::NEG_INFINITY));
+/// assert_eq!(str_to_number("inf"), None);
+/// assert_eq!(str_to_number("1 2"), None);
+/// ```
 pub fn str_to_number<S: AsRef<str>>(string: S) -> Option<f64> {
-    let s = string.as_ref();
-    if s == "" {
-        Some(0.0)
-    } else {
-        f64::from_str(s).ok()
+    // JS' whitespace, which doesn't include U+0085 but does include the BOM
+    let s = string.as_ref().trim_matches(|c: char| {
+        (c.is_whitespace() && c != '\u{85}') || c == '\u{feff}'
+    });
+    if s.is_empty() {
+        return Some(0.0);
+    }
+    let radix = match s.get(..2) {
+        Some("0x" | "0X") => Some(16),
+        Some("0o" | "0O") => Some(8),
+        Some("0b" | "0B") => Some(2),
+        _ => None,
+    };
+    if let Some(radix) = radix {
+        let digits = &s[2..];
+        if digits.is_empty() {
+            return None;
+        }
+        // Accumulated as a float, like JS, so that long literals round
+        // rather than overflow
+        return digits.chars().try_fold(0.0, |acc, c| {
+            c.to_digit(radix)
+                .map(|digit| acc * f64::from(radix) + f64::from(digit))
+        });
+    }
+    if s.trim_start_matches(['+', '-']) == "Infinity" {
+        return f64::from_str(s).ok();
+    }
+    if s.contains(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E') {
+        return None;
+    }
+    f64::from_str(s).ok()
+}
+
+/// Convert a string to a number, with the given decimal separator
+///
+/// ```rust
+/// use jsonlogic_rs::DecimalSeparator;
+/// use jsonlogic_rs::js_op::str_to_number_with;
+///
+/// assert_eq!(str_to_number_with("1.234,56", DecimalSeparator::Comma), Some(1234.56));
+/// assert_eq!(str_to_number_with("1.234,56", DecimalSeparator::Point), None);
+/// ```
+pub fn str_to_number_with<S: AsRef<str>>(
+    string: S,
+    separator: DecimalSeparator,
+) -> Option<f64> {
+    str_to_number(normalize_decimal(string.as_ref(), separator))
+}
+
+/// Rewrite a leading number in a string to use a decimal point
+///
+/// With `DecimalSeparator::Comma`, thousands separators (`.`) are dropped
+/// from the leading number and the decimal comma becomes a point, so
+/// "1.234,56 EUR" becomes "1234.56 EUR". Anything after the number is left
+/// as is, so that it is still accepted by `parse_float` but not by
+/// `str_to_number`.
+pub fn normalize_decimal(string: &str, separator: DecimalSeparator) -> Cow<'_, str> {
+    match separator {
+        DecimalSeparator::Point => Cow::Borrowed(string),
+        DecimalSeparator::Comma => {
+            let start = string.len() - string.trim_start().len();
+            let end = string[start..]
+                .char_indices()
+                .find(|&(idx, c)| {
+                    !(c.is_ascii_digit()
+                        || c == '.'
+                        || c == ','
+                        || (idx == 0 && (c == '-' || c == '+')))
+                })
+                .map(|(idx, _)| start + idx)
+                .unwrap_or_else(|| string.len());
+            let number = &string[start..end];
+            if !number.contains(['.', ',']) {
+                return Cow::Borrowed(string);
+            }
+            let mut normalized = String::with_capacity(string.len());
+            normalized.push_str(&string[..start]);
+            normalized.extend(number.chars().filter(|&c| c != '.').map(|c| {
+                if c == ',' {
+                    '.'
+                } else {
+                    c
+                }
+            }));
+            normalized.push_str(&string[end..]);
+            Cow::Owned(normalized)
+        }
     }
 }
 
@@ -240,7 +344,7 @@ pub fn abstract_eq(first: &Value, second: &Value) -> bool {
         }
         // 6. If Type(x) is Boolean, return the result of the comparison ToNumber(x) == y.
         (Value::Bool(x), _) => match x {
-            true => Number::from_f64(1 as f64)
+            true => Number::from_f64(1_f64)
                 .map(|num| {
                     let value = Value::Number(num);
                     abstract_eq(&value, second)
@@ -255,7 +359,7 @@ pub fn abstract_eq(first: &Value, second: &Value) -> bool {
         },
         // 7. If Type(y) is Boolean, return the result of the comparison x == ToNumber(y).
         (_, Value::Bool(y)) => match y {
-            true => Number::from_f64(1 as f64)
+            true => Number::from_f64(1_f64)
                 .map(|num| {
                     let value = Value::Number(num);
                     abstract_eq(first, &value)
@@ -342,6 +446,25 @@ pub fn strict_ne(first: &Value, second: &Value) -> bool {
     !strict_eq(first, second)
 }
 
+/// Compare values as JS' Abstract Relational Comparison does
+///
+/// Returns whether the first value is less than the second, or None where
+/// JS would return `undefined` because a value converts to `NaN`. The
+/// relational operators are all defined in terms of this, so that e.g.
+/// `null <= 0` is true, as `0 < null` is false.
+pub(crate) fn abstract_rel(first: &Value, second: &Value) -> Option<bool> {
+    let lt = |f: f64, s: f64| f.partial_cmp(&s).map(|_| f < s);
+    match (
+        to_primitive(first, PrimitiveHint::Number),
+        to_primitive(second, PrimitiveHint::Number),
+    ) {
+        (Primitive::String(f), Primitive::String(s)) => Some(f < s),
+        (Primitive::Number(f), Primitive::Number(s)) => lt(f, s),
+        (Primitive::String(f), Primitive::Number(s)) => lt(str_to_number(f)?, s),
+        (Primitive::Number(f), Primitive::String(s)) => lt(f, str_to_number(s)?),
+    }
+}
+
 /// Perform JS-style abstract less-than
 ///
 ///
@@ -356,27 +479,7 @@ pub fn strict_ne(first: &Value, second: &Value) -> bool {
 /// assert_eq!(abstract_lt(&json!(0), &json!("a")), false);
 /// ```
 pub fn abstract_lt(first: &Value, second: &Value) -> bool {
-    match (
-        to_primitive(first, PrimitiveHint::Number),
-        to_primitive(second, PrimitiveHint::Number),
-    ) {
-        (Primitive::String(f), Primitive::String(s)) => f < s,
-        (Primitive::Number(f), Primitive::Number(s)) => f < s,
-        (Primitive::String(f), Primitive::Number(s)) => {
-            if let Some(f) = str_to_number(f) {
-                f < s
-            } else {
-                false
-            }
-        }
-        (Primitive::Number(f), Primitive::String(s)) => {
-            if let Some(s) = str_to_number(s) {
-                f < s
-            } else {
-                false
-            }
-        }
-    }
+    abstract_rel(first, second) == Some(true)
 }
 
 /// JS-style abstract gt
@@ -391,27 +494,7 @@ pub fn abstract_lt(first: &Value, second: &Value) -> bool {
 /// assert_eq!(abstract_gt(&json!("1"), &json!(0)), true);
 /// ```
 pub fn abstract_gt(first: &Value, second: &Value) -> bool {
-    match (
-        to_primitive(first, PrimitiveHint::Number),
-        to_primitive(second, PrimitiveHint::Number),
-    ) {
-        (Primitive::String(f), Primitive::String(s)) => f > s,
-        (Primitive::Number(f), Primitive::Number(s)) => f > s,
-        (Primitive::String(f), Primitive::Number(s)) => {
-            if let Some(f) = str_to_number(f) {
-                f > s
-            } else {
-                false
-            }
-        }
-        (Primitive::Number(f), Primitive::String(s)) => {
-            if let Some(s) = str_to_number(s) {
-                f > s
-            } else {
-                false
-            }
-        }
-    }
+    abstract_rel(second, first) == Some(true)
 }
 
 /// Abstract inequality
@@ -421,18 +504,18 @@ pub fn abstract_ne(first: &Value, second: &Value) -> bool {
 
 /// Provide abstract <= comparisons
 pub fn abstract_lte(first: &Value, second: &Value) -> bool {
-    abstract_lt(first, second) || abstract_eq(first, second)
+    abstract_rel(second, first) == Some(false)
 }
 
 /// Provide abstract >= comparisons
 pub fn abstract_gte(first: &Value, second: &Value) -> bool {
-    abstract_gt(first, second) || abstract_eq(first, second)
+    abstract_rel(first, second) == Some(false)
 }
 
 /// Get the max of an array of values, performing abstract type conversion
-pub fn abstract_max(items: &Vec<&Value>) -> Result<f64, Error> {
+pub fn abstract_max(items: &[&Value]) -> Result<f64, Error> {
     items
-        .into_iter()
+        .iter()
         .map(|v| {
             to_number(v).ok_or_else(|| Error::InvalidArgument {
                 value: (*v).clone(),
@@ -440,43 +523,33 @@ pub fn abstract_max(items: &Vec<&Value>) -> Result<f64, Error> {
                 reason: "Could not convert value to number".into(),
             })
         })
-        .fold(Ok(f64::NEG_INFINITY), |acc, cur| {
-            let max = acc?;
-            match cur {
-                Ok(num) => {
-                    if num > max {
-                        Ok(num)
-                    } else {
-                        Ok(max)
-                    }
-                }
-                _ => cur,
+        .try_fold(f64::NEG_INFINITY, |max, cur| {
+            let num = cur?;
+            if num > max {
+                Ok(num)
+            } else {
+                Ok(max)
             }
         })
 }
 
 /// Get the max of an array of values, performing abstract type conversion
-pub fn abstract_min(items: &Vec<&Value>) -> Result<f64, Error> {
+pub fn abstract_min(items: &[&Value]) -> Result<f64, Error> {
     items
-        .into_iter()
+        .iter()
         .map(|v| {
             to_number(v).ok_or_else(|| Error::InvalidArgument {
                 value: (*v).clone(),
-                operation: "max".into(),
+                operation: "min".into(),
                 reason: "Could not convert value to number".into(),
             })
         })
-        .fold(Ok(f64::INFINITY), |acc, cur| {
-            let min = acc?;
-            match cur {
-                Ok(num) => {
-                    if num < min {
-                        Ok(num)
-                    } else {
-                        Ok(min)
-                    }
-                }
-                _ => cur,
+        .try_fold(f64::INFINITY, |min, cur| {
+            let num = cur?;
+            if num < min {
+                Ok(num)
+            } else {
+                Ok(min)
             }
         })
 }
@@ -486,11 +559,11 @@ pub fn abstract_plus(first: &Value, second: &Value) -> Value {
     let first_num = to_primitive_number(first);
     let second_num = to_primitive_number(second);
 
-    match (first_num, second_num) {
-        (Some(f), Some(s)) => {
-            return Value::Number(Number::from_f64(f + s).unwrap());
-        }
-        _ => {}
+    if let (Some(f), Some(s)) = (first_num, second_num) {
+        // Like JSON.stringify(), represent non-finite results as null
+        return Number::from_f64(f + s)
+            .map(Value::Number)
+            .unwrap_or(Value::Null);
     };
 
     let first_string = to_string(first);
@@ -515,8 +588,8 @@ pub fn abstract_plus(first: &Value, second: &Value) -> Value {
 /// the behavior for non-numeric inputs is not specified in the spec,
 /// and returning errors seems like a more reasonable course of action
 /// than returning null.
-pub fn parse_float_add(vals: &Vec<&Value>) -> Result<f64, Error> {
-    vals.into_iter()
+pub fn parse_float_add(vals: &[&Value]) -> Result<f64, Error> {
+    vals.iter()
         .map(|&v| {
             parse_float(v).ok_or_else(|| Error::InvalidArgument {
                 value: v.clone(),
@@ -524,13 +597,7 @@ pub fn parse_float_add(vals: &Vec<&Value>) -> Result<f64, Error> {
                 reason: "Argument could not be converted to a float".into(),
             })
         })
-        .fold(Ok(0.0), |acc, cur| {
-            let total = acc?;
-            match cur {
-                Ok(num) => Ok(total + num),
-                _ => cur,
-            }
-        })
+        .try_fold(0.0, |total, cur| cur.map(|num| total + num))
 }
 
 /// Multiply values, parsing to floats first
@@ -538,8 +605,8 @@ pub fn parse_float_add(vals: &Vec<&Value>) -> Result<f64, Error> {
 /// See notes for parse_float_add on how this differs from normal number
 /// conversion as is done for _other_ arithmetic operators in the reference
 /// implementation
-pub fn parse_float_mul(vals: &Vec<&Value>) -> Result<f64, Error> {
-    vals.into_iter()
+pub fn parse_float_mul(vals: &[&Value]) -> Result<f64, Error> {
+    vals.iter()
         .map(|&v| {
             parse_float(v).ok_or_else(|| Error::InvalidArgument {
                 value: v.clone(),
@@ -547,13 +614,7 @@ pub fn parse_float_mul(vals: &Vec<&Value>) -> Result<f64, Error> {
                 reason: "Argument could not be converted to a float".into(),
             })
         })
-        .fold(Ok(1.0), |acc, cur| {
-            let total = acc?;
-            match cur {
-                Ok(num) => Ok(total * num),
-                _ => cur,
-            }
-        })
+        .try_fold(1.0, |total, cur| cur.map(|num| total * num))
 }
 
 /// Do minus
@@ -561,14 +622,14 @@ pub fn abstract_minus(first: &Value, second: &Value) -> Result<f64, Error> {
     let first_num = to_number(first);
     let second_num = to_number(second);
 
-    if let None = first_num {
+    if first_num.is_none() {
         return Err(Error::InvalidArgument {
             value: first.clone(),
             operation: "-".into(),
             reason: "Could not convert value to number.".into(),
         });
     }
-    if let None = second_num {
+    if second_num.is_none() {
         return Err(Error::InvalidArgument {
             value: second.clone(),
             operation: "-".into(),
@@ -584,14 +645,14 @@ pub fn abstract_div(first: &Value, second: &Value) -> Result<f64, Error> {
     let first_num = to_number(first);
     let second_num = to_number(second);
 
-    if let None = first_num {
+    if first_num.is_none() {
         return Err(Error::InvalidArgument {
             value: first.clone(),
             operation: "/".into(),
             reason: "Could not convert value to number.".into(),
         });
     }
-    if let None = second_num {
+    if second_num.is_none() {
         return Err(Error::InvalidArgument {
             value: second.clone(),
             operation: "/".into(),
@@ -607,14 +668,14 @@ pub fn abstract_mod(first: &Value, second: &Value) -> Result<f64, Error> {
     let first_num = to_number(first);
     let second_num = to_number(second);
 
-    if let None = first_num {
+    if first_num.is_none() {
         return Err(Error::InvalidArgument {
             value: first.clone(),
             operation: "%".into(),
             reason: "Could not convert value to number.".into(),
         });
     }
-    if let None = second_num {
+    if second_num.is_none() {
         return Err(Error::InvalidArgument {
             value: second.clone(),
             operation: "%".into(),
@@ -628,7 +689,7 @@ pub fn abstract_mod(first: &Value, second: &Value) -> Result<f64, Error> {
 /// Attempt to convert a value to a negative number
 pub fn to_negative(val: &Value) -> Result<f64, Error> {
     to_number(val)
-        .map(|v| -1.0 * v)
+        .map(|v| -v)
         .ok_or_else(|| Error::InvalidArgument {
             value: val.clone(),
             operation: "to_negative".into(),
@@ -643,7 +704,7 @@ pub fn to_negative(val: &Value) -> Result<f64, Error> {
 /// quite follow the spec exactly: we don't deal with infinity
 /// and NaN. That is okay, because this is only used in a context dealing
 /// with JSON values, which can't be Infinity or NaN.
-fn parse_float_string(val: &String) -> Option<f64> {
+fn parse_float_string(val: &str) -> Option<f64> {
     let (mut leading_numerics, _, _) = val.trim().chars().fold(
         (Vec::new(), false, false),
         |(mut acc, broke, saw_decimal), c| {
@@ -667,7 +728,7 @@ fn parse_float_string(val: &String) -> Option<f64> {
         },
     );
     // don't bother collecting into a string if we don't need to
-    if leading_numerics.len() == 0 {
+    if leading_numerics.is_empty() {
         return None;
     };
     if let Some('e') | Some('E') = leading_numerics.last() {
@@ -693,7 +754,26 @@ pub fn parse_float(val: &Value) -> Option<f64> {
     match val {
         Value::Number(num) => num.as_f64(),
         Value::String(string) => parse_float_string(string),
-        _ => parse_float(&Value::String(to_string(&val))),
+        _ => parse_float(&Value::String(to_string(val))),
+    }
+}
+
+/// Attempt to parse a value into a float, with the given decimal separator
+///
+/// ```rust
+/// use jsonlogic_rs::DecimalSeparator;
+/// use jsonlogic_rs::js_op::parse_float_with;
+/// use serde_json::json;
+///
+/// assert_eq!(parse_float_with(&json!("12,5kg"), DecimalSeparator::Comma), Some(12.5));
+/// assert_eq!(parse_float_with(&json!("12,5kg"), DecimalSeparator::Point), Some(12.0));
+/// ```
+pub fn parse_float_with(val: &Value, separator: DecimalSeparator) -> Option<f64> {
+    match val {
+        Value::String(string) => {
+            parse_float_string(&normalize_decimal(string, separator))
+        }
+        _ => parse_float(val),
     }
 }
 
@@ -866,6 +946,8 @@ mod abstract_operations {
             (json!(1), json!([1, 2]), json!("11,2")),
             (json!(1), json!([1, null, 3]), json!("11,,3")),
             (json!(1), json!({}), json!("1[object Object]")),
+            // Infinity can't be represented in JSON
+            (json!(1e308), json!(1e308), json!(null)),
         ]
     }
 
@@ -908,7 +990,7 @@ mod abstract_operations {
     fn test_abstract_eq() {
         equal_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert!(abstract_eq(&first, &second), true);
+            assert!(abstract_eq(first, second));
         })
     }
 
@@ -916,7 +998,7 @@ mod abstract_operations {
     fn test_abstract_ne() {
         ne_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_ne(&first, &second), true);
+            assert!(abstract_ne(first, second));
         })
     }
 
@@ -924,7 +1006,7 @@ mod abstract_operations {
     fn test_abstract_lt() {
         lt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_lt(&first, &second), true);
+            assert!(abstract_lt(first, second));
         })
     }
 
@@ -932,7 +1014,7 @@ mod abstract_operations {
     fn test_abstract_gt() {
         gt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_gt(&first, &second), true);
+            assert!(abstract_gt(first, second));
         })
     }
 
@@ -940,7 +1022,7 @@ mod abstract_operations {
     fn test_eq_values_are_not_lt() {
         equal_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_lt(&first, &second), false);
+            assert!(!abstract_lt(first, second));
         })
     }
 
@@ -948,7 +1030,7 @@ mod abstract_operations {
     fn test_eq_values_are_not_gt() {
         equal_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_gt(&first, &second), false);
+            assert!(!abstract_gt(first, second));
         })
     }
 
@@ -956,7 +1038,7 @@ mod abstract_operations {
     fn test_eq_values_are_not_ne() {
         equal_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_ne(&first, &second), false);
+            assert!(!abstract_ne(first, second));
         })
     }
 
@@ -964,7 +1046,7 @@ mod abstract_operations {
     fn test_lt_values_are_not_eq() {
         lt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_eq(&first, &second), false);
+            assert!(!abstract_eq(first, second));
         })
     }
 
@@ -972,7 +1054,7 @@ mod abstract_operations {
     fn test_lt_values_are_not_gt() {
         lt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_gt(&first, &second), false);
+            assert!(!abstract_gt(first, second));
         })
     }
 
@@ -980,7 +1062,7 @@ mod abstract_operations {
     fn test_lt_values_are_ne() {
         lt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_ne(&first, &second), true);
+            assert!(abstract_ne(first, second));
         })
     }
 
@@ -988,7 +1070,7 @@ mod abstract_operations {
     fn test_gt_values_are_not_eq() {
         gt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_eq(&first, &second), false);
+            assert!(!abstract_eq(first, second));
         })
     }
 
@@ -996,7 +1078,7 @@ mod abstract_operations {
     fn test_gt_values_are_not_lt() {
         gt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_lt(&first, &second), false);
+            assert!(!abstract_lt(first, second));
         })
     }
 
@@ -1004,7 +1086,7 @@ mod abstract_operations {
     fn test_gt_values_are_ne() {
         gt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_ne(&first, &second), true);
+            assert!(abstract_ne(first, second));
         })
     }
 
@@ -1012,9 +1094,9 @@ mod abstract_operations {
     fn test_incomparable() {
         not_gt_not_lt_not_eq().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_lt(&first, &second), false);
-            assert_eq!(abstract_gt(&first, &second), false);
-            assert_eq!(abstract_eq(&first, &second), false);
+            assert!(!abstract_lt(first, second));
+            assert!(!abstract_gt(first, second));
+            assert!(!abstract_eq(first, second));
         })
     }
 
@@ -1024,7 +1106,7 @@ mod abstract_operations {
     fn test_lt_values_are_lte() {
         lt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_lte(&first, &second), true);
+            assert!(abstract_lte(first, second));
         })
     }
 
@@ -1032,7 +1114,7 @@ mod abstract_operations {
     fn test_eq_values_are_lte() {
         equal_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_lte(&first, &second), true);
+            assert!(abstract_lte(first, second));
         })
     }
 
@@ -1040,7 +1122,7 @@ mod abstract_operations {
     fn test_gt_values_are_not_lte() {
         gt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_lte(&first, &second), false);
+            assert!(!abstract_lte(first, second));
         })
     }
 
@@ -1050,7 +1132,7 @@ mod abstract_operations {
     fn test_gt_values_are_gte() {
         gt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_gte(&first, &second), true);
+            assert!(abstract_gte(first, second));
         })
     }
 
@@ -1058,7 +1140,7 @@ mod abstract_operations {
     fn test_eq_values_are_gte() {
         equal_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_gte(&first, &second), true);
+            assert!(abstract_gte(first, second));
         })
     }
 
@@ -1066,7 +1148,7 @@ mod abstract_operations {
     fn test_lt_values_are_not_gte() {
         lt_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert_eq!(abstract_gte(&first, &second), false);
+            assert!(!abstract_gte(first, second));
         })
     }
 
@@ -1074,17 +1156,18 @@ mod abstract_operations {
     fn test_abstract_plus() {
         plus_cases().iter().for_each(|(first, second, exp)| {
             println!("{:?}-{:?}", &first, &second);
-            let result = abstract_plus(&first, &second);
+            let result = abstract_plus(first, second);
             match result {
                 Value::Number(ref i) => match exp {
                     Value::Number(j) => assert_eq!(i, j),
-                    _ => assert!(false),
+                    _ => panic!(),
                 },
                 Value::String(ref i) => match exp {
                     Value::String(j) => assert_eq!(i, j),
-                    _ => assert!(false),
+                    _ => panic!(),
                 },
-                _ => assert!(false),
+                Value::Null => assert_eq!(exp, &Value::Null),
+                _ => panic!(),
             }
         })
     }
@@ -1112,7 +1195,7 @@ mod test_abstract_max {
     fn test_abstract_max() {
         max_cases().into_iter().for_each(|(items, exp)| {
             println!("Max: {:?}", items);
-            let res = abstract_max(&items.iter().collect());
+            let res = abstract_max(&items.iter().collect::<Vec<&Value>>());
             println!("Res: {:?}", res);
             match exp {
                 Ok(exp) => assert_eq!(res.unwrap(), exp),
@@ -1146,7 +1229,7 @@ mod test_abstract_min {
     fn test_abstract_min() {
         min_cases().into_iter().for_each(|(items, exp)| {
             println!("Min: {:?}", items);
-            let res = abstract_min(&items.iter().collect());
+            let res = abstract_min(&items.iter().collect::<Vec<&Value>>());
             println!("Res: {:?}", res);
             match exp {
                 Ok(exp) => assert_eq!(res.unwrap(), exp),
@@ -1229,11 +1312,11 @@ mod test_strict {
     fn test_strict_eq() {
         eq_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert!(strict_eq(&first, &second));
+            assert!(strict_eq(first, second));
         });
         ne_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert!(!strict_eq(&first, &second));
+            assert!(!strict_eq(first, second));
         });
     }
 
@@ -1247,11 +1330,11 @@ mod test_strict {
     fn test_strict_ne() {
         ne_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert!(strict_ne(&first, &second));
+            assert!(strict_ne(first, second));
         });
         eq_values().iter().for_each(|(first, second)| {
             println!("{:?}-{:?}", &first, &second);
-            assert!(!strict_ne(&first, &second));
+            assert!(!strict_ne(first, second));
         });
     }
 
@@ -1305,3 +1388,74 @@ mod test_parse_float {
             .for_each(|(input, exp)| assert_eq!(parse_float(&input), exp));
     }
 }
+
+#[cfg(test)]
+mod test_decimal_comma {
+    use super::*;
+    use serde_json::json;
+
+    fn number_cases() -> Vec<(&'static str, Option<f64>)> {
+        vec![
+            ("", Some(0.0)),
+            ("1", Some(1.0)),
+            ("1,5", Some(1.5)),
+            ("-1,5", Some(-1.5)),
+            ("+1,5", Some(1.5)),
+            ("1.234", Some(1234.0)),
+            ("1.234,56", Some(1234.56)),
+            ("1.234.567,8", Some(1234567.8)),
+            ("1,5e2", Some(150.0)),
+            ("1,5,5", None),
+            ("1,5kg", None),
+            // Surrounding whitespace is ignored, as with a decimal point
+            (" 1,5", Some(1.5)),
+            ("1,5 ", Some(1.5)),
+            ("abc", None),
+        ]
+    }
+
+    #[test]
+    fn test_str_to_number_with() {
+        number_cases().into_iter().for_each(|(input, exp)| {
+            assert_eq!(
+                str_to_number_with(input, DecimalSeparator::Comma),
+                exp,
+                "{}",
+                input
+            )
+        });
+    }
+
+    #[test]
+    fn test_parse_float_with() {
+        vec![
+            (json!(1.5), Some(1.5)),
+            (json!("1,5"), Some(1.5)),
+            (json!("1.234,56 EUR"), Some(1234.56)),
+            (json!("  12,5kg"), Some(12.5)),
+            (json!("1,5,5"), Some(1.5)),
+            (json!(null), None),
+        ]
+        .into_iter()
+        .for_each(|(input, exp)| {
+            assert_eq!(
+                parse_float_with(&input, DecimalSeparator::Comma),
+                exp,
+                "{}",
+                input
+            )
+        });
+    }
+
+    #[test]
+    fn test_point_is_unchanged() {
+        assert_eq!(
+            normalize_decimal("1.234,56", DecimalSeparator::Point),
+            "1.234,56"
+        );
+        assert_eq!(
+            str_to_number_with("1.5", DecimalSeparator::Point),
+            Some(1.5)
+        );
+    }
+}
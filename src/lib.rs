@@ -1,23 +1,100 @@
-use serde_json;
+//! JsonLogic for Rust
+//!
+//! Rules are evaluated with [`apply()`], or with an [`Engine`] to configure
+//! evaluation and register custom operators.
+//!
+//! # Public API
+//!
+//! The types needed to embed the crate are re-exported from the crate root,
+//! and are covered by semantic versioning: the engine and its options,
+//! [`CompiledRule`], [`Error`] along with the [`NumParams`] its variants
+//! refer to, [`Stats`], [`AuditRecord`] and its hashing functions, and the
+//! types returned by linting, explanation, and rule sets. The public modules
+//! group optional functionality:
+//!
+//! - [`conformance`]: checking an engine against the official tests
+//! - [`graph`]: rendering rules as diagrams
+//! - [`import`] and [`transpile`]: converting rules to and from other formats
+//! - [`migrations`]: rewriting stored rules as operators change
+//! - [`packs`]: groups of operators with their own tests
+//! - [`plugins`]: operators loaded at runtime
+//! - [`snapshot`]: recording evaluations to replay them later
+//! - [`templates`]: evaluating rules from Handlebars and Tera templates
+//!
+//! Anything hidden from the documentation, like the `js_op` module used by
+//! `jsonlogic-macros`, is an implementation detail and may change in any
+//! release.
+
 use serde_json::Value;
 
+mod audit;
+mod builder;
+#[cfg(feature = "arrow")]
+mod columnar;
+mod compare;
+mod compiled;
+pub mod conformance;
+mod context;
+mod engine;
 mod error;
-// TODO consider whether this should be public; move doctests if so
+mod explain;
+mod format;
+pub mod graph;
+pub mod import;
+// Public for the code generated by `jsonlogic-macros`, not for general use
+#[doc(hidden)]
 pub mod js_op;
+mod lint;
+pub mod migrations;
 mod op;
+pub mod packs;
+pub mod plugins;
+mod policy;
+mod redact;
+mod rule_set;
+mod schema;
+mod shrink;
+pub mod snapshot;
+mod source;
+mod telemetry;
+pub mod templates;
+pub mod transpile;
 mod value;
 
-use error::Error;
-use value::{Evaluated, Parsed};
+pub use audit::{canonical_json, data_hash, rule_hash, AuditRecord};
+pub use builder::EngineBuilder;
+pub use compare::Comparator;
+pub use compiled::CompiledRule;
+use context::Context;
+pub use context::Stats;
+pub use engine::{
+    default_engine, set_default_engine, ApplyIter, CatCoercion, Compatibility,
+    DecimalSeparator, DivisionByZero, Engine, NumberOutput, NumericKeys, LOGIC_MARKER,
+};
+pub use error::Error;
+pub use explain::Failure;
+pub use format::{format_rule, FormatStyle};
+pub use lint::{LintKind, LintWarning};
+#[cfg(feature = "money")]
+pub use op::ExchangeRates;
+pub use op::NumParams;
+pub use policy::Policy;
+pub use rule_set::{RuleOutcome, RuleSet, RuleSetResults, Score, Severity};
+pub use schema::infer_schema;
+pub use shrink::Shrunk;
+pub use source::DataSource;
+use value::Evaluated;
 
 const NULL: Value = Value::Null;
 
 trait Parser<'a>: Sized + Into<Value> {
-    fn from_value(value: &'a Value) -> Result<Option<Self>, Error>;
-    fn evaluate(&self, data: &'a Value) -> Result<Evaluated, Error>;
+    fn from_value(value: &'a Value, engine: &Engine) -> Result<Option<Self>, Error>;
+    fn evaluate(&self, data: &Value, ctx: &Context) -> Result<Evaluated<'_>, Error>;
 }
 
-#[cfg(feature = "wasm")]
+// wasm-bindgen assumes a JavaScript host, so it is kept out of WASI builds,
+// which use the plain command interface in `wasi.rs`.
+#[cfg(all(feature = "wasm", not(target_os = "wasi")))]
 pub mod javascript_iface {
     use serde_json::Value;
     use wasm_bindgen::prelude::*;
@@ -50,7 +127,51 @@ pub mod javascript_iface {
             .map_err(|err| format!("{}", err))
             .map_err(JsValue::from)?;
 
-        JsValue::from_serde(&res)
+        to_js_value(&res)
+    }
+
+    fn to_js_value(value: &Value) -> Result<JsValue, JsValue> {
+        JsValue::from_serde(value)
+            .map_err(|err| format!("{}", err))
+            .map_err(JsValue::from)
+    }
+
+    /// A parsed rule, which can be applied repeatedly without parsing it
+    /// again. Call `free()` once it is no longer needed to release its
+    /// memory.
+    #[wasm_bindgen]
+    pub struct RuleHandle {
+        rule: crate::CompiledRule<'static>,
+    }
+    #[wasm_bindgen]
+    impl RuleHandle {
+        pub fn apply(&self, data: JsValue) -> Result<JsValue, JsValue> {
+            let data_json = to_serde_value(data)?;
+            let res = self
+                .rule
+                .apply(&data_json)
+                .map_err(|err| format!("{}", err))
+                .map_err(JsValue::from)?;
+            to_js_value(&res)
+        }
+    }
+
+    /// A stable hash identifying a rule up to how its operations are
+    /// written, as 16 hexadecimal digits
+    #[wasm_bindgen]
+    pub fn fingerprint(value: JsValue) -> Result<String, JsValue> {
+        Ok(crate::fingerprint(&to_serde_value(value)?))
+    }
+
+    /// Parse a rule once, to apply it to many values
+    ///
+    /// Like `apply()`, this uses the default engine.
+    #[wasm_bindgen]
+    pub fn compile(value: JsValue) -> Result<RuleHandle, JsValue> {
+        let value_json = to_serde_value(value)?;
+        crate::default_engine()
+            .compile_owned(value_json)
+            .map(|rule| RuleHandle { rule })
             .map_err(|err| format!("{}", err))
             .map_err(JsValue::from)
     }
@@ -58,41 +179,399 @@ pub mod javascript_iface {
 
 #[cfg(feature = "python")]
 pub mod python_iface {
+    use std::cell::RefCell;
+
     use cpython::exc::ValueError;
-    use cpython::{py_fn, py_module_initializer, PyErr, PyResult, Python};
+    use cpython::{
+        py_class, py_fn, py_module_initializer, ObjectProtocol, PyErr, PyObject,
+        PyResult, Python,
+    };
+    use serde_json::Value;
 
     py_module_initializer!(jsonlogic, initjsonlogic, PyInit_jsonlogic, |py, m| {
         m.add(py, "__doc__", "Python bindings for json-logic-rs")?;
         m.add(py, "apply", py_fn!(py, py_apply(value: &str, data: &str)))?;
+        m.add(py, "fingerprint", py_fn!(py, py_fingerprint(value: &str)))?;
+        m.add_class::<Engine>(py)?;
         Ok(())
     });
 
-    fn apply(value: &str, data: &str) -> Result<String, String> {
-        let value_json =
-            serde_json::from_str(value).map_err(|err| format!("{}", err))?;
-        let data_json = serde_json::from_str(data).map_err(|err| format!("{}", err))?;
+    // Rules, data, and the arguments and results of custom operations cross
+    // the boundary as JSON, which the Python wrapper converts.
+    py_class!(pub class Engine |py| {
+        data engine: RefCell<crate::Engine>;
 
-        crate::apply(&value_json, &data_json)
-            .map_err(|err| format!("{}", err))
+        def __new__(_cls) -> PyResult<Engine> {
+            Engine::create_instance(py, RefCell::new(crate::Engine::new()))
+        }
+
+        // Register a custom operation. `callable` is called with the
+        // operation's arguments as a JSON array, and returns a JSON object
+        // with either its "result" or an "error" message.
+        def add_operation(
+            &self,
+            name: &str,
+            callable: PyObject
+        ) -> PyResult<PyObject> {
+            let mut engine = self.engine(py).borrow_mut();
+            let symbol = name.to_string();
+            *engine = engine.clone().add_operator(name, move |args| {
+                call_operation(&symbol, &callable, args)
+            });
+            Ok(py.None())
+        }
+
+        def apply(&self, value: &str, data: &str) -> PyResult<String> {
+            // Clone the engine, so that operations may register others
+            let engine = self.engine(py).borrow().clone();
+            apply(&engine, value, data)
+                .map_err(|err| PyErr::new::<ValueError, _>(py, err))
+        }
+    });
+
+    fn call_operation(
+        symbol: &str,
+        callable: &PyObject,
+        args: &[&Value],
+    ) -> Result<Value, crate::Error> {
+        let plugin_error = |reason: String| crate::Error::PluginError {
+            operator: Some(symbol.into()),
+            reason,
+        };
+        let args = Value::Array(args.iter().map(|arg| (*arg).clone()).collect());
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let response = callable
+            .call(py, (args.to_string(),), None)
+            .and_then(|response| response.extract::<String>(py))
+            .map_err(|_| plugin_error("The operation failed to respond".into()))?;
+        let mut response: Value = serde_json::from_str(&response)
+            .map_err(|err| plugin_error(err.to_string()))?;
+        match response.get("error") {
+            Some(Value::String(reason)) => Err(plugin_error(reason.clone())),
+            Some(reason) => Err(plugin_error(reason.to_string())),
+            None => Ok(response
+                .get_mut("result")
+                .map(Value::take)
+                .unwrap_or(Value::Null)),
+        }
+    }
+
+    /// Errors are returned as JSON, so that the Python wrapper can expose
+    /// their code and details.
+    fn apply(
+        engine: &crate::Engine,
+        value: &str,
+        data: &str,
+    ) -> Result<String, String> {
+        let json_err = |err: serde_json::Error| {
+            serde_json::json!({
+                "code": "invalid_json",
+                "message": err.to_string(),
+                "details": {},
+            })
+            .to_string()
+        };
+        let value_json = serde_json::from_str(value).map_err(json_err)?;
+        let data_json = serde_json::from_str(data).map_err(json_err)?;
+
+        engine
+            .apply(&value_json, &data_json)
+            .map_err(|err| err.to_json().to_string())
             .map(|res| res.to_string())
     }
 
     fn py_apply(py: Python, value: &str, data: &str) -> PyResult<String> {
-        apply(value, data).map_err(|err| PyErr::new::<ValueError, _>(py, err))
+        apply(&crate::Engine::new(), value, data)
+            .map_err(|err| PyErr::new::<ValueError, _>(py, err))
+    }
+
+    fn py_fingerprint(py: Python, value: &str) -> PyResult<String> {
+        serde_json::from_str(value)
+            .map(|value_json| crate::fingerprint(&value_json))
+            .map_err(|err| PyErr::new::<ValueError, _>(py, err.to_string()))
+    }
+}
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!("jsonlogic");
+
+#[cfg(feature = "uniffi")]
+pub mod uniffi_iface {
+    use std::sync::{Arc, RwLock};
+
+    use serde_json::Value;
+
+    use crate::{Engine, Error};
+
+    /// Errors surfaced to foreign languages
+    #[derive(Debug, thiserror::Error, uniffi::Error)]
+    pub enum JsonLogicError {
+        #[error("Invalid JSON: {message}")]
+        InvalidJson { message: String },
+        /// An error from the engine. `code` is a stable identifier for the
+        /// kind of error, and `details` is a JSON object of its fields.
+        #[error("{message}")]
+        Logic {
+            code: String,
+            message: String,
+            details: String,
+        },
+        #[error("Callback failed: {message}")]
+        Callback { message: String },
+    }
+    impl From<Error> for JsonLogicError {
+        fn from(err: Error) -> Self {
+            let rendered = err.to_json();
+            Self::Logic {
+                code: err.code().into(),
+                message: err.to_string(),
+                details: rendered["details"].to_string(),
+            }
+        }
+    }
+    impl From<serde_json::Error> for JsonLogicError {
+        fn from(err: serde_json::Error) -> Self {
+            Self::InvalidJson {
+                message: err.to_string(),
+            }
+        }
+    }
+    impl From<uniffi::UnexpectedUniFFICallbackError> for JsonLogicError {
+        fn from(err: uniffi::UnexpectedUniFFICallbackError) -> Self {
+            Self::Callback {
+                message: err.reason,
+            }
+        }
+    }
+
+    /// A custom operator implemented in the foreign language
+    ///
+    /// The operator receives its evaluated arguments as a JSON array and
+    /// must return its result as JSON.
+    #[uniffi::export(with_foreign)]
+    pub trait ScalarOperator: Send + Sync {
+        fn call(&self, arguments: String) -> Result<String, JsonLogicError>;
+    }
+
+    fn call_foreign(
+        name: &str,
+        operator: &dyn ScalarOperator,
+        args: &[&Value],
+    ) -> Result<Value, Error> {
+        let args = Value::Array(args.iter().map(|v| (*v).clone()).collect());
+        let err = |reason: String| Error::InvalidArgument {
+            value: args.clone(),
+            operation: name.into(),
+            reason,
+        };
+        let res = operator
+            .call(args.to_string())
+            .map_err(|e| err(e.to_string()))?;
+        serde_json::from_str(&res).map_err(|e| err(format!("Invalid result: {}", e)))
+    }
+
+    /// A JsonLogic engine, which may be extended with custom operators
+    #[derive(uniffi::Object, Default)]
+    pub struct JsonLogic {
+        engine: RwLock<Engine>,
+    }
+    impl JsonLogic {
+        fn engine(&self) -> Engine {
+            self.engine.read().expect("engine lock poisoned").clone()
+        }
+    }
+    #[uniffi::export]
+    impl JsonLogic {
+        #[uniffi::constructor]
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Register a custom operator
+        pub fn add_operator(&self, name: String, operator: Arc<dyn ScalarOperator>) {
+            let mut engine = self.engine.write().expect("engine lock poisoned");
+            let op_name = name.clone();
+            *engine = std::mem::take(&mut *engine).add_operator(&name, move |args| {
+                call_foreign(&op_name, operator.as_ref(), args)
+            });
+        }
+
+        /// Apply a JSON rule to JSON data, returning the JSON result
+        pub fn apply(
+            &self,
+            rule: String,
+            data: String,
+        ) -> Result<String, JsonLogicError> {
+            self.compile(rule)?.apply(data)
+        }
+
+        /// Check that a JSON rule is valid without evaluating it
+        pub fn validate(&self, rule: String) -> Result<(), JsonLogicError> {
+            self.compile(rule).map(|_| ())
+        }
+
+        /// A stable hash identifying a rule up to how its operations are
+        /// written, recognizing this engine's custom operators
+        pub fn fingerprint(&self, rule: String) -> Result<String, JsonLogicError> {
+            let rule: Value = serde_json::from_str(&rule)?;
+            Ok(self.engine().fingerprint(&rule))
+        }
+
+        /// Validate a rule and bind it to this engine for repeated use
+        ///
+        /// Operators added to the engine after compilation are not visible
+        /// to the compiled rule.
+        pub fn compile(
+            &self,
+            rule: String,
+        ) -> Result<Arc<CompiledRule>, JsonLogicError> {
+            let rule: Value = serde_json::from_str(&rule)?;
+            let engine = self.engine();
+            engine.parse(&rule)?;
+            Ok(Arc::new(CompiledRule { rule, engine }))
+        }
+    }
+
+    /// A validated rule, bound to the engine that compiled it
+    #[derive(uniffi::Object)]
+    pub struct CompiledRule {
+        rule: Value,
+        engine: Engine,
+    }
+    #[uniffi::export]
+    impl CompiledRule {
+        /// Apply the rule to JSON data, returning the JSON result
+        pub fn apply(&self, data: String) -> Result<String, JsonLogicError> {
+            let data: Value = serde_json::from_str(&data)?;
+            Ok(self.engine.apply(&self.rule, &data)?.to_string())
+        }
+    }
+
+    /// Apply a JSON rule to JSON data, returning the JSON result
+    #[uniffi::export]
+    pub fn apply(rule: String, data: String) -> Result<String, JsonLogicError> {
+        JsonLogic::new().apply(rule, data)
+    }
+
+    /// Check that a JSON rule is valid without evaluating it
+    #[uniffi::export]
+    pub fn validate(rule: String) -> Result<(), JsonLogicError> {
+        JsonLogic::new().validate(rule)
+    }
+
+    /// A stable hash identifying a rule up to how its operations are written
+    #[uniffi::export]
+    pub fn fingerprint(rule: String) -> Result<String, JsonLogicError> {
+        JsonLogic::new().fingerprint(rule)
     }
 }
 
 /// Run JSONLogic for the given operation and data.
 ///
+/// This uses the [`default_engine()`], which has default options unless
+/// another engine was set with [`set_default_engine()`]. Use an engine
+/// directly to customize evaluation in one place.
 pub fn apply(value: &Value, data: &Value) -> Result<Value, Error> {
-    let parsed = Parsed::from_value(&value)?;
-    parsed.evaluate(data).map(Value::from)
+    default_engine().apply(value, data)
+}
+
+/// Run JSONLogic against any serializable data, like a Rust struct
+///
+/// See [`Engine::apply_serde()`].
+pub fn apply_serde<T>(value: &Value, data: &T) -> Result<Value, Error>
+where
+    T: serde::Serialize + ?Sized,
+{
+    default_engine().apply_serde(value, data)
+}
+
+/// Evaluate the rules embedded in a document under `$logic` keys
+///
+/// See [`Engine::apply_all_paths()`].
+pub fn apply_all_paths(document: &Value, data: &Value) -> Result<Value, Error> {
+    default_engine().apply_all_paths(document, data)
+}
+
+/// A stable hash identifying a rule up to how its operations are written
+///
+/// Rules are often written differently while meaning the same thing, as
+/// with `{"var": "a"}` and `{"var": ["a"]}`. The fingerprint is the
+/// [`rule_hash()`] of the rule with its operations written in one form, with
+/// their arguments in an array, aliases like `?:` replaced by the operators
+/// they stand for, and object keys in any order, so that duplicate rules can
+/// be found and caches keyed consistently. It's stable in the same way as
+/// `rule_hash()`.
+///
+/// ```rust
+/// use serde_json::json;
+///
+/// assert_eq!(
+///     jsonlogic_rs::fingerprint(&json!({"?:": [{"var": "a"}, 1, 2]})),
+///     jsonlogic_rs::fingerprint(&json!({"if": [{"var": ["a"]}, 1, 2]})),
+/// );
+/// ```
+pub fn fingerprint(value: &Value) -> String {
+    default_engine().fingerprint(value)
+}
+
+/// Find suspicious constructs in a rule
+///
+/// Lint warnings describe rules that are valid, but which probably don't do
+/// what their author intended, like comparisons whose result doesn't depend
+/// on the data.
+///
+/// ```rust
+/// use jsonlogic_rs::LintKind;
+/// use serde_json::json;
+///
+/// let warnings = jsonlogic_rs::lint(&json!({"if": [{"<": [1, 2]}, "a", "b"]}));
+/// assert_eq!(warnings[0].kind, LintKind::ConstantComparison);
+/// assert_eq!(warnings[0].path, "/if/0");
+/// ```
+pub fn lint(value: &Value) -> Vec<LintWarning> {
+    default_engine().lint(value)
+}
+
+/// Explain why a rule evaluates to a falsy value for some data
+///
+/// Returns the leaf conditions that made the rule falsy, or nothing if it
+/// is truthy. Failing any one of the conditions is enough to fail the rule,
+/// except for those under an `or`, all of which failed.
+///
+/// ```rust
+/// use serde_json::json;
+///
+/// let rule = json!({"and": [
+///     {">=": [{"var": "age"}, 18]},
+///     {"or": [
+///         {"in": [{"var": "country"}, ["US", "CA"]]},
+///         {"var": "has_waiver"},
+///     ]},
+/// ]});
+/// let data = json!({"age": 21, "country": "DE"});
+/// let reasons: Vec<String> = jsonlogic_rs::explain_failure(&rule, &data)
+///     .unwrap()
+///     .iter()
+///     .map(|failure| failure.to_string())
+///     .collect();
+/// assert_eq!(
+///     reasons,
+///     vec![
+///         r#"/and/1/or/0: country "DE" not in ["US","CA"]"#,
+///         "/and/1/or/1: has_waiver null is falsy",
+///     ]
+/// );
+/// ```
+pub fn explain_failure(value: &Value, data: &Value) -> Result<Vec<Failure>, Error> {
+    default_engine().explain_failure(value, data)
 }
 
 #[cfg(test)]
 mod jsonlogic_tests {
     use super::*;
     use serde_json::json;
+    use std::sync::{Arc, Mutex};
 
     fn no_op_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
         vec![
@@ -237,6 +716,9 @@ mod jsonlogic_tests {
             (json!({"var": 1}), json!(["foo", "bar"]), Ok(json!("bar"))),
             // Absent variable
             (json!({"var": "foo"}), json!({}), Ok(json!(null))),
+            // Extreme indexes must not overflow
+            (json!({"var": i64::MIN}), json!([1, 2]), Ok(json!(null))),
+            (json!({"var": i64::MAX}), json!([1, 2]), Ok(json!(null))),
             (
                 json!({"==": [{"var": "first"}, true]}),
                 json!({"first": true}),
@@ -274,6 +756,131 @@ mod jsonlogic_tests {
         ]
     }
 
+    fn get_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"get": [{"var": "prices"}, {"var": "tier"}]}),
+                json!({"prices": {"gold": 10, "silver": 5}, "tier": "gold"}),
+                Ok(json!(10)),
+            ),
+            (
+                json!({"get": [{"var": "prices"}, {"var": "tier"}]}),
+                json!({"prices": {"gold": 10}, "tier": "bronze"}),
+                Ok(json!(null)),
+            ),
+            (
+                json!({"get": [{"var": "prices"}, {"var": "tier"}, 0]}),
+                json!({"prices": {"gold": 10}, "tier": "bronze"}),
+                Ok(json!(0)),
+            ),
+            // Keys are not split on dots
+            (
+                json!({"get": [{"var": ""}, "a.b"]}),
+                json!({"a.b": 1, "a": {"b": 2}}),
+                Ok(json!(1)),
+            ),
+            (json!({"get": [[1, 2, 3], 1]}), json!({}), Ok(json!(2))),
+            (json!({"get": [[1, 2, 3], -1]}), json!({}), Ok(json!(3))),
+            (json!({"get": [[1, 2, 3], "0"]}), json!({}), Ok(json!(1))),
+            (json!({"get": [[1, 2, 3], 5]}), json!({}), Ok(json!(null))),
+            (
+                json!({"get": [{"1": "one"}, 1]}),
+                json!({}),
+                Ok(json!("one")),
+            ),
+            (json!({"get": ["abc", 1]}), json!({}), Ok(json!("b"))),
+            (
+                json!({"get": [{"a": 1}, null]}),
+                json!({}),
+                Ok(json!({"a": 1})),
+            ),
+            (json!({"get": [5, "a"]}), json!({}), Ok(json!(null))),
+            (
+                json!({"get": [
+                    {"merge": [[1], [2]]},
+                    {"-": [{"var": "n"}, 1]}
+                ]}),
+                json!({"n": 2}),
+                Ok(json!(2)),
+            ),
+            (json!({"get": [{"a": 1}, ["a"]]}), json!({}), Err(())),
+            (json!({"get": [[1], 0.5]}), json!({}), Err(())),
+            (json!({"get": [{"a": 1}]}), json!({}), Err(())),
+        ]
+    }
+
+    fn require_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"require": [["a", "b.c", "d"]]}),
+                json!({"a": 1, "b": {"c": null}}),
+                Ok(
+                    json!({"missing": ["d"], "present": ["a", "b.c"], "satisfied": false}),
+                ),
+            ),
+            (
+                json!({"require": [["a", "b"]]}),
+                json!({"a": 1, "b": 2}),
+                Ok(json!({"missing": [], "present": ["a", "b"], "satisfied": true})),
+            ),
+            (
+                json!({"require": [["a", "b", "c"], {"min_present": 1}]}),
+                json!({"b": 2}),
+                Ok(json!({"missing": ["a", "c"], "present": ["b"], "satisfied": true})),
+            ),
+            (
+                json!({"require": [["a", "a", null]]}),
+                json!({}),
+                Ok(json!({"missing": ["a"], "present": [], "satisfied": false})),
+            ),
+            // Arguments are evaluated
+            (
+                json!({"require": [{"var": "fields"}]}),
+                json!({"fields": ["fields", "x"]}),
+                Ok(
+                    json!({"missing": ["x"], "present": ["fields"], "satisfied": false}),
+                ),
+            ),
+            (json!({"require": ["a"]}), json!({}), Err(())),
+            (
+                json!({"require": [["a"], {"min_present": -1}]}),
+                json!({}),
+                Err(()),
+            ),
+            (
+                json!({"require": [["a"], {"maximum": 1}]}),
+                json!({}),
+                Err(()),
+            ),
+            (json!({"require": [["a"], 1]}), json!({}), Err(())),
+        ]
+    }
+
+    fn typed_var_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"var_str": "a"}), json!({"a": "x"}), Ok(json!("x"))),
+            (json!({"var_str": "a"}), json!({"a": 1}), Err(())),
+            (json!({"var_str": "a"}), json!({}), Err(())),
+            (json!({"var_str": ["a", "x"]}), json!({}), Ok(json!("x"))),
+            (json!({"var_str": ["a", 1]}), json!({}), Err(())),
+            (
+                json!({"var_num": "a.b"}),
+                json!({"a": {"b": 1.5}}),
+                Ok(json!(1.5)),
+            ),
+            (json!({"var_num": "a"}), json!({"a": "1"}), Err(())),
+            (json!({"var_num": 1}), json!([0, 1]), Ok(json!(1))),
+            (
+                json!({"var_bool": "a"}),
+                json!({"a": false}),
+                Ok(json!(false)),
+            ),
+            (json!({"var_bool": "a"}), json!({"a": 0}), Err(())),
+            (json!({"var_bool": "a"}), json!({"a": null}), Err(())),
+            (json!({"var_bool": []}), json!(true), Ok(json!(true))),
+        ]
+    }
+
     fn missing_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
         vec![
             // "missing" data operator
@@ -357,6 +964,56 @@ mod jsonlogic_tests {
         ]
     }
 
+    /// Argument counts that aren't defined by the specification, but are by
+    /// the official tests
+    fn if_special_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"if": []}), json!({}), Ok(json!(null))),
+            (json!({"if": [true]}), json!({}), Ok(json!(true))),
+            (json!({"if": [false]}), json!({}), Ok(json!(false))),
+            (json!({"if": [{"var": "a"}]}), json!({"a": 0}), Ok(json!(0))),
+            (json!({"if": [[1, 2]]}), json!({}), Ok(json!([1, 2]))),
+            (
+                json!({"if": [true, "apple"]}),
+                json!({}),
+                Ok(json!("apple")),
+            ),
+            (json!({"if": [false, "apple"]}), json!({}), Ok(json!(null))),
+            (
+                json!({"if": [false, "apple", false, "banana"]}),
+                json!({}),
+                Ok(json!(null)),
+            ),
+            // Only the branch taken is evaluated
+            (
+                json!({"if": [true, "apple", {"/": ["foo", 2]}]}),
+                json!({}),
+                Ok(json!("apple")),
+            ),
+        ]
+    }
+
+    /// A single argument not wrapped in an array
+    fn if_non_array_cases() -> Vec<(Value, Value, Value)> {
+        vec![
+            (json!({"if": true}), json!({}), json!(true)),
+            (json!({"if": 0}), json!({}), json!(0)),
+            (json!({"if": "apple"}), json!({}), json!("apple")),
+            (json!({"if": null}), json!({}), json!(null)),
+            (json!({"if": {"var": "a"}}), json!({"a": "b"}), json!("b")),
+            (
+                json!({"if": {"<": [{"var": "a"}, 2]}}),
+                json!({"a": 1}),
+                json!(true),
+            ),
+            (
+                json!({"if": {"if": [{"var": "a"}]}}),
+                json!({"a": [1]}),
+                json!([1]),
+            ),
+        ]
+    }
+
     fn or_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
         vec![
             (json!({"or": [true]}), json!({}), Ok(json!(true))),
@@ -433,6 +1090,60 @@ mod jsonlogic_tests {
         ]
     }
 
+    fn seq_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"seq": []}), json!({}), Err(())),
+            (json!({"seq": [1]}), json!({}), Ok(json!(1))),
+            (json!({"seq": {"var": "a"}}), json!({"a": 2}), Ok(json!(2))),
+            (
+                json!({"seq": [{"log": "a"}, false, {"var": "a"}]}),
+                json!({"a": 2}),
+                Ok(json!(2)),
+            ),
+            // Errors from any expression are propagated
+            (json!({"seq": [{"==": [1]}, 1]}), json!({}), Err(())),
+        ]
+    }
+
+    fn try_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"try": []}), json!({}), Err(())),
+            (json!({"try": [1, 2, 3]}), json!({}), Err(())),
+            (
+                json!({"try": [{"var": "a"}, 0]}),
+                json!({"a": 2}),
+                Ok(json!(2)),
+            ),
+            (
+                json!({"try": [{"var": "a"}]}),
+                json!({"a": 2}),
+                Ok(json!(2)),
+            ),
+            (
+                json!({"try": [{"toNumber": "x"}, {"var": "a"}]}),
+                json!({"a": 2}),
+                Ok(json!(2)),
+            ),
+            // Errors in the fallback aren't caught
+            (
+                json!({"try": [{"toNumber": "x"}, {"toNumber": "y"}]}),
+                json!({}),
+                Err(()),
+            ),
+            (
+                json!({"try": [{"toNumber": "x"}, {"try": [{"toNumber": "y"}, 0]}]}),
+                json!({}),
+                Ok(json!(0)),
+            ),
+            // Only the failing branch of a larger rule falls back
+            (
+                json!({"+": [1, {"try": [{"toNumber": "x"}, 2]}]}),
+                json!({}),
+                Ok(json!(3)),
+            ),
+        ]
+    }
+
     fn map_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
         vec![
             (
@@ -744,6 +1455,79 @@ mod jsonlogic_tests {
         ]
     }
 
+    /// The first argument is handled the same way by every operator that
+    /// iterates over it
+    fn iteration_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        let data = json!({
+            "nums": [1, 2],
+            "word": "ab",
+            "rules": [{"var": "nums"}],
+            "count": 3,
+        });
+        let mut cases = Vec::new();
+        for (op, expected_nums, expected_word) in [
+            ("map", json!([1, 2]), json!(["a", "b"])),
+            ("filter", json!([1, 2]), json!(["a", "b"])),
+            ("all", json!(true), json!(true)),
+            ("some", json!(true), json!(true)),
+            ("none", json!(false), json!(false)),
+            ("max_by", json!(1), json!("a")),
+            ("min_by", json!(1), json!("a")),
+        ] {
+            let predicate = match op {
+                "max_by" | "min_by" => json!(0),
+                _ => json!({"var": ""}),
+            };
+            let rule = |items: Value| json!({op: [items, predicate]});
+            cases.extend(vec![
+                // Literal arrays, with their items evaluated
+                (
+                    rule(json!([1, {"var": "nums.1"}])),
+                    data.clone(),
+                    Ok(expected_nums.clone()),
+                ),
+                // Arrays and strings read from the data
+                (
+                    rule(json!({"var": "nums"})),
+                    data.clone(),
+                    Ok(expected_nums),
+                ),
+                (
+                    rule(json!({"var": "word"})),
+                    data.clone(),
+                    Ok(expected_word.clone()),
+                ),
+                (rule(json!("ab")), data.clone(), Ok(expected_word)),
+                // Items from the data aren't evaluated as rules
+                (
+                    rule(json!({"var": "rules"})),
+                    data.clone(),
+                    Ok(match op {
+                        "map" | "filter" => json!([{"var": "nums"}]),
+                        "all" | "some" => json!(true),
+                        "none" => json!(false),
+                        _ => json!({"var": "nums"}),
+                    }),
+                ),
+                // Invalid first arguments
+                (rule(json!({"var": "count"})), data.clone(), Err(())),
+                (rule(json!(true)), data.clone(), Err(())),
+                (rule(json!({"==": [1]})), data.clone(), Err(())),
+            ]);
+        }
+        cases.push((
+            json!({"reduce": ["abc", {"cat": [{"var": "current"}, {"var": "accumulator"}]}, ""]}),
+            json!(null),
+            Ok(json!("cba")),
+        ));
+        cases.push((
+            json!({"reduce": [{"var": "count"}, {"var": "current"}, 0]}),
+            data,
+            Err(()),
+        ));
+        cases
+    }
+
     fn none_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
         vec![
             // Invalid first arguments
@@ -826,60 +1610,344 @@ mod jsonlogic_tests {
         ]
     }
 
-    fn merge_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+    fn flatten_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
         vec![
-            (json!({"merge": []}), json!({}), Ok(json!([]))),
-            (json!({"merge": [1]}), json!({}), Ok(json!([1]))),
-            (json!({"merge": [1, 2]}), json!({}), Ok(json!([1, 2]))),
             (
-                json!({"merge": [[1, 2], 2]}),
+                json!({"flatten": [[1, [2, [3, [4]]]]]}),
                 json!({}),
-                Ok(json!([1, 2, 2])),
+                Ok(json!([1, 2, 3, 4])),
             ),
-            (json!({"merge": [[1], [2]]}), json!({}), Ok(json!([1, 2]))),
-            (json!({"merge": [1, [2]]}), json!({}), Ok(json!([1, 2]))),
             (
-                json!({"merge": [1, [2, [3, 4]]]}),
+                json!({"flatten": [[1, [2, [3, [4]]]], 1]}),
                 json!({}),
-                Ok(json!([1, 2, [3, 4]])),
+                Ok(json!([1, 2, [3, [4]]])),
             ),
             (
-                json!({"merge": [{"var": "foo"}, [2]]}),
-                json!({"foo": 1}),
-                Ok(json!([1, 2])),
+                json!({"flatten": [[1, [2, [3, [4]]]], 2]}),
+                json!({}),
+                Ok(json!([1, 2, 3, [4]])),
             ),
-            (json!({"merge": [[], [2]]}), json!(null), Ok(json!([2]))),
             (
-                json!({"merge": [[[]], [2]]}),
-                json!(null),
-                Ok(json!([[], 2])),
+                json!({"flatten": [[1, [2]], 0]}),
+                json!({}),
+                Ok(json!([1, [2]])),
             ),
-            (json!({"merge": [{}, [2]]}), json!(null), Ok(json!([{}, 2]))),
             (
-                json!({"merge": [{}, [2], 3, false]}),
-                json!(null),
-                Ok(json!([{}, 2, 3, false])),
+                json!({"flatten": {"var": "groups"}}),
+                json!({"groups": [[{"a": [1]}], [], [[2]]]}),
+                Ok(json!([{"a": [1]}, 2])),
             ),
+            (json!({"flatten": [[]]}), json!({}), Ok(json!([]))),
+            (
+                json!({"flatten": {"var": "missing"}}),
+                json!({}),
+                Ok(json!([])),
+            ),
+            (json!({"flatten": "abc"}), json!({}), Err(())),
+            (json!({"flatten": [[1], -1]}), json!({}), Err(())),
+            (json!({"flatten": [[1], 1.5]}), json!({}), Err(())),
         ]
     }
 
-    fn cat_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
-        vec![
-            (json!({"cat": []}), json!({}), Ok(json!(""))),
-            (json!({"cat": [1]}), json!({}), Ok(json!("1"))),
-            (json!({"cat": ["a"]}), json!({}), Ok(json!("a"))),
-            (json!({"cat": ["a", "b"]}), json!({}), Ok(json!("ab"))),
-            (json!({"cat": ["a", "b", "c"]}), json!({}), Ok(json!("abc"))),
-            (json!({"cat": ["a", "b", 1]}), json!({}), Ok(json!("ab1"))),
-        ]
-    }
-
-    fn substr_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+    fn is_sorted_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
         vec![
-            // Wrong number of arguments
-            (json!({"substr": []}), json!({}), Err(())),
+            (
+                json!({"is_sorted": [[1, 2, 2, 3]]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"is_sorted": [[1, 3, 2]]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"is_sorted": [[1, 2, 3], "asc"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"is_sorted": [[3, 2, 2], "desc"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"is_sorted": [[1, 2], "desc"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"is_sorted": [["a", "b", "c"]]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"is_sorted": {"var": "dates"}}),
+                json!({"dates": ["2020-01-01", "2020-02-01"]}),
+                Ok(json!(true)),
+            ),
+            (json!({"is_sorted": [[]]}), json!({}), Ok(json!(true))),
+            (json!({"is_sorted": [[1]]}), json!({}), Ok(json!(true))),
+            (
+                json!({"is_sorted": {"var": "missing"}}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (json!({"is_sorted": [[1, 2], "up"]}), json!({}), Err(())),
+            (json!({"is_sorted": 5}), json!({}), Err(())),
+        ]
+    }
+
+    fn all_distinct_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"all_distinct": [[1, 2, 3]]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"all_distinct": [[1, 2, 1]]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"all_distinct": [[1, "1"]]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"all_distinct": [[{"a": 1}, {"a": 1}]]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"all_distinct": [[[1, 2], [2, 1]]]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"all_distinct": {"map": [{"var": "users"}, {"var": "email"}]}}),
+                json!({"users": [{"email": "a@x"}, {"email": "b@x"}, {"email": "a@x"}]}),
+                Ok(json!(false)),
+            ),
+            (json!({"all_distinct": [[]]}), json!({}), Ok(json!(true))),
+            (json!({"all_distinct": "aba"}), json!({}), Err(())),
+        ]
+    }
+
+    fn contains_all_any_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"contains_all": [{"var": "roles"}, ["admin", "billing"]]}),
+                json!({"roles": ["billing", "admin", "user"]}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"contains_all": [{"var": "roles"}, ["admin", "billing"]]}),
+                json!({"roles": ["admin", "user"]}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"contains_any": [{"var": "roles"}, ["admin", "billing"]]}),
+                json!({"roles": ["admin", "user"]}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"contains_any": [{"var": "roles"}, ["admin", "billing"]]}),
+                json!({"roles": ["user"]}),
+                Ok(json!(false)),
+            ),
+            // Matching is by value, without type coercion
+            (
+                json!({"contains_all": [[1, 2], ["1"]]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"contains_any": [[[1, 2], {"a": 1}], [{"a": 1}]]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            // A single needle need not be wrapped in an array
+            (
+                json!({"contains_all": [[1, 2], 2]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"contains_all": [[1, 2], []]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"contains_any": [[1, 2], []]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"contains_any": [null, [1]]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"contains_all": ["hello world", ["hello", "world"]]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"contains_any": ["hello world", ["bye", "moon"]]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (json!({"contains_all": ["hello", [1]]}), json!({}), Err(())),
+            (json!({"contains_any": [5, [5]]}), json!({}), Err(())),
+        ]
+    }
+
+    fn merge_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"merge": []}), json!({}), Ok(json!([]))),
+            (json!({"merge": [1]}), json!({}), Ok(json!([1]))),
+            (json!({"merge": [1, 2]}), json!({}), Ok(json!([1, 2]))),
+            (
+                json!({"merge": [[1, 2], 2]}),
+                json!({}),
+                Ok(json!([1, 2, 2])),
+            ),
+            (json!({"merge": [[1], [2]]}), json!({}), Ok(json!([1, 2]))),
+            (json!({"merge": [1, [2]]}), json!({}), Ok(json!([1, 2]))),
+            (
+                json!({"merge": [1, [2, [3, 4]]]}),
+                json!({}),
+                Ok(json!([1, 2, [3, 4]])),
+            ),
+            (
+                json!({"merge": [{"var": "foo"}, [2]]}),
+                json!({"foo": 1}),
+                Ok(json!([1, 2])),
+            ),
+            (json!({"merge": [[], [2]]}), json!(null), Ok(json!([2]))),
+            (
+                json!({"merge": [[[]], [2]]}),
+                json!(null),
+                Ok(json!([[], 2])),
+            ),
+            (json!({"merge": [{}, [2]]}), json!(null), Ok(json!([{}, 2]))),
+            (
+                json!({"merge": [{}, [2], 3, false]}),
+                json!(null),
+                Ok(json!([{}, 2, 3, false])),
+            ),
+        ]
+    }
+
+    fn cat_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"cat": []}), json!({}), Ok(json!(""))),
+            (json!({"cat": [1]}), json!({}), Ok(json!("1"))),
+            (json!({"cat": ["a"]}), json!({}), Ok(json!("a"))),
+            (json!({"cat": ["a", "b"]}), json!({}), Ok(json!("ab"))),
+            (json!({"cat": ["a", "b", "c"]}), json!({}), Ok(json!("abc"))),
+            (json!({"cat": ["a", "b", 1]}), json!({}), Ok(json!("ab1"))),
+            (json!({"cat": "a"}), json!({}), Ok(json!("a"))),
+            (
+                json!({"cat": [{"var": "a"}, " ", {"var": "b"}, {"+": [1, 1]}]}),
+                json!({"a": "hello", "b": "world"}),
+                Ok(json!("hello world2")),
+            ),
+            (
+                json!({"cat": ["a", {"var": "missing"}, true]}),
+                json!({}),
+                Ok(json!("anulltrue")),
+            ),
+            (json!({"cat": ["a", {"/": [1, 0]}]}), json!({}), Err(())),
+            (
+                json!({"cat": ["user: ", {"var": "user"}]}),
+                json!({"user": {"id": 7}}),
+                Ok(json!("user: [object Object]")),
+            ),
+            (
+                json!({"cat": ["ids: ", {"var": "ids"}]}),
+                json!({"ids": [1, null, [2, 3]]}),
+                Ok(json!("ids: 1,,2,3")),
+            ),
+        ]
+    }
+
+    fn cat_json_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"cat": ["user: ", {"var": "user"}]}),
+                json!({"user": {"id": 7, "tags": ["a"]}}),
+                Ok(json!(r#"user: {"id":7,"tags":["a"]}"#)),
+            ),
+            (
+                json!({"cat": ["ids: ", {"var": "ids"}]}),
+                json!({"ids": [1, null, [2, 3]]}),
+                Ok(json!("ids: [1,null,[2,3]]")),
+            ),
+            (
+                json!({"cat": ["a", [1, "b"]]}),
+                json!({}),
+                Ok(json!(r#"a[1,"b"]"#)),
+            ),
+            (
+                json!({"cat": ["quote: ", {"var": "q"}]}),
+                json!({"q": ["\""]}),
+                Ok(json!(r#"quote: ["\""]"#)),
+            ),
+            // Other values are unaffected
+            (
+                json!({"cat": ["a", 1.5, null, true, "b"]}),
+                json!({}),
+                Ok(json!("a1.5nulltrueb")),
+            ),
+        ]
+    }
+
+    fn cat_error_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"cat": ["user: ", {"var": "user"}]}),
+                json!({"user": {"id": 7}}),
+                Err(()),
+            ),
+            (
+                json!({"cat": ["ids: ", {"var": "ids"}]}),
+                json!({"ids": []}),
+                Err(()),
+            ),
+            (json!({"cat": ["a", [1, "b"]]}), json!({}), Err(())),
+            (
+                json!({"cat": ["a", 1.5, null, true, "b"]}),
+                json!({}),
+                Ok(json!("a1.5nulltrueb")),
+            ),
+        ]
+    }
+
+    fn substr_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            // Wrong number of arguments
+            (json!({"substr": []}), json!({}), Err(())),
             (json!({"substr": ["foo"]}), json!({}), Err(())),
             (json!({"substr": ["foo", 1, 2, 3]}), json!({}), Err(())),
+            // Extreme indexes must not overflow
+            (
+                json!({"substr": ["foo", i64::MIN]}),
+                json!({}),
+                Ok(json!("foo")),
+            ),
+            (
+                json!({"substr": ["foo", 1, i64::MIN]}),
+                json!({}),
+                Ok(json!("")),
+            ),
+            (
+                json!({"substr": ["foo", i64::MAX, i64::MAX]}),
+                json!({}),
+                Ok(json!("")),
+            ),
             // Wrong argument types
             (json!({"substr": [12, 1]}), json!({}), Err(())),
             (json!({"substr": ["foo", "12"]}), json!({}), Err(())),
@@ -974,6 +2042,14 @@ mod jsonlogic_tests {
             (json!({"<": [1, 2, 3]}), json!({}), Ok(json!(true))),
             (json!({"<": [3, 2, 3]}), json!({}), Ok(json!(false))),
             (json!({"<": [1, 2, 1]}), json!({}), Ok(json!(false))),
+            (json!({"<": [1, 2, 3, 4]}), json!({}), Ok(json!(true))),
+            (json!({"<": [1, 3, 2, 4]}), json!({}), Ok(json!(false))),
+            (
+                json!({"<": [1, {"var": "x"}, {"var": "y"}, 10]}),
+                json!({"x": 2, "y": 9}),
+                Ok(json!(true)),
+            ),
+            (json!({"<": [1]}), json!({}), Err(())),
         ]
     }
 
@@ -990,6 +2066,8 @@ mod jsonlogic_tests {
             (json!({">": [3, 2, 3]}), json!({}), Ok(json!(false))),
             (json!({">": [1, 2, 1]}), json!({}), Ok(json!(false))),
             (json!({">": [3, 2, 1]}), json!({}), Ok(json!(true))),
+            (json!({">": [5, 4, 3, 2, 1]}), json!({}), Ok(json!(true))),
+            (json!({">": [5, 4, 6, 1]}), json!({}), Ok(json!(false))),
         ]
     }
 
@@ -1007,6 +2085,15 @@ mod jsonlogic_tests {
                 json!({}),
                 Ok(json!(125)),
             ),
+            // A single array argument is spread
+            (json!({"+": [[1, 2, "3"]]}), json!({}), Ok(json!(6))),
+            (
+                json!({"+": {"var": "a"}}),
+                json!({"a": [1, 2.5]}),
+                Ok(json!(3.5)),
+            ),
+            (json!({"+": {"var": "a"}}), json!({"a": []}), Ok(json!(0))),
+            (json!({"+": [[1, 2], 3]}), json!({}), Ok(json!(4))),
         ]
     }
 
@@ -1029,6 +2116,18 @@ mod jsonlogic_tests {
             (json!({"*": [1, 2, "3"]}), json!({}), Ok(json!(6))),
             (json!({"*": [1, "2abc", "3"]}), json!({}), Ok(json!(6))),
             (json!({"*": []}), json!({}), Err(())),
+            // A single array argument is spread
+            (json!({"*": [[2, 3, 4]]}), json!({}), Ok(json!(24))),
+            (
+                json!({"*": {"var": "a"}}),
+                json!({"a": [2, "3"]}),
+                Ok(json!(6)),
+            ),
+            (json!({"*": {"var": "a"}}), json!({"a": []}), Err(())),
+            (json!({"*": [[2, 3], 4]}), json!({}), Ok(json!(8))),
+            // Whole numbers outside of the i64 range stay floats
+            (json!({"*": [1e300, 1]}), json!({}), Ok(json!(1e300))),
+            (json!({"*": [1e300, 1e300]}), json!({}), Err(())),
         ]
     }
 
@@ -1038,6 +2137,12 @@ mod jsonlogic_tests {
             (json!({"/": [1, 2]}), json!({}), Ok(json!(0.5))),
             (json!({"/": [1, "2"]}), json!({}), Ok(json!(0.5))),
             (json!({"/": [12, "-2"]}), json!({}), Ok(json!(-6))),
+            (json!({"/": [0, 2]}), json!({}), Ok(json!(0))),
+            // Division by zero is an error by default
+            (json!({"/": [1, 0]}), json!({}), Err(())),
+            (json!({"/": [0, 0]}), json!({}), Err(())),
+            (json!({"/": [1, "0"]}), json!({}), Err(())),
+            (json!({"/": [1, null]}), json!({}), Err(())),
             (json!({"/": []}), json!({}), Err(())),
             (json!({"/": [5]}), json!({}), Err(())),
             (json!({"/": [5, 2, 1]}), json!({}), Err(())),
@@ -1050,6 +2155,7 @@ mod jsonlogic_tests {
             (json!({"%": [1, 2]}), json!({}), Ok(json!(1))),
             (json!({"%": [1, "2"]}), json!({}), Ok(json!(1))),
             (json!({"%": [12, "-2"]}), json!({}), Ok(json!(0))),
+            (json!({"%": [1, 0]}), json!({}), Err(())),
             (json!({"%": []}), json!({}), Err(())),
             (json!({"%": [5]}), json!({}), Err(())),
             (json!({"%": [5, 2, 1]}), json!({}), Err(())),
@@ -1062,6 +2168,14 @@ mod jsonlogic_tests {
             (json!({"max": [false, -1, 2]}), json!({}), Ok(json!(2))),
             (json!({"max": [0, -1, true]}), json!({}), Ok(json!(1))),
             (json!({"max": [0, -1, true, [3]]}), json!({}), Ok(json!(3))),
+            // A single array argument is spread
+            (json!({"max": [[1, 3, 2]]}), json!({}), Ok(json!(3))),
+            (
+                json!({"max": {"var": "a"}}),
+                json!({"a": [1, "4", 2]}),
+                Ok(json!(4)),
+            ),
+            (json!({"max": {"var": "a"}}), json!({"a": []}), Err(())),
         ]
     }
 
@@ -1075,48 +2189,197 @@ mod jsonlogic_tests {
                 json!({}),
                 Ok(json!(-1)),
             ),
+            (json!({"min": [[1, -3, 2]]}), json!({}), Ok(json!(-3))),
+            (
+                json!({"min": {"var": "a"}}),
+                json!({"a": [2, 1.5]}),
+                Ok(json!(1.5)),
+            ),
+            (json!({"min": {"var": "a"}}), json!({"a": []}), Err(())),
         ]
     }
 
-    fn bang_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
-        vec![
-            (json!( {"!": []} ), json!({}), Err(())),
-            (json!( {"!": [1, 2]} ), json!({}), Err(())),
-            (json!({"!": [true]}), json!({}), Ok(json!(false))),
-            (json!({"!": [1]}), json!({}), Ok(json!(false))),
-            (json!({"!": [0]}), json!({}), Ok(json!(true))),
-            (json!({"!": [[]]}), json!({}), Ok(json!(true))),
-            (json!({"!": [{}]}), json!({}), Ok(json!(false))),
-            (json!({"!": [""]}), json!({}), Ok(json!(true))),
-            (json!({"!": ["foo"]}), json!({}), Ok(json!(false))),
-            (json!({"!": true}), json!({}), Ok(json!(false))),
-        ]
-    }
-
-    fn in_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+    fn max_by_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
         vec![
-            // Invalid inputs
-            (json!( {"in": []} ), json!({}), Err(())),
-            (json!( {"in": [1, [], 1]} ), json!({}), Err(())),
-            (json!( {"in": [1, "foo"]} ), json!({}), Err(())),
-            (json!( {"in": [1, 1]} ), json!({}), Err(())),
-            // Valid inputs
-            (json!( {"in": [1, null]} ), json!({}), Ok(json!(false))),
-            (json!( {"in": [1, [1, 2]]} ), json!({}), Ok(json!(true))),
-            (json!( {"in": [1, [0, 2]]} ), json!({}), Ok(json!(false))),
-            (json!( {"in": ["f", "foo"]} ), json!({}), Ok(json!(true))),
-            (json!( {"in": ["f", "bar"]} ), json!({}), Ok(json!(false))),
-            (json!( {"in": ["f", null]} ), json!({}), Ok(json!(false))),
             (
-                json!( {"in": [null, [1, null]]} ),
-                json!({}),
-                Ok(json!(true)),
+                json!({"max_by": [{"var": "people"}, {"var": "age"}]}),
+                json!({"people": [
+                    {"name": "a", "age": 30},
+                    {"name": "b", "age": 40},
+                    {"name": "c", "age": 40},
+                ]}),
+                Ok(json!({"name": "b", "age": 40})),
             ),
-            (json!( {"in": [null, [1, 2]]} ), json!({}), Ok(json!(false))),
             (
-                json!( {"in": [true, [true, 2]]} ),
+                json!({"max_by": [[1, -5, 3], {"*": [{"var": ""}, {"var": ""}]}]}),
                 json!({}),
-                Ok(json!(true)),
+                Ok(json!(-5)),
+            ),
+            (
+                json!({"max_by": [[], {"var": ""}]}),
+                json!({}),
+                Ok(json!(null)),
+            ),
+            (
+                json!({"max_by": [{"var": "a"}, {"var": ""}]}),
+                json!({}),
+                Ok(json!(null)),
+            ),
+            (json!({"max_by": [["a"], {"var": ""}]}), json!({}), Err(())),
+            (json!({"max_by": [1, {"var": ""}]}), json!({}), Err(())),
+        ]
+    }
+
+    fn min_by_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"min_by": [{"var": "people"}, {"var": "age"}]}),
+                json!({"people": [
+                    {"name": "a", "age": 30},
+                    {"name": "b", "age": 20},
+                    {"name": "c", "age": 20},
+                ]}),
+                Ok(json!({"name": "b", "age": 20})),
+            ),
+            (
+                json!({"min_by": [[], {"var": ""}]}),
+                json!({}),
+                Ok(json!(null)),
+            ),
+        ]
+    }
+
+    fn take_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"take": [{"var": "xs"}, 2]}),
+                json!({"xs": [1, 2, 3]}),
+                Ok(json!([1, 2])),
+            ),
+            (
+                json!({"take": [{"var": "xs"}, {"var": "n"}]}),
+                json!({"xs": [1, 2, 3], "n": 5}),
+                Ok(json!([1, 2, 3])),
+            ),
+            (
+                json!({"take": ["abc", 2]}),
+                json!({}),
+                Ok(json!(["a", "b"])),
+            ),
+            (json!({"take": [null, 2]}), json!({}), Ok(json!([]))),
+            (json!({"take": [[1, 2], 0]}), json!({}), Ok(json!([]))),
+            // Items past the count are never evaluated
+            (
+                json!({"take": [[{"var": "a"}, {"/": [1, 0]}], 1]}),
+                json!({"a": 1}),
+                Ok(json!([1])),
+            ),
+            (json!({"take": [[1, {"/": [1, 0]}], 2]}), json!({}), Err(())),
+            (json!({"take": [[1, 2], -1]}), json!({}), Err(())),
+            (json!({"take": [[1, 2], 1.5]}), json!({}), Err(())),
+            (json!({"take": [[1, 2], "1"]}), json!({}), Err(())),
+            (json!({"take": [1, 1]}), json!({}), Err(())),
+            (json!({"take": [[1, 2]]}), json!({}), Err(())),
+        ]
+    }
+
+    fn take_while_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"take_while": [{"var": "xs"}, {"<": [{"var": ""}, 3]}]}),
+                json!({"xs": [1, 2, 3, 1]}),
+                Ok(json!([1, 2])),
+            ),
+            (
+                json!({"take_while": [[1, 2], true]}),
+                json!({}),
+                Ok(json!([1, 2])),
+            ),
+            (json!({"take_while": [[], true]}), json!({}), Ok(json!([]))),
+            // Items after the first failing one are never evaluated
+            (
+                json!({"take_while": [[1, 5, {"/": [1, 0]}], {"<": [{"var": ""}, 3]}]}),
+                json!({}),
+                Ok(json!([1])),
+            ),
+            (
+                json!({"take_while": [[1, {"/": [1, 0]}], {"<": [{"var": ""}, 3]}]}),
+                json!({}),
+                Err(()),
+            ),
+        ]
+    }
+
+    fn first_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"first": [{"var": "people"}, {">=": [{"var": "age"}, 18]}]}),
+                json!({"people": [
+                    {"name": "a", "age": 12},
+                    {"name": "b", "age": 20},
+                    {"name": "c", "age": 30},
+                ]}),
+                Ok(json!({"name": "b", "age": 20})),
+            ),
+            (
+                json!({"first": [[1, 2], {">": [{"var": ""}, 5]}]}),
+                json!({}),
+                Ok(json!(null)),
+            ),
+            (json!({"first": ["abc", true]}), json!({}), Ok(json!("a"))),
+            // Items after the first match are never evaluated
+            (
+                json!({"first": [[1, 7, {"/": [1, 0]}], {">": [{"var": ""}, 5]}]}),
+                json!({}),
+                Ok(json!(7)),
+            ),
+            (
+                json!({"first": [[{"/": [1, 0]}, 7], {">": [{"var": ""}, 5]}]}),
+                json!({}),
+                Err(()),
+            ),
+        ]
+    }
+
+    fn bang_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!( {"!": []} ), json!({}), Err(())),
+            (json!( {"!": [1, 2]} ), json!({}), Err(())),
+            (json!({"!": [true]}), json!({}), Ok(json!(false))),
+            (json!({"!": [1]}), json!({}), Ok(json!(false))),
+            (json!({"!": [0]}), json!({}), Ok(json!(true))),
+            (json!({"!": [[]]}), json!({}), Ok(json!(true))),
+            (json!({"!": [{}]}), json!({}), Ok(json!(false))),
+            (json!({"!": [""]}), json!({}), Ok(json!(true))),
+            (json!({"!": ["foo"]}), json!({}), Ok(json!(false))),
+            (json!({"!": true}), json!({}), Ok(json!(false))),
+        ]
+    }
+
+    fn in_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            // Invalid inputs
+            (json!( {"in": []} ), json!({}), Err(())),
+            (json!( {"in": [1, [], 1]} ), json!({}), Err(())),
+            (json!( {"in": [1, "foo"]} ), json!({}), Err(())),
+            (json!( {"in": [1, 1]} ), json!({}), Err(())),
+            // Valid inputs
+            (json!( {"in": [1, null]} ), json!({}), Ok(json!(false))),
+            (json!( {"in": [1, [1, 2]]} ), json!({}), Ok(json!(true))),
+            (json!( {"in": [1, [0, 2]]} ), json!({}), Ok(json!(false))),
+            (json!( {"in": ["f", "foo"]} ), json!({}), Ok(json!(true))),
+            (json!( {"in": ["f", "bar"]} ), json!({}), Ok(json!(false))),
+            (json!( {"in": ["f", null]} ), json!({}), Ok(json!(false))),
+            (
+                json!( {"in": [null, [1, null]]} ),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (json!( {"in": [null, [1, 2]]} ), json!({}), Ok(json!(false))),
+            (
+                json!( {"in": [true, [true, 2]]} ),
+                json!({}),
+                Ok(json!(true)),
             ),
             (json!( {"in": [true, [1, 2]]} ), json!({}), Ok(json!(false))),
             (
@@ -1144,260 +2407,2222 @@ mod jsonlogic_tests {
                 json!({}),
                 Ok(json!(false)),
             ),
+            // Items of a literal haystack are evaluated
+            (
+                json!( {"in": [{"var": "x"}, [{"var": "a"}, {"var": "b"}]]} ),
+                json!({"x": 2, "a": 1, "b": 2}),
+                Ok(json!(true)),
+            ),
+            // Items after a match are never evaluated
+            (
+                json!( {"in": [1, [1, {"/": [1, 0]}]]} ),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (json!( {"in": [1, [{"/": [1, 0]}, 1]]} ), json!({}), Err(())),
+            // Evaluated haystacks are not evaluated again
+            (
+                json!( {"in": [{"var": "x"}, {"var": "list"}]} ),
+                json!({"x": {"var": "a"}, "list": [{"var": "a"}]}),
+                Ok(json!(true)),
+            ),
         ]
     }
 
-    fn assert_jsonlogic((op, data, exp): (Value, Value, Result<Value, ()>)) -> () {
-        println!("Running rule: {:?} with data: {:?}", op, data);
-        let result = apply(&op, &data);
-        println!("- Result: {:?}", result);
-        println!("- Expected: {:?}", exp);
-        if exp.is_ok() {
-            assert_eq!(result.unwrap(), exp.unwrap());
-        } else {
-            result.unwrap_err();
-        }
+    fn bucket_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"bucket": ["abc", 100]}), json!({}), Ok(json!(49))),
+            (json!({"bucket": ["abc", 10]}), json!({}), Ok(json!(9))),
+            (
+                json!({"bucket": [{"var": "id"}, 100]}),
+                json!({"id": "user-1"}),
+                Ok(json!(56)),
+            ),
+            (
+                json!({"bucket": [{"var": "id"}, 100]}),
+                json!({"id": "user-2"}),
+                Ok(json!(89)),
+            ),
+            // Numbers hash the same as their string representation
+            (json!({"bucket": [123, 100]}), json!({}), Ok(json!(49))),
+            (json!({"bucket": ["123", 100]}), json!({}), Ok(json!(49))),
+            (json!({"bucket": ["abc", 1]}), json!({}), Ok(json!(0))),
+            (
+                json!({"<": [{"bucket": [{"var": "id"}, 100]}, 60]}),
+                json!({"id": "user-1"}),
+                Ok(json!(true)),
+            ),
+            // Invalid keys
+            (json!({"bucket": [null, 100]}), json!({}), Err(())),
+            (json!({"bucket": [{"var": "id"}, 100]}), json!({}), Err(())),
+            (json!({"bucket": [[1], 100]}), json!({}), Err(())),
+            // Invalid bucket counts
+            (json!({"bucket": ["abc", 0]}), json!({}), Err(())),
+            (json!({"bucket": ["abc", -1]}), json!({}), Err(())),
+            (json!({"bucket": ["abc", 1.5]}), json!({}), Err(())),
+            (json!({"bucket": ["abc", "10"]}), json!({}), Err(())),
+            // Wrong number of arguments
+            (json!({"bucket": ["abc"]}), json!({}), Err(())),
+        ]
     }
 
-    fn replace_operator(
-        old_op: &'static str,
-        new_op: &'static str,
-        (op, data, exp): (Value, Value, Result<Value, ()>),
-    ) -> (Value, Value, Result<Value, ()>) {
-        (
-            match op {
-                Value::Object(obj) => json!({new_op: obj.get(old_op).unwrap()}),
-                _ => panic!(),
-            },
-            data,
-            exp,
-        )
+    fn checksum_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"luhn_valid": "4111 1111 1111 1111"}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"luhn_valid": [79927398713_u64]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"luhn_valid": {"var": "card"}}),
+                json!({"card": "4111-1111-1111-1112"}),
+                Ok(json!(false)),
+            ),
+            (json!({"luhn_valid": ""}), json!({}), Ok(json!(false))),
+            (
+                json!({"checksum": ["GB82 WEST 1234 5698 7654 32", "iban"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"checksum": ["978-0-306-40615-7", "gtin"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"checksum": ["0-306-40615-3", "isbn10"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"checksum": [2363, "verhoeff"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"checksum": ["2363", "luhn"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            // Invalid arguments
+            (json!({"luhn_valid": null}), json!({}), Err(())),
+            (json!({"luhn_valid": {"var": "card"}}), json!({}), Err(())),
+            (json!({"checksum": ["2363", "mod11"]}), json!({}), Err(())),
+            (json!({"checksum": ["2363"]}), json!({}), Err(())),
+        ]
+    }
+
+    fn to_number_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"toNumber": ["12"]}), json!({}), Ok(json!(12))),
+            (json!({"toNumber": "12.5"}), json!({}), Ok(json!(12.5))),
+            (json!({"toNumber": ["-3e2"]}), json!({}), Ok(json!(-300))),
+            (json!({"toNumber": [7]}), json!({}), Ok(json!(7))),
+            (json!({"toNumber": [" 8 "]}), json!({}), Ok(json!(8))),
+            (
+                json!({"toNumber": {"var": "age"}}),
+                json!({"age": "42"}),
+                Ok(json!(42)),
+            ),
+            // Unlike the implicit parseFloat used by `+`, trailing garbage
+            // is not accepted
+            (json!({"toNumber": ["12px"]}), json!({}), Err(())),
+            (json!({"toNumber": ["abc"]}), json!({}), Err(())),
+            (json!({"toNumber": ["NaN"]}), json!({}), Err(())),
+            (json!({"toNumber": [{}]}), json!({}), Err(())),
+            (json!({"toNumber": [1, 2]}), json!({}), Err(())),
+            // Nor are values that JS coerces to numbers but aren't numeric
+            (json!({"toNumber": [null]}), json!({}), Err(())),
+            (json!({"toNumber": [""]}), json!({}), Err(())),
+            (json!({"toNumber": ["  "]}), json!({}), Err(())),
+            (json!({"toNumber": [true]}), json!({}), Err(())),
+            (json!({"toNumber": [false]}), json!({}), Err(())),
+            (json!({"toNumber": [["5"]]}), json!({}), Err(())),
+            (json!({"toNumber": [[]]}), json!({}), Err(())),
+        ]
+    }
+
+    fn to_string_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"toString": [12]}), json!({}), Ok(json!("12"))),
+            (json!({"toString": 1.5}), json!({}), Ok(json!("1.5"))),
+            (json!({"toString": ["foo"]}), json!({}), Ok(json!("foo"))),
+            (json!({"toString": [null]}), json!({}), Ok(json!("null"))),
+            (json!({"toString": [false]}), json!({}), Ok(json!("false"))),
+            (json!({"toString": [[[1, 2]]]}), json!({}), Ok(json!("1,2"))),
+            (
+                json!({"toString": [{}]}),
+                json!({}),
+                Ok(json!("[object Object]")),
+            ),
+            (
+                json!({"toString": {"var": "id"}}),
+                json!({"id": 1234}),
+                Ok(json!("1234")),
+            ),
+            (json!({"toString": [1, 2]}), json!({}), Err(())),
+        ]
+    }
+
+    fn starts_with_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"startsWith": ["foobar", "foo"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"startsWith": ["foobar", "bar"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"startsWith": ["foobar", ""]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"startsWith": ["", "foo"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"startsWith": [{"var": "email"}, "admin@"]}),
+                json!({"email": "admin@example.com"}),
+                Ok(json!(true)),
+            ),
+            (json!({"startsWith": [1, "1"]}), json!({}), Err(())),
+            (json!({"startsWith": ["1", 1]}), json!({}), Err(())),
+            (json!({"startsWith": ["foo"]}), json!({}), Err(())),
+        ]
+    }
+
+    fn ends_with_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"endsWith": ["foobar", "bar"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"endsWith": ["foobar", "foo"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"endsWith": ["foobar", ""]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"endsWith": [{"var": "email"}, "@example.com"]}),
+                json!({"email": "admin@example.com"}),
+                Ok(json!(true)),
+            ),
+            (json!({"endsWith": [null, "a"]}), json!({}), Err(())),
+            (json!({"endsWith": ["a", ["a"]]}), json!({}), Err(())),
+        ]
     }
 
-    fn flip_boolean_exp(
-        (op, data, exp): (Value, Value, Result<Value, ()>),
-    ) -> (Value, Value, Result<Value, ()>) {
-        (
-            op,
-            data,
-            match exp {
-                Err(_) => exp,
-                Ok(Value::Bool(exp)) => Ok(Value::Bool(!exp)),
-                _ => panic!(),
-            },
-        )
+    fn contains_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"contains": ["foobar", "oba"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"contains": ["foobar", "baz"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"contains": ["foobar", ""]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"contains": ["foobar", "FOO"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            // Arrays are not strings
+            (json!({"contains": [["foo"], "foo"]}), json!({}), Err(())),
+            (json!({"contains": ["foo", 1]}), json!({}), Err(())),
+        ]
+    }
+
+    fn eq_i_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"==i": ["abc", "ABC"]}), json!({}), Ok(json!(true))),
+            (json!({"==i": ["abc", "abd"]}), json!({}), Ok(json!(false))),
+            (
+                json!({"==i": ["ÉCOLE", "école"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"==i": [{"var": "name"}, "alice"]}),
+                json!({"name": "ALICE"}),
+                Ok(json!(true)),
+            ),
+            // Non-strings still use abstract equality
+            (json!({"==i": [1, "1"]}), json!({}), Ok(json!(true))),
+            (json!({"==i": [null, "null"]}), json!({}), Ok(json!(false))),
+            (json!({"==i": ["A"]}), json!({}), Err(())),
+        ]
+    }
+
+    fn in_i_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"in_i": ["SPRING", "Springfield"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"in_i": ["field", "SPRINGFIELD"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"in_i": ["fall", "Springfield"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"in_i": ["us", ["US", "CA"]]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"in_i": ["De", ["US", "CA"]]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (json!({"in_i": [1, [1, 2]]}), json!({}), Ok(json!(true))),
+            (json!({"in_i": ["a", null]}), json!({}), Ok(json!(false))),
+            (json!({"in_i": [1, "abc"]}), json!({}), Err(())),
+        ]
+    }
+
+    #[cfg(feature = "measure")]
+    fn measure_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"measure": ["5kg", "g"]}), json!({}), Ok(json!(5000))),
+            (
+                json!({"measure": ["2h30m", "min"]}),
+                json!({}),
+                Ok(json!(150)),
+            ),
+            (
+                json!({"measure": ["1 mi", "km"]}),
+                json!({}),
+                Ok(json!(1.609344)),
+            ),
+            (json!({"measure": ["5kg", "m"]}), json!({}), Err(())),
+            (json!({"measure": ["5kg", "stone"]}), json!({}), Err(())),
+            (
+                json!({"measure_lt": ["5kg", "12lb"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"measure_gt": ["5kg", "12lb"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"measure_eq": ["1ft", "12in"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"measure_lte": ["1ft", "12in"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"measure_lt": ["1ft", "12in"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"measure_gte": ["90min", "1h30m"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"measure_lt": ["1h", {"var": "eta"}, "1d"]}),
+                json!({"eta": "2h30m"}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"measure_lt": [{"var": "weight"}, "20kg"]}),
+                json!({"weight": "30 lb"}),
+                Ok(json!(true)),
+            ),
+            // Quantities must be strings of the same dimension
+            (json!({"measure_lt": ["5kg", "5m"]}), json!({}), Err(())),
+            (json!({"measure_lt": [5, "5kg"]}), json!({}), Err(())),
+            (json!({"measure_lt": ["5", "5kg"]}), json!({}), Err(())),
+        ]
+    }
+
+    #[cfg(feature = "measure")]
+    #[test]
+    fn test_measure() {
+        measure_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[cfg(feature = "money")]
+    fn money_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"money": "10.50 USD"}),
+                json!({}),
+                Ok(json!({"amount": "10.50", "currency": "USD"})),
+            ),
+            (
+                json!({"money_add": ["0.10 USD", "0.20 USD"]}),
+                json!({}),
+                Ok(json!({"amount": "0.30", "currency": "USD"})),
+            ),
+            (
+                json!({"money_sub": [{"var": "price"}, "2.5 EUR"]}),
+                json!({"price": {"amount": 10, "currency": "EUR"}}),
+                Ok(json!({"amount": "7.5", "currency": "EUR"})),
+            ),
+            (
+                json!({"money_round": {"money_mul": ["19.99 USD", "0.0825"]}}),
+                json!({}),
+                Ok(json!({"amount": "1.65", "currency": "USD"})),
+            ),
+            (
+                json!({"money_round": ["0.125 USD", 2]}),
+                json!({}),
+                Ok(json!({"amount": "0.13", "currency": "USD"})),
+            ),
+            (
+                json!({"money_eq": ["10.5 USD", "USD 10.50"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"money_lt": ["9.99 USD", {"var": "limit"}, "100 USD"]}),
+                json!({"limit": "50 USD"}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"money_gte": ["10 USD", "10.00 USD", "9.99 USD"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"money_gt": ["10 USD", "10 USD"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"money_lt": ["10 USD", "20 EUR"]}),
+                json!({}),
+                Err(()),
+            ),
+            (
+                json!({"money_add": ["10 USD", "20 EUR"]}),
+                json!({}),
+                Err(()),
+            ),
+            (json!({"money": ["10 USD", "EUR"]}), json!({}), Err(())),
+            (json!({"money_lt": [10, "20 USD"]}), json!({}), Err(())),
+            (json!({"money_mul": ["10 USD", "x"]}), json!({}), Err(())),
+        ]
+    }
+
+    #[cfg(feature = "money")]
+    #[test]
+    fn test_money() {
+        money_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[cfg(feature = "money")]
+    #[test]
+    fn test_money_exchange_rates() {
+        let rates = ExchangeRates::from_value(&json!({
+            "base": "USD",
+            "rates": {"EUR": "0.8", "JPY": 150},
+        }))
+        .unwrap();
+        let engine = Engine::new().exchange_rates(rates);
+        let cases = vec![
+            (
+                json!({"money_eq": ["8 EUR", "10 USD", "1500 JPY"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"money_lt": ["7.99 EUR", "10 USD"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"money": ["10 USD", "EUR"]}),
+                json!({}),
+                Ok(json!({"amount": "8.0", "currency": "EUR"})),
+            ),
+            (
+                json!({"money_add": ["1 USD", "0.8 EUR", "150 JPY"]}),
+                json!({}),
+                Ok(json!({"amount": "3", "currency": "USD"})),
+            ),
+            (json!({"money_lt": ["1 USD", "1 GBP"]}), json!({}), Err(())),
+        ];
+        for case in cases {
+            assert_jsonlogic_with(&engine, case)
+        }
+    }
+
+    #[cfg(feature = "net")]
+    fn in_cidr_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"in_cidr": ["10.1.2.3", "10.0.0.0/8"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"in_cidr": ["11.1.2.3", "10.0.0.0/8"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"in_cidr": [{"var": "ip"}, ["10.0.0.0/8", "192.168.0.0/16"]]}),
+                json!({"ip": "192.168.4.20"}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"in_cidr": ["2001:db8::1", ["10.0.0.0/8", "2001:db8::/32"]]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"in_cidr": ["10.1.2.3", []]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"in_cidr": ["10.1.2.3", "10.1.2.3"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            // Every block is validated, even after a match
+            (
+                json!({"in_cidr": ["10.1.2.3", ["10.0.0.0/8", "10.0.0.0/40"]]}),
+                json!({}),
+                Err(()),
+            ),
+            (
+                json!({"in_cidr": ["10.1.2", "10.0.0.0/8"]}),
+                json!({}),
+                Err(()),
+            ),
+            (json!({"in_cidr": [null, "10.0.0.0/8"]}), json!({}), Err(())),
+            (json!({"in_cidr": ["10.1.2.3", 10]}), json!({}), Err(())),
+        ]
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn test_in_cidr() {
+        in_cidr_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[cfg(feature = "regions")]
+    fn in_region_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"in_region": ["FR", "EU"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"in_region": ["no", "eu"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"in_region": ["NO", "EEA"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"in_region": ["CH", "Schengen"]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"in_region": ["PL", "EUROZONE"]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"in_region": [{"var": "country"}, ["EEA", "CH", "GB"]]}),
+                json!({"country": "GB"}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"in_region": ["US", ["EU", "CA"]]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"in_region": ["US", []]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"in_region": ["FR", ["EU", "ASEAN"]]}),
+                json!({}),
+                Err(()),
+            ),
+            (json!({"in_region": ["FR", 1]}), json!({}), Err(())),
+            (json!({"in_region": ["FRA", "EU"]}), json!({}), Err(())),
+            (json!({"in_region": [null, "EU"]}), json!({}), Err(())),
+        ]
+    }
+
+    #[cfg(feature = "regions")]
+    #[test]
+    fn test_in_region() {
+        in_region_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[cfg(feature = "hashing")]
+    fn hashing_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (
+                json!({"sha256": "hello"}),
+                json!({}),
+                Ok(json!(
+                    "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+                )),
+            ),
+            (
+                json!({"sha256": ""}),
+                json!({}),
+                Ok(json!(
+                    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+                )),
+            ),
+            (
+                json!({"sha256": 42}),
+                json!({}),
+                Ok(json!(
+                    "73475cb40a568e8da8a045ced110137e159f890ac4da883b6b17dc651b3a8049"
+                )),
+            ),
+            (
+                json!({"md5": {"var": "email"}}),
+                json!({"email": "hello"}),
+                Ok(json!("5d41402abc4b2a76b9719d911017c592")),
+            ),
+            (
+                json!({"xxh64": "hello"}),
+                json!({}),
+                Ok(json!("26c7827d889f6da3")),
+            ),
+            (
+                json!({"==": [{"md5": {"var": "email"}}, "5d41402abc4b2a76b9719d911017c592"]}),
+                json!({"email": "hello"}),
+                Ok(json!(true)),
+            ),
+            (json!({"sha256": null}), json!({}), Err(())),
+            (json!({"md5": [["a"]]}), json!({}), Err(())),
+            (json!({"xxh64": {"var": "missing"}}), json!({}), Err(())),
+        ]
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_hashing() {
+        hashing_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    fn decimal_comma_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"+": ["1.234,5", 1]}), json!({}), Ok(json!(1235.5))),
+            (json!({"+": [["1,5", "2,5"]]}), json!({}), Ok(json!(4))),
+            (json!({"*": ["12,5kg", 2]}), json!({}), Ok(json!(25))),
+            (json!({"-": ["2,5", "0,5"]}), json!({}), Ok(json!(2))),
+            (json!({"-": ["2,5"]}), json!({}), Ok(json!(-2.5))),
+            (json!({"/": ["1,5", "0,5"]}), json!({}), Ok(json!(3))),
+            (json!({"%": ["5,5", 2]}), json!({}), Ok(json!(1.5))),
+            (json!({"<": ["1,5", "1,75"]}), json!({}), Ok(json!(true))),
+            (json!({"<": [1, "1,5", 2]}), json!({}), Ok(json!(true))),
+            (json!({">=": ["1.000", 999]}), json!({}), Ok(json!(true))),
+            (json!({"max": ["1,5", "1,25"]}), json!({}), Ok(json!(1.5))),
+            (
+                json!({"min": [["1,5", "1,25"]]}),
+                json!({}),
+                Ok(json!(1.25)),
+            ),
+            (
+                json!({"toNumber": "1.234,56"}),
+                json!({}),
+                Ok(json!(1234.56)),
+            ),
+            (json!({"toNumber": "12,5kg"}), json!({}), Err(())),
+            (
+                json!({"toNumber": {"var": "price"}}),
+                json!({"price": "9,99"}),
+                Ok(json!(9.99)),
+            ),
+            (
+                json!({"max_by": [{"var": "items"}, {"var": "price"}]}),
+                json!({"items": [{"price": "9,99"}, {"price": "10,5"}]}),
+                Ok(json!({"price": "10,5"})),
+            ),
+            // Equality agrees with the other comparisons
+            (json!({"==": ["1,5", 1.5]}), json!({}), Ok(json!(true))),
+            (json!({"<=": ["1,5", 1.5]}), json!({}), Ok(json!(true))),
+            (json!({"==": [1.5, "1,5"]}), json!({}), Ok(json!(true))),
+            (json!({"!=": ["1,5", 1.5]}), json!({}), Ok(json!(false))),
+            (json!({"==": ["1.000", 1000]}), json!({}), Ok(json!(true))),
+            (json!({"==": ["1,0", true]}), json!({}), Ok(json!(true))),
+            // Strings are still compared with one another as written
+            (json!({"==": ["1,5", "1.5"]}), json!({}), Ok(json!(false))),
+            (json!({"==": ["1,5", "1,5"]}), json!({}), Ok(json!(true))),
+        ]
+    }
+
+    fn division_by_zero_null_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"/": [1, 0]}), json!({}), Ok(json!(null))),
+            (json!({"/": [-1, 0]}), json!({}), Ok(json!(null))),
+            (json!({"/": [0, 0]}), json!({}), Ok(json!(null))),
+            (json!({"/": [1, "0"]}), json!({}), Ok(json!(null))),
+            (json!({"%": [1, 0]}), json!({}), Ok(json!(null))),
+            (
+                json!({"/": [{"var": "a"}, {"var": "b"}]}),
+                json!({"a": 10, "b": 0}),
+                Ok(json!(null)),
+            ),
+            // Non-zero divisors behave normally
+            (json!({"/": [1, 2]}), json!({}), Ok(json!(0.5))),
+            // Non-numeric arguments are still errors
+            (json!({"/": ["foo", 0]}), json!({}), Err(())),
+        ]
+    }
+
+    fn division_by_zero_infinity_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"/": [1, 0]}), json!({}), Ok(json!(null))),
+            (json!({"/": [-1, 0]}), json!({}), Ok(json!(null))),
+            (json!({"/": [1, "0"]}), json!({}), Ok(json!(null))),
+            (
+                json!({"/": [{"var": "a"}, {"var": "b"}]}),
+                json!({"a": 10, "b": 0}),
+                Ok(json!(null)),
+            ),
+            // Results that would be NaN are errors
+            (json!({"/": [0, 0]}), json!({}), Err(())),
+            (json!({"%": [1, 0]}), json!({}), Err(())),
+            (json!({"%": [0, 0]}), json!({}), Err(())),
+            // Non-zero divisors behave normally
+            (json!({"/": [1, 2]}), json!({}), Ok(json!(0.5))),
+            (json!({"%": [5, 2]}), json!({}), Ok(json!(1))),
+            // Non-numeric arguments are still errors
+            (json!({"/": ["foo", 0]}), json!({}), Err(())),
+        ]
+    }
+
+    fn reference_unary_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            // Only the first argument is used
+            (json!({"!": [1, 2]}), json!({}), Ok(json!(false))),
+            (json!({"!": [0, 1]}), json!({}), Ok(json!(true))),
+            (json!({"!!": [0, 1]}), json!({}), Ok(json!(false))),
+            (json!({"!!": ["a", 0, null]}), json!({}), Ok(json!(true))),
+            (
+                json!({"!": [{"var": "a"}, {"var": "b"}]}),
+                json!({"a": [], "b": true}),
+                Ok(json!(true)),
+            ),
+            // No arguments are like `undefined`
+            (json!({"!": []}), json!({}), Ok(json!(true))),
+            (json!({"!!": []}), json!({}), Ok(json!(false))),
+            // Unary forms are unchanged
+            (json!({"!": [[]]}), json!({}), Ok(json!(true))),
+            (json!({"!!": [[0]]}), json!({}), Ok(json!(true))),
+            (json!({"!": 0}), json!({}), Ok(json!(true))),
+            // Later arguments are still evaluated
+            (json!({"!": [0, {"/": ["foo", 2]}]}), json!({}), Err(())),
+            // Other operators still check their arguments
+            (json!({"toNumber": ["1", "2"]}), json!({}), Err(())),
+        ]
+    }
+
+    fn number_output_float_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"+": [1, 1]}), json!({}), Ok(json!(2.0))),
+            (json!({"-": [3, 1]}), json!({}), Ok(json!(2.0))),
+            (json!({"-": 2}), json!({}), Ok(json!(-2.0))),
+            (json!({"*": [2, 3]}), json!({}), Ok(json!(6.0))),
+            (json!({"/": [4, 2]}), json!({}), Ok(json!(2.0))),
+            (json!({"%": [5, 3]}), json!({}), Ok(json!(2.0))),
+            (json!({"max": [1, 2]}), json!({}), Ok(json!(2.0))),
+            (json!({"min": [1, 2]}), json!({}), Ok(json!(1.0))),
+            (json!({"toNumber": "7"}), json!({}), Ok(json!(7.0))),
+            (json!({"/": [1, 2]}), json!({}), Ok(json!(0.5))),
+            // Values that aren't computed are left as they are
+            (json!({"var": "a"}), json!({"a": 1}), Ok(json!(1))),
+        ]
+    }
+
+    fn number_output_preserve_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"+": [1, 1]}), json!({}), Ok(json!(2))),
+            (json!({"+": [1.5, 0.5]}), json!({}), Ok(json!(2.0))),
+            (json!({"+": [1, "1"]}), json!({}), Ok(json!(2.0))),
+            (json!({"+": [[1, 2]]}), json!({}), Ok(json!(3))),
+            (json!({"+": [[1, 2.0]]}), json!({}), Ok(json!(3.0))),
+            (json!({"-": 2}), json!({}), Ok(json!(-2))),
+            (json!({"*": [2.0, 3]}), json!({}), Ok(json!(6.0))),
+            (json!({"/": [4, 2]}), json!({}), Ok(json!(2))),
+            (json!({"/": [5, 2]}), json!({}), Ok(json!(2.5))),
+            (json!({"%": [5.0, 3]}), json!({}), Ok(json!(2.0))),
+            (json!({"max": [1, 2]}), json!({}), Ok(json!(2))),
+            (json!({"min": [1.0, 2]}), json!({}), Ok(json!(1.0))),
+            (json!({"toNumber": "7"}), json!({}), Ok(json!(7.0))),
+            (
+                json!({"+": [{"var": "a"}, {"var": "b"}]}),
+                json!({"a": 1, "b": 2}),
+                Ok(json!(3)),
+            ),
+        ]
+    }
+
+    fn assert_jsonlogic(case: (Value, Value, Result<Value, ()>)) {
+        assert_jsonlogic_with(&Engine::new(), case)
+    }
+
+    fn assert_jsonlogic_with(
+        engine: &Engine,
+        (op, data, exp): (Value, Value, Result<Value, ()>),
+    ) {
+        println!("Running rule: {:?} with data: {:?}", op, data);
+        let result = engine.apply(&op, &data);
+        println!("- Result: {:?}", result);
+        println!("- Expected: {:?}", exp);
+        match exp {
+            Ok(exp) => assert_eq!(result.unwrap(), exp),
+            Err(_) => {
+                result.unwrap_err();
+            }
+        }
+    }
+
+    fn replace_operator(
+        old_op: &'static str,
+        new_op: &'static str,
+        (op, data, exp): (Value, Value, Result<Value, ()>),
+    ) -> (Value, Value, Result<Value, ()>) {
+        (
+            match op {
+                Value::Object(obj) => json!({new_op: obj.get(old_op).unwrap()}),
+                _ => panic!(),
+            },
+            data,
+            exp,
+        )
+    }
+
+    fn flip_boolean_exp(
+        (op, data, exp): (Value, Value, Result<Value, ()>),
+    ) -> (Value, Value, Result<Value, ()>) {
+        (
+            op,
+            data,
+            match exp {
+                Err(_) => exp,
+                Ok(Value::Bool(exp)) => Ok(Value::Bool(!exp)),
+                _ => panic!(),
+            },
+        )
+    }
+
+    fn only_boolean(
+        wanted: bool,
+        (_, _, exp): &(Value, Value, Result<Value, ()>),
+    ) -> bool {
+        match exp {
+            Err(_) => false,
+            Ok(Value::Bool(exp)) => *exp == wanted,
+            _ => panic!("unexpected type of expectation"),
+        }
+    }
+
+    #[test]
+    fn test_no_op() {
+        no_op_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_abstract_eq_op() {
+        abstract_eq_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_abstract_ne_op() {
+        abstract_ne_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_strict_eq_op() {
+        strict_eq_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_strict_ne_op() {
+        strict_ne_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_var_data_op() {
+        var_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_get_op() {
+        get_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_require_op() {
+        require_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_typed_var_ops() {
+        typed_var_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_typed_var_error_message() {
+        match apply(&json!({"var_num": "a.b"}), &json!({"a": {"b": "1"}})) {
+            Err(Error::InvalidVariable { value, reason }) => {
+                assert_eq!(value, json!("1"));
+                assert_eq!(
+                    reason,
+                    r#"var_num expected "a.b" to be a number, got string"#
+                );
+            }
+            other => panic!("expected an InvalidVariable error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_data_op() {
+        missing_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_missing_some_data_op() {
+        missing_some_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_allowed_vars() {
+        let engine = Engine::new().policy(Policy::default().allow_var("order"));
+        let data = json!({
+            "order": {"items": [{"sku": "a"}, {"sku": "b"}], "field": "sku"},
+            "user": {"ssn": "123-45-6789"},
+        });
+        let apply = |rule: Value| engine.apply(&rule, &data);
+        assert_eq!(
+            apply(json!({"map": [{"var": "order.items"}, {"var": "sku"}]})).unwrap(),
+            json!(["a", "b"])
+        );
+        assert_eq!(
+            apply(json!({"var": {"cat": ["order.", "field"]}})).unwrap(),
+            json!("sku")
+        );
+        for rule in [
+            json!({"var": {"cat": ["user", ".ssn"]}}),
+            json!({"missing": {"merge": ["user.ssn"]}}),
+            json!({"reduce": [[1], {"var": "current"}, {"var": {"cat": ["user"]}}]}),
+        ] {
+            let err = apply(rule.clone()).unwrap_err();
+            assert_eq!(err.code(), "invalid_variable", "{}", rule);
+        }
+        assert_eq!(
+            apply(json!({"var": "user.ssn"})).unwrap_err().code(),
+            "policy_violation"
+        );
+        assert!(engine.validate(&json!({"var": "user"})).is_err());
+        assert!(engine
+            .apply_source(&json!({"var": {"cat": ["user"]}}), &data)
+            .is_err());
+    }
+
+    #[test]
+    fn test_missing_errors() {
+        let details = |rule: Value| {
+            let err = apply(&rule, &json!({})).unwrap_err();
+            assert_eq!(err.code(), "malformed_argument", "{}", rule);
+            err.to_json()["details"].clone()
+        };
+        let position = |rule: Value| {
+            let details = details(rule);
+            (details["index"].clone(), details["item"].clone())
+        };
+        assert_eq!(
+            details(json!({"missing_some": ["1", ["a"]]})),
+            json!({
+                "operator": "missing_some",
+                "index": 0,
+                "item": null,
+                "value": "1",
+                "expected": "a non-negative integer threshold",
+            })
+        );
+        assert_eq!(
+            details(json!({"missing_some": [1, "a"]}))["expected"],
+            json!("an array of keys")
+        );
+        assert_eq!(
+            position(json!({"missing_some": [1, ["a", {"b": 1}]]})),
+            (json!(1), json!(1))
+        );
+        assert_eq!(
+            position(json!({"missing": ["a", "b", [1]]})),
+            (json!(2), json!(null))
+        );
+        assert_eq!(
+            position(json!({"missing": [["a", 1.5]]})),
+            (json!(0), json!(1))
+        );
+        assert_eq!(
+            position(json!({"require": [["a", {"b": 1}]]})),
+            (json!(0), json!(1))
+        );
+
+        let err =
+            apply(&json!({"missing_some": [1, ["a", true]]}), &json!({})).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Malformed argument 1[1] for 'missing_some' - 'Bool(true)', \
+             expected: a string, an integer, or null"
+        );
+    }
+
+    #[test]
+    fn test_if_op() {
+        if_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_ternary_op() {
+        if_cases()
+            .into_iter()
+            .map(|case| replace_operator("if", "?:", case))
+            .for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_if_special_cases() {
+        let reference = Engine::new().compatibility(Compatibility::Reference);
+        for case in if_special_cases() {
+            for op in ["if", "?:"] {
+                let case = replace_operator("if", op, case.clone());
+                assert_jsonlogic(case.clone());
+                assert_jsonlogic_with(&reference, case);
+            }
+        }
+        if_cases()
+            .into_iter()
+            .for_each(|case| assert_jsonlogic_with(&reference, case));
+    }
+
+    #[test]
+    fn test_if_non_array() {
+        let reference = Engine::new().compatibility(Compatibility::Reference);
+        for (rule, data, expected) in if_non_array_cases() {
+            for op in ["if", "?:"] {
+                let (rule, data, _) = replace_operator(
+                    "if",
+                    op,
+                    (rule.clone(), data.clone(), Ok(expected.clone())),
+                );
+                assert_eq!(
+                    Engine::new().apply(&rule, &data).unwrap(),
+                    expected,
+                    "{}",
+                    rule
+                );
+                assert_eq!(
+                    reference.apply(&rule, &data).unwrap(),
+                    expected,
+                    "{}",
+                    rule
+                );
+
+                // Wrapping the argument in an array gives the same result
+                let wrapped = json!({ op: [rule[op].clone()] });
+                assert_eq!(Engine::new().apply(&wrapped, &data).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_or_op() {
+        or_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_and_op() {
+        and_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_map_op() {
+        map_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_filter_op() {
+        filter_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_reduce_op() {
+        reduce_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_iteration_ops() {
+        iteration_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_all_op() {
+        all_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_some_op() {
+        some_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_none_op() {
+        none_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_flatten_op() {
+        flatten_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_is_sorted_op() {
+        is_sorted_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_all_distinct_op() {
+        all_distinct_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_contains_all_any_ops() {
+        contains_all_any_cases()
+            .into_iter()
+            .for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_merge_op() {
+        merge_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_cat_op() {
+        cat_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_cat_coercion() {
+        let engine = Engine::new().cat_coercion(CatCoercion::Json);
+        cat_json_cases()
+            .into_iter()
+            .for_each(|case| assert_jsonlogic_with(&engine, case));
+        let engine = Engine::new().cat_coercion(CatCoercion::Error);
+        cat_error_cases()
+            .into_iter()
+            .for_each(|case| assert_jsonlogic_with(&engine, case));
+        match engine.apply(&json!({"cat": ["a", {"var": ""}]}), &json!({})) {
+            Err(Error::InvalidArgument { operation, .. }) => {
+                assert_eq!(operation, "cat")
+            }
+            other => panic!("expected an invalid argument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_substr_op() {
+        substr_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_log_op() {
+        log_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_lt_op() {
+        lt_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_lte_op() {
+        lt_cases()
+            .into_iter()
+            .map(|case| replace_operator("<", "<=", case))
+            .for_each(assert_jsonlogic);
+        abstract_eq_cases()
+            .into_iter()
+            // Only get cases that are equal, since we don't know whether
+            // non-equality cases were lt or gt or what.
+            .filter(|case| only_boolean(true, case))
+            .map(|case| replace_operator("==", "<=", case))
+            .for_each(assert_jsonlogic);
+    }
+
+    #[test]
+    fn test_gt_op() {
+        gt_cases().into_iter().for_each(assert_jsonlogic);
+    }
+
+    #[test]
+    fn test_gte_op() {
+        gt_cases()
+            .into_iter()
+            .map(|case| replace_operator(">", ">=", case))
+            .for_each(assert_jsonlogic);
+        abstract_eq_cases()
+            .into_iter()
+            // Only get cases that are equal, since we don't know whether
+            // non-equality cases were lt or gt or what.
+            .filter(|case| only_boolean(true, case))
+            .map(|case| replace_operator("==", ">=", case))
+            .for_each(assert_jsonlogic);
+    }
+
+    #[test]
+    fn test_plus_op() {
+        plus_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_minus_op() {
+        minus_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_mul_op() {
+        multiplication_cases()
+            .into_iter()
+            .for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_div_op() {
+        division_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_mod_op() {
+        modulo_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_division_by_zero_null() {
+        let engine = Engine::new().division_by_zero(DivisionByZero::Null);
+        division_by_zero_null_cases()
+            .into_iter()
+            .for_each(|case| assert_jsonlogic_with(&engine, case))
+    }
+
+    #[test]
+    fn test_division_by_zero_infinity_as_null() {
+        let engine = Engine::new().division_by_zero(DivisionByZero::InfinityAsNull);
+        division_by_zero_infinity_cases()
+            .into_iter()
+            .for_each(|case| assert_jsonlogic_with(&engine, case))
+    }
+
+    #[test]
+    fn test_overflow() {
+        let division = json!({"/": [1e308, 1e-308]});
+        let others = vec![
+            json!({"*": [1e308, 10]}),
+            json!({"+": [1e308, 1e308]}),
+            json!({"-": [-1e308, 1e308]}),
+            json!({"+": ["Infinity", 1]}),
+        ];
+        for behavior in [
+            DivisionByZero::Error,
+            DivisionByZero::Null,
+            DivisionByZero::InfinityAsNull,
+        ] {
+            let engine = Engine::new().division_by_zero(behavior);
+            let code =
+                |rule: &Value| engine.apply(rule, &json!(null)).unwrap_err().code();
+            match behavior {
+                DivisionByZero::Error => {
+                    assert_eq!(code(&division), "invalid_argument")
+                }
+                _ => assert_eq!(
+                    engine.apply(&division, &json!(null)).unwrap(),
+                    json!(null)
+                ),
+            }
+            // Only division is affected by the option
+            for rule in &others {
+                assert_eq!(code(rule), "invalid_argument", "{} {:?}", rule, behavior);
+            }
+        }
+        // NaN results are only null if all non-finite results are
+        let nan = json!({"/": ["Infinity", "Infinity"]});
+        let engine = Engine::new().division_by_zero(DivisionByZero::InfinityAsNull);
+        assert!(engine.apply(&nan, &json!(null)).is_err());
+        let engine = Engine::new().division_by_zero(DivisionByZero::Null);
+        assert_eq!(engine.apply(&nan, &json!(null)).unwrap(), json!(null));
+    }
+
+    #[test]
+    fn test_reference_unary() {
+        let engine = Engine::new().compatibility(Compatibility::Reference);
+        reference_unary_cases()
+            .into_iter()
+            .for_each(|case| assert_jsonlogic_with(&engine, case));
+        assert_jsonlogic((json!({"!!": [0, 1]}), json!({}), Err(())));
+    }
+
+    #[test]
+    fn test_decimal_comma() {
+        let engine = Engine::new().decimal_separator(DecimalSeparator::Comma);
+        decimal_comma_cases()
+            .into_iter()
+            .for_each(|case| assert_jsonlogic_with(&engine, case))
+    }
+
+    #[test]
+    fn test_number_output() {
+        let engine = Engine::new().number_output(NumberOutput::Float);
+        number_output_float_cases()
+            .into_iter()
+            .for_each(|case| assert_jsonlogic_with(&engine, case));
+        let engine = Engine::new().number_output(NumberOutput::Preserve);
+        number_output_preserve_cases()
+            .into_iter()
+            .for_each(|case| assert_jsonlogic_with(&engine, case));
+        assert_jsonlogic((json!({"/": [4.0, 2]}), json!({}), Ok(json!(2))));
+    }
+
+    #[test]
+    fn test_apply_serde() {
+        #[derive(serde::Serialize)]
+        struct Item {
+            #[serde(rename = "sku")]
+            id: String,
+            quantity: u32,
+        }
+        #[derive(serde::Serialize)]
+        struct Cart {
+            items: Vec<Item>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            coupon: Option<String>,
+        }
+
+        let cart = Cart {
+            items: vec![
+                Item {
+                    id: "a".into(),
+                    quantity: 2,
+                },
+                Item {
+                    id: "b".into(),
+                    quantity: 3,
+                },
+            ],
+            coupon: None,
+        };
+        let rule = json!({"reduce": [
+            {"var": "items"},
+            {"+": [{"var": "accumulator"}, {"var": "current.quantity"}]},
+            0
+        ]});
+        assert_eq!(apply_serde(&rule, &cart).unwrap(), json!(5));
+        assert_eq!(
+            apply_serde(&json!({"var": "items.1.sku"}), &cart).unwrap(),
+            json!("b")
+        );
+        assert_eq!(
+            apply_serde(&json!({"missing": ["coupon"]}), &cart).unwrap(),
+            json!(["coupon"])
+        );
+
+        // Maps with non-string keys can't be represented as JSON
+        let mut bad = std::collections::BTreeMap::new();
+        bad.insert(vec![1], 1);
+        match apply_serde(&json!({"var": ""}), &bad) {
+            Err(Error::InvalidData { .. }) => {}
+            other => panic!("expected InvalidData, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_apply_source() {
+        use std::borrow::Cow;
+        use std::cell::RefCell;
+
+        /// A flat table of columns, recording the paths requested
+        struct Row {
+            columns: Vec<(&'static str, Value)>,
+            requested: RefCell<Vec<String>>,
+        }
+        impl DataSource for Row {
+            fn get_path(&self, segments: &[&str]) -> Option<Cow<'_, Value>> {
+                self.requested.borrow_mut().push(segments.join("."));
+                match segments {
+                    [] => Some(Cow::Owned(Value::Object(
+                        self.columns
+                            .iter()
+                            .map(|(k, v)| (k.to_string(), v.clone()))
+                            .collect(),
+                    ))),
+                    [column, rest @ ..] => self
+                        .columns
+                        .iter()
+                        .find(|(k, _)| k == column)
+                        .and_then(|(_, v)| v.get_path(rest))
+                        .map(|v| Cow::Owned(v.into_owned())),
+                }
+            }
+        }
+
+        let row = Row {
+            columns: vec![("age", json!(42)), ("tags", json!(["a", "b"]))],
+            requested: RefCell::new(Vec::new()),
+        };
+        let engine = Engine::new();
+        let apply = |rule: Value| engine.apply_source(&rule, &row).unwrap();
+
+        assert_eq!(apply(json!({">": [{"var": "age"}, 18]})), json!(true));
+        assert_eq!(apply(json!({"var": "tags.1"})), json!("b"));
+        assert_eq!(apply(json!({"var": ["name", "anon"]})), json!("anon"));
+        assert_eq!(apply(json!({"missing": ["age", "name"]})), json!(["name"]));
+        assert_eq!(
+            apply(json!({"missing_some": [1, ["name", "age"]]})),
+            json!([])
+        );
+        assert_eq!(
+            apply(json!({"require": [["age", "tags"]]}))["satisfied"],
+            json!(true)
+        );
+        assert_eq!(
+            apply(json!({"var": ""})),
+            json!({"age": 42, "tags": ["a", "b"]})
+        );
+        // Within iterators, the data is each item rather than the source
+        assert_eq!(
+            apply(json!({"map": [{"var": "tags"}, {"cat": [{"var": ""}, "!"]}]})),
+            json!(["a!", "b!"])
+        );
+        assert_eq!(
+            row.requested.into_inner(),
+            vec![
+                "age", "tags.1", "name", "age", "name", "name", "age", "age", "tags",
+                "", "tags"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_with_stats() {
+        let engine = Engine::new();
+
+        let (res, stats) = engine
+            .apply_with_stats(
+                &json!({"map": [{"var": "xs"}, {"*": [{"var": ""}, 2]}]}),
+                &json!({"xs": [1, 2, 3]}),
+            )
+            .unwrap();
+        assert_eq!(res, json!([2, 4, 6]));
+        // map + var, then * + var for each of three items
+        assert_eq!(stats.operations, 8);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.data_lookups, 4);
+
+        let (_, stats) = engine
+            .apply_with_stats(&json!({"missing": ["a", "b", "c"]}), &json!({"a": 1}))
+            .unwrap();
+        assert_eq!(stats.operations, 1);
+        assert_eq!(stats.max_depth, 1);
+        assert_eq!(stats.data_lookups, 3);
+
+        let (_, stats) = engine.apply_with_stats(&json!(1), &json!(null)).unwrap();
+        assert_eq!(stats.operations, 0);
+        assert_eq!(stats.max_depth, 0);
+    }
+
+    #[test]
+    fn test_apply_with_branch() {
+        let engine = Engine::new();
+        let branch =
+            |rule: Value, data: Value| engine.apply_with_branch(&rule, &data).unwrap();
+
+        let rule = json!({"if": [{"var": "a"}, "x", {"var": "b"}, "y", "z"]});
+        assert_eq!(branch(rule.clone(), json!({"a": 1})), (json!("x"), Some(1)));
+        assert_eq!(branch(rule.clone(), json!({"b": 1})), (json!("y"), Some(3)));
+        assert_eq!(branch(rule, json!({})), (json!("z"), Some(4)));
+        assert_eq!(
+            branch(json!({"if": [false, "x", false, "y"]}), json!(null)),
+            (json!(null), None)
+        );
+        assert_eq!(
+            branch(json!({"?:": [true]}), json!(null)),
+            (json!(true), Some(0))
+        );
+
+        assert_eq!(
+            branch(json!({"or": [0, {"var": "a"}, 2]}), json!({"a": "yes"})),
+            (json!("yes"), Some(1))
+        );
+        assert_eq!(
+            branch(json!({"or": [0, false]}), json!(null)),
+            (json!(false), Some(1))
+        );
+        assert_eq!(
+            branch(json!({"and": [1, 0, 2]}), json!(null)),
+            (json!(0), Some(1))
+        );
+        assert_eq!(
+            branch(json!({"and": [1, 2]}), json!(null)),
+            (json!(2), Some(1))
+        );
+
+        // Only the top-level operator is considered
+        assert_eq!(
+            branch(json!({"!": {"if": [true, false, true]}}), json!(null)),
+            (json!(true), None)
+        );
+        assert_eq!(
+            branch(
+                json!({"if": [{"or": [false, true]}, {"and": [1, 2]}, "no"]}),
+                json!(null)
+            ),
+            (json!(2), Some(1))
+        );
+        assert_eq!(branch(json!({"+": [1, 2]}), json!(null)), (json!(3), None));
+        assert_eq!(
+            branch(json!([{"if": [true, 1, 2]}]), json!(null)),
+            (json!([1]), None)
+        );
+        let engine = Engine::new().evaluate_objects(true);
+        assert_eq!(
+            engine
+                .apply_with_branch(&json!({"a": {"or": [0, 1]}}), &json!(null))
+                .unwrap(),
+            (json!({"a": 1}), None)
+        );
     }
 
-    fn only_boolean(
-        wanted: bool,
-        (_, _, exp): &(Value, Value, Result<Value, ()>),
-    ) -> bool {
-        match exp {
-            Err(_) => false,
-            Ok(Value::Bool(exp)) => *exp == wanted,
-            _ => panic!("unexpected type of expectation"),
+    #[test]
+    fn test_apply_all_paths() {
+        let document = json!({
+            "name": "checkout",
+            "steps": [
+                "cart",
+                {"$logic": {"if": [{"var": "guest"}, "signup", "address"]}},
+                {"label": {"$logic": {"cat": ["Pay ", {"var": "total"}]}}},
+            ],
+            "rule": {"var": "total"},
+        });
+        let data = json!({"guest": true, "total": 12});
+        assert_eq!(
+            apply_all_paths(&document, &data).unwrap(),
+            json!({
+                "name": "checkout",
+                "steps": ["cart", "signup", {"label": "Pay 12"}],
+                "rule": {"var": "total"},
+            })
+        );
+        assert_eq!(
+            apply_all_paths(&json!([1, "a"]), &data).unwrap(),
+            json!([1, "a"])
+        );
+
+        // The marker is configurable
+        let engine = Engine::new().logic_marker("=");
+        assert_eq!(
+            engine
+                .apply_all_paths(&json!({"a": {"=": {"var": "total"}}}), &data)
+                .unwrap(),
+            json!({"a": 12})
+        );
+
+        // Errors in embedded rules are returned
+        assert!(apply_all_paths(&json!([{"$logic": {"==": [1]}}]), &data).is_err());
+        match apply_all_paths(&json!({"$logic": true, "other": 1}), &data) {
+            Err(Error::InvalidData { .. }) => {}
+            other => panic!("Unexpected result: {:?}", other),
         }
     }
 
     #[test]
-    fn test_no_op() {
-        no_op_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_numeric_keys() {
+        let data = json!({
+            "ids": {"7": "seven", "07": "oh seven", "1.5": "one and a half"},
+            "items": ["a", "b"],
+        });
+        let lookup = |engine: &Engine, key: &str| {
+            engine.apply(&json!({"var": key}), &data).unwrap()
+        };
+
+        let engine = Engine::new();
+        assert_eq!(lookup(&engine, "ids.7"), json!("seven"));
+        assert_eq!(lookup(&engine, "ids.07"), json!("oh seven"));
+        assert_eq!(lookup(&engine, "ids.7e0"), json!(null));
+
+        let engine = Engine::new().numeric_keys(NumericKeys::ExactFirst);
+        assert_eq!(lookup(&engine, "ids.07"), json!("oh seven"));
+        assert_eq!(lookup(&engine, "ids.007"), json!("seven"));
+        assert_eq!(lookup(&engine, "ids.+7"), json!("seven"));
+        assert_eq!(lookup(&engine, r"ids.1\.50"), json!("one and a half"));
+        assert_eq!(lookup(&engine, "items.01"), json!("b"));
+        assert_eq!(lookup(&engine, "ids.9007199254740999"), json!(null));
+        assert_eq!(
+            engine
+                .apply(&json!({"missing": ["ids.007", "ids.8"]}), &data)
+                .unwrap(),
+            json!(["ids.8"])
+        );
+
+        let engine = Engine::new().numeric_keys(NumericKeys::NumberFirst);
+        assert_eq!(lookup(&engine, "ids.07"), json!("seven"));
+        assert_eq!(lookup(&engine, "ids.x"), json!(null));
     }
 
     #[test]
-    fn test_abstract_eq_op() {
-        abstract_eq_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_max_op() {
+        max_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_abstract_ne_op() {
-        abstract_ne_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_min_op() {
+        min_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_strict_eq_op() {
-        strict_eq_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_max_by_op() {
+        max_by_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_strict_ne_op() {
-        strict_ne_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_min_by_op() {
+        min_by_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_var_data_op() {
-        var_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_take_op() {
+        take_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_missing_data_op() {
-        missing_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_take_while_op() {
+        take_while_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_missing_some_data_op() {
-        missing_some_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_first_op() {
+        first_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_if_op() {
-        if_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_seq_op() {
+        seq_cases().into_iter().for_each(assert_jsonlogic);
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let engine = Engine::new().add_operator("record", move |args: &[&Value]| {
+            recorded.lock().unwrap().push(args[0].clone());
+            Ok(args[0].clone())
+        });
+        let rule = json!({"seq": [
+            {"record": "first"},
+            {"if": [false, {"record": "skipped"}, {"record": "second"}]},
+            {"record": "third"},
+        ]});
+        assert_eq!(engine.apply(&rule, &json!(null)).unwrap(), json!("third"));
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![json!("first"), json!("second"), json!("third")]
+        );
     }
 
     #[test]
-    fn test_or_op() {
-        or_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_try_op() {
+        try_cases().into_iter().for_each(assert_jsonlogic);
+
+        let result = apply(&json!({"try": {"==": [1]}}), &json!(null)).unwrap();
+        assert_eq!(result["code"], json!("wrong_argument_count"));
+        assert_eq!(result["details"]["operator"], json!("=="));
     }
 
     #[test]
-    fn test_and_op() {
-        and_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_throw_and_assert_ops() {
+        vec![
+            (json!({"throw": []}), json!({}), Err(())),
+            (json!({"throw": "bad"}), json!({}), Err(())),
+            (json!({"assert": []}), json!({}), Err(())),
+            (
+                json!({"assert": [{"var": "a"}, "a is required"]}),
+                json!({}),
+                Err(()),
+            ),
+            (
+                json!({"assert": [{"var": "a"}, "a is required"]}),
+                json!({"a": 2}),
+                Ok(json!(2)),
+            ),
+            // Messages are only evaluated if the assertion fails
+            (
+                json!({"assert": [true, {"toNumber": "x"}]}),
+                json!({}),
+                Ok(json!(true)),
+            ),
+            (
+                json!({"try": [{"throw": "bad"}, "recovered"]}),
+                json!({}),
+                Ok(json!("recovered")),
+            ),
+        ]
+        .into_iter()
+        .for_each(assert_jsonlogic);
+
+        let raised = |rule: Value, data: Value| match apply(&rule, &data) {
+            Err(Error::RuleRaised { operator, message }) => (operator, message),
+            result => panic!("unexpected result: {:?}", result),
+        };
+        assert_eq!(
+            raised(
+                json!({"throw": {"cat": ["bad ", {"var": "x"}]}}),
+                json!({"x": 1})
+            ),
+            ("throw".into(), "bad 1".into())
+        );
+        assert_eq!(
+            raised(json!({"throw": {"var": "x"}}), json!({"x": {"a": 1}})),
+            ("throw".into(), r#"{"a":1}"#.into())
+        );
+        assert_eq!(
+            raised(
+                json!({"assert": [{">": [{"var": "age"}, 17]}, "Too young"]}),
+                json!({"age": 12})
+            ),
+            ("assert".into(), "Too young".into())
+        );
+        assert_eq!(
+            raised(json!({"assert": {"var": "ok"}}), json!({})),
+            ("assert".into(), r#"Assertion failed: {"var":"ok"}"#.into())
+        );
+        let err = apply(&json!({"throw": "bad"}), &json!(null)).unwrap_err();
+        assert_eq!(err.code(), "rule_raised");
+        assert_eq!(err.to_json()["details"]["message"], json!("bad"));
     }
 
     #[test]
-    fn test_map_op() {
-        map_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_comparison_parity() {
+        vec![
+            (json!({"==": [1, " 1\n"]}), json!({}), Ok(json!(true))),
+            (json!({"==": [16, "0x10"]}), json!({}), Ok(json!(true))),
+            (json!({"==": [0, "inf"]}), json!({}), Ok(json!(false))),
+            (json!({"<=": [null, 0]}), json!({}), Ok(json!(true))),
+            (json!({">=": [[2], "2"]}), json!({}), Ok(json!(true))),
+            (json!({"<=": ["a", 1]}), json!({}), Ok(json!(false))),
+            (json!({"in": [1, [1.0]]}), json!({}), Ok(json!(true))),
+            (
+                json!({"all_distinct": [[1, 1.0]]}),
+                json!({}),
+                Ok(json!(false)),
+            ),
+            (
+                json!({"missing_some": [1, ["a", "a"]]}),
+                json!({}),
+                Ok(json!(["a"])),
+            ),
+        ]
+        .into_iter()
+        .for_each(assert_jsonlogic);
     }
 
     #[test]
-    fn test_filter_op() {
-        filter_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_comparator() {
+        let engine =
+            Engine::new().comparator(Comparator::default().case_insensitive(true));
+        vec![
+            (json!({"==": ["A", "a"]}), Ok(json!(true))),
+            (json!({"===": ["A", "a"]}), Ok(json!(true))),
+            (json!({"<": ["a", "B"]}), Ok(json!(true))),
+            (json!({"in": ["B", ["a", "b"]]}), Ok(json!(true))),
+            (json!({"in": ["ELL", "hello"]}), Ok(json!(true))),
+            (
+                json!({"contains_all": [["a", "B"], ["A", "b"]]}),
+                Ok(json!(true)),
+            ),
+            (json!({"all_distinct": [["a", "A"]]}), Ok(json!(false))),
+            (
+                json!({"require": [["a", "A"]]}),
+                Ok(json!({"missing": ["a"], "present": [], "satisfied": false})),
+            ),
+        ]
+        .into_iter()
+        .for_each(|(rule, exp)| assert_jsonlogic_with(&engine, (rule, json!({}), exp)));
+
+        let engine =
+            Engine::new().comparator(Comparator::default().strict(true).deep(true));
+        vec![
+            (json!({"==": [1, "1"]}), Ok(json!(false))),
+            (json!({"==": [[1, [2]], [1, [2]]]}), Ok(json!(true))),
+            (json!({"!==": [{"a": 1}, {"a": 1}]}), Ok(json!(false))),
+            (json!({"<": [1, "2"]}), Ok(json!(false))),
+            (json!({"is_sorted": [[1, "2", 3]]}), Ok(json!(false))),
+            (json!({"==i": ["A", "a"]}), Ok(json!(true))),
+        ]
+        .into_iter()
+        .for_each(|(rule, exp)| assert_jsonlogic_with(&engine, (rule, json!({}), exp)));
     }
 
     #[test]
-    fn test_reduce_op() {
-        reduce_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_pure_only() {
+        let engine = Engine::new().pure_only(true);
+        vec![
+            (json!({"log": 1}), json!(null), Err(())),
+            (json!({"+": [1, {"log": 1}]}), json!(null), Err(())),
+            (
+                json!({"map": [[1], {"log": {"var": ""}}]}),
+                json!(null),
+                Err(()),
+            ),
+            (json!({"+": [1, 1]}), json!(null), Ok(json!(2))),
+        ]
+        .into_iter()
+        .for_each(|case| assert_jsonlogic_with(&engine, case));
+        // Impure operators are allowed by default
+        assert_jsonlogic((json!({"log": 1}), json!(null), Ok(json!(1))));
     }
 
     #[test]
-    fn test_all_op() {
-        all_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_apply_iter() {
+        let engine = Engine::new();
+        let data = json!({"xs": [1, 2, 0, 4]});
+
+        // Items are evaluated only as far as they're requested
+        let rule = json!({"map": [{"var": "xs"}, {"/": [4, {"var": ""}]}]});
+        let mut iter = engine.apply_iter(&rule, &data).unwrap();
+        assert_eq!(iter.next().unwrap().unwrap(), json!(4));
+        assert_eq!(iter.next().unwrap().unwrap(), json!(2));
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+
+        let rule = json!({"filter": [
+            [{"var": "xs.0"}, 2, {"var": "xs.1"}, {"/": [1, 0]}],
+            {"==": [{"var": ""}, 2]}
+        ]});
+        let first: Vec<Value> = engine
+            .apply_iter(&rule, &data)
+            .unwrap()
+            .take(2)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(first, vec![json!(2), json!(2)]);
+        assert!(engine
+            .apply_iter(&rule, &data)
+            .unwrap()
+            .collect::<Result<Vec<Value>, Error>>()
+            .is_err());
+
+        // Other rules are evaluated in full
+        let rule = json!({"merge": [{"var": "xs"}, 5]});
+        let items: Vec<Value> = engine
+            .apply_iter(&rule, &data)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            items,
+            vec![json!(1), json!(2), json!(0), json!(4), json!(5)]
+        );
+
+        // Streamed results are limited in size as they would be in full
+        let engine = Engine::new().max_result_size(4);
+        let rule = json!({"map": [{"var": "xs"}, {"cat": ["x", {"var": ""}]}]});
+        let mut iter = engine.apply_iter(&rule, &data).unwrap();
+        assert_eq!(iter.next().unwrap().unwrap(), json!("x1"));
+        assert!(matches!(
+            iter.next().unwrap(),
+            Err(Error::ResultTooLarge { .. })
+        ));
+        assert!(iter.next().is_none());
+
+        assert!(engine.apply_iter(&json!({"+": [1, 2]}), &data).is_err());
+        assert!(engine
+            .apply_iter(&json!({"map": [{"var": "xs"}]}), &data)
+            .is_err());
+        assert!(engine
+            .apply_iter(&json!({"filter": [1, {"var": ""}]}), &data)
+            .is_err());
     }
 
     #[test]
-    fn test_some_op() {
-        some_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_max_result_size() {
+        let engine = Engine::new().max_result_size(6);
+        vec![
+            (
+                json!({"cat": ["abc", "def"]}),
+                json!(null),
+                Ok(json!("abcdef")),
+            ),
+            (json!({"cat": ["abc", "defg"]}), json!(null), Err(())),
+            (
+                json!({"merge": [[1, 2], 3]}),
+                json!(null),
+                Ok(json!([1, 2, 3])),
+            ),
+            // Strings in arrays count toward their size
+            (json!({"merge": [["abc"], "de"]}), json!(null), Err(())),
+            (
+                json!({"merge": [[1, 2, 3], [4, 5, 6, 7]]}),
+                json!(null),
+                Err(()),
+            ),
+            (
+                json!({"map": [[1, 2, 3], {"*": [{"var": ""}, 2]}]}),
+                json!(null),
+                Ok(json!([2, 4, 6])),
+            ),
+            (
+                json!({"map": [{"var": "xs"}, {"cat": ["a", {"var": ""}]}]}),
+                json!({"xs": ["b", "c", "d"]}),
+                Err(()),
+            ),
+        ]
+        .into_iter()
+        .for_each(|case| assert_jsonlogic_with(&engine, case));
+        assert_eq!(
+            engine
+                .apply(&json!({"cat": ["abc", "defg"]}), &json!(null))
+                .unwrap_err()
+                .to_json()["details"],
+            json!({"operator": "cat", "size": 7, "max": 6})
+        );
+        // Results are unlimited by default
+        assert_jsonlogic((
+            json!({"cat": ["abc", "defg"]}),
+            json!(null),
+            Ok(json!("abcdefg")),
+        ));
+    }
+
+    fn custom_operator_cases() -> Vec<(Value, Value, Result<Value, ()>)> {
+        vec![
+            (json!({"count": []}), json!(null), Ok(json!(0))),
+            (json!({"count": [1, 2, 3]}), json!(null), Ok(json!(3))),
+            // Unary form
+            (json!({"count": "a"}), json!(null), Ok(json!(1))),
+            // Arguments are evaluated first
+            (
+                json!({"count": [{"var": "a"}, {"count": [1, 2]}]}),
+                json!({"a": 1}),
+                Ok(json!(2)),
+            ),
+            // Custom operators nest within built-in ones
+            (
+                json!({"map": [[1, 2], {"count": [{"var": ""}, 1]}]}),
+                json!(null),
+                Ok(json!([2, 2])),
+            ),
+            (json!({"fail": []}), json!(null), Err(())),
+            // Built-in operators take precedence
+            (json!({"+": [1, 2]}), json!(null), Ok(json!(3))),
+        ]
     }
 
     #[test]
-    fn test_none_op() {
-        none_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_custom_operators() {
+        let engine = Engine::new()
+            .add_operator("count", |args| Ok(json!(args.len())))
+            .add_operator("fail", |_| Err(Error::UnexpectedError("fail".into())))
+            .add_operator("+", |_| Ok(json!("overridden")));
+        custom_operator_cases()
+            .into_iter()
+            .for_each(|case| assert_jsonlogic_with(&engine, case));
+
+        // Without registration, the operator is just a raw object
+        assert_jsonlogic((
+            json!({"count": [1]}),
+            json!(null),
+            Ok(json!({"count": [1]})),
+        ));
     }
 
     #[test]
-    fn test_merge_op() {
-        merge_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_custom_operator_arity() {
+        let engine = Engine::new()
+            .add_operator_arity("pick", 1, |_| Ok(json!("one")))
+            .add_operator_arity("pick", 3, |_| Ok(json!("three")));
+        vec![
+            (json!({"pick": [1]}), json!(null), Ok(json!("one"))),
+            (json!({"pick": 1}), json!(null), Ok(json!("one"))),
+            (json!({"pick": [1, 2, 3]}), json!(null), Ok(json!("three"))),
+            (json!({"pick": [1, 2]}), json!(null), Err(())),
+            (json!({"pick": []}), json!(null), Err(())),
+        ]
+        .into_iter()
+        .for_each(|case| assert_jsonlogic_with(&engine, case));
+        let err = engine.apply(&json!({"pick": [1, 2]}), &json!(null));
+        assert_eq!(
+            err.unwrap_err().to_json()["details"]["expected"],
+            json!("exactly 1 or 3")
+        );
+
+        // A variadic registration handles the other counts, in either order
+        let engine = Engine::new()
+            .add_operator("pick", |_| Ok(json!("any")))
+            .add_operator_arity("pick", 2, |_| Ok(json!("two")));
+        // Replacing an implementation on a clone leaves the original as is
+        let replaced = engine.clone().add_operator("pick", |_| Ok(json!("other")));
+        vec![
+            (json!({"pick": [1, 2]}), json!(null), Ok(json!("two"))),
+            (json!({"pick": []}), json!(null), Ok(json!("other"))),
+        ]
+        .into_iter()
+        .for_each(|case| assert_jsonlogic_with(&replaced, case));
+        assert_jsonlogic_with(
+            &engine,
+            (json!({"pick": []}), json!(null), Ok(json!("any"))),
+        );
     }
 
     #[test]
-    fn test_cat_op() {
-        cat_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_operator_namespaces() {
+        let engine = Engine::new()
+            .add_operator("acme:discount_rate", |_| Ok(json!(0.1)))
+            .register_namespace("tax");
+        let invalid_operation = |rule: Value| match engine.validate(&rule) {
+            Err(Error::InvalidOperation { reason, .. }) => reason,
+            other => panic!("expected an invalid operation, got {:?}", other),
+        };
+        vec![
+            (
+                json!({"acme:discount_rate": []}),
+                json!(null),
+                Ok(json!(0.1)),
+            ),
+            (
+                json!({"*": [{"var": "total"}, {"acme:discount_rate": []}]}),
+                json!({"total": 50}),
+                Ok(json!(5)),
+            ),
+            // Keys that aren't namespaced are still literals
+            (
+                json!({"https://example.com": 1}),
+                json!(null),
+                Ok(json!({"https://example.com": 1})),
+            ),
+            (json!({":x": 1}), json!(null), Ok(json!({":x": 1}))),
+            (json!({"tax:rate": []}), json!(null), Err(())),
+        ]
+        .into_iter()
+        .for_each(|case| assert_jsonlogic_with(&engine, case));
+
+        assert_eq!(
+            invalid_operation(json!({"if": [true, {"acme:rebate": [1]}]})),
+            r#"Unknown operator in namespace "acme""#
+        );
+        assert_eq!(
+            invalid_operation(json!({"tax:rate": []})),
+            r#"Unknown operator in namespace "tax""#
+        );
+        assert_eq!(
+            invalid_operation(json!({"acne:discount_rate": []})),
+            r#"Unknown operator namespace "acne""#
+        );
+
+        // Without namespaces, unknown operators are literals as usual
+        assert_jsonlogic((
+            json!({"acme:discount_rate": []}),
+            json!(null),
+            Ok(json!({"acme:discount_rate": []})),
+        ));
     }
 
     #[test]
-    fn test_substr_op() {
-        substr_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_array_rules() {
+        let data = json!({"a": 1, "b": 2});
+        vec![
+            (
+                json!([{"var": "a"}, {"var": "b"}]),
+                data.clone(),
+                Ok(json!([1, 2])),
+            ),
+            (
+                json!([1, [{"var": "a"}, "x"], {"+": [{"var": "b"}, 1]}]),
+                data.clone(),
+                Ok(json!([1, [1, "x"], 3])),
+            ),
+            (json!(["a", "b"]), data.clone(), Ok(json!(["a", "b"]))),
+            (
+                json!({"merge": [[{"var": "a"}], {"var": "b"}]}),
+                data.clone(),
+                Ok(json!([1, 2])),
+            ),
+            (json!([1, {"==": [1]}]), data.clone(), Err(())),
+        ]
+        .into_iter()
+        .for_each(assert_jsonlogic);
     }
 
     #[test]
-    fn test_log_op() {
-        log_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_evaluate_objects() {
+        let engine = Engine::new().evaluate_objects(true);
+        let data = json!({"user": {"name": "Ada", "age": 36}});
+        vec![
+            (
+                json!({"name": {"var": "user.name"}, "tags": {"a": {"var": "user.age"}}}),
+                data.clone(),
+                Ok(json!({"name": "Ada", "tags": {"a": 36}})),
+            ),
+            // Operations and objects of literals are as they were
+            (json!({"var": "user.age"}), data.clone(), Ok(json!(36))),
+            (json!({"a": 1, "b": [2]}), data.clone(), Ok(json!({"a": 1, "b": [2]}))),
+            (
+                json!({"if": [true, {"n": {"+": [1, 1]}}, null]}),
+                data.clone(),
+                Ok(json!({"n": 2})),
+            ),
+            (json!({"a": {"==": [1]}, "b": 1}), data.clone(), Err(())),
+        ]
+        .into_iter()
+        .for_each(|case| assert_jsonlogic_with(&engine, case));
+
+        // Without the option, templates are literal data
+        let rule = json!({"name": {"var": "user.name"}, "n": 1});
+        assert_eq!(Engine::new().apply(&rule, &data).unwrap(), rule);
     }
 
     #[test]
-    fn test_lt_op() {
-        lt_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_bang_op() {
+        bang_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_lte_op() {
-        lt_cases()
-            .into_iter()
-            .map(|case| replace_operator("<", "<=", case))
-            .for_each(assert_jsonlogic);
-        abstract_eq_cases()
+    fn test_bang_bang_op() {
+        // just assert the opposite for all the bang cases
+        bang_cases()
             .into_iter()
-            // Only get cases that are equal, since we don't know whether
-            // non-equality cases were lt or gt or what.
-            .filter(|case| only_boolean(true, case))
-            .map(|case| replace_operator("==", "<=", case))
-            .for_each(assert_jsonlogic);
+            .map(|case| replace_operator("!", "!!", case))
+            .map(flip_boolean_exp)
+            .for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_gt_op() {
-        gt_cases().into_iter().for_each(assert_jsonlogic);
+    fn test_in_op() {
+        in_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_gte_op() {
-        gt_cases()
-            .into_iter()
-            .map(|case| replace_operator(">", ">=", case))
-            .for_each(assert_jsonlogic);
-        abstract_eq_cases()
+    fn test_not_in_op() {
+        in_cases()
             .into_iter()
-            // Only get cases that are equal, since we don't know whether
-            // non-equality cases were lt or gt or what.
-            .filter(|case| only_boolean(true, case))
-            .map(|case| replace_operator("==", ">=", case))
+            .map(|case| replace_operator("in", "not_in", case))
+            .map(flip_boolean_exp)
             .for_each(assert_jsonlogic);
+        assert_jsonlogic((
+            json!({"not_in": [{"var": "country"}, ["US", "CA"]]}),
+            json!({"country": "DE"}),
+            Ok(json!(true)),
+        ));
     }
 
     #[test]
-    fn test_plus_op() {
-        plus_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_to_number_op() {
+        to_number_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_minus_op() {
-        minus_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_to_string_op() {
+        to_string_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_mul_op() {
-        multiplication_cases()
-            .into_iter()
-            .for_each(assert_jsonlogic)
+    fn test_starts_with_op() {
+        starts_with_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_div_op() {
-        division_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_ends_with_op() {
+        ends_with_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_mod_op() {
-        modulo_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_contains_op() {
+        contains_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_max_op() {
-        max_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_eq_i_op() {
+        eq_i_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_min_op() {
-        min_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_ne_i_op() {
+        eq_i_cases()
+            .into_iter()
+            .map(|case| replace_operator("==i", "!=i", case))
+            .map(flip_boolean_exp)
+            .for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_bang_op() {
-        bang_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_in_i_op() {
+        in_i_cases().into_iter().for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_bang_bang_op() {
-        // just assert the opposite for all the bang cases
-        bang_cases()
+    fn test_not_in_i_op() {
+        in_i_cases()
             .into_iter()
-            .map(|case| replace_operator("!", "!!", case))
+            .map(|case| replace_operator("in_i", "not_in_i", case))
             .map(flip_boolean_exp)
             .for_each(assert_jsonlogic)
     }
 
     #[test]
-    fn test_in_op() {
-        in_cases().into_iter().for_each(assert_jsonlogic)
+    fn test_bucket_op() {
+        bucket_cases().into_iter().for_each(assert_jsonlogic)
+    }
+
+    #[test]
+    fn test_checksum_ops() {
+        checksum_cases().into_iter().for_each(assert_jsonlogic)
     }
 }
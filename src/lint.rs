@@ -0,0 +1,480 @@
+//! Rule linting
+//!
+//! Linting looks for constructs that are valid but probably not what the
+//! rule's author intended. Unlike validation errors, lint warnings never
+//! prevent a rule from being applied.
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::engine::Engine;
+use crate::op;
+use crate::policy::Walk;
+
+/// The number of `if` operations nested in each other's final ("else")
+/// branch at which the chain is flagged as hard to read
+const NESTED_IF_CHAIN_LEN: usize = 3;
+
+/// Comparison operators, with the result of comparing a value to itself
+const COMPARISONS: &[(&str, bool)] = &[
+    ("==", true),
+    ("===", true),
+    ("!=", false),
+    ("!==", false),
+    ("<", false),
+    ("<=", true),
+    (">", false),
+    (">=", true),
+    ("==i", true),
+    ("!=i", false),
+];
+
+/// Operators that compare objects by identity
+const EQUALITY: &[&str] = &["==", "===", "!=", "!=="];
+
+/// The kind of problem a lint warning describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum LintKind {
+    /// A comparison whose result does not depend on the data
+    ConstantComparison,
+    /// An equality comparison against an object literal, which compares by
+    /// identity rather than by contents
+    ObjectEquality,
+    /// A branch of `if`, `and`, or `or` that can never be evaluated, or
+    /// that is always taken
+    UnusedBranch,
+    /// A chain of `if` operations nested in each other's final branch,
+    /// which could be written as a single `if` with multiple conditions
+    NestedIf,
+    /// Use of a deprecated alias for an operator
+    DeprecatedAlias,
+    /// Use of an operator deprecated with `Engine::deprecate_operator()`
+    DeprecatedOperator,
+}
+impl LintKind {
+    /// A stable, machine-readable identifier for the kind of warning
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ConstantComparison => "constant_comparison",
+            Self::ObjectEquality => "object_equality",
+            Self::UnusedBranch => "unused_branch",
+            Self::NestedIf => "nested_if",
+            Self::DeprecatedAlias => "deprecated_alias",
+            Self::DeprecatedOperator => "deprecated_operator",
+        }
+    }
+}
+
+/// A suspicious construct found in a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LintWarning {
+    pub kind: LintKind,
+    /// A JSON Pointer to the offending value in the rule
+    pub path: String,
+    pub message: String,
+}
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}: {}", self.kind.code(), self.path, self.message)
+    }
+}
+
+/// Find suspicious constructs in a rule
+///
+/// Custom operators registered on the engine are recognized as operators.
+pub(crate) fn lint(engine: &Engine, rule: &Value) -> Vec<LintWarning> {
+    let mut linter = Linter {
+        engine,
+        warnings: Vec::new(),
+    };
+    linter.lint_value(rule, &mut Walk::new(), false);
+    linter.warnings
+}
+
+struct Linter<'e> {
+    engine: &'e Engine,
+    warnings: Vec<LintWarning>,
+}
+impl Linter<'_> {
+    fn warn(&mut self, kind: LintKind, path: &str, message: String) {
+        self.warnings.push(LintWarning {
+            kind,
+            path: if path.is_empty() {
+                "/".into()
+            } else {
+                path.into()
+            },
+            message,
+        })
+    }
+
+    /// Warn about an argument of the operation at the walk's location
+    fn warn_at_argument(
+        &mut self,
+        walk: &mut Walk,
+        key: &str,
+        idx: usize,
+        kind: LintKind,
+        message: String,
+    ) {
+        walk.descend(key, |walk| {
+            walk.descend(&idx.to_string(), |walk| {
+                self.warn(kind, &walk.path, message)
+            })
+        })
+    }
+
+    /// Whether a value is free of operations, so that it evaluates to itself
+    fn is_literal(&self, value: &Value) -> bool {
+        match value {
+            Value::Array(items) => items.iter().all(|item| self.is_literal(item)),
+            Value::Object(_) => self.operation(value).is_none(),
+            _ => true,
+        }
+    }
+
+    /// The operator and arguments of an operation
+    fn operation<'v>(&self, value: &'v Value) -> Option<(&'v str, Vec<&'v Value>)> {
+        op::split_operation(self.engine, value)
+    }
+
+    /// Whether a value is an `if` operation
+    fn is_if(&self, value: &Value) -> bool {
+        match self.operation(value) {
+            Some((key, _)) => key == "if" || op::alias_target(key) == Some("if"),
+            None => false,
+        }
+    }
+
+    /// Lint a value and everything in it. `in_if_chain` is set for the
+    /// final branch of an `if`, so that chains are only flagged once.
+    fn lint_value(&mut self, value: &Value, walk: &mut Walk, in_if_chain: bool) {
+        if let Some((key, args)) = self.operation(value) {
+            self.lint_operation(value, key, &args, walk, in_if_chain);
+        }
+        match value {
+            Value::Array(items) => items.iter().enumerate().for_each(|(idx, item)| {
+                walk.descend(&idx.to_string(), |walk| {
+                    self.lint_value(item, walk, false)
+                })
+            }),
+            Value::Object(obj) => {
+                let is_if = self.is_if(value);
+                obj.iter().for_each(|(key, val)| {
+                    walk.descend(key, |walk| match val {
+                        // Handle the argument array here, so we know which
+                        // argument is the final branch of an `if`
+                        Value::Array(args) if is_if => {
+                            args.iter().enumerate().for_each(|(idx, arg)| {
+                                let is_else =
+                                    idx > 0 && idx % 2 == 0 && idx == args.len() - 1;
+                                walk.descend(&idx.to_string(), |walk| {
+                                    self.lint_value(arg, walk, is_else)
+                                })
+                            })
+                        }
+                        _ => self.lint_value(val, walk, false),
+                    })
+                })
+            }
+            _ => {}
+        }
+    }
+
+    fn lint_operation(
+        &mut self,
+        value: &Value,
+        key: &str,
+        args: &[&Value],
+        walk: &mut Walk,
+        in_if_chain: bool,
+    ) {
+        if let Some(target) = op::deprecated_alias_target(key) {
+            self.warn(
+                LintKind::DeprecatedAlias,
+                &walk.path,
+                format!("`{}` is deprecated, use `{}` instead", key, target),
+            );
+        }
+        if let Some(note) = self.engine.deprecated_operators.get(key) {
+            self.warn(
+                LintKind::DeprecatedOperator,
+                &walk.path,
+                format!("`{}` is deprecated: {}", key, note),
+            );
+        }
+
+        if let Some((_, self_result)) = COMPARISONS.iter().find(|(op, _)| *op == key) {
+            self.lint_comparison(value, key, args, *self_result, walk);
+        }
+
+        if self.is_if(value) {
+            self.lint_if(key, args, walk, in_if_chain);
+        }
+
+        match key {
+            "and" => self.lint_short_circuit(key, args, false, walk),
+            "or" => self.lint_short_circuit(key, args, true, walk),
+            _ => {}
+        }
+    }
+
+    fn lint_comparison(
+        &mut self,
+        value: &Value,
+        key: &str,
+        args: &[&Value],
+        self_result: bool,
+        walk: &mut Walk,
+    ) {
+        if EQUALITY.contains(&key) {
+            let object_literal = args
+                .iter()
+                .any(|arg| arg.is_object() && self.operation(arg).is_none());
+            if object_literal {
+                self.warn(
+                    LintKind::ObjectEquality,
+                    &walk.path,
+                    format!(
+                        "`{}` compares objects by identity, so an object literal \
+                         never equals another object",
+                        key
+                    ),
+                );
+                return;
+            }
+        }
+        if args.len() < 2 {
+            return;
+        }
+        if args.iter().all(|arg| self.is_literal(arg)) {
+            // Errors are for validation to report, not linting
+            if let Ok(Value::Bool(result)) = self.engine.apply(value, &Value::Null) {
+                self.warn(
+                    LintKind::ConstantComparison,
+                    &walk.path,
+                    format!("Comparison of literals is always {}", result),
+                );
+            }
+        } else if args.windows(2).all(|pair| pair[0] == pair[1]) {
+            self.warn(
+                LintKind::ConstantComparison,
+                &walk.path,
+                format!(
+                    "Comparison of an expression with itself is always {}",
+                    self_result
+                ),
+            );
+        }
+    }
+
+    fn lint_if(
+        &mut self,
+        key: &str,
+        args: &[&Value],
+        walk: &mut Walk,
+        in_if_chain: bool,
+    ) {
+        // Conditions are at even indexes, except for a trailing else branch
+        for idx in (0..args.len().saturating_sub(1)).step_by(2) {
+            if !self.is_literal(args[idx]) {
+                continue;
+            }
+            let always_true = op::truthy(args[idx]);
+            let message = match (always_true, idx + 2 < args.len()) {
+                (true, true) => {
+                    "Condition is always true, so later branches are never used"
+                }
+                (true, false) => {
+                    "Condition is always true, so the else branch is never used"
+                }
+                (false, _) => "Condition is always false, so its branch is never used",
+            };
+            self.warn_at_argument(
+                walk,
+                key,
+                idx,
+                LintKind::UnusedBranch,
+                message.into(),
+            );
+            if always_true {
+                break;
+            }
+        }
+
+        if !in_if_chain {
+            let chain_len = self.if_chain_len(args);
+            if chain_len >= NESTED_IF_CHAIN_LEN {
+                self.warn(
+                    LintKind::NestedIf,
+                    &walk.path,
+                    format!(
+                        "{} nested `if` operations could be a single `if` with \
+                         multiple conditions: [cond1, value1, cond2, value2, ..., else]",
+                        chain_len
+                    ),
+                );
+            }
+        }
+    }
+
+    /// The number of `if` operations chained through their else branches,
+    /// starting with one with the given arguments
+    fn if_chain_len(&self, args: &[&Value]) -> usize {
+        let else_branch = match args.len() {
+            len if len >= 3 && len % 2 == 1 => args[len - 1],
+            _ => return 1,
+        };
+        match self.operation(else_branch) {
+            Some((_, else_args)) if self.is_if(else_branch) => {
+                1 + self.if_chain_len(&else_args)
+            }
+            _ => 1,
+        }
+    }
+
+    /// Check for literal arguments to `and` or `or` that end evaluation,
+    /// i.e. falsy ones for `and` and truthy ones for `or`
+    fn lint_short_circuit(
+        &mut self,
+        key: &str,
+        args: &[&Value],
+        stops_when: bool,
+        walk: &mut Walk,
+    ) {
+        let last = args.len().saturating_sub(1);
+        let stop = args[..last]
+            .iter()
+            .position(|arg| self.is_literal(arg) && op::truthy(arg) == stops_when);
+        if let Some(idx) = stop {
+            self.warn_at_argument(
+                walk,
+                key,
+                idx,
+                LintKind::UnusedBranch,
+                format!(
+                    "Argument is always {}, so later arguments to `{}` are never used",
+                    if stops_when { "truthy" } else { "falsy" },
+                    key
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_lint {
+    use super::*;
+    use serde_json::json;
+
+    /// The kinds and paths of the warnings for a rule
+    fn warnings(rule: Value) -> Vec<(LintKind, String)> {
+        lint(&Engine::new(), &rule)
+            .into_iter()
+            .map(|warning| (warning.kind, warning.path))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_rule() {
+        assert_eq!(
+            warnings(json!({"if": [{"<": [{"var": "a"}, 2]}, "a", "b"]})),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_constant_comparison() {
+        assert_eq!(
+            warnings(json!({"and": [{"var": "a"}, {"==": [1, "1"]}]})),
+            vec![(LintKind::ConstantComparison, "/and/1".into())]
+        );
+        assert_eq!(
+            warnings(json!({"<": [{"var": "a"}, {"var": "a"}]})),
+            vec![(LintKind::ConstantComparison, "/".into())]
+        );
+        let warning = &lint(&Engine::new(), &json!({">": [1, 2]}))[0];
+        assert_eq!(warning.message, "Comparison of literals is always false");
+    }
+
+    #[test]
+    fn test_object_equality() {
+        assert_eq!(
+            warnings(json!({"===": [{"var": "a"}, {"b": 1}]})),
+            vec![(LintKind::ObjectEquality, "/".into())]
+        );
+        // Custom operators are operations rather than object literals
+        let engine = Engine::new().add_operator("double", |_| Ok(json!(2)));
+        assert_eq!(
+            lint(&engine, &json!({"==": [{"double": {"var": "a"}}, 2]})),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_unused_branch() {
+        assert_eq!(
+            warnings(json!({"if": [false, 1, {"var": "a"}, 2, true, 3, 4]})),
+            vec![
+                (LintKind::UnusedBranch, "/if/0".into()),
+                (LintKind::UnusedBranch, "/if/4".into()),
+            ]
+        );
+        assert_eq!(
+            warnings(json!({"and": [{"var": "a"}, 0, {"var": "b"}]})),
+            vec![(LintKind::UnusedBranch, "/and/1".into())]
+        );
+        assert_eq!(
+            warnings(json!({"or": [{"var": "a"}, "yes", {"var": "b"}]})),
+            vec![(LintKind::UnusedBranch, "/or/1".into())]
+        );
+        // Literals at the end are the result, not a branch
+        assert_eq!(warnings(json!({"or": [{"var": "a"}, "default"]})), vec![]);
+    }
+
+    #[test]
+    fn test_nested_if() {
+        let cond = json!({"var": "a"});
+        let two = json!({"if": [cond, 1, {"if": [cond, 2, 3]}]});
+        assert_eq!(warnings(two.clone()), vec![]);
+        let three = json!({"if": [cond, 1, {"if": [cond, 2, two]}]});
+        assert_eq!(warnings(three), vec![(LintKind::NestedIf, "/".into())]);
+        // An `if` in a non-final branch doesn't continue the chain
+        let branch = json!({"if": [cond, {"if": [cond, 1, {"if": [cond, 2, 3]}]}, 4]});
+        assert_eq!(warnings(branch), vec![]);
+    }
+
+    #[test]
+    fn test_deprecated_alias() {
+        // `?:` is an alias for `if`, but isn't deprecated
+        assert_eq!(warnings(json!({"?:": [{"var": "a"}, 1, 2]})), vec![]);
+    }
+
+    #[test]
+    fn test_deprecated_operator() {
+        let engine = Engine::new()
+            .add_operator("old", |_| Ok(json!(1)))
+            .deprecate_operator("old", "use `new` instead")
+            .deprecate_operator("substr", "slice strings upstream");
+        let kinds = |rule| {
+            lint(&engine, &rule)
+                .into_iter()
+                .map(|warning| (warning.kind, warning.path))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds(json!({"and": [{"old": []}, {"substr": ["abc", 1]}]})),
+            vec![
+                (LintKind::DeprecatedOperator, "/and/0".into()),
+                (LintKind::DeprecatedOperator, "/and/1".into()),
+            ]
+        );
+        // Keys that aren't operators aren't flagged, even if deprecated
+        let engine = Engine::new().deprecate_operator("old", "");
+        assert_eq!(lint(&engine, &json!({"old": []})), vec![]);
+    }
+}
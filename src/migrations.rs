@@ -0,0 +1,352 @@
+//! Rule migrations
+//!
+//! Stored rules outlive the operator vocabulary they were written against.
+//! A [`Migration`] describes how to rewrite rules when that vocabulary
+//! changes, e.g. when an operator is renamed or its arguments reordered,
+//! and [`Migrations`] applies a numbered sequence of them to bring a rule
+//! written for any earlier version up to date.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::policy::Walk;
+
+type RewriteFn = dyn Fn(&[Value]) -> Option<Value> + Send + Sync;
+
+enum Rewrite {
+    Rename(String),
+    Reorder(Vec<usize>),
+    Custom(Box<RewriteFn>),
+}
+
+/// The rewrites that bring rules from one version to the next
+///
+/// Rewrites apply to every operation with a given operator, wherever it
+/// appears in a rule, in the order they were added. The operator doesn't
+/// need to be known to the engine, so rules using operators that no longer
+/// exist can be migrated.
+#[derive(Default)]
+pub struct Migration {
+    rewrites: Vec<(String, Rewrite)>,
+}
+impl Migration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rename an operator, keeping its arguments
+    pub fn rename_operator(mut self, from: &str, to: &str) -> Self {
+        self.rewrites
+            .push((from.into(), Rewrite::Rename(to.into())));
+        self
+    }
+
+    /// Reorder an operator's arguments
+    ///
+    /// `order` lists the current index of each argument in its new
+    /// position, so `&[1, 0]` swaps the first two arguments. Arguments past
+    /// the end of `order` keep their positions, and operations with too
+    /// few arguments for the reordering are left alone.
+    pub fn reorder_arguments(mut self, operator: &str, order: &[usize]) -> Self {
+        self.rewrites
+            .push((operator.into(), Rewrite::Reorder(order.to_vec())));
+        self
+    }
+
+    /// Rewrite operations with an operator using a function of their
+    /// arguments
+    ///
+    /// The function returns the replacement for the whole operation, or
+    /// `None` to leave it alone. The replacement's arguments are migrated
+    /// in turn, but the replacement itself is not.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::migrations::{Migration, Migrations};
+    /// use serde_json::json;
+    ///
+    /// // `between` is replaced by a chained `<=`
+    /// let migration = Migration::new().rewrite("between", |args| {
+    ///     Some(json!({"<=": [args.get(1)?, args.first()?, args.get(2)?]}))
+    /// });
+    /// let migrated = Migrations::new()
+    ///     .register(1, migration)
+    ///     .migrate(&json!({"between": [{"var": "x"}, 0, 10]}), 0);
+    /// assert_eq!(migrated.rule, json!({"<=": [0, {"var": "x"}, 10]}));
+    /// ```
+    pub fn rewrite<F>(mut self, operator: &str, func: F) -> Self
+    where
+        F: Fn(&[Value]) -> Option<Value> + Send + Sync + 'static,
+    {
+        self.rewrites
+            .push((operator.into(), Rewrite::Custom(Box::new(func))));
+        self
+    }
+}
+
+/// A change made to a rule by a migration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The version whose migration made the change
+    pub version: u32,
+    /// A JSON Pointer to the changed operation, in the rule as migrated
+    /// up to that change
+    pub path: String,
+    pub description: String,
+}
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "v{} at {}: {}",
+            self.version, self.path, self.description
+        )
+    }
+}
+
+/// The result of migrating a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migrated {
+    pub rule: Value,
+    /// The version the rule is now at
+    pub version: u32,
+    pub changes: Vec<Change>,
+}
+
+/// A numbered sequence of migrations
+///
+/// Each migration is registered with the version it produces, and rules
+/// are migrated from the version they were written for by applying each
+/// later migration in order.
+///
+/// ```rust
+/// use jsonlogic_rs::migrations::{Migration, Migrations};
+/// use serde_json::json;
+///
+/// let migrations = Migrations::new()
+///     .register(2, Migration::new().rename_operator("starts", "starts_with"))
+///     .register(3, Migration::new().reorder_arguments("starts_with", &[1, 0]));
+///
+/// let migrated = migrations.migrate(&json!({"starts": ["ab", {"var": "s"}]}), 1);
+/// assert_eq!(migrated.rule, json!({"starts_with": [{"var": "s"}, "ab"]}));
+/// assert_eq!(migrated.version, 3);
+/// assert_eq!(migrated.changes.len(), 2);
+/// ```
+#[derive(Default)]
+pub struct Migrations {
+    migrations: Vec<(u32, Migration)>,
+}
+impl Migrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the migration that produces a version
+    ///
+    /// Registering a version again replaces its migration.
+    pub fn register(mut self, version: u32, migration: Migration) -> Self {
+        self.migrations.retain(|(v, _)| *v != version);
+        let idx = self.migrations.partition_point(|(v, _)| *v < version);
+        self.migrations.insert(idx, (version, migration));
+        self
+    }
+
+    /// The highest registered version, or `0` if there are none
+    pub fn latest_version(&self) -> u32 {
+        self.migrations.last().map_or(0, |(v, _)| *v)
+    }
+
+    /// Migrate a rule written for `from_version` to the latest version
+    ///
+    /// Rules already at or past the latest version are returned unchanged,
+    /// at their own version.
+    pub fn migrate(&self, rule: &Value, from_version: u32) -> Migrated {
+        let mut migrated = Migrated {
+            rule: rule.clone(),
+            version: from_version,
+            changes: Vec::new(),
+        };
+        for (version, migration) in &self.migrations {
+            if *version <= from_version {
+                continue;
+            }
+            let mut migrator = Migrator {
+                version: *version,
+                migration,
+                changes: &mut migrated.changes,
+            };
+            migrated.rule = migrator.migrate(migrated.rule, &mut Walk::new());
+            migrated.version = *version;
+        }
+        migrated
+    }
+}
+
+struct Migrator<'a> {
+    version: u32,
+    migration: &'a Migration,
+    changes: &'a mut Vec<Change>,
+}
+impl Migrator<'_> {
+    fn record(&mut self, walk: &Walk, description: String) {
+        self.changes.push(Change {
+            version: self.version,
+            path: if walk.path.is_empty() {
+                "/".into()
+            } else {
+                walk.path.clone()
+            },
+            description,
+        })
+    }
+
+    /// Apply a rewrite to an operation, returning the new operation if the
+    /// rewrite applies
+    fn rewrite(
+        &mut self,
+        walk: &Walk,
+        key: &str,
+        args: &Value,
+        rewrite: &Rewrite,
+    ) -> Option<Value> {
+        let arg_list = match args {
+            Value::Array(items) => items.clone(),
+            _ => vec![args.clone()],
+        };
+        match rewrite {
+            Rewrite::Rename(to) => {
+                self.record(walk, format!("Renamed {:?} to {:?}", key, to));
+                let mut obj = Map::with_capacity(1);
+                obj.insert(to.clone(), args.clone());
+                Some(Value::Object(obj))
+            }
+            Rewrite::Reorder(order) => {
+                if order.iter().any(|idx| *idx >= arg_list.len()) {
+                    return None;
+                }
+                let mut reordered: Vec<Value> =
+                    order.iter().map(|idx| arg_list[*idx].clone()).collect();
+                reordered.extend(arg_list.iter().skip(order.len()).cloned());
+                self.record(walk, format!("Reordered the arguments of {:?}", key));
+                let mut obj = Map::with_capacity(1);
+                obj.insert(key.into(), Value::Array(reordered));
+                Some(Value::Object(obj))
+            }
+            Rewrite::Custom(func) => {
+                let replacement = func(&arg_list)?;
+                self.record(walk, format!("Rewrote {:?}", key));
+                Some(replacement)
+            }
+        }
+    }
+
+    fn migrate(&mut self, mut value: Value, walk: &mut Walk) -> Value {
+        let migration = self.migration;
+        for (operator, rewrite) in &migration.rewrites {
+            let replacement = match &value {
+                Value::Object(obj) if obj.len() == 1 => match obj.iter().next() {
+                    Some((key, args)) if key == operator => {
+                        self.rewrite(walk, key, args, rewrite)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(replacement) = replacement {
+                value = replacement;
+            }
+        }
+        match value {
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, item)| {
+                        walk.descend(&idx.to_string(), |walk| self.migrate(item, walk))
+                    })
+                    .collect(),
+            ),
+            Value::Object(obj) => Value::Object(
+                obj.into_iter()
+                    .map(|(key, val)| {
+                        let val = walk.descend(&key, |walk| self.migrate(val, walk));
+                        (key, val)
+                    })
+                    .collect(),
+            ),
+            _ => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_migrations {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_rewrites() {
+        let migrations = Migrations::new().register(
+            1,
+            Migration::new()
+                .rename_operator("old", "new")
+                .reorder_arguments("new", &[2, 0, 1])
+                .rewrite("double", |args| Some(json!({"*": [args.first()?, 2]}))),
+        );
+        let migrated = migrations.migrate(
+            &json!({"and": [
+                {"old": [1, 2, 3, 4]},
+                {"old": {"double": {"var": "x"}}},
+                {"double": []},
+            ]}),
+            0,
+        );
+        assert_eq!(
+            migrated.rule,
+            json!({"and": [
+                {"new": [3, 1, 2, 4]},
+                {"new": {"*": [{"var": "x"}, 2]}},
+                {"double": []},
+            ]})
+        );
+        let changes: Vec<String> =
+            migrated.changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            changes,
+            vec![
+                r#"v1 at /and/0: Renamed "old" to "new""#,
+                r#"v1 at /and/0: Reordered the arguments of "new""#,
+                r#"v1 at /and/1: Renamed "old" to "new""#,
+                r#"v1 at /and/1/new: Rewrote "double""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_versions() {
+        let migrations = Migrations::new()
+            .register(3, Migration::new().rename_operator("b", "c"))
+            .register(2, Migration::new().rename_operator("a", "b"))
+            .register(4, Migration::new());
+        assert_eq!(migrations.latest_version(), 4);
+
+        let migrated = migrations.migrate(&json!({"a": 1}), 1);
+        assert_eq!(migrated.rule, json!({"c": 1}));
+        assert_eq!(migrated.version, 4);
+        assert_eq!(
+            migrated
+                .changes
+                .iter()
+                .map(|c| c.version)
+                .collect::<Vec<u32>>(),
+            vec![2, 3]
+        );
+
+        let migrated = migrations.migrate(&json!({"a": 1}), 2);
+        assert_eq!(migrated.rule, json!({"a": 1}));
+        assert!(migrated.changes.is_empty());
+
+        let migrated = migrations.migrate(&json!({"a": 1}), 7);
+        assert_eq!(migrated.version, 7);
+    }
+}
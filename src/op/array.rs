@@ -4,92 +4,216 @@
 //! of characters.
 
 use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 
+use crate::compare::Comparator;
+use crate::context::Context;
+use crate::engine::result_size;
 use crate::error::Error;
+use crate::js_op;
 use crate::op::logic;
-use crate::value::{Evaluated, Parsed};
+use crate::value::Parsed;
 
-/// Map an operation onto values
-pub fn map(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
-    let (items, expression) = (args[0], args[1]);
-
-    let _parsed = Parsed::from_value(items)?;
-    let evaluated_items = _parsed.evaluate(data)?;
-
-    let values: Vec<&Value> = match evaluated_items {
-        Evaluated::New(Value::Array(ref vals)) => vals.iter().collect(),
-        Evaluated::Raw(Value::Array(vals)) => vals.iter().collect(),
-        // null is treated as an empty array in the reference tests,
-        // for whatever reason
-        Evaluated::New(Value::Null) => vec![],
-        Evaluated::Raw(Value::Null) => vec![],
-        _ => {
-            return Err(Error::InvalidArgument {
-                value: args[0].clone(),
-                operation: "map".into(),
+/// The items of the array that an iterating operator (`map`, `filter`,
+/// `all`, etc.) is given as its first argument
+///
+/// The argument may be a literal array, whose items are evaluated one at a
+/// time as they're reached so that `all` and `some` can stop early, or an
+/// operation evaluating to an array, a string (iterated over as an array
+/// of characters), or null (treated as an empty array, as in the reference
+/// tests). A literal string is also iterated over by character.
+enum Items<'a> {
+    Unevaluated(&'a [Value]),
+    Evaluated(Vec<Value>),
+}
+impl<'a> Items<'a> {
+    fn from_arg(
+        ctx: &Context,
+        data: &Value,
+        arg: &'a Value,
+        operation: &str,
+    ) -> Result<Self, Error> {
+        if let Value::Array(items) = arg {
+            return Ok(Items::Unevaluated(items));
+        }
+        let evaluated =
+            Value::from(Parsed::from_value(arg, ctx.engine)?.evaluate(data, ctx)?);
+        match evaluated {
+            Value::Array(items) => Ok(Items::Evaluated(items)),
+            Value::String(string) => Ok(Items::Evaluated(
+                string
+                    .chars()
+                    .map(|c| Value::String(c.to_string()))
+                    .collect(),
+            )),
+            Value::Null => Ok(Items::Evaluated(Vec::new())),
+            _ => Err(Error::InvalidArgument {
+                value: arg.clone(),
+                operation: operation.into(),
                 reason: format!(
-                    "First argument to map must evaluate to an array. Got {:?}",
-                    evaluated_items
+                    "First argument to {} must evaluate to an array, a string, or null, got {}",
+                    operation, evaluated
                 ),
-            })
+            }),
         }
-    };
+    }
 
-    let parsed_expression = Parsed::from_value(expression)?;
+    fn is_empty(&self) -> bool {
+        match self {
+            Items::Unevaluated(items) => items.is_empty(),
+            Items::Evaluated(items) => items.is_empty(),
+        }
+    }
 
-    values
-        .iter()
-        .map(|v| parsed_expression.evaluate(v).map(Value::from))
-        .collect::<Result<Vec<Value>, Error>>()
-        .map(Value::Array)
-}
+    /// Evaluate the item at an index, if there is one
+    fn item<'s>(
+        &'s self,
+        idx: usize,
+        ctx: &Context,
+        data: &Value,
+    ) -> Option<Result<Cow<'s, Value>, Error>> {
+        match self {
+            Items::Unevaluated(items) => {
+                let item = items.get(idx)?;
+                Some(
+                    Parsed::from_value(item, ctx.engine)
+                        .and_then(|parsed| Ok(parsed.evaluate(data, ctx)?.into()))
+                        .map(Cow::Owned),
+                )
+            }
+            Items::Evaluated(items) => {
+                items.get(idx).map(|item| Ok(Cow::Borrowed(item)))
+            }
+        }
+    }
 
-/// Filter values by some predicate
-pub fn filter(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
-    let (items, expression) = (args[0], args[1]);
+    /// Evaluate the items in order
+    fn evaluate<'s>(
+        &'s self,
+        ctx: &'s Context,
+        data: &'s Value,
+    ) -> impl Iterator<Item = Result<Cow<'s, Value>, Error>> + 's {
+        (0..).map_while(move |idx| self.item(idx, ctx, data))
+    }
+}
 
-    let _parsed = Parsed::from_value(items)?;
-    let evaluated_items = _parsed.evaluate(data)?;
+/// A `map` or `filter` whose results are produced one at a time, as
+/// they're requested
+pub(crate) struct Stream<'a> {
+    filter: bool,
+    items: Items<'a>,
+    expression: Parsed<'a>,
+    next: usize,
+    /// The size of the results so far, as limited by `max_result_size()`
+    size: usize,
+}
+impl<'a> Stream<'a> {
+    /// Start streaming the results of an operation, if it's a `map` or a
+    /// `filter`
+    pub(crate) fn new(
+        ctx: &Context,
+        data: &Value,
+        operator: &str,
+        args: &'a [Value],
+    ) -> Result<Option<Self>, Error> {
+        let filter = match operator {
+            "map" => false,
+            "filter" => true,
+            _ => return Ok(None),
+        };
+        Ok(Some(Stream {
+            filter,
+            items: Items::from_arg(ctx, data, &args[0], operator)?,
+            expression: Parsed::from_value(&args[1], ctx.engine)?,
+            next: 0,
+            size: 0,
+        }))
+    }
 
-    let values: Vec<Value> = match evaluated_items {
-        Evaluated::New(Value::Array(vals)) => vals,
-        Evaluated::Raw(Value::Array(vals)) => {
-            vals.into_iter().map(|v| v.clone()).collect()
+    /// Evaluate items until the next result is found
+    ///
+    /// The stream ends after the first error.
+    pub(crate) fn next(
+        &mut self,
+        ctx: &Context,
+        data: &Value,
+    ) -> Option<Result<Value, Error>> {
+        loop {
+            let item = match self.items.item(self.next, ctx, data)? {
+                Ok(item) => item,
+                Err(err) => {
+                    self.next = usize::MAX;
+                    return Some(Err(err));
+                }
+            };
+            self.next += 1;
+            let result = match ctx.in_item(|| self.expression.evaluate(&item, ctx)) {
+                Ok(result) => result,
+                Err(err) => {
+                    self.next = usize::MAX;
+                    return Some(Err(err));
+                }
+            };
+            if !self.filter {
+                let result: Value = result.into();
+                if ctx.engine.max_result_size.is_some() {
+                    self.size += 1 + result_size(&result);
+                    if let Err(err) = ctx.engine.check_result_size("map", self.size) {
+                        self.next = usize::MAX;
+                        return Some(Err(err));
+                    }
+                }
+                return Some(Ok(result));
+            }
+            if logic::truthy_from_evaluated(&result) {
+                return Some(Ok(item.into_owned()));
+            }
         }
-        // null is treated as an empty array in the reference tests,
-        // for whatever reason
-        Evaluated::New(Value::Null) => vec![],
-        Evaluated::Raw(Value::Null) => vec![],
-        _ => {
-            return Err(Error::InvalidArgument {
-                value: args[0].clone(),
-                operation: "map".into(),
-                reason: format!(
-                    "First argument to filter must evaluate to an array. Got {:?}",
-                    evaluated_items
-                ),
-            })
+    }
+}
+
+/// Map an operation onto values
+pub fn map(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let (items, expression) = (args[0], args[1]);
+
+    let items = Items::from_arg(ctx, data, items, "map")?;
+    let parsed_expression = Parsed::from_value(expression, ctx.engine)?;
+
+    let mut mapped = Vec::new();
+    let mut size = 0;
+    for item in items.evaluate(ctx, data) {
+        let item = item?;
+        let result: Value = ctx
+            .in_item(|| parsed_expression.evaluate(&item, ctx))?
+            .into();
+        if ctx.engine.max_result_size.is_some() {
+            size += 1 + result_size(&result);
+            ctx.engine.check_result_size("map", size)?;
         }
-    };
+        mapped.push(result);
+    }
+    Ok(Value::Array(mapped))
+}
+
+/// Filter values by some predicate
+pub fn filter(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let (items, expression) = (args[0], args[1]);
 
-    let parsed_expression = Parsed::from_value(expression)?;
+    let items = Items::from_arg(ctx, data, items, "filter")?;
+    let parsed_expression = Parsed::from_value(expression, ctx.engine)?;
 
-    let value_vec: Vec<Value> = Vec::with_capacity(values.len());
-    values
-        .into_iter()
-        .fold(Ok(value_vec), |acc, cur| {
-            let mut filtered = acc?;
-            let predicate = parsed_expression.evaluate(&cur)?;
+    let mut filtered = Vec::new();
+    for cur in items.evaluate(ctx, data) {
+        let cur = cur?;
+        let predicate = ctx.in_item(|| parsed_expression.evaluate(&cur, ctx))?;
 
-            match logic::truthy_from_evaluated(&predicate) {
-                true => {
-                    filtered.push(cur);
-                    Ok(filtered)
-                }
-                false => Ok(filtered),
-            }
-        })
-        .map(Value::Array)
+        if logic::truthy_from_evaluated(&predicate) {
+            filtered.push(cur.into_owned());
+        }
+    }
+    Ok(Value::Array(filtered))
 }
 
 /// Reduce values into a single result
@@ -97,130 +221,77 @@ pub fn filter(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
 /// Note this differs from the reference implementation of jsonlogic
 /// (but not the spec), in that it evaluates the initializer as a
 /// jsonlogic expression rather than a raw value.
-pub fn reduce(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
+pub fn reduce(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
     let (items, expression, initializer) = (args[0], args[1], args[2]);
 
-    let _parsed_items = Parsed::from_value(items)?;
-    let evaluated_items = _parsed_items.evaluate(data)?;
-
-    let _parsed_initializer = Parsed::from_value(initializer)?;
-    let evaluated_initializer = _parsed_initializer.evaluate(data)?;
+    let items = Items::from_arg(ctx, data, items, "reduce")?;
 
-    let values: Vec<Value> = match evaluated_items {
-        Evaluated::New(Value::Array(vals)) => vals,
-        Evaluated::Raw(Value::Array(vals)) => vals.iter().map(|v| v.clone()).collect(),
-        // null is treated as an empty array in the reference tests,
-        // for whatever reason
-        Evaluated::New(Value::Null) => vec![],
-        Evaluated::Raw(Value::Null) => vec![],
-        _ => {
-            return Err(Error::InvalidArgument {
-                value: args[0].clone(),
-                operation: "map".into(),
-                reason: format!(
-                    "First argument to filter must evaluate to an array. Got {:?}",
-                    evaluated_items
-                ),
-            })
-        }
-    };
+    let _parsed_initializer = Parsed::from_value(initializer, ctx.engine)?;
+    let evaluated_initializer = _parsed_initializer.evaluate(data, ctx)?;
 
-    let parsed_expression = Parsed::from_value(expression)?;
+    let parsed_expression = Parsed::from_value(expression, ctx.engine)?;
 
-    values
-        .into_iter()
-        .fold(Ok(Value::from(evaluated_initializer)), |acc, cur| {
-            let accumulator = acc?;
-            let mut data = Map::with_capacity(2);
-            data.insert("current".into(), cur);
-            data.insert("accumulator".into(), accumulator);
+    let mut accumulator = Value::from(evaluated_initializer);
+    for cur in items.evaluate(ctx, data) {
+        let mut data = Map::with_capacity(2);
+        data.insert("current".into(), cur?.into_owned());
+        data.insert("accumulator".into(), accumulator);
 
-            parsed_expression
-                .evaluate(&Value::Object(data))
-                .map(Value::from)
-        })
+        let data = Value::Object(data);
+        accumulator = ctx
+            .in_item(|| parsed_expression.evaluate(&data, ctx))?
+            .into();
+    }
+    Ok(accumulator)
 }
 
-/// Return whether all members of an array or string satisfy a predicate.
+/// Check whether any of the items in an iterating operator's first
+/// argument satisfies a predicate, or (if `expected` is false) fails to
 ///
-/// The predicate does not need to return true or false explicitly. Its
-/// return is evaluated using the "truthy" definition specified in the
-/// jsonlogic spec.
-pub fn all(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
+/// Items are evaluated one at a time, stopping at the first one for which
+/// the predicate's truthiness matches `expected`.
+fn any_item(
+    ctx: &Context,
+    data: &Value,
+    args: &[&Value],
+    operation: &str,
+    expected: bool,
+) -> Result<Option<bool>, Error> {
     let (first_arg, second_arg) = (args[0], args[1]);
 
-    // The first argument must be an array of values or a string of chars
-    // We won't bother parsing yet if the value is anything other than
-    // an object, because we can short-circuit this function if any of
-    // the items fail to match the predicate. However, we will parse
-    // if it's an object, in case it evaluates to a string or array, which
-    // we will then pass on
-
-    let _new_item: Value;
-    let potentially_evaled_first_arg = match first_arg {
-        Value::Object(_) => {
-            let parsed = Parsed::from_value(first_arg)?;
-            let evaluated = parsed.evaluate(data)?;
-            _new_item = evaluated.into();
-            &_new_item
-        }
-        _ => first_arg,
-    };
-
-    let _new_arr: Vec<Value>;
-    let items = match potentially_evaled_first_arg {
-        Value::Array(items) => items,
-        Value::String(string) => {
-            _new_arr = string
-                .chars()
-                .into_iter()
-                .map(|c| Value::String(c.to_string()))
-                .collect();
-            &_new_arr
-        }
-        Value::Null => {
-            _new_arr = Vec::new();
-            &_new_arr
-        }
-        _ => {
-            return Err(Error::InvalidArgument {
-                value: first_arg.clone(),
-                operation: "all".into(),
-                reason: format!(
-                "First argument to all must evaluate to an array, string, or null, got {}",
-                potentially_evaled_first_arg
-            ),
-            })
-        }
-    };
+    let items = Items::from_arg(ctx, data, first_arg, operation)?;
 
     // Special-case the empty array, since it for some reason is specified
-    // to return false.
-    if items.len() == 0 {
-        return Ok(Value::Bool(false));
+    // to return false for both all and some.
+    if items.is_empty() {
+        return Ok(None);
     }
 
     // Note we _expect_ the predicate to be an operator, but it doesn't
     // necessarily have to be. all([1, 2, 3], 1) is a valid operation,
     // returning 1 for each of the items and thus evaluating to true.
-    let predicate = Parsed::from_value(second_arg)?;
-
-    let result = items.into_iter().fold(Ok(true), |acc, i| {
-        acc.and_then(|res| {
-            // "Short-circuit": return false if the previous eval was false
-            if !res {
-                return Ok(false);
-            };
-            let _parsed_item = Parsed::from_value(i)?;
-            // Evaluate each item as we go, in case we can short-circuit
-            let evaluated_item = _parsed_item.evaluate(data)?;
-            Ok(logic::truthy_from_evaluated(
-                &predicate.evaluate(&evaluated_item.into())?,
-            ))
-        })
-    })?;
+    let predicate = Parsed::from_value(second_arg, ctx.engine)?;
+
+    for item in items.evaluate(ctx, data) {
+        let item = item?;
+        let matched = logic::truthy_from_evaluated(
+            &ctx.in_item(|| predicate.evaluate(&item, ctx))?,
+        );
+        if matched == expected {
+            return Ok(Some(true));
+        }
+    }
+    Ok(Some(false))
+}
 
-    Ok(Value::Bool(result))
+/// Return whether all members of an array or string satisfy a predicate.
+///
+/// The predicate does not need to return true or false explicitly. Its
+/// return is evaluated using the "truthy" definition specified in the
+/// jsonlogic spec.
+pub fn all(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let any_failed = any_item(ctx, data, args, "all", false)?;
+    Ok(Value::Bool(any_failed == Some(false)))
 }
 
 /// Return whether some members of an array or string satisfy a predicate.
@@ -228,124 +299,366 @@ pub fn all(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
 /// The predicate does not need to return true or false explicitly. Its
 /// return is evaluated using the "truthy" definition specified in the
 /// jsonlogic spec.
-pub fn some(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
-    let (first_arg, second_arg) = (args[0], args[1]);
+pub fn some(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let any_passed = any_item(ctx, data, args, "some", true)?;
+    Ok(Value::Bool(any_passed == Some(true)))
+}
 
-    // The first argument must be an array of values or a string of chars
-    // We won't bother parsing yet if the value is anything other than
-    // an object, because we can short-circuit this function if any of
-    // the items fail to match the predicate. However, we will parse
-    // if it's an object, in case it evaluates to a string or array, which
-    // we will then pass on
-
-    let _new_item: Value;
-    let potentially_evaled_first_arg = match first_arg {
-        Value::Object(_) => {
-            let parsed = Parsed::from_value(first_arg)?;
-            let evaluated = parsed.evaluate(data)?;
-            _new_item = evaluated.into();
-            &_new_item
-        }
-        _ => first_arg,
-    };
+/// Return whether no members of an array or string satisfy a predicate.
+///
+/// The predicate does not need to return true or false explicitly. Its
+/// return is evaluated using the "truthy" definition specified in the
+/// jsonlogic spec.
+pub fn none(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let any_passed = any_item(ctx, data, args, "none", true)?;
+    Ok(Value::Bool(any_passed != Some(true)))
+}
+
+/// Find the item for which a key expression is greatest or least
+///
+/// Each item is used as the data for the key expression, as with `map`,
+/// and the key is converted to a number. `better` decides whether a key
+/// should replace the best seen so far, so ties go to the earliest item.
+/// An empty array (or null) evaluates to null.
+fn extreme_by<F>(
+    ctx: &Context,
+    data: &Value,
+    args: &[&Value],
+    operation: &str,
+    better: F,
+) -> Result<Value, Error>
+where
+    F: Fn(f64, f64) -> bool,
+{
+    let (items, expression) = (args[0], args[1]);
+
+    let items = Items::from_arg(ctx, data, items, operation)?;
+    let parsed_expression = Parsed::from_value(expression, ctx.engine)?;
 
-    let _new_arr: Vec<Value>;
-    let items = match potentially_evaled_first_arg {
-        Value::Array(items) => items,
-        Value::String(string) => {
-            _new_arr = string
-                .chars()
-                .into_iter()
-                .map(|c| Value::String(c.to_string()))
-                .collect();
-            &_new_arr
+    let mut best: Option<(f64, Cow<Value>)> = None;
+    for value in items.evaluate(ctx, data) {
+        let value = value?;
+        let key = Value::from(ctx.in_item(|| parsed_expression.evaluate(&value, ctx))?);
+        let num = match &key {
+            Value::String(s) => {
+                js_op::str_to_number_with(s, ctx.engine.decimal_separator)
+            }
+            _ => js_op::to_number(&key),
         }
-        Value::Null => {
-            _new_arr = Vec::new();
-            &_new_arr
+        .ok_or_else(|| Error::InvalidArgument {
+            value: key.clone(),
+            operation: operation.into(),
+            reason: "Could not convert key to number".into(),
+        })?;
+        match best {
+            Some((best_num, _)) if !better(num, best_num) => {}
+            _ => best = Some((num, value)),
         }
-        _ => {
+    }
+    Ok(best
+        .map(|(_, value)| value.into_owned())
+        .unwrap_or(Value::Null))
+}
+
+/// Find the item with the greatest key: `[items, key_expression]`
+pub fn max_by(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    extreme_by(ctx, data, args, "max_by", |num, best| num > best)
+}
+
+/// Find the item with the least key: `[items, key_expression]`
+pub fn min_by(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    extreme_by(ctx, data, args, "min_by", |num, best| num < best)
+}
+
+/// Take the first items of an array: `[items, count]`
+///
+/// Only the items taken are evaluated, so items of a literal array past
+/// the count are never evaluated.
+pub fn take(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let items = Items::from_arg(ctx, data, args[0], "take")?;
+    let count =
+        Value::from(Parsed::from_value(args[1], ctx.engine)?.evaluate(data, ctx)?);
+    let count = match count.as_u64() {
+        Some(count) => usize::try_from(count).unwrap_or(usize::MAX),
+        None => {
             return Err(Error::InvalidArgument {
-                value: first_arg.clone(),
-                operation: "all".into(),
-                reason: format!(
-                "First argument must evaluate to an array, a string, or null, got {}",
-                potentially_evaled_first_arg
-            ),
+                value: count,
+                operation: "take".into(),
+                reason: "Second argument to take must be a non-negative integer".into(),
             })
         }
     };
 
-    // Special-case the empty array, since it for some reason is specified
-    // to return false.
-    if items.len() == 0 {
-        return Ok(Value::Bool(false));
+    let mut taken = Vec::new();
+    for item in items.evaluate(ctx, data).take(count) {
+        taken.push(item?.into_owned());
     }
+    Ok(Value::Array(taken))
+}
 
-    // Note we _expect_ the predicate to be an operator, but it doesn't
-    // necessarily have to be. all([1, 2, 3], 1) is a valid operation,
-    // returning 1 for each of the items and thus evaluating to true.
-    let predicate = Parsed::from_value(second_arg)?;
-
-    let result = items.into_iter().fold(Ok(false), |acc, i| {
-        acc.and_then(|res| {
-            // "Short-circuit": return false if the previous eval was false
-            if res {
-                return Ok(true);
-            };
-            let _parsed_item = Parsed::from_value(i)?;
-            // Evaluate each item as we go, in case we can short-circuit
-            let evaluated_item = _parsed_item.evaluate(data)?;
-            Ok(logic::truthy_from_evaluated(
-                &predicate.evaluate(&evaluated_item.into())?,
-            ))
-        })
-    })?;
-
-    Ok(Value::Bool(result))
+/// Take items of an array while a predicate holds: `[items, predicate]`
+///
+/// Items after the first for which the predicate is falsy are never
+/// evaluated.
+pub fn take_while(
+    ctx: &Context,
+    data: &Value,
+    args: &[&Value],
+) -> Result<Value, Error> {
+    let items = Items::from_arg(ctx, data, args[0], "take_while")?;
+    let predicate = Parsed::from_value(args[1], ctx.engine)?;
+
+    let mut taken = Vec::new();
+    for item in items.evaluate(ctx, data) {
+        let item = item?;
+        if !logic::truthy_from_evaluated(
+            &ctx.in_item(|| predicate.evaluate(&item, ctx))?,
+        ) {
+            break;
+        }
+        taken.push(item.into_owned());
+    }
+    Ok(Value::Array(taken))
 }
 
-/// Return whether no members of an array or string satisfy a predicate.
+/// Find the first item for which a predicate holds: `[items, predicate]`
 ///
-/// The predicate does not need to return true or false explicitly. Its
-/// return is evaluated using the "truthy" definition specified in the
-/// jsonlogic spec.
-pub fn none(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
-    some(data, args).and_then(|had_some| match had_some {
-        Value::Bool(res) => Ok(Value::Bool(!res)),
-        _ => Err(Error::UnexpectedError(
-            "Unexpected return type from op_some".into(),
-        )),
-    })
+/// Evaluates to `null` if there is no such item. Items after the first
+/// match are never evaluated.
+pub fn first(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let items = Items::from_arg(ctx, data, args[0], "first")?;
+    let predicate = Parsed::from_value(args[1], ctx.engine)?;
+
+    for item in items.evaluate(ctx, data) {
+        let item = item?;
+        if logic::truthy_from_evaluated(
+            &ctx.in_item(|| predicate.evaluate(&item, ctx))?,
+        ) {
+            return Ok(item.into_owned());
+        }
+    }
+    Ok(Value::Null)
 }
 
 /// Merge one to n arrays, flattening them by one level.
 ///
 /// Values that are not arrays are (effectively) converted to arrays
 /// before flattening.
-pub fn merge(items: &Vec<&Value>) -> Result<Value, Error> {
+pub fn merge(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    if ctx.engine.max_result_size.is_some() {
+        let size = items
+            .iter()
+            .map(|item| match item {
+                Value::Array(_) => result_size(item),
+                _ => 1 + result_size(item),
+            })
+            .sum();
+        ctx.engine.check_result_size("merge", size)?;
+    }
     let rv_vec: Vec<Value> = Vec::new();
-    Ok(Value::Array(items.into_iter().fold(
-        rv_vec,
-        |mut acc, i| {
-            match i {
-                Value::Array(i_vals) => {
-                    i_vals.into_iter().for_each(|val| acc.push((*val).clone()));
+    Ok(Value::Array(items.iter().fold(rv_vec, |mut acc, i| {
+        match i {
+            Value::Array(i_vals) => {
+                i_vals.iter().for_each(|val| acc.push((*val).clone()));
+            }
+            _ => acc.push((**i).clone()),
+        };
+        acc
+    })))
+}
+
+/// Push values onto `acc`, flattening arrays up to `depth` levels deep
+fn flatten_into(acc: &mut Vec<Value>, values: &[Value], depth: Option<u64>) {
+    for value in values {
+        match value {
+            Value::Array(inner) if depth != Some(0) => {
+                flatten_into(acc, inner, depth.map(|d| d - 1))
+            }
+            _ => acc.push(value.clone()),
+        }
+    }
+}
+
+/// Recursively flatten nested arrays: `[array, depth?]`
+///
+/// Without a depth, arrays are flattened completely. A depth of 1 is
+/// equivalent to `merge` on the array's items, and a depth of 0 returns
+/// the array unchanged. `null` is treated as an empty array.
+pub fn flatten(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let values = array_arg(items[0], "flatten")?;
+    let depth = match items.get(1) {
+        None => None,
+        Some(Value::Number(n)) if n.as_u64().is_some() => n.as_u64(),
+        Some(depth) => {
+            return Err(Error::InvalidArgument {
+                value: (*depth).clone(),
+                operation: "flatten".into(),
+                reason: "Second argument to flatten must be a non-negative integer"
+                    .into(),
+            })
+        }
+    };
+
+    let mut rv = Vec::with_capacity(values.len());
+    flatten_into(&mut rv, values, depth);
+    Ok(Value::Array(rv))
+}
+
+/// Get an array argument, treating `null` as an empty array
+fn array_arg<'a>(value: &'a Value, operation: &str) -> Result<&'a [Value], Error> {
+    match value {
+        Value::Array(vals) => Ok(vals),
+        Value::Null => Ok(&[]),
+        _ => Err(Error::InvalidArgument {
+            value: value.clone(),
+            operation: operation.into(),
+            reason: format!("First argument to {} must be an array", operation),
+        }),
+    }
+}
+
+/// Check whether an array is sorted: `[array, direction?]`
+///
+/// The direction is "asc" (the default) or "desc". Adjacent items are
+/// compared as by `<=` or `>=`, so equal items are allowed.
+pub fn is_sorted(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let values = array_arg(items[0], "is_sorted")?;
+    let cmp = &ctx.engine.comparator;
+    let compare = match items.get(1).copied() {
+        None => Comparator::lte,
+        Some(Value::String(dir)) if dir == "asc" => Comparator::lte,
+        Some(Value::String(dir)) if dir == "desc" => Comparator::gte,
+        Some(direction) => {
+            return Err(Error::InvalidArgument {
+                value: direction.clone(),
+                operation: "is_sorted".into(),
+                reason: "Second argument to is_sorted must be \"asc\" or \"desc\""
+                    .into(),
+            })
+        }
+    };
+    Ok(Value::Bool(
+        values
+            .windows(2)
+            .all(|pair| compare(cmp, &pair[0], &pair[1])),
+    ))
+}
+
+/// Check whether all items in an array are different from one another
+///
+/// Items are compared as by `in`, so arrays and objects are compared by
+/// value, and no type coercion is done.
+pub fn all_distinct(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let values = array_arg(items[0], "all_distinct")?;
+    let mut seen = HashSet::with_capacity(values.len());
+    Ok(Value::Bool(
+        values
+            .iter()
+            .all(|v| seen.insert(ctx.engine.comparator.key(v))),
+    ))
+}
+
+/// Check the needles found in a haystack: `[haystack, needles]`
+///
+/// Array haystacks are checked by value, as with `in`, but using a set
+/// rather than a search of the haystack per needle. String haystacks are
+/// checked for substrings, in which case every needle must be a string.
+/// A single non-array needle is treated as a list of one.
+fn count_found(
+    cmp: &Comparator,
+    items: &[&Value],
+    operation: &str,
+) -> Result<(usize, usize), Error> {
+    let (haystack, needles) = (items[0], items[1]);
+    let needles: Vec<&Value> = match needles {
+        Value::Array(vals) => vals.iter().collect(),
+        _ => vec![needles],
+    };
+    let found =
+        match haystack {
+            Value::String(haystack) => {
+                let mut found = 0;
+                for needle in &needles {
+                    match needle {
+                        Value::String(needle) => {
+                            found += usize::from(cmp.contains_str(haystack, needle))
+                        }
+                        _ => return Err(Error::InvalidArgument {
+                            value: (*needle).clone(),
+                            operation: operation.into(),
+                            reason:
+                                "If the haystack is a string, needles must be strings"
+                                    .into(),
+                        }),
+                    }
                 }
-                _ => acc.push((**i).clone()),
-            };
-            acc
-        },
-    )))
+                found
+            }
+            Value::Array(_) | Value::Null => {
+                let haystack: HashSet<String> = array_arg(haystack, operation)?
+                    .iter()
+                    .map(|item| cmp.key(item))
+                    .collect();
+                needles
+                    .iter()
+                    .filter(|needle| haystack.contains(&cmp.key(needle)))
+                    .count()
+            }
+            _ => {
+                return Err(Error::InvalidArgument {
+                    value: haystack.clone(),
+                    operation: operation.into(),
+                    reason: "First argument must be an array or a string".into(),
+                })
+            }
+        };
+    Ok((found, needles.len()))
+}
+
+/// Check that a haystack contains every needle: `[haystack, needles]`
+pub fn contains_all(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let (found, total) = count_found(&ctx.engine.comparator, items, "contains_all")?;
+    Ok(Value::Bool(found == total))
+}
+
+/// Check that a haystack contains at least one needle: `[haystack, needles]`
+pub fn contains_any(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let (found, _) = count_found(&ctx.engine.comparator, items, "contains_any")?;
+    Ok(Value::Bool(found > 0))
 }
 
 /// Perform containment checks with "in"
-// TODO: make this a lazy operator, since we don't need to parse things
-// later on in the list if we find something that matches early.
-pub fn in_(items: &Vec<&Value>) -> Result<Value, Error> {
-    let needle = items[0];
-    let haystack = items[1];
+///
+/// When the haystack is a literal array, its items are evaluated one at a
+/// time, and items after the first match are never evaluated.
+pub fn in_(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let needle =
+        Value::from(Parsed::from_value(args[0], ctx.engine)?.evaluate(data, ctx)?);
+
+    if let Value::Array(possibles) = args[1] {
+        for possible in possibles {
+            let possible = Parsed::from_value(possible, ctx.engine)?;
+            if ctx
+                .engine
+                .comparator
+                .same(&possible.evaluate(data, ctx)?.into(), &needle)
+            {
+                return Ok(Value::Bool(true));
+            }
+        }
+        return Ok(Value::Bool(false));
+    }
+    let haystack =
+        Value::from(Parsed::from_value(args[1], ctx.engine)?.evaluate(data, ctx)?);
+    contains(&ctx.engine.comparator, &needle, &haystack)
+}
 
+/// Check whether an evaluated haystack contains a needle, as "in" does
+pub fn contains(
+    cmp: &Comparator,
+    needle: &Value,
+    haystack: &Value,
+) -> Result<Value, Error> {
     match haystack {
         // Note: our containment check for array values is actually a bit
         // more robust than JS. This by default does array equality (e.g.
@@ -355,7 +668,9 @@ pub fn in_(items: &Vec<&Value>) -> Result<Value, Error> {
         // implementation is relying on broken, undefined behavior, it seems
         // okay to update that behavior to work in a more intuitive way.
         Value::Null => Ok(Value::Bool(false)),
-        Value::Array(possibles) => Ok(Value::Bool(possibles.contains(needle))),
+        Value::Array(possibles) => Ok(Value::Bool(
+            possibles.iter().any(|possible| cmp.same(possible, needle)),
+        )),
         Value::String(haystack_string) => {
             // Note: the reference implementation uses the regular old
             // String.prototype.indexOf() function to check for containment,
@@ -375,7 +690,9 @@ pub fn in_(items: &Vec<&Value>) -> Result<Value, Error> {
                                 .into(),
                     }),
                 };
-            Ok(Value::Bool(haystack_string.contains(needle_string)))
+            Ok(Value::Bool(
+                cmp.contains_str(haystack_string, needle_string),
+            ))
         }
         _ => Err(Error::InvalidArgument {
             value: haystack.clone(),
@@ -0,0 +1,246 @@
+//! Checksum Operations
+//!
+//! These check the check digits of identifiers like card numbers, IBANs,
+//! and barcodes, so that typos can be caught without looking the
+//! identifier up anywhere. Spaces and hyphens are ignored, as they're
+//! commonly used to group digits. Anything else that isn't part of the
+//! scheme makes an identifier invalid, rather than being an error.
+
+use serde_json::Value;
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::js_op;
+
+/// The checksum schemes supported by `checksum`
+const SCHEMES: &[&str] = &["luhn", "iban", "gtin", "isbn10", "verhoeff"];
+
+fn invalid(value: &Value, operation: &str, reason: String) -> Error {
+    Error::InvalidArgument {
+        value: value.clone(),
+        operation: operation.into(),
+        reason,
+    }
+}
+
+/// Get the identifier to check, without its separators
+///
+/// As with hashing, numbers are checked using their string representation,
+/// and other types are rejected.
+fn identifier(value: &Value, operation: &str) -> Result<String, Error> {
+    let string = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(_) => js_op::to_string(value),
+        _ => {
+            return Err(invalid(
+                value,
+                operation,
+                format!(
+                    "First argument to {} must be a string or a number",
+                    operation
+                ),
+            ))
+        }
+    };
+    Ok(string
+        .chars()
+        .filter(|c| !(c.is_whitespace() || *c == '-'))
+        .collect())
+}
+
+/// The values of a string's digits, if it's all digits
+fn digits(string: &str) -> Option<Vec<u32>> {
+    string.chars().map(|c| c.to_digit(10)).collect()
+}
+
+/// The Luhn (mod 10) check, used by payment card numbers, IMEIs, and many
+/// national IDs
+fn luhn(string: &str) -> bool {
+    let digits = match digits(string) {
+        Some(digits) if digits.len() >= 2 => digits,
+        _ => return false,
+    };
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(idx, digit)| match idx % 2 {
+            0 => *digit,
+            _ if *digit > 4 => digit * 2 - 9,
+            _ => digit * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// The ISO 13616 check of an International Bank Account Number: the
+/// country code and check digits are moved to the end, letters become
+/// numbers from 10 to 35, and the result must be 1 modulo 97
+fn iban(string: &str) -> bool {
+    let string = string.to_ascii_uppercase();
+    let bytes = string.as_bytes();
+    if !(15..=34).contains(&bytes.len())
+        || !bytes[..2].iter().all(u8::is_ascii_uppercase)
+        || !bytes[2..4].iter().all(u8::is_ascii_digit)
+    {
+        return false;
+    }
+    let mut remainder = 0;
+    for c in string[4..].chars().chain(string[..4].chars()) {
+        let value = match c.to_digit(36) {
+            Some(value) => value,
+            None => return false,
+        };
+        remainder = match value {
+            0..=9 => (remainder * 10 + value) % 97,
+            _ => (remainder * 100 + value) % 97,
+        };
+    }
+    remainder == 1
+}
+
+/// The GS1 check of a GTIN-8, -12 (UPC-A), -13 (EAN-13 and ISBN-13), or
+/// -14 barcode number
+fn gtin(string: &str) -> bool {
+    let digits = match digits(string) {
+        Some(digits) if [8, 12, 13, 14].contains(&digits.len()) => digits,
+        _ => return false,
+    };
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(idx, digit)| match idx % 2 {
+            0 => *digit,
+            _ => digit * 3,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// The check of a 10-digit ISBN, whose last digit may be "X" for 10
+fn isbn10(string: &str) -> bool {
+    if string.len() != 10 {
+        return false;
+    }
+    let mut sum = 0;
+    for (idx, c) in string.chars().enumerate() {
+        let value = match (idx, c) {
+            (9, 'X' | 'x') => 10,
+            _ => match c.to_digit(10) {
+                Some(value) => value,
+                None => return false,
+            },
+        };
+        sum += (10 - idx as u32) * value;
+    }
+    sum.is_multiple_of(11)
+}
+
+/// Multiplication in the dihedral group D5, for the Verhoeff check
+const VERHOEFF_D: [[u8; 10]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+    [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+    [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+    [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+    [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+    [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+    [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+    [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+    [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+];
+
+/// The Verhoeff permutation, applied once per position from the right
+const VERHOEFF_P: [[u8; 10]; 8] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+    [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+    [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+    [9, 4, 5, 3, 1, 2, 8, 7, 0, 6],
+    [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+    [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+    [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+];
+
+/// The Verhoeff check, used by e.g. India's Aadhaar numbers, which catches
+/// all single-digit errors and transpositions of adjacent digits
+fn verhoeff(string: &str) -> bool {
+    let digits = match digits(string) {
+        Some(digits) if digits.len() >= 2 => digits,
+        _ => return false,
+    };
+    let check = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .fold(0, |check, (idx, digit)| {
+            VERHOEFF_D[check as usize][VERHOEFF_P[idx % 8][*digit as usize] as usize]
+        });
+    check == 0
+}
+
+/// Check the check digit of a number using the Luhn algorithm
+pub fn luhn_valid(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    Ok(Value::Bool(luhn(&identifier(items[0], "luhn_valid")?)))
+}
+
+/// Check an identifier against a checksum scheme: `[identifier, scheme]`
+///
+/// The schemes are "luhn", "iban", "gtin", "isbn10", and "verhoeff".
+pub fn checksum(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let (identifier_arg, scheme_arg) = (items[0], items[1]);
+    let string = identifier(identifier_arg, "checksum")?;
+    let valid = match scheme_arg.as_str() {
+        Some("luhn") => luhn(&string),
+        Some("iban") => iban(&string),
+        Some("gtin") => gtin(&string),
+        Some("isbn10") => isbn10(&string),
+        Some("verhoeff") => verhoeff(&string),
+        _ => {
+            return Err(invalid(
+                scheme_arg,
+                "checksum",
+                format!("Second argument to checksum must be one of {:?}", SCHEMES),
+            ))
+        }
+    };
+    Ok(Value::Bool(valid))
+}
+
+#[cfg(test)]
+mod test_checksum {
+    use super::*;
+
+    #[test]
+    fn test_schemes() {
+        assert!(luhn("79927398713"));
+        assert!(!luhn("79927398710"));
+        assert!(luhn("4111111111111111"));
+        assert!(!luhn("0"));
+        assert!(!luhn("4111a11111111111"));
+
+        assert!(iban("GB82WEST12345698765432"));
+        assert!(iban("de89370400440532013000"));
+        assert!(!iban("GB82WEST12345698765433"));
+        assert!(!iban("GB82WEST"));
+        assert!(!iban("1282WEST12345698765432"));
+
+        assert!(gtin("4006381333931"));
+        assert!(gtin("9780306406157"));
+        assert!(gtin("036000291452"));
+        assert!(gtin("96385074"));
+        assert!(!gtin("4006381333932"));
+        assert!(!gtin("400638133393"));
+
+        assert!(isbn10("0306406152"));
+        assert!(isbn10("080442957X"));
+        assert!(!isbn10("0306406153"));
+        assert!(!isbn10("X306406152"));
+
+        assert!(verhoeff("2363"));
+        assert!(verhoeff("123451"));
+        assert!(!verhoeff("123415"));
+        assert!(!verhoeff("2364"));
+    }
+}
@@ -6,8 +6,11 @@ use std::convert::TryInto;
 
 use serde_json::Value;
 
+use crate::context::Context;
+use crate::engine::NumericKeys;
 use crate::error::Error;
-use crate::value::{Evaluated, Parsed};
+use crate::source::DataSource;
+use crate::value::{to_number_value, Evaluated, Parsed};
 use crate::NULL;
 
 /// Valid types of variable keys
@@ -67,43 +70,152 @@ impl<'a> TryFrom<Evaluated<'a>> for KeyType<'a> {
     }
 }
 
-/// A get operation that supports negative indexes
-fn get<T>(slice: &[T], idx: i64) -> Option<&T> {
-    let vec_len = slice.len();
-    let usize_idx: usize = idx.abs().try_into().ok()?;
-
-    let adjusted_idx = if idx >= 0 {
-        usize_idx
-    } else {
-        vec_len.checked_sub(usize_idx)?
+/// Check that the engine's policy allows reading a key from the data
+///
+/// Only reads from the data at the root are restricted, rather than from
+/// e.g. the items passed to the expression of a `map`.
+fn check_allowed(ctx: &Context, key: &KeyType, operation: &str) -> Result<(), Error> {
+    let policy = match &ctx.engine.policy {
+        Some(policy) if ctx.is_root() => policy,
+        _ => return Ok(()),
     };
-
-    slice.get(adjusted_idx)
+    let (segments, value) = match key {
+        KeyType::Null => (Vec::new(), Value::Null),
+        KeyType::String(k) => (split_with_escape(k, '.'), Value::String(k.to_string())),
+        KeyType::Number(i) => (vec![i.to_string()], Value::from(*i)),
+    };
+    match policy.allows_var(&segments) {
+        true => Ok(()),
+        false => Err(Error::InvalidVariable {
+            value,
+            reason: format!("{} may not read this variable by policy", operation),
+        }),
+    }
 }
 
 /// Retrieve a variable from the data
 ///
 /// Note that the reference implementation does not support negative
 /// indexing for numeric values, but we do.
-pub fn var(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
+pub fn var(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
     let arg_count = args.len();
     if arg_count == 0 {
-        return Ok(data.clone());
+        check_allowed(ctx, &KeyType::Null, "var")?;
+        return Ok(get_key(ctx.source(data), KeyType::Null).unwrap_or(NULL));
     };
 
     let key = args[0].try_into()?;
-    let val = get_key(data, key);
+    check_allowed(ctx, &key, "var")?;
+    ctx.record_lookup();
+    let val = get_var(ctx, data, key);
 
     Ok(val.unwrap_or(if arg_count < 2 {
         NULL
     } else {
-        let _parsed_default = Parsed::from_value(args[1])?;
-        _parsed_default.evaluate(&data)?.into()
+        let _parsed_default = Parsed::from_value(args[1], ctx.engine)?;
+        _parsed_default.evaluate(data, ctx)?.into()
     }))
 }
 
+/// Get a property of an evaluated value: `[container, key, default?]`
+///
+/// Unlike `var`, the key is a single property name or index rather than a
+/// dotted path, so keys containing dots are looked up as is. Negative
+/// indexes count back from the end of arrays and strings.
+pub fn get_property(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let (container, key) = (items[0], items[1]);
+    let val = match KeyType::try_from(key)? {
+        KeyType::Null => Some(container.clone()),
+        KeyType::String(k) => match container {
+            Value::Object(map) => map.get(k.as_ref()).cloned(),
+            _ => k
+                .parse::<i64>()
+                .ok()
+                .and_then(|i| get_key(container, KeyType::Number(i))),
+        },
+        KeyType::Number(i) => get_key(container, KeyType::Number(i)),
+    };
+    Ok(val.unwrap_or_else(|| items.get(2).map_or(NULL, |v| (*v).clone())))
+}
+
+/// The name of a value's JSON type, for error messages
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Retrieve a variable, erroring if it isn't of the expected type
+///
+/// A missing variable without a default resolves to null, which is an
+/// error for all types, so these also catch missing data.
+fn typed_var(
+    ctx: &Context,
+    data: &Value,
+    args: &[&Value],
+    operation: &str,
+    expected: &str,
+    is_expected: fn(&Value) -> bool,
+) -> Result<Value, Error> {
+    let val = var(ctx, data, args)?;
+    if is_expected(&val) {
+        return Ok(val);
+    }
+    let key = match args.first() {
+        Some(key) => key.to_string(),
+        None => "the data".into(),
+    };
+    Err(Error::InvalidVariable {
+        reason: format!(
+            "{} expected {} to be {}, got {}",
+            operation,
+            key,
+            expected,
+            type_name(&val)
+        ),
+        value: val,
+    })
+}
+
+/// Retrieve a variable that must be a string
+pub fn var_str(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    typed_var(ctx, data, args, "var_str", "a string", Value::is_string)
+}
+
+/// Retrieve a variable that must be a number
+pub fn var_num(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    typed_var(ctx, data, args, "var_num", "a number", Value::is_number)
+}
+
+/// Retrieve a variable that must be a boolean
+pub fn var_bool(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    typed_var(ctx, data, args, "var_bool", "a boolean", Value::is_boolean)
+}
+
+/// Parse a key given as an operator's argument, or an item of one,
+/// reporting its position if it isn't a valid key
+fn argument_key<'a>(
+    value: &'a Value,
+    operation: &str,
+    index: usize,
+    item: Option<usize>,
+) -> Result<KeyType<'a>, Error> {
+    KeyType::try_from(value).map_err(|_| Error::MalformedArgument {
+        operation: operation.into(),
+        index,
+        item,
+        value: value.clone(),
+        expected: "a string, an integer, or null".into(),
+    })
+}
+
 /// Check for keys that are missing from the data
-pub fn missing(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
+pub fn missing(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
     let mut missing_keys: Vec<Value> = Vec::new();
 
     // This bit of insanity is because for some reason the reference
@@ -111,32 +223,39 @@ pub fn missing(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
     // multiple args and the first arg is an array, _that_ array is
     // treated as the only argument.
     let inner_vec: Vec<&Value>;
-    let adjusted_args = if args.len() > 0 {
+    let (adjusted_args, in_list) = if !args.is_empty() {
         match args[0] {
             Value::Array(vals) => {
                 inner_vec = vals.iter().collect();
-                &inner_vec
+                (&inner_vec[..], true)
             }
-            _ => args,
+            _ => (args, false),
         }
     } else {
-        args
+        (args, false)
     };
 
-    adjusted_args.into_iter().fold(Ok(()), |had_error, arg| {
-        had_error?;
-        let key: KeyType = (*arg).try_into()?;
-        match key {
-            KeyType::Null => Ok(()),
-            _ => {
-                let val = get_key(data, key);
-                if val.is_none() {
-                    missing_keys.push((*arg).clone());
-                };
-                Ok(())
+    adjusted_args
+        .iter()
+        .enumerate()
+        .try_fold((), |_, (idx, arg)| {
+            let key = match in_list {
+                true => argument_key(arg, "missing", 0, Some(idx))?,
+                false => argument_key(arg, "missing", idx, None)?,
+            };
+            match key {
+                KeyType::Null => Ok(()),
+                _ => {
+                    check_allowed(ctx, &key, "missing")?;
+                    ctx.record_lookup();
+                    let val = get_var(ctx, data, key);
+                    if val.is_none() {
+                        missing_keys.push((*arg).clone());
+                    };
+                    Ok(())
+                }
             }
-        }
-    })?;
+        })?;
     Ok(Value::Array(missing_keys))
 }
 
@@ -148,57 +267,74 @@ pub fn missing(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
 /// to or more than the threshold value _present_ in the data, an empty
 /// array is returned. Otherwise, an array containing all missing keys
 /// is returned.
-pub fn missing_some(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
+pub fn missing_some(
+    ctx: &Context,
+    data: &Value,
+    args: &[&Value],
+) -> Result<Value, Error> {
     let (threshold_arg, keys_arg) = (args[0], args[1]);
 
     let threshold = match threshold_arg {
         Value::Number(n) => n.as_u64(),
         _ => None,
     }
-    .ok_or_else(|| Error::InvalidArgument {
-        value: threshold_arg.clone(),
+    .ok_or_else(|| Error::MalformedArgument {
         operation: "missing_some".into(),
-        reason: "missing_some threshold must be a valid, positive integer".into(),
+        index: 0,
+        item: None,
+        value: threshold_arg.clone(),
+        expected: "a non-negative integer threshold".into(),
     })?;
 
     let keys = match keys_arg {
         Value::Array(keys) => Ok(keys),
-        _ => Err(Error::InvalidArgument {
+        _ => Err(Error::MalformedArgument {
+            operation: "missing_some".into(),
+            index: 1,
+            item: None,
             value: keys_arg.clone(),
-            operation: "missig_some".into(),
-            reason: "missing_some keys must be an array".into(),
+            expected: "an array of keys".into(),
         }),
     }?;
 
     let mut missing_keys: Vec<Value> = Vec::new();
-    let present_count = keys.into_iter().fold(Ok(0 as u64), |last, key| {
-        // Don't bother evaluating once we've met the threshold.
-        let prev_present_count = last?;
-        if prev_present_count >= threshold {
-            return Ok(prev_present_count);
-        };
+    let present_count =
+        keys.iter()
+            .enumerate()
+            .try_fold(0_u64, |prev_present_count, (idx, key)| {
+                // Don't bother evaluating once we've met the threshold.
+                if prev_present_count >= threshold {
+                    return Ok(prev_present_count);
+                };
 
-        let parsed_key: KeyType = key.try_into()?;
-        let current_present_count = match parsed_key {
-            // In the reference implementation, I believe null actually is
-            // buggy. Since usually, getting "null" as a var against the
-            // data returns the whole data, "null" in a `missing_some`
-            // list of keys _automatically_ counts as a present key, regardless
-            // of what keys are in the data. This behavior is neither in the
-            // specification nor the tests, so I'm going to SKIP null keys,
-            // since they aren't valid Object or Array keys in JSON.
-            KeyType::Null => prev_present_count,
-            _ => {
-                if get_key(data, parsed_key).is_none() && !missing_keys.contains(key) {
-                    missing_keys.push((*key).clone());
-                    prev_present_count
-                } else {
-                    prev_present_count + 1
-                }
-            }
-        };
-        Ok(current_present_count)
-    })?;
+                let parsed_key = argument_key(key, "missing_some", 1, Some(idx))?;
+                let current_present_count = match parsed_key {
+                    // In the reference implementation, I believe null actually is
+                    // buggy. Since usually, getting "null" as a var against the
+                    // data returns the whole data, "null" in a `missing_some`
+                    // list of keys _automatically_ counts as a present key, regardless
+                    // of what keys are in the data. This behavior is neither in the
+                    // specification nor the tests, so I'm going to SKIP null keys,
+                    // since they aren't valid Object or Array keys in JSON.
+                    KeyType::Null => prev_present_count,
+                    _ => {
+                        check_allowed(ctx, &parsed_key, "missing_some")?;
+                        ctx.record_lookup();
+                        if get_var(ctx, data, parsed_key).is_some() {
+                            return Ok(prev_present_count + 1);
+                        }
+                        // Keys given more than once are only reported once
+                        if !missing_keys
+                            .iter()
+                            .any(|missing| ctx.engine.comparator.same(missing, key))
+                        {
+                            missing_keys.push((*key).clone());
+                        }
+                        prev_present_count
+                    }
+                };
+                Ok(current_present_count)
+            })?;
 
     let met_threshold = present_count >= threshold;
 
@@ -209,22 +345,155 @@ pub fn missing_some(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
     }
 }
 
-fn get_key(data: &Value, key: KeyType) -> Option<Value> {
-    match key {
-        // If the key is null, we return the data, always, even if there
-        // is a default parameter.
-        KeyType::Null => return Some(data.clone()),
-        KeyType::String(k) => get_str_key(data, k),
-        KeyType::Number(i) => match data {
-            Value::Object(_) => get_str_key(data, i.to_string()),
-            Value::Array(arr) => get(arr, i).map(Value::clone),
-            Value::String(s) => {
-                let s_vec: Vec<char> = s.chars().collect();
-                get(&s_vec, i).map(|c| c.to_string()).map(Value::String)
+/// Report which of a list of required keys are present in the data
+///
+/// Takes an array of keys and an optional options object, and returns an
+/// object listing the `missing` and `present` keys, along with whether the
+/// requirement is `satisfied`. By default all keys are required, but the
+/// `min` option lowers this to a minimum number of present keys, as with
+/// `missing_some`. As in `missing_some`, null keys are skipped.
+pub fn require(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let keys = match args[0] {
+        Value::Array(keys) => keys,
+        _ => {
+            return Err(Error::InvalidArgument {
+                value: args[0].clone(),
+                operation: "require".into(),
+                reason: "First argument to require must be an array of keys".into(),
+            })
+        }
+    };
+
+    let mut min: Option<u64> = None;
+    if let Some(options) = args.get(1) {
+        let options = match options {
+            Value::Object(options) => options,
+            _ => {
+                return Err(Error::InvalidArgument {
+                    value: (*options).clone(),
+                    operation: "require".into(),
+                    reason: "Second argument to require must be an options object"
+                        .into(),
+                })
             }
-            _ => None,
-        },
+        };
+        for (name, value) in options {
+            match name.as_str() {
+                "min_present" => {
+                    min = Some(value.as_u64().ok_or_else(|| {
+                        Error::InvalidArgument {
+                            value: value.clone(),
+                            operation: "require".into(),
+                            reason:
+                                "The min_present option must be a non-negative integer"
+                                    .into(),
+                        }
+                    })?)
+                }
+                _ => {
+                    return Err(Error::InvalidArgument {
+                        value: Value::String(name.clone()),
+                        operation: "require".into(),
+                        reason: format!("Unknown option for require: {}", name),
+                    })
+                }
+            }
+        }
+    }
+
+    let mut missing_keys: Vec<Value> = Vec::new();
+    let mut present_keys: Vec<Value> = Vec::new();
+    for (idx, key) in keys.iter().enumerate() {
+        let parsed_key = argument_key(key, "require", 0, Some(idx))?;
+        if let KeyType::Null = parsed_key {
+            continue;
+        }
+        check_allowed(ctx, &parsed_key, "require")?;
+        ctx.record_lookup();
+        let found = get_var(ctx, data, parsed_key).is_some();
+        let bucket = if found {
+            &mut present_keys
+        } else {
+            &mut missing_keys
+        };
+        // Keys given more than once are only reported once
+        if !bucket
+            .iter()
+            .any(|listed| ctx.engine.comparator.same(listed, key))
+        {
+            bucket.push(key.clone());
+        }
     }
+
+    let satisfied = match min {
+        Some(min) => present_keys.len() as u64 >= min,
+        None => missing_keys.is_empty(),
+    };
+    Ok(serde_json::json!({
+        "missing": missing_keys,
+        "present": present_keys,
+        "satisfied": satisfied,
+    }))
+}
+
+/// Look up a variable in the data, trying numeric path segments in their
+/// usual form as well as literally, per the engine's `numeric_keys()`
+fn get_var(ctx: &Context, data: &Value, key: KeyType) -> Option<Value> {
+    let source = ctx.source(data);
+    let numeric_keys = ctx.engine.numeric_keys;
+    let segments = match (&key, numeric_keys) {
+        (KeyType::String(k), NumericKeys::ExactFirst | NumericKeys::NumberFirst) => {
+            split_with_escape(k, '.')
+        }
+        _ => return get_key(source, key),
+    };
+    let numbers: Vec<String> = segments
+        .iter()
+        .map(|segment| number_key(segment).unwrap_or_else(|| segment.clone()))
+        .collect();
+    if numbers == segments {
+        return get_key(source, key);
+    }
+    let get_numbers = || {
+        source
+            .get_path(&numbers.iter().map(String::as_str).collect::<Vec<&str>>())
+            .map(Cow::into_owned)
+    };
+    match numeric_keys {
+        NumericKeys::NumberFirst => get_numbers().or_else(|| get_key(source, key)),
+        _ => get_key(source, key).or_else(get_numbers),
+    }
+}
+
+/// The usual form of a path segment that is a number, like "1" for "01" or
+/// "1.0", as JSON would write the number
+fn number_key(segment: &str) -> Option<String> {
+    if let Ok(int) = segment.parse::<i64>() {
+        return Some(int.to_string());
+    }
+    let number = segment.parse::<f64>().ok()?;
+    // Whole numbers this large can't be told apart from their neighbors, so
+    // they would find the key of a different number
+    if number.fract() == 0.0 && number.abs() >= 9_007_199_254_740_992.0 {
+        return None;
+    }
+    to_number_value(number)
+        .ok()
+        .map(|number| number.to_string())
+}
+
+fn get_key(source: &dyn DataSource, key: KeyType) -> Option<Value> {
+    let value = match key {
+        // If the key is null, we return the data, always, even if there
+        // is a default parameter.
+        KeyType::Null => source.get_path(&[]),
+        KeyType::String(k) => {
+            let segments = split_with_escape(&k, '.');
+            source.get_path(&segments.iter().map(String::as_str).collect::<Vec<&str>>())
+        }
+        KeyType::Number(i) => source.get_path(&[&i.to_string()]),
+    };
+    value.map(Cow::into_owned)
 }
 
 pub fn split_with_escape(input: &str, delimiter: char) -> Vec<String> {
@@ -253,44 +522,6 @@ pub fn split_with_escape(input: &str, delimiter: char) -> Vec<String> {
     result
 }
 
-fn get_str_key<K: AsRef<str>>(data: &Value, key: K) -> Option<Value> {
-    let k = key.as_ref();
-    if k == "" {
-        return Some(data.clone());
-    };
-    match data {
-        Value::Object(_) | Value::Array(_) | Value::String(_) => {
-            // Exterior ref in case we need to make a new value in the match.
-            split_with_escape(k, '.')
-                .into_iter()
-                .fold(Some(data.clone()), |acc, i| match acc? {
-                    // If the current value is an object, try to get the value
-                    Value::Object(map) => map.get(&i).map(Value::clone),
-                    // If the current value is an array, we need an integer
-                    // index. If integer conversion fails, return None.
-                    Value::Array(arr) => i
-                        .parse::<i64>()
-                        .ok()
-                        .and_then(|i| get(&arr, i))
-                        .map(Value::clone),
-                    // Same deal if it's a string.
-                    Value::String(s) => {
-                        let s_chars: Vec<char> = s.chars().collect();
-                        i.parse::<i64>()
-                            .ok()
-                            .and_then(|i| get(&s_chars, i))
-                            .map(|c| c.to_string())
-                            .map(Value::String)
-                    }
-                    // This handles cases where we've got an un-indexable
-                    // type or similar.
-                    _ => None,
-                })
-        }
-        _ => None,
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -318,6 +549,6 @@ mod tests {
     fn test_split_with_escape() {
         cases()
             .into_iter()
-            .for_each(|(input, exp)| assert_eq!(split_with_escape(&input, '.'), exp));
+            .for_each(|(input, exp)| assert_eq!(split_with_escape(input, '.'), exp));
     }
 }
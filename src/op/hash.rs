@@ -0,0 +1,113 @@
+//! Hashing Operations
+
+#[cfg(feature = "hashing")]
+use md5::Md5;
+#[cfg(feature = "hashing")]
+use phf::phf_map;
+use serde_json::Value;
+#[cfg(feature = "hashing")]
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh64::xxh64;
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::js_op;
+#[cfg(feature = "hashing")]
+use crate::op::{NumParams, Operator};
+
+/// Operators producing lowercase hex digests of a string's UTF-8 bytes
+#[cfg(feature = "hashing")]
+pub const HASH_OPERATOR_MAP: phf::Map<&'static str, Operator> = phf_map! {
+    "sha256" => Operator {
+        symbol: "sha256",
+        operator: sha256,
+        num_params: NumParams::Unary,
+    },
+    "md5" => Operator {
+        symbol: "md5",
+        operator: md5,
+        num_params: NumParams::Unary,
+    },
+    "xxh64" => Operator {
+        symbol: "xxh64",
+        operator: xxhash64,
+        num_params: NumParams::Unary,
+    },
+};
+
+/// Get the string to hash for a value
+///
+/// Numbers are hashed using their string representation. Other types are
+/// rejected, since hashing e.g. `null` would silently give every record
+/// with a missing key the same hash.
+fn hash_input(value: &Value, operation: &str) -> Result<String, Error> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(_) => Ok(js_op::to_string(value)),
+        _ => Err(Error::InvalidArgument {
+            value: value.clone(),
+            operation: operation.into(),
+            reason: format!(
+                "First argument to {} must be a string or a number",
+                operation
+            ),
+        }),
+    }
+}
+
+/// Deterministically assign a key to one of `n` buckets.
+///
+/// The key is hashed with 64-bit xxHash (seed 0) over its UTF-8 bytes,
+/// and the bucket is the hash modulo the number of buckets. Because
+/// xxHash is widely implemented, the same key lands in the same bucket
+/// in any language, which makes this suitable for percentage rollouts,
+/// e.g. "10% of users" as `{"<": [{"bucket": [{"var": "id"}, 100]}, 10]}`.
+pub fn bucket(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let (key_arg, buckets_arg) = (items[0], items[1]);
+
+    let key = hash_input(key_arg, "bucket")?;
+
+    let buckets = match buckets_arg {
+        Value::Number(n) => n.as_u64().filter(|n| *n > 0),
+        _ => None,
+    }
+    .ok_or_else(|| Error::InvalidArgument {
+        value: buckets_arg.clone(),
+        operation: "bucket".into(),
+        reason: "Second argument to bucket must be a positive integer".into(),
+    })?;
+
+    Ok(Value::from(xxh64(key.as_bytes(), 0) % buckets))
+}
+
+#[cfg(feature = "hashing")]
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Hash a value with SHA-256
+#[cfg(feature = "hashing")]
+pub fn sha256(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let input = hash_input(items[0], "sha256")?;
+    Ok(Value::String(to_hex(&Sha256::digest(input.as_bytes()))))
+}
+
+/// Hash a value with MD5
+///
+/// MD5 is not collision resistant, so this is only suitable for matching
+/// against existing MD5 digests, not for verifying untrusted data.
+#[cfg(feature = "hashing")]
+pub fn md5(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let input = hash_input(items[0], "md5")?;
+    Ok(Value::String(to_hex(&Md5::digest(input.as_bytes()))))
+}
+
+/// Hash a value with 64-bit xxHash (seed 0), as used by `bucket`
+#[cfg(feature = "hashing")]
+pub fn xxhash64(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let input = hash_input(items[0], "xxh64")?;
+    Ok(Value::String(format!(
+        "{:016x}",
+        xxh64(input.as_bytes(), 0)
+    )))
+}
@@ -2,14 +2,19 @@
 
 use serde_json::Value;
 
+use crate::context::Context;
 use crate::error::Error;
 
+/// Operators with side effects, which are rejected by engines configured
+/// with `pure_only`. Any operator added to this module must be listed here.
+pub const IMPURE_OPERATORS: &[&str] = &["log"];
+
 /// Log the Operation's Value(s)
 ///
 /// The reference implementation ignores any arguments beyond the first,
 /// and the specification seems to indicate that the first argument is
 /// the only one considered, so we're doing the same.
-pub fn log(items: &Vec<&Value>) -> Result<Value, Error> {
+pub fn log(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
     println!("{}", items[0]);
     Ok(items[0].clone())
 }
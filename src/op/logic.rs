@@ -2,6 +2,7 @@
 
 use serde_json::Value;
 
+use crate::context::Context;
 use crate::error::Error;
 use crate::value::{Evaluated, Parsed};
 use crate::NULL;
@@ -12,7 +13,7 @@ use crate::NULL;
 /// However, it can lso work like:
 ///     [condition, true, condition2, true2, false2]
 ///     for an if/elseif/else type of operation
-pub fn if_(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
+pub fn if_(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
     // Special case incorrect arguments. These are not defined in the
     // specification, but they are defined in the test cases.
     match args.len() {
@@ -24,33 +25,38 @@ pub fn if_(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
         // evaluates, but this is I can gather is the expected behavior
         // from the tests.
         1 => {
-            let parsed = Parsed::from_value(args[0])?;
-            let evaluated = parsed.evaluate(&data)?;
+            let parsed = Parsed::from_value(args[0], ctx.engine)?;
+            let evaluated = parsed.evaluate(data, ctx)?;
+            ctx.record_branch(0);
             return Ok(evaluated.into());
         }
         _ => {}
     }
 
-    args.into_iter()
+    args.iter()
         .enumerate()
         // Our accumulator is:
         //  - last conditional evaluation value,
         //  - whether that evaluation is truthy,
         //  - whether we know we should return without further evaluation
-        .fold(Ok((NULL, false, false)), |last_res, (i, val)| {
-            let (last_eval, was_truthy, should_return) = last_res?;
+        .try_fold((NULL, false, false), |last_res, (i, val)| {
+            let (last_eval, was_truthy, should_return) = last_res;
             // We hit a final value already
             if should_return {
                 Ok((last_eval, was_truthy, should_return))
             }
             // Potential false-value, initial evaluation, or else-if clause
             else if i % 2 == 0 {
-                let parsed = Parsed::from_value(val)?;
-                let eval = parsed.evaluate(data)?;
+                let parsed = Parsed::from_value(val, ctx.engine)?;
+                let eval = parsed.evaluate(data, ctx)?;
                 let is_truthy = match eval {
                     Evaluated::New(ref v) => truthy(v),
                     Evaluated::Raw(v) => truthy(v),
                 };
+                // The last argument is the result if no condition held
+                if i == args.len() - 1 {
+                    ctx.record_branch(i);
+                }
                 // We're not sure we're the return value, so don't
                 // force a return.
                 Ok((eval.into(), is_truthy, false))
@@ -60,8 +66,9 @@ pub fn if_(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
                 // If there was a previous evaluation and it was truthy,
                 // return, and indicate we're a final value.
                 if was_truthy {
-                    let parsed = Parsed::from_value(val)?;
-                    let t_eval = parsed.evaluate(data)?;
+                    let parsed = Parsed::from_value(val, ctx.engine)?;
+                    let t_eval = parsed.evaluate(data, ctx)?;
+                    ctx.record_branch(i);
                     Ok((Value::from(t_eval), true, true))
                 } else {
                     // Return a null for the last eval to handle cases
@@ -74,32 +81,32 @@ pub fn if_(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
 }
 
 /// Perform short-circuiting or evaluation
-pub fn or(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
+pub fn or(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
     enum OrResult {
         Uninitialized,
         Truthy(Value),
         Current(Value),
     }
 
-    let eval =
-        args.into_iter()
-            .fold(Ok(OrResult::Uninitialized), |last_res, current| {
-                let last_eval = last_res?;
-
-                // if we've found a truthy value, don't evaluate anything else
-                if let OrResult::Truthy(_) = last_eval {
-                    return Ok(last_eval);
-                }
+    let eval = args.iter().enumerate().try_fold(
+        OrResult::Uninitialized,
+        |last_eval, (i, current)| {
+            // if we've found a truthy value, don't evaluate anything else
+            if let OrResult::Truthy(_) = last_eval {
+                return Ok(last_eval);
+            }
 
-                let parsed = Parsed::from_value(current)?;
-                let evaluated = parsed.evaluate(data)?;
+            let parsed = Parsed::from_value(current, ctx.engine)?;
+            let evaluated = parsed.evaluate(data, ctx)?;
+            ctx.record_branch(i);
 
-                if truthy_from_evaluated(&evaluated) {
-                    return Ok(OrResult::Truthy(evaluated.into()));
-                }
+            if truthy_from_evaluated(&evaluated) {
+                return Ok(OrResult::Truthy(evaluated.into()));
+            }
 
-                Ok(OrResult::Current(evaluated.into()))
-            })?;
+            Ok::<OrResult, Error>(OrResult::Current(evaluated.into()))
+        },
+    )?;
 
     match eval {
         OrResult::Truthy(v) => Ok(v),
@@ -111,31 +118,31 @@ pub fn or(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
 }
 
 /// Perform short-circuiting and evaluation
-pub fn and(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
+pub fn and(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
     enum AndResult {
         Uninitialized,
         Falsey(Value),
         Current(Value),
     }
 
-    let eval =
-        args.into_iter()
-            .fold(Ok(AndResult::Uninitialized), |last_res, current| {
-                let last_eval = last_res?;
-
-                if let AndResult::Falsey(_) = last_eval {
-                    return Ok(last_eval);
-                }
+    let eval = args.iter().enumerate().try_fold(
+        AndResult::Uninitialized,
+        |last_eval, (i, current)| {
+            if let AndResult::Falsey(_) = last_eval {
+                return Ok(last_eval);
+            }
 
-                let parsed = Parsed::from_value(current)?;
-                let evaluated = parsed.evaluate(data)?;
+            let parsed = Parsed::from_value(current, ctx.engine)?;
+            let evaluated = parsed.evaluate(data, ctx)?;
+            ctx.record_branch(i);
 
-                if !truthy_from_evaluated(&evaluated) {
-                    return Ok(AndResult::Falsey(evaluated.into()));
-                }
+            if !truthy_from_evaluated(&evaluated) {
+                return Ok(AndResult::Falsey(evaluated.into()));
+            }
 
-                Ok(AndResult::Current(evaluated.into()))
-            })?;
+            Ok::<AndResult, Error>(AndResult::Current(evaluated.into()))
+        },
+    )?;
 
     match eval {
         AndResult::Falsey(v) => Ok(v),
@@ -146,6 +153,73 @@ pub fn and(data: &Value, args: &Vec<&Value>) -> Result<Value, Error> {
     }
 }
 
+/// Evaluate expressions in order, returning the value of the last
+///
+/// Every expression is evaluated, even if an earlier one is falsey, so this
+/// sequences operators with side effects, like `log`.
+pub fn seq(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    args.iter().try_fold(NULL, |_, arg| {
+        let parsed = Parsed::from_value(arg, ctx.engine)?;
+        Ok(parsed.evaluate(data, ctx)?.into())
+    })
+}
+
+/// Evaluate an expression, recovering from any error it raises
+///
+/// If the first expression fails, the second is evaluated instead, or, if
+/// there isn't one, the error is returned as JSON per `Error::to_json()`.
+pub fn try_(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let attempt = Parsed::from_value(args[0], ctx.engine)
+        .and_then(|parsed| Ok(parsed.evaluate(data, ctx)?.into()));
+    match (attempt, args.get(1)) {
+        (Ok(result), _) => Ok(result),
+        (Err(_), Some(fallback)) => {
+            let parsed = Parsed::from_value(fallback, ctx.engine)?;
+            Ok(parsed.evaluate(data, ctx)?.into())
+        }
+        (Err(err), None) => Ok(err.to_json()),
+    }
+}
+
+/// Render an evaluated message for a raised error
+fn raised_message(message: &Value) -> String {
+    match message {
+        Value::String(message) => message.clone(),
+        message => message.to_string(),
+    }
+}
+
+/// Raise an error with a message
+pub fn throw(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    Err(Error::RuleRaised {
+        operator: "throw".into(),
+        message: raised_message(items[0]),
+    })
+}
+
+/// Raise an error with a message if a condition is falsey
+///
+/// Otherwise, the condition's value is returned. The message is only
+/// evaluated if the assertion fails.
+pub fn assert(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let parsed = Parsed::from_value(args[0], ctx.engine)?;
+    let condition = parsed.evaluate(data, ctx)?;
+    if truthy_from_evaluated(&condition) {
+        return Ok(condition.into());
+    }
+    let message = match args.get(1) {
+        Some(message) => {
+            let parsed = Parsed::from_value(message, ctx.engine)?;
+            raised_message(&parsed.evaluate(data, ctx)?.into())
+        }
+        None => format!("Assertion failed: {}", args[0]),
+    };
+    Err(Error::RuleRaised {
+        operator: "assert".into(),
+        message,
+    })
+}
+
 pub fn truthy_from_evaluated(evaluated: &Evaluated) -> bool {
     match evaluated {
         Evaluated::New(ref v) => truthy(v),
@@ -167,24 +241,9 @@ pub fn truthy(val: &Value) -> bool {
     match val {
         Value::Null => false,
         Value::Bool(v) => *v,
-        Value::Number(v) => v
-            .as_f64()
-            .map(|v_num| if v_num == 0.0 { false } else { true })
-            .unwrap_or(false),
-        Value::String(v) => {
-            if v == "" {
-                false
-            } else {
-                true
-            }
-        }
-        Value::Array(v) => {
-            if v.len() == 0 {
-                false
-            } else {
-                true
-            }
-        }
+        Value::Number(v) => v.as_f64().map(|v_num| v_num != 0.0).unwrap_or(false),
+        Value::String(v) => !v.is_empty(),
+        Value::Array(v) => !v.is_empty(),
         Value::Object(_) => true,
     }
 }
@@ -209,7 +268,7 @@ mod test_truthy {
 
         let falses = [json!(false), json!([]), json!(""), json!(0), json!(null)];
 
-        trues.iter().for_each(|v| assert!(truthy(&v)));
-        falses.iter().for_each(|v| assert!(!truthy(&v)));
+        trues.iter().for_each(|v| assert!(truthy(v)));
+        falses.iter().for_each(|v| assert!(!truthy(v)));
     }
 }
@@ -0,0 +1,326 @@
+//! Unit-aware Operations
+//!
+//! Quantities are strings like "5kg", "12 lb", or "2h30m", which are
+//! converted to a common base unit for their dimension before being
+//! compared. Only quantities of the same dimension can be compared.
+
+use phf::phf_map;
+use serde_json::Value;
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::op::{NumParams, Operator};
+
+pub const MEASURE_OPERATOR_MAP: phf::Map<&'static str, Operator> = phf_map! {
+    "measure" => Operator {
+        symbol: "measure",
+        operator: measure,
+        num_params: NumParams::Exactly(2),
+    },
+    "measure_eq" => Operator {
+        symbol: "measure_eq",
+        operator: measure_eq,
+        num_params: NumParams::AtLeast(2),
+    },
+    "measure_lt" => Operator {
+        symbol: "measure_lt",
+        operator: measure_lt,
+        num_params: NumParams::AtLeast(2),
+    },
+    "measure_lte" => Operator {
+        symbol: "measure_lte",
+        operator: measure_lte,
+        num_params: NumParams::AtLeast(2),
+    },
+    "measure_gt" => Operator {
+        symbol: "measure_gt",
+        operator: measure_gt,
+        num_params: NumParams::AtLeast(2),
+    },
+    "measure_gte" => Operator {
+        symbol: "measure_gte",
+        operator: measure_gte,
+        num_params: NumParams::AtLeast(2),
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Mass,
+    Length,
+    Volume,
+    Duration,
+}
+
+/// A unit: its dimension and its size in the dimension's base unit
+#[derive(Debug, Clone, Copy)]
+struct Unit {
+    dimension: Dimension,
+    factor: f64,
+}
+
+const fn unit(dimension: Dimension, factor: f64) -> Unit {
+    Unit { dimension, factor }
+}
+
+/// Units by their lowercase symbols. Base units are grams, metres, litres,
+/// and seconds. Imperial units are the international (and, for gallons,
+/// US) definitions.
+const UNITS: phf::Map<&'static str, Unit> = phf_map! {
+    "mg" => unit(Dimension::Mass, 0.001),
+    "g" => unit(Dimension::Mass, 1.0),
+    "kg" => unit(Dimension::Mass, 1_000.0),
+    "t" => unit(Dimension::Mass, 1_000_000.0),
+    "oz" => unit(Dimension::Mass, 28.349_523_125),
+    "lb" => unit(Dimension::Mass, 453.592_37),
+    "lbs" => unit(Dimension::Mass, 453.592_37),
+    "mm" => unit(Dimension::Length, 0.001),
+    "cm" => unit(Dimension::Length, 0.01),
+    "m" => unit(Dimension::Length, 1.0),
+    "km" => unit(Dimension::Length, 1_000.0),
+    "in" => unit(Dimension::Length, 0.0254),
+    "ft" => unit(Dimension::Length, 0.3048),
+    "yd" => unit(Dimension::Length, 0.9144),
+    "mi" => unit(Dimension::Length, 1_609.344),
+    "ml" => unit(Dimension::Volume, 0.001),
+    "cl" => unit(Dimension::Volume, 0.01),
+    "l" => unit(Dimension::Volume, 1.0),
+    "gal" => unit(Dimension::Volume, 3.785_411_784),
+    "ms" => unit(Dimension::Duration, 0.001),
+    "s" => unit(Dimension::Duration, 1.0),
+    "sec" => unit(Dimension::Duration, 1.0),
+    "min" => unit(Dimension::Duration, 60.0),
+    "h" => unit(Dimension::Duration, 3_600.0),
+    "hr" => unit(Dimension::Duration, 3_600.0),
+    "d" => unit(Dimension::Duration, 86_400.0),
+    "w" => unit(Dimension::Duration, 604_800.0),
+};
+
+/// Minutes, for "m" in a compound duration like "2h30m"
+const MINUTE: Unit = unit(Dimension::Duration, 60.0);
+
+/// A quantity in the base unit of its dimension
+#[derive(Debug, Clone, Copy)]
+struct Quantity {
+    dimension: Dimension,
+    base: f64,
+}
+
+fn invalid(value: &Value, operation: &str, reason: String) -> Error {
+    Error::InvalidArgument {
+        value: value.clone(),
+        operation: operation.into(),
+        reason,
+    }
+}
+
+fn lookup_unit(symbol: &str) -> Option<Unit> {
+    UNITS.get(symbol.to_lowercase().as_str()).copied()
+}
+
+/// Split a quantity string into its (number, unit) components
+fn components(string: &str) -> Option<Vec<(f64, &str)>> {
+    let mut rest = string.trim();
+    let mut components = Vec::new();
+    while !rest.is_empty() {
+        let signed = components.is_empty() && rest.starts_with(['-', '+']);
+        let number_len = rest
+            .char_indices()
+            .find(|&(idx, c)| !(c.is_ascii_digit() || c == '.' || (signed && idx == 0)))
+            .map_or(rest.len(), |(idx, _)| idx);
+        let number = rest[..number_len].parse::<f64>().ok()?;
+        rest = rest[number_len..].trim_start();
+        let unit_len = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        if unit_len == 0 {
+            return None;
+        }
+        components.push((number, &rest[..unit_len]));
+        rest = rest[unit_len..].trim_start();
+    }
+    match components.is_empty() {
+        true => None,
+        false => Some(components),
+    }
+}
+
+/// Parse a quantity like "5kg" or "2h30m"
+///
+/// A compound quantity sums its components, which must all share a
+/// dimension. In a compound duration, "m" means minutes rather than metres.
+fn parse_quantity(value: &Value, operation: &str) -> Result<Quantity, Error> {
+    let string = match value {
+        Value::String(s) => s,
+        _ => {
+            return Err(invalid(
+                value,
+                operation,
+                format!(
+                    "Arguments to {} must be quantity strings like \"5kg\"",
+                    operation
+                ),
+            ))
+        }
+    };
+    let components = components(string).ok_or_else(|| {
+        invalid(
+            value,
+            operation,
+            format!("Could not parse quantity {:?}", string),
+        )
+    })?;
+    let units = components
+        .iter()
+        .map(|(_, symbol)| {
+            lookup_unit(symbol).ok_or_else(|| {
+                invalid(value, operation, format!("Unknown unit {:?}", symbol))
+            })
+        })
+        .collect::<Result<Vec<Unit>, Error>>()?;
+    let compound_duration =
+        units.len() > 1 && units.iter().any(|u| u.dimension == Dimension::Duration);
+
+    let mut quantity: Option<Quantity> = None;
+    for ((number, symbol), unit) in components.iter().zip(units) {
+        let unit = match compound_duration && symbol.eq_ignore_ascii_case("m") {
+            true => MINUTE,
+            false => unit,
+        };
+        quantity = match quantity {
+            None => Some(Quantity {
+                dimension: unit.dimension,
+                base: number * unit.factor,
+            }),
+            Some(q) if q.dimension == unit.dimension => Some(Quantity {
+                dimension: q.dimension,
+                base: q.base + number * unit.factor,
+            }),
+            Some(_) => {
+                return Err(invalid(
+                    value,
+                    operation,
+                    format!("Quantity {:?} mixes units of different kinds", string),
+                ))
+            }
+        };
+    }
+    quantity.ok_or_else(|| {
+        invalid(
+            value,
+            operation,
+            format!("Could not parse quantity {:?}", string),
+        )
+    })
+}
+
+/// Whether two base quantities are equal, allowing for rounding in unit
+/// conversion (e.g. 12in is 0.30479999999999996m)
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * a.abs().max(b.abs())
+}
+
+/// Convert a quantity to a number of the given unit: `[quantity, unit]`
+pub fn measure(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let quantity = parse_quantity(items[0], "measure")?;
+    let unit = match items[1] {
+        Value::String(s) => lookup_unit(s),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        invalid(
+            items[1],
+            "measure",
+            "Second argument to measure must be a known unit".into(),
+        )
+    })?;
+    if unit.dimension != quantity.dimension {
+        return Err(invalid(
+            items[1],
+            "measure",
+            format!("Cannot convert {} to {}", items[0], items[1]),
+        ));
+    }
+    ctx.engine
+        .number_value("measure", quantity.base / unit.factor, items)
+}
+
+/// Check that `func` holds for each adjacent pair of quantities
+fn compare<F>(operation: &str, items: &[&Value], func: F) -> Result<Value, Error>
+where
+    F: Fn(f64, f64) -> bool,
+{
+    let quantities = items
+        .iter()
+        .map(|item| parse_quantity(item, operation))
+        .collect::<Result<Vec<Quantity>, Error>>()?;
+    if let Some(idx) = quantities
+        .windows(2)
+        .position(|pair| pair[0].dimension != pair[1].dimension)
+    {
+        return Err(invalid(
+            items[idx + 1],
+            operation,
+            format!("Cannot compare {} with {}", items[idx], items[idx + 1]),
+        ));
+    }
+    Ok(Value::Bool(
+        quantities
+            .windows(2)
+            .all(|pair| func(pair[0].base, pair[1].base)),
+    ))
+}
+
+/// Check quantities are all equal
+pub fn measure_eq(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    compare("measure_eq", items, approx_eq)
+}
+
+/// Check quantities are in increasing order
+pub fn measure_lt(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    compare("measure_lt", items, |a, b| a < b && !approx_eq(a, b))
+}
+
+/// Check quantities are in non-decreasing order
+pub fn measure_lte(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    compare("measure_lte", items, |a, b| a < b || approx_eq(a, b))
+}
+
+/// Check quantities are in decreasing order
+pub fn measure_gt(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    compare("measure_gt", items, |a, b| a > b && !approx_eq(a, b))
+}
+
+/// Check quantities are in non-increasing order
+pub fn measure_gte(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    compare("measure_gte", items, |a, b| a > b || approx_eq(a, b))
+}
+
+#[cfg(test)]
+mod test_measure {
+    use super::*;
+    use serde_json::json;
+
+    fn base(string: &str) -> Option<f64> {
+        parse_quantity(&json!(string), "measure")
+            .ok()
+            .map(|q| q.base)
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(base("5kg"), Some(5_000.0));
+        assert_eq!(base("5 KG"), Some(5_000.0));
+        assert_eq!(base("-1.5m"), Some(-1.5));
+        assert_eq!(base("30m"), Some(30.0));
+        assert_eq!(base("2h30m"), Some(9_000.0));
+        assert_eq!(base("1h 30m 15s"), Some(5_415.0));
+        assert_eq!(base("5ft 2in"), Some(5.0 * 0.3048 + 2.0 * 0.0254));
+        assert_eq!(base("5kg3m"), None);
+        assert_eq!(base("5"), None);
+        assert_eq!(base("kg"), None);
+        assert_eq!(base("5 parsecs"), None);
+        assert_eq!(base("1-2kg"), None);
+        assert_eq!(base(""), None);
+    }
+}
@@ -11,66 +11,87 @@
 
 use phf::phf_map;
 use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::sync::Arc;
 
+use crate::context::Context;
+use crate::engine::{Compatibility, Engine};
 use crate::error::Error;
-use crate::value::to_number_value;
 use crate::value::{Evaluated, Parsed};
-use crate::{js_op, Parser};
+use crate::Parser;
 
 mod array;
+mod checksum;
 mod data;
+mod hash;
 mod impure;
 mod logic;
+#[cfg(feature = "measure")]
+mod measure;
+#[cfg(feature = "money")]
+mod money;
+#[cfg(feature = "net")]
+mod net;
 mod numeric;
+#[cfg(feature = "regions")]
+mod region;
 mod string;
 
+pub(crate) use array::Stream;
+pub use data::split_with_escape;
+pub use logic::truthy;
+#[cfg(feature = "money")]
+pub use money::ExchangeRates;
+
 pub const OPERATOR_MAP: phf::Map<&'static str, Operator> = phf_map! {
     "==" => Operator {
         symbol: "==",
-        operator: |items| Ok(Value::Bool(js_op::abstract_eq(items[0], items[1]))),
+        operator: numeric::eq,
         num_params: NumParams::Exactly(2)},
     "!=" => Operator {
         symbol: "!=",
-        operator: |items| Ok(Value::Bool(js_op::abstract_ne(items[0], items[1]))),
+        operator: numeric::ne,
         num_params: NumParams::Exactly(2)},
     "===" => Operator {
         symbol: "===",
-        operator: |items| Ok(Value::Bool(js_op::strict_eq(items[0], items[1]))),
+        operator: |ctx, items| Ok(Value::Bool(ctx.engine.comparator.strict_equal(items[0], items[1]))),
         num_params: NumParams::Exactly(2)},
     "!==" => Operator {
         symbol: "!==",
-        operator: |items| Ok(Value::Bool(js_op::strict_ne(items[0], items[1]))),
+        operator: |ctx, items| Ok(Value::Bool(!ctx.engine.comparator.strict_equal(items[0], items[1]))),
         num_params: NumParams::Exactly(2)},
     // Note: the ! and !! behavior conforms to the specification, but not the
     // reference implementation. The specification states: "Note: unary
     // operators can also take a single, non array argument." However,
     // if a non-unary array of arguments is passed to `!` or `!!` in the
-    // reference implementation, it treats them as though they were a unary
-    // array argument. I have chosen to conform to the spec because it leads
-    // to less surprising behavior. I also think that the idea of taking
-    // non-array unary arguments is ridiculous, particularly given that
-    // the homepage of jsonlogic _also_ states that a "Virtue" of jsonlogic
-    // is that it is "Consistent. `{"operator" : ["values" ... ]}` Always"
+    // reference implementation, it evaluates them all and then applies the
+    // operator to the first one alone. I have chosen to conform to the spec
+    // because it leads to less surprising behavior. I also think that the
+    // idea of taking non-array unary arguments is ridiculous, particularly
+    // given that the homepage of jsonlogic _also_ states that a "Virtue" of
+    // jsonlogic is that it is "Consistent. `{"operator" : ["values" ... ]}`
+    // Always". `Compatibility::Reference` follows the reference
+    // implementation instead (see `op_from_map()`).
     "!" => Operator {
         symbol: "!",
-        operator: |items| Ok(Value::Bool(!logic::truthy(items[0]))),
+        operator: |_, items| Ok(Value::Bool(!logic::truthy(items[0]))),
         num_params: NumParams::Unary,
     },
     "!!" => Operator {
         symbol: "!!",
-        operator: |items| Ok(Value::Bool(logic::truthy(items[0]))),
+        operator: |_, items| Ok(Value::Bool(logic::truthy(items[0]))),
         num_params: NumParams::Unary,
     },
     "<" => Operator {
         symbol: "<",
         operator: numeric::lt,
-        num_params: NumParams::Variadic(2..4),
+        num_params: NumParams::AtLeast(2),
     },
     "<=" => Operator {
         symbol: "<=",
         operator: numeric::lte,
-        num_params: NumParams::Variadic(2..4),
+        num_params: NumParams::AtLeast(2),
     },
     // Note: this is actually an _expansion_ on the specification and the
     // reference implementation. The spec states that < and <= can be used
@@ -78,20 +99,21 @@ pub const OPERATOR_MAP: phf::Map<&'static str, Operator> = phf_map! {
     // e.g. `1 < 2 < 3 == true`. However, this isn't explicitly supported
     // for > and >=, and the reference implementation simply ignores any
     // third value for these operators. This to me violates the principle
-    // of least surprise, so we do support those operations.
+    // of least surprise, so we do support those operations. All four
+    // comparisons also extend the chain to any number of arguments.
     ">" => Operator {
         symbol: ">",
         operator: numeric::gt,
-        num_params: NumParams::Variadic(2..4),
+        num_params: NumParams::AtLeast(2),
     },
     ">=" => Operator {
         symbol: ">=",
         operator: numeric::gte,
-        num_params: NumParams::Variadic(2..4),
+        num_params: NumParams::AtLeast(2),
     },
     "+" => Operator {
         symbol: "+",
-        operator: |items| js_op::parse_float_add(items).and_then(to_number_value),
+        operator: numeric::plus,
         num_params: NumParams::Any,
     },
     "-" => Operator {
@@ -101,66 +123,188 @@ pub const OPERATOR_MAP: phf::Map<&'static str, Operator> = phf_map! {
     },
     "*" => Operator {
         symbol: "*",
-        operator: |items| js_op::parse_float_mul(items).and_then(to_number_value),
+        operator: numeric::mul,
         num_params: NumParams::AtLeast(1),
     },
     "/" => Operator {
         symbol: "/",
-        operator: |items| js_op::abstract_div(items[0], items[1])
-            .and_then(to_number_value),
+        operator: numeric::div,
         num_params: NumParams::Exactly(2),
     },
     "%" => Operator {
         symbol: "%",
-        operator: |items| js_op::abstract_mod(items[0], items[1])
-            .and_then(to_number_value),
+        operator: numeric::modulo,
         num_params: NumParams::Exactly(2),
     },
     "max" => Operator {
         symbol: "max",
-        operator: |items| js_op::abstract_max(items)
-            .and_then(to_number_value),
+        operator: numeric::max,
         num_params: NumParams::AtLeast(1),
     },
     "min" => Operator {
         symbol: "min",
-        operator: |items| js_op::abstract_min(items)
-            .and_then(to_number_value),
+        operator: numeric::min,
         num_params: NumParams::AtLeast(1),
     },
+    "toNumber" => Operator {
+        symbol: "toNumber",
+        operator: numeric::to_number,
+        num_params: NumParams::Unary,
+    },
+    "toString" => Operator {
+        symbol: "toString",
+        operator: string::to_string,
+        num_params: NumParams::Unary,
+    },
     "merge" => Operator {
         symbol: "merge",
         operator: array::merge,
         num_params: NumParams::Any,
     },
-    "in" => Operator {
-        symbol: "in",
-        operator: array::in_,
+    "flatten" => Operator {
+        symbol: "flatten",
+        operator: array::flatten,
+        num_params: NumParams::Variadic(1..3),
+    },
+    "is_sorted" => Operator {
+        symbol: "is_sorted",
+        operator: array::is_sorted,
+        num_params: NumParams::Variadic(1..3),
+    },
+    "all_distinct" => Operator {
+        symbol: "all_distinct",
+        operator: array::all_distinct,
+        num_params: NumParams::Unary,
+    },
+    "contains_all" => Operator {
+        symbol: "contains_all",
+        operator: array::contains_all,
         num_params: NumParams::Exactly(2),
     },
-    "cat" => Operator {
-        symbol: "cat",
-        operator: string::cat,
-        num_params: NumParams::Any,
+    "contains_any" => Operator {
+        symbol: "contains_any",
+        operator: array::contains_any,
+        num_params: NumParams::Exactly(2),
     },
     "substr" => Operator {
         symbol: "substr",
         operator: string::substr,
         num_params: NumParams::Variadic(2..4),
     },
+    "startsWith" => Operator {
+        symbol: "startsWith",
+        operator: string::starts_with,
+        num_params: NumParams::Exactly(2),
+    },
+    "endsWith" => Operator {
+        symbol: "endsWith",
+        operator: string::ends_with,
+        num_params: NumParams::Exactly(2),
+    },
+    "contains" => Operator {
+        symbol: "contains",
+        operator: string::contains,
+        num_params: NumParams::Exactly(2),
+    },
+    "==i" => Operator {
+        symbol: "==i",
+        operator: string::eq_i,
+        num_params: NumParams::Exactly(2),
+    },
+    "!=i" => Operator {
+        symbol: "!=i",
+        operator: string::ne_i,
+        num_params: NumParams::Exactly(2),
+    },
+    "in_i" => Operator {
+        symbol: "in_i",
+        operator: string::in_i,
+        num_params: NumParams::Exactly(2),
+    },
+    "throw" => Operator {
+        symbol: "throw",
+        operator: logic::throw,
+        num_params: NumParams::Unary,
+    },
     "log" => Operator {
         symbol: "log",
         operator: impure::log,
         num_params: NumParams::Unary,
     },
+    "get" => Operator {
+        symbol: "get",
+        operator: data::get_property,
+        num_params: NumParams::Variadic(2..4),
+    },
+    "bucket" => Operator {
+        symbol: "bucket",
+        operator: hash::bucket,
+        num_params: NumParams::Exactly(2),
+    },
+    "luhn_valid" => Operator {
+        symbol: "luhn_valid",
+        operator: checksum::luhn_valid,
+        num_params: NumParams::Unary,
+    },
+    "checksum" => Operator {
+        symbol: "checksum",
+        operator: checksum::checksum,
+        num_params: NumParams::Exactly(2),
+    },
 };
 
+/// The eagerly evaluated operators: the core set, plus those enabled by
+/// optional features
+const OPERATOR_MAPS: &[&phf::Map<&'static str, Operator>] = &[
+    &OPERATOR_MAP,
+    #[cfg(feature = "measure")]
+    &measure::MEASURE_OPERATOR_MAP,
+    #[cfg(feature = "money")]
+    &money::MONEY_OPERATOR_MAP,
+    #[cfg(feature = "net")]
+    &net::NET_OPERATOR_MAP,
+    #[cfg(feature = "regions")]
+    &region::REGION_OPERATOR_MAP,
+    #[cfg(feature = "hashing")]
+    &hash::HASH_OPERATOR_MAP,
+];
+
+/// The operators enabled by each optional feature, by the feature's name
+pub(crate) const FEATURE_OPERATOR_MAPS: &[(&str, &phf::Map<&'static str, Operator>)] =
+    &[
+        #[cfg(feature = "measure")]
+        ("measure", &measure::MEASURE_OPERATOR_MAP),
+        #[cfg(feature = "money")]
+        ("money", &money::MONEY_OPERATOR_MAP),
+        #[cfg(feature = "net")]
+        ("net", &net::NET_OPERATOR_MAP),
+        #[cfg(feature = "regions")]
+        ("regions", &region::REGION_OPERATOR_MAP),
+        #[cfg(feature = "hashing")]
+        ("hashing", &hash::HASH_OPERATOR_MAP),
+    ];
+
 pub const DATA_OPERATOR_MAP: phf::Map<&'static str, DataOperator> = phf_map! {
     "var" => DataOperator {
         symbol: "var",
         operator: data::var,
         num_params: NumParams::Variadic(0..3)
     },
+    "var_str" => DataOperator {
+        symbol: "var_str",
+        operator: data::var_str,
+        num_params: NumParams::Variadic(0..3)
+    },
+    "var_num" => DataOperator {
+        symbol: "var_num",
+        operator: data::var_num,
+        num_params: NumParams::Variadic(0..3)
+    },
+    "var_bool" => DataOperator {
+        symbol: "var_bool",
+        operator: data::var_bool,
+        num_params: NumParams::Variadic(0..3)
+    },
     "missing" => DataOperator {
         symbol: "missing",
         operator: data::missing,
@@ -171,6 +315,11 @@ pub const DATA_OPERATOR_MAP: phf::Map<&'static str, DataOperator> = phf_map! {
         operator: data::missing_some,
         num_params: NumParams::Exactly(2),
     },
+    "require" => DataOperator {
+        symbol: "require",
+        operator: data::require,
+        num_params: NumParams::Variadic(1..3),
+    },
 };
 
 pub const LAZY_OPERATOR_MAP: phf::Map<&'static str, LazyOperator> = phf_map! {
@@ -180,13 +329,6 @@ pub const LAZY_OPERATOR_MAP: phf::Map<&'static str, LazyOperator> = phf_map! {
         operator: logic::if_,
         num_params: NumParams::Any,
     },
-    // Note this operator isn't defined in the specification, but is
-    // present in the tests as what looks like an alias for "if".
-    "?:" => LazyOperator {
-        symbol: "?:",
-        operator: logic::if_,
-        num_params: NumParams::Any,
-    },
     "or" => LazyOperator {
         symbol: "or",
         operator: logic::or,
@@ -197,6 +339,21 @@ pub const LAZY_OPERATOR_MAP: phf::Map<&'static str, LazyOperator> = phf_map! {
         operator: logic::and,
         num_params: NumParams::AtLeast(1),
     },
+    "seq" => LazyOperator {
+        symbol: "seq",
+        operator: logic::seq,
+        num_params: NumParams::AtLeast(1),
+    },
+    "try" => LazyOperator {
+        symbol: "try",
+        operator: logic::try_,
+        num_params: NumParams::Variadic(1..3),
+    },
+    "assert" => LazyOperator {
+        symbol: "assert",
+        operator: logic::assert,
+        num_params: NumParams::Variadic(1..3),
+    },
     "map" => LazyOperator {
         symbol: "map",
         operator: array::map,
@@ -227,16 +384,123 @@ pub const LAZY_OPERATOR_MAP: phf::Map<&'static str, LazyOperator> = phf_map! {
         operator: array::none,
         num_params: NumParams::Exactly(2),
     },
+    "max_by" => LazyOperator {
+        symbol: "max_by",
+        operator: array::max_by,
+        num_params: NumParams::Exactly(2),
+    },
+    "min_by" => LazyOperator {
+        symbol: "min_by",
+        operator: array::min_by,
+        num_params: NumParams::Exactly(2),
+    },
+    "take" => LazyOperator {
+        symbol: "take",
+        operator: array::take,
+        num_params: NumParams::Exactly(2),
+    },
+    "take_while" => LazyOperator {
+        symbol: "take_while",
+        operator: array::take_while,
+        num_params: NumParams::Exactly(2),
+    },
+    "first" => LazyOperator {
+        symbol: "first",
+        operator: array::first,
+        num_params: NumParams::Exactly(2),
+    },
+    "in" => LazyOperator {
+        symbol: "in",
+        operator: array::in_,
+        num_params: NumParams::Exactly(2),
+    },
+    "cat" => LazyOperator {
+        symbol: "cat",
+        operator: string::cat,
+        num_params: NumParams::Any,
+    },
+};
+
+/// Alternative names for operators
+///
+/// An alias resolves to its target in whichever operator map contains it,
+/// so it takes the same arguments, optionally negating the result.
+pub const ALIAS_MAP: phf::Map<&'static str, Alias> = phf_map! {
+    // Note this operator isn't defined in the specification, but is
+    // present in the tests as what looks like an alias for "if".
+    "?:" => Alias {
+        symbol: "?:",
+        target: "if",
+        negated: false,
+        deprecated: false,
+    },
+    "not_in" => Alias {
+        symbol: "not_in",
+        target: "in",
+        negated: true,
+        deprecated: false,
+    },
+    "not_in_i" => Alias {
+        symbol: "not_in_i",
+        target: "in_i",
+        negated: true,
+        deprecated: false,
+    },
 };
 
-#[derive(Debug, Clone)]
+/// The longest argument list included in error messages before truncation
+const MAX_SNIPPET_LEN: usize = 80;
+
+/// Render arguments as JSON for an error message, truncated if long
+fn args_snippet(args: &[&Value]) -> String {
+    let rendered =
+        Value::Array(args.iter().map(|v| (*v).clone()).collect()).to_string();
+    match rendered.char_indices().nth(MAX_SNIPPET_LEN) {
+        Some((idx, _)) => format!("{}...", &rendered[..idx]),
+        None => rendered,
+    }
+}
+
+/// The number of arguments an operator accepts
+///
+/// This is reported by [`Error::WrongArgumentCount`] when an operation is
+/// given a different number.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum NumParams {
     None,
     Any,
+    /// Exactly one, which may be given without wrapping it in an array
     Unary,
     Exactly(usize),
     AtLeast(usize),
     Variadic(std::ops::Range<usize>), // [inclusive, exclusive)
+    /// Any of a set of counts, for operators overloaded by arity
+    OneOf(Vec<usize>),
+}
+impl fmt::Display for NumParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "no arguments"),
+            Self::Any => write!(f, "any number of arguments"),
+            Self::Unary => write!(f, "exactly 1"),
+            Self::Exactly(num) => write!(f, "exactly {}", num),
+            Self::AtLeast(num) => write!(f, "at least {}", num),
+            Self::Variadic(range) => {
+                write!(f, "between {} and {}", range.start, range.end - 1)
+            }
+            Self::OneOf(nums) => {
+                let nums: Vec<String> = nums.iter().map(usize::to_string).collect();
+                match nums.split_last() {
+                    Some((last, [])) => write!(f, "exactly {}", last),
+                    Some((last, [first])) => write!(f, "exactly {} or {}", first, last),
+                    Some((last, rest)) => {
+                        write!(f, "exactly {}, or {}", rest.join(", "), last)
+                    }
+                    None => write!(f, "no arguments"),
+                }
+            }
+        }
+    }
 }
 impl NumParams {
     fn is_valid_len(&self, len: &usize) -> bool {
@@ -247,14 +511,21 @@ impl NumParams {
             Self::AtLeast(num) => len >= num,
             Self::Exactly(num) => len == num,
             Self::Variadic(range) => range.contains(len),
+            Self::OneOf(nums) => nums.contains(len),
         }
     }
-    fn check_len<'a>(&self, len: &'a usize) -> Result<&'a usize, Error> {
-        match self.is_valid_len(len) {
-            true => Ok(len),
+    fn check_len<'a>(
+        &self,
+        symbol: &str,
+        args: &'a [&Value],
+    ) -> Result<&'a [&'a Value], Error> {
+        match self.is_valid_len(&args.len()) {
+            true => Ok(args),
             false => Err(Error::WrongArgumentCount {
+                operator: symbol.into(),
                 expected: self.clone(),
-                actual: len.clone(),
+                actual: args.len(),
+                arguments: args_snippet(args),
             }),
         }
     }
@@ -266,6 +537,7 @@ impl NumParams {
             Self::AtLeast(num) => num >= &1,
             Self::Exactly(num) => num == &1,
             Self::Variadic(range) => range.contains(&1),
+            Self::OneOf(nums) => nums.contains(&1),
         }
     }
 }
@@ -273,6 +545,11 @@ impl NumParams {
 trait CommonOperator {
     fn param_info(&self) -> &NumParams;
 }
+impl<T: CommonOperator> CommonOperator for Arc<T> {
+    fn param_info(&self) -> &NumParams {
+        self.as_ref().param_info()
+    }
+}
 
 pub struct Operator {
     symbol: &'static str,
@@ -280,8 +557,8 @@ pub struct Operator {
     num_params: NumParams,
 }
 impl Operator {
-    pub fn execute(&self, items: &Vec<&Value>) -> Result<Value, Error> {
-        (self.operator)(items)
+    pub fn execute(&self, ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+        (self.operator)(ctx, items)
     }
 }
 impl CommonOperator for Operator {
@@ -304,8 +581,13 @@ pub struct LazyOperator {
     num_params: NumParams,
 }
 impl LazyOperator {
-    pub fn execute(&self, data: &Value, items: &Vec<&Value>) -> Result<Value, Error> {
-        (self.operator)(data, items)
+    pub fn execute(
+        &self,
+        ctx: &Context,
+        data: &Value,
+        items: &[&Value],
+    ) -> Result<Value, Error> {
+        (self.operator)(ctx, data, items)
     }
 }
 impl CommonOperator for LazyOperator {
@@ -333,8 +615,13 @@ pub struct DataOperator {
     num_params: NumParams,
 }
 impl DataOperator {
-    pub fn execute(&self, data: &Value, items: &Vec<&Value>) -> Result<Value, Error> {
-        (self.operator)(data, items)
+    pub fn execute(
+        &self,
+        ctx: &Context,
+        data: &Value,
+        items: &[&Value],
+    ) -> Result<Value, Error> {
+        (self.operator)(ctx, data, items)
     }
 }
 impl CommonOperator for DataOperator {
@@ -351,43 +638,151 @@ impl fmt::Debug for DataOperator {
     }
 }
 
-type OperatorFn = fn(&Vec<&Value>) -> Result<Value, Error>;
-type LazyOperatorFn = fn(&Value, &Vec<&Value>) -> Result<Value, Error>;
-type DataOperatorFn = fn(&Value, &Vec<&Value>) -> Result<Value, Error>;
+/// An operator provided by the user rather than by this crate
+///
+/// Custom operators are registered on an [`Engine`](crate::Engine). Like
+/// regular operators, their arguments are evaluated before being passed in.
+#[derive(Clone)]
+pub struct CustomOperator {
+    symbol: String,
+    /// Implementations for specific numbers of arguments
+    overloads: BTreeMap<usize, Arc<CustomOperatorFn>>,
+    /// The implementation for any other number of arguments
+    fallback: Option<Arc<CustomOperatorFn>>,
+    num_params: NumParams,
+}
+impl CustomOperator {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.into(),
+            overloads: BTreeMap::new(),
+            fallback: None,
+            num_params: NumParams::OneOf(Vec::new()),
+        }
+    }
+
+    /// Set the implementation used for any number of arguments without an
+    /// overload
+    pub fn set_fallback(&mut self, operator: Arc<CustomOperatorFn>) {
+        self.fallback = Some(operator);
+        self.num_params = NumParams::Any;
+    }
+
+    /// Set the implementation used for exactly `num_args` arguments
+    pub fn add_overload(&mut self, num_args: usize, operator: Arc<CustomOperatorFn>) {
+        self.overloads.insert(num_args, operator);
+        if self.fallback.is_none() {
+            self.num_params =
+                NumParams::OneOf(self.overloads.keys().copied().collect());
+        }
+    }
+
+    pub fn execute(&self, items: &[&Value]) -> Result<Value, Error> {
+        let operator = self.overloads.get(&items.len()).or(self.fallback.as_ref());
+        match operator {
+            Some(operator) => operator(items),
+            None => Err(Error::WrongArgumentCount {
+                operator: self.symbol.clone(),
+                expected: self.num_params.clone(),
+                actual: items.len(),
+                arguments: args_snippet(items),
+            }),
+        }
+    }
+}
+impl CommonOperator for CustomOperator {
+    fn param_info(&self) -> &NumParams {
+        &self.num_params
+    }
+}
+impl fmt::Debug for CustomOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomOperator")
+            .field("symbol", &self.symbol)
+            .field("overloads", &self.overloads.keys().collect::<Vec<_>>())
+            .field("fallback", &self.fallback.as_ref().map(|_| "<operator fn>"))
+            .finish()
+    }
+}
+
+/// An alternative name for a built-in operator
+#[derive(Debug)]
+pub struct Alias {
+    symbol: &'static str,
+    target: &'static str,
+    negated: bool,
+    /// Whether rules should use the target operator instead
+    deprecated: bool,
+}
+impl Alias {
+    /// Transform the result of the target operator
+    fn apply(&self, result: Value) -> Value {
+        match self.negated {
+            true => Value::Bool(!logic::truthy(&result)),
+            false => result,
+        }
+    }
+}
+
+/// Apply an operation's alias, if any, to its result
+fn apply_alias(alias: Option<&Alias>, result: Value) -> Value {
+    match alias {
+        Some(alias) => alias.apply(result),
+        None => result,
+    }
+}
+
+/// The symbol to use when converting an operation back into a value
+fn display_symbol(alias: Option<&Alias>, symbol: &'static str) -> &'static str {
+    alias.map_or(symbol, |alias| alias.symbol)
+}
+
+type OperatorFn = fn(&Context, &[&Value]) -> Result<Value, Error>;
+type LazyOperatorFn = fn(&Context, &Value, &[&Value]) -> Result<Value, Error>;
+type DataOperatorFn = fn(&Context, &Value, &[&Value]) -> Result<Value, Error>;
+pub type CustomOperatorFn = dyn Fn(&[&Value]) -> Result<Value, Error> + Send + Sync;
 
 /// An operation that doesn't do any recursive parsing or evaluation.
 ///
 /// Any operator functions used must handle parsing of values themselves.
 #[derive(Debug)]
-pub struct LazyOperation<'a> {
-    operator: &'a LazyOperator,
+pub struct LazyOperation {
+    operator: &'static LazyOperator,
+    alias: Option<&'static Alias>,
     arguments: Vec<Value>,
 }
-impl<'a> Parser<'a> for LazyOperation<'a> {
-    fn from_value(value: &'a Value) -> Result<Option<Self>, Error> {
-        op_from_map(&LAZY_OPERATOR_MAP, value).and_then(|opt| {
+impl LazyOperation {
+    /// The symbol the operation was invoked with
+    pub fn symbol(&self) -> &str {
+        display_symbol(self.alias, self.operator.symbol)
+    }
+}
+impl<'a> Parser<'a> for LazyOperation {
+    fn from_value(value: &'a Value, engine: &Engine) -> Result<Option<Self>, Error> {
+        op_from_map(&LAZY_OPERATOR_MAP, value, engine).and_then(|opt| {
             opt.map(|op| {
                 Ok(LazyOperation {
                     operator: op.op,
-                    arguments: op.args.into_iter().map(|v| v.clone()).collect(),
+                    alias: op.alias,
+                    arguments: op.args.into_iter().cloned().collect(),
                 })
             })
             .transpose()
         })
     }
 
-    fn evaluate(&self, data: &'a Value) -> Result<Evaluated, Error> {
+    fn evaluate(&self, data: &Value, ctx: &Context) -> Result<Evaluated<'_>, Error> {
         self.operator
-            .execute(data, &self.arguments.iter().collect())
-            .map(Evaluated::New)
+            .execute(ctx, data, &self.arguments.iter().collect::<Vec<&Value>>())
+            .map(|result| Evaluated::New(apply_alias(self.alias, result)))
     }
 }
 
-impl From<LazyOperation<'_>> for Value {
+impl From<LazyOperation> for Value {
     fn from(op: LazyOperation) -> Value {
         let mut rv = Map::with_capacity(1);
         rv.insert(
-            op.operator.symbol.into(),
+            display_symbol(op.alias, op.operator.symbol).into(),
             Value::Array(op.arguments.clone()),
         );
         Value::Object(rv)
@@ -396,16 +791,33 @@ impl From<LazyOperation<'_>> for Value {
 
 #[derive(Debug)]
 pub struct Operation<'a> {
-    operator: &'a Operator,
+    operator: &'static Operator,
+    alias: Option<&'static Alias>,
     arguments: Vec<Parsed<'a>>,
 }
+impl Operation<'_> {
+    /// The symbol the operation was invoked with
+    pub fn symbol(&self) -> &str {
+        display_symbol(self.alias, self.operator.symbol)
+    }
+
+    /// Copy any borrowed literals, detaching the operation from the rule
+    pub fn into_owned(self) -> Operation<'static> {
+        Operation {
+            operator: self.operator,
+            alias: self.alias,
+            arguments: self.arguments.into_iter().map(Parsed::into_owned).collect(),
+        }
+    }
+}
 impl<'a> Parser<'a> for Operation<'a> {
-    fn from_value(value: &'a Value) -> Result<Option<Self>, Error> {
-        op_from_map(&OPERATOR_MAP, value).and_then(|opt| {
+    fn from_value(value: &'a Value, engine: &Engine) -> Result<Option<Self>, Error> {
+        op_from_map(OPERATOR_MAPS, value, engine).and_then(|opt| {
             opt.map(|op| {
                 Ok(Operation {
                     operator: op.op,
-                    arguments: Parsed::from_values(op.args)?,
+                    alias: op.alias,
+                    arguments: Parsed::from_values(op.args, engine)?,
                 })
             })
             .transpose()
@@ -413,15 +825,15 @@ impl<'a> Parser<'a> for Operation<'a> {
     }
 
     /// Evaluate the operation after recursively evaluating any nested operations
-    fn evaluate(&self, data: &'a Value) -> Result<Evaluated, Error> {
+    fn evaluate(&self, data: &Value, ctx: &Context) -> Result<Evaluated<'_>, Error> {
         let arguments = self
             .arguments
             .iter()
-            .map(|value| value.evaluate(data).map(Value::from))
+            .map(|value| value.evaluate(data, ctx).map(Value::from))
             .collect::<Result<Vec<Value>, Error>>()?;
         self.operator
-            .execute(&arguments.iter().collect())
-            .map(Evaluated::New)
+            .execute(ctx, &arguments.iter().collect::<Vec<&Value>>())
+            .map(|result| Evaluated::New(apply_alias(self.alias, result)))
     }
 }
 
@@ -433,23 +845,43 @@ impl From<Operation<'_>> for Value {
             .into_iter()
             .map(Value::from)
             .collect::<Vec<Value>>();
-        rv.insert(op.operator.symbol.into(), Value::Array(values));
+        rv.insert(
+            display_symbol(op.alias, op.operator.symbol).into(),
+            Value::Array(values),
+        );
         Value::Object(rv)
     }
 }
 
 #[derive(Debug)]
 pub struct DataOperation<'a> {
-    operator: &'a DataOperator,
+    operator: &'static DataOperator,
+    alias: Option<&'static Alias>,
     arguments: Vec<Parsed<'a>>,
 }
+impl DataOperation<'_> {
+    /// The symbol the operation was invoked with
+    pub fn symbol(&self) -> &str {
+        display_symbol(self.alias, self.operator.symbol)
+    }
+
+    /// Copy any borrowed literals, detaching the operation from the rule
+    pub fn into_owned(self) -> DataOperation<'static> {
+        DataOperation {
+            operator: self.operator,
+            alias: self.alias,
+            arguments: self.arguments.into_iter().map(Parsed::into_owned).collect(),
+        }
+    }
+}
 impl<'a> Parser<'a> for DataOperation<'a> {
-    fn from_value(value: &'a Value) -> Result<Option<Self>, Error> {
-        op_from_map(&DATA_OPERATOR_MAP, value).and_then(|opt| {
+    fn from_value(value: &'a Value, engine: &Engine) -> Result<Option<Self>, Error> {
+        op_from_map(&DATA_OPERATOR_MAP, value, engine).and_then(|opt| {
             opt.map(|op| {
                 Ok(DataOperation {
                     operator: op.op,
-                    arguments: Parsed::from_values(op.args)?,
+                    alias: op.alias,
+                    arguments: Parsed::from_values(op.args, engine)?,
                 })
             })
             .transpose()
@@ -457,15 +889,15 @@ impl<'a> Parser<'a> for DataOperation<'a> {
     }
 
     /// Evaluate the operation after recursively evaluating any nested operations
-    fn evaluate(&self, data: &'a Value) -> Result<Evaluated, Error> {
+    fn evaluate(&self, data: &Value, ctx: &Context) -> Result<Evaluated<'_>, Error> {
         let arguments = self
             .arguments
             .iter()
-            .map(|value| value.evaluate(data).map(Value::from))
+            .map(|value| value.evaluate(data, ctx).map(Value::from))
             .collect::<Result<Vec<Value>, Error>>()?;
         self.operator
-            .execute(data, &arguments.iter().collect())
-            .map(Evaluated::New)
+            .execute(ctx, data, &arguments.iter().collect::<Vec<&Value>>())
+            .map(|result| Evaluated::New(apply_alias(self.alias, result)))
     }
 }
 impl From<DataOperation<'_>> for Value {
@@ -476,19 +908,236 @@ impl From<DataOperation<'_>> for Value {
             .into_iter()
             .map(Value::from)
             .collect::<Vec<Value>>();
-        rv.insert(op.operator.symbol.into(), Value::Array(values));
+        rv.insert(
+            display_symbol(op.alias, op.operator.symbol).into(),
+            Value::Array(values),
+        );
+        Value::Object(rv)
+    }
+}
+
+#[derive(Debug)]
+pub struct CustomOperation<'a> {
+    operator: Arc<CustomOperator>,
+    arguments: Vec<Parsed<'a>>,
+}
+impl CustomOperation<'_> {
+    /// The symbol the operation was invoked with
+    pub fn symbol(&self) -> &str {
+        &self.operator.symbol
+    }
+
+    /// Copy any borrowed literals, detaching the operation from the rule
+    pub fn into_owned(self) -> CustomOperation<'static> {
+        CustomOperation {
+            operator: self.operator,
+            arguments: self.arguments.into_iter().map(Parsed::into_owned).collect(),
+        }
+    }
+}
+impl<'a> Parser<'a> for CustomOperation<'a> {
+    fn from_value(value: &'a Value, engine: &Engine) -> Result<Option<Self>, Error> {
+        op_from_map(&engine.custom_operators, value, engine).and_then(|opt| {
+            opt.map(|op| {
+                Ok(CustomOperation {
+                    operator: Arc::clone(op.op),
+                    arguments: Parsed::from_values(op.args, engine)?,
+                })
+            })
+            .transpose()
+        })
+    }
+
+    /// Evaluate the operation after recursively evaluating any nested operations
+    fn evaluate(&self, data: &Value, ctx: &Context) -> Result<Evaluated<'_>, Error> {
+        let arguments = self
+            .arguments
+            .iter()
+            .map(|value| value.evaluate(data, ctx).map(Value::from))
+            .collect::<Result<Vec<Value>, Error>>()?;
+        self.operator
+            .execute(&arguments.iter().collect::<Vec<&Value>>())
+            .map(Evaluated::New)
+    }
+}
+impl From<CustomOperation<'_>> for Value {
+    fn from(op: CustomOperation) -> Value {
+        let mut rv = Map::with_capacity(1);
+        let values = op
+            .arguments
+            .into_iter()
+            .map(Value::from)
+            .collect::<Vec<Value>>();
+        rv.insert(op.operator.symbol.clone(), Value::Array(values));
         Value::Object(rv)
     }
 }
 
+/// Whether a key names an operator, either built-in or registered on the engine
+pub fn is_operator(engine: &Engine, key: &str) -> bool {
+    OPERATOR_MAPS.iter().any(|map| map.contains_key(key))
+        || ALIAS_MAP.contains_key(key)
+        || LAZY_OPERATOR_MAP.contains_key(key)
+        || DATA_OPERATOR_MAP.contains_key(key)
+        || engine.custom_operators.contains_key(key)
+}
+
+/// Split a namespaced operator symbol, like `acme:discount_rate`, into its
+/// namespace and name
+///
+/// Namespaces are made of letters, digits, `_`, and `-`, and names can't
+/// contain `:`, `/`, or whitespace, so that e.g. URLs and times aren't
+/// mistaken for operators.
+pub fn split_namespace(key: &str) -> Option<(&str, &str)> {
+    let (namespace, name) = key.split_once(':')?;
+    let is_namespace = !namespace.is_empty()
+        && namespace
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    let is_name = !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c == ':' || c == '/' || c.is_whitespace());
+    match is_namespace && is_name {
+        true => Some((namespace, name)),
+        false => None,
+    }
+}
+
+/// Check that a rule doesn't use operators from namespaces, or operators
+/// within them, that the engine doesn't have
+///
+/// This only applies once the engine has any namespaces, as otherwise
+/// objects that look like namespaced operators are literals, like any
+/// other object whose key isn't an operator.
+pub fn check_namespaces(engine: &Engine, value: &Value) -> Result<(), Error> {
+    if engine.namespaces.is_empty() {
+        return Ok(());
+    }
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .try_for_each(|item| check_namespaces(engine, item)),
+        Value::Object(obj) => {
+            if let (1, Some(key)) = (obj.len(), obj.keys().next()) {
+                if let Some((namespace, _)) = split_namespace(key) {
+                    if !is_operator(engine, key) {
+                        let reason = match engine.namespaces.contains(namespace) {
+                            true => {
+                                format!("Unknown operator in namespace {:?}", namespace)
+                            }
+                            false => {
+                                format!("Unknown operator namespace {:?}", namespace)
+                            }
+                        };
+                        return Err(Error::InvalidOperation {
+                            key: key.clone(),
+                            reason,
+                        });
+                    }
+                }
+            }
+            obj.values()
+                .try_for_each(|val| check_namespaces(engine, val))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether a key names an operator that reads from the data, like `var`
+pub fn is_data_operator(key: &str) -> bool {
+    DATA_OPERATOR_MAP.contains_key(key)
+}
+
+/// Split an operation into its operator and arguments, without parsing it
+///
+/// Returns `None` if the value is not an operation. A non-array argument is
+/// returned as the sole argument, as for unary operators.
+pub fn split_operation<'v>(
+    engine: &Engine,
+    value: &'v Value,
+) -> Option<(&'v str, Vec<&'v Value>)> {
+    match value {
+        Value::Object(obj) if obj.len() == 1 => {
+            let (key, args) = obj.iter().next()?;
+            if !is_operator(engine, key) {
+                return None;
+            }
+            let args = match args {
+                Value::Array(items) => items.iter().collect(),
+                _ => vec![args],
+            };
+            Some((key.as_str(), args))
+        }
+        _ => None,
+    }
+}
+
+/// The operator an alias refers to, if the key is an alias
+pub fn alias_target(key: &str) -> Option<&'static str> {
+    ALIAS_MAP.get(key).map(|alias| alias.target)
+}
+
+/// The operator the key stands for, if it's an alias with the same result
+pub fn equivalent_alias_target(key: &str) -> Option<&'static str> {
+    ALIAS_MAP
+        .get(key)
+        .filter(|alias| !alias.negated)
+        .map(|alias| alias.target)
+}
+
+/// The operator to use instead, if the key is a deprecated alias
+pub fn deprecated_alias_target(key: &str) -> Option<&'static str> {
+    ALIAS_MAP
+        .get(key)
+        .filter(|alias| alias.deprecated)
+        .map(|alias| alias.target)
+}
+
+/// A mapping of operator symbols to operators
+trait OperatorMap<T> {
+    /// Get an operator by symbol, along with the alias used to refer to
+    /// it, if any
+    fn get_operator(&self, symbol: &str) -> Option<(&T, Option<&'static Alias>)>;
+}
+impl<T> OperatorMap<T> for phf::Map<&'static str, T> {
+    fn get_operator(&self, symbol: &str) -> Option<(&T, Option<&'static Alias>)> {
+        match ALIAS_MAP.get(symbol) {
+            Some(alias) => self.get(alias.target).map(|op| (op, Some(alias))),
+            None => self.get(symbol).map(|op| (op, None)),
+        }
+    }
+}
+impl<T> OperatorMap<T> for [&phf::Map<&'static str, T>] {
+    fn get_operator(&self, symbol: &str) -> Option<(&T, Option<&'static Alias>)> {
+        self.iter().find_map(|map| map.get_operator(symbol))
+    }
+}
+// Aliases only apply to built-in operators
+impl<T> OperatorMap<T> for HashMap<String, T> {
+    fn get_operator(&self, symbol: &str) -> Option<(&T, Option<&'static Alias>)> {
+        self.get(symbol).map(|op| (op, None))
+    }
+}
+
 struct OpArgs<'a, 'b, T> {
     op: &'a T,
+    alias: Option<&'static Alias>,
     args: Vec<&'b Value>,
 }
 
+/// Operators that accept any number of arguments with
+/// `Compatibility::Reference`, using only the first
+const REFERENCE_UNARY_OPERATORS: &[&str] = &["!", "!!"];
+
+/// The argument seen by operators given none with `Compatibility::Reference`,
+/// standing in for JavaScript's `undefined`
+static MISSING_ARGUMENT: Value = Value::Null;
+
 fn op_from_map<'a, 'b, T: CommonOperator>(
-    map: &'a phf::Map<&'static str, T>,
+    map: &'a (impl OperatorMap<T> + ?Sized),
     value: &'b Value,
+    engine: &Engine,
 ) -> Result<Option<OpArgs<'a, 'b, T>>, Error> {
     let obj = match value {
         Value::Object(obj) => obj,
@@ -515,8 +1164,8 @@ fn op_from_map<'a, 'b, T: CommonOperator>(
     })?;
 
     // See if the key is an operator. If it's not, return None.
-    let op = match map.get(key.as_str()) {
-        Some(op) => op,
+    let (op, alias) = match map.get_operator(key.as_str()) {
+        Some(found) => found,
         _ => return Ok(None),
     };
 
@@ -527,10 +1176,18 @@ fn op_from_map<'a, 'b, T: CommonOperator>(
         })
     };
 
+    let target = alias.map_or(key.as_str(), |alias| alias.target);
+    if engine.pure_only && impure::IMPURE_OPERATORS.contains(&target) {
+        return Err(Error::InvalidOperation {
+            key: key.clone(),
+            reason: "Impure operators are disabled for this engine".into(),
+        });
+    }
+
     let param_info = op.param_info();
     // If args value is not an array, and the operator is unary,
     // the value is treated as a unary argument array.
-    let args = match val {
+    let mut args = match val {
         Value::Array(args) => args.iter().collect::<Vec<&Value>>(),
         _ => match param_info.can_accept_unary() {
             true => vec![val],
@@ -538,9 +1195,20 @@ fn op_from_map<'a, 'b, T: CommonOperator>(
         },
     };
 
-    param_info.check_len(&args.len())?;
+    if engine.compatibility == Compatibility::Reference
+        && REFERENCE_UNARY_OPERATORS.contains(&target)
+    {
+        // The reference implementation passes every argument to the
+        // operator, which only looks at the first, and sees `undefined` if
+        // there are none
+        if args.is_empty() {
+            args.push(&MISSING_ARGUMENT);
+        }
+    } else {
+        param_info.check_len(key, &args)?;
+    }
 
-    Ok(Some(OpArgs { op, args }))
+    Ok(Some(OpArgs { op, alias, args }))
 }
 
 #[cfg(test)]
@@ -550,11 +1218,47 @@ mod test_operators {
     /// All operators symbols must match their keys
     #[test]
     fn test_operator_map_symbols() {
-        OPERATOR_MAP
-            .into_iter()
+        OPERATOR_MAPS
+            .iter()
+            .flat_map(|map| map.into_iter())
             .for_each(|(k, op)| assert_eq!(*k, op.symbol))
     }
 
+    /// Operators enabled by features must not shadow one another
+    #[test]
+    fn test_operator_maps_distinct() {
+        let mut symbols: Vec<&str> = OPERATOR_MAPS
+            .iter()
+            .flat_map(|map| map.keys())
+            .copied()
+            .collect();
+        let count = symbols.len();
+        symbols.sort_unstable();
+        symbols.dedup();
+        assert_eq!(symbols.len(), count);
+    }
+
+    /// All aliases' symbols must match their keys, and their targets must
+    /// exist
+    #[test]
+    fn test_alias_map() {
+        ALIAS_MAP.into_iter().for_each(|(k, alias)| {
+            assert_eq!(*k, alias.symbol);
+            assert!(
+                OPERATOR_MAP.contains_key(alias.target)
+                    || LAZY_OPERATOR_MAP.contains_key(alias.target)
+                    || DATA_OPERATOR_MAP.contains_key(alias.target)
+            );
+        })
+    }
+
+    #[test]
+    fn test_alias_round_trip() {
+        let rule = serde_json::json!({"not_in": [1, [2]]});
+        let parsed = Parsed::from_value(&rule, &Engine::new()).unwrap();
+        assert_eq!(Value::from(parsed), rule);
+    }
+
     /// All lazy operators symbols must match their keys
     #[test]
     fn test_lazy_operator_map_symbols() {
@@ -562,4 +1266,30 @@ mod test_operators {
             .into_iter()
             .for_each(|(k, op)| assert_eq!(*k, op.symbol))
     }
+
+    #[test]
+    fn test_wrong_argument_count_names_operator() {
+        let rule = serde_json::json!({"+": [1, {"==": [1, 2, 3]}]});
+        match Parsed::from_value(&rule, &Engine::new()) {
+            Err(Error::WrongArgumentCount {
+                operator,
+                actual,
+                arguments,
+                ..
+            }) => {
+                assert_eq!(operator, "==");
+                assert_eq!(actual, 3);
+                assert_eq!(arguments, "[1,2,3]");
+            }
+            other => panic!("expected WrongArgumentCount, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_args_snippet_truncation() {
+        let long = Value::String("x".repeat(200));
+        let snippet = args_snippet(&[&long]);
+        assert_eq!(snippet.len(), MAX_SNIPPET_LEN + 3);
+        assert!(snippet.ends_with("..."));
+    }
 }
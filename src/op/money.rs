@@ -0,0 +1,509 @@
+//! Currency-aware Operations
+//!
+//! Amounts of money are objects like `{"amount": "10.50", "currency": "USD"}`
+//! or strings like "10.50 USD", where currencies are three-letter codes.
+//! Amounts are exact decimals rather than floats, so e.g. adding "0.10 USD"
+//! to "0.20 USD" is exactly "0.30 USD". Amounts in different currencies can
+//! only be compared or combined if the engine has [`ExchangeRates`].
+//!
+//! Operators producing money return it as an object, with the amount as a
+//! string so that it isn't rounded to a float.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use phf::phf_map;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde_json::{json, Value};
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::op::{NumParams, Operator};
+
+pub const MONEY_OPERATOR_MAP: phf::Map<&'static str, Operator> = phf_map! {
+    "money" => Operator {
+        symbol: "money",
+        operator: money,
+        num_params: NumParams::Variadic(1..3),
+    },
+    "money_eq" => Operator {
+        symbol: "money_eq",
+        operator: money_eq,
+        num_params: NumParams::AtLeast(2),
+    },
+    "money_lt" => Operator {
+        symbol: "money_lt",
+        operator: money_lt,
+        num_params: NumParams::AtLeast(2),
+    },
+    "money_lte" => Operator {
+        symbol: "money_lte",
+        operator: money_lte,
+        num_params: NumParams::AtLeast(2),
+    },
+    "money_gt" => Operator {
+        symbol: "money_gt",
+        operator: money_gt,
+        num_params: NumParams::AtLeast(2),
+    },
+    "money_gte" => Operator {
+        symbol: "money_gte",
+        operator: money_gte,
+        num_params: NumParams::AtLeast(2),
+    },
+    "money_add" => Operator {
+        symbol: "money_add",
+        operator: money_add,
+        num_params: NumParams::AtLeast(1),
+    },
+    "money_sub" => Operator {
+        symbol: "money_sub",
+        operator: money_sub,
+        num_params: NumParams::Exactly(2),
+    },
+    "money_mul" => Operator {
+        symbol: "money_mul",
+        operator: money_mul,
+        num_params: NumParams::Exactly(2),
+    },
+    "money_round" => Operator {
+        symbol: "money_round",
+        operator: money_round,
+        num_params: NumParams::Variadic(1..3),
+    },
+};
+
+/// Exchange rates between currencies, used by the `money` feature's
+/// operators to compare and combine amounts in different currencies
+///
+/// Rates are given relative to a base currency, as the number of units of
+/// each currency worth one unit of the base, which is the format most
+/// exchange rate services publish.
+///
+/// ```rust
+/// use jsonlogic_rs::{Engine, ExchangeRates};
+/// use serde_json::json;
+///
+/// let rates = ExchangeRates::from_value(&json!({
+///     "base": "USD",
+///     "rates": {"EUR": "0.92", "GBP": 0.79},
+/// }))
+/// .unwrap();
+/// let engine = Engine::new().exchange_rates(rates);
+/// let rule = json!({"money_lt": ["10.00 EUR", "11.00 USD"]});
+/// assert_eq!(engine.apply(&rule, &json!(null)).unwrap(), json!(true));
+///
+/// // Without rates, comparing currencies is an error
+/// assert!(Engine::new().apply(&rule, &json!(null)).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExchangeRates {
+    base: String,
+    rates: HashMap<String, Decimal>,
+}
+impl ExchangeRates {
+    /// Read rates from an object like
+    /// `{"base": "USD", "rates": {"EUR": "0.92"}}`
+    ///
+    /// Rates may be numbers or strings, and must be positive.
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidData {
+            value: value.clone(),
+            reason: reason.into(),
+        };
+        let base = value
+            .get("base")
+            .and_then(Value::as_str)
+            .and_then(currency_code)
+            .ok_or_else(|| invalid("Exchange rates need a base currency code"))?;
+        let rates = value
+            .get("rates")
+            .and_then(Value::as_object)
+            .ok_or_else(|| invalid("Exchange rates need an object of rates"))?
+            .iter()
+            .map(|(currency, rate)| {
+                let currency = currency_code(currency).ok_or_else(|| {
+                    invalid(&format!("Invalid currency code {:?}", currency))
+                })?;
+                match parse_decimal(rate) {
+                    Some(rate) if rate > Decimal::ZERO => Ok((currency, rate)),
+                    _ => Err(invalid(&format!(
+                        "The rate for {} must be a positive number",
+                        currency
+                    ))),
+                }
+            })
+            .collect::<Result<HashMap<String, Decimal>, Error>>()?;
+        Ok(Self { base, rates })
+    }
+
+    /// The rates, in the form read by [`from_value()`](Self::from_value)
+    pub(crate) fn to_value(&self) -> Value {
+        let rates: serde_json::Map<String, Value> = self
+            .rates
+            .iter()
+            .map(|(currency, rate)| (currency.clone(), Value::String(rate.to_string())))
+            .collect();
+        serde_json::json!({"base": self.base, "rates": rates})
+    }
+
+    /// The number of units of a currency worth one unit of the base
+    fn rate(&self, currency: &str) -> Option<Decimal> {
+        match currency == self.base {
+            true => Some(Decimal::ONE),
+            false => self.rates.get(currency).copied(),
+        }
+    }
+}
+
+/// An amount of money
+#[derive(Debug, Clone, PartialEq)]
+struct Money {
+    amount: Decimal,
+    currency: String,
+}
+impl From<Money> for Value {
+    fn from(money: Money) -> Self {
+        json!({"amount": money.amount.to_string(), "currency": money.currency})
+    }
+}
+
+fn invalid(value: &Value, operation: &str, reason: String) -> Error {
+    Error::InvalidArgument {
+        value: value.clone(),
+        operation: operation.into(),
+        reason,
+    }
+}
+
+/// A currency code, which must be three ASCII letters, in upper case
+fn currency_code(code: &str) -> Option<String> {
+    match code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) {
+        true => Some(code.to_ascii_uppercase()),
+        false => None,
+    }
+}
+
+/// Parse a decimal from a number or a numeric string, without going
+/// through a float
+fn parse_decimal(value: &Value) -> Option<Decimal> {
+    let string = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        _ => return None,
+    };
+    Decimal::from_str(&string)
+        .or_else(|_| Decimal::from_scientific(&string))
+        .ok()
+}
+
+/// Parse an amount of money, like "10.50 USD" or
+/// `{"amount": "10.50", "currency": "USD"}`
+///
+/// In a string, the currency may also come first, as in "USD 10.50".
+fn parse_money(value: &Value, operation: &str) -> Result<Money, Error> {
+    let parsed = match value {
+        Value::Object(obj) => obj.get("amount").and_then(parse_decimal).zip(
+            obj.get("currency")
+                .and_then(Value::as_str)
+                .and_then(currency_code),
+        ),
+        Value::String(s) => {
+            let parts: Vec<&str> = s.split_whitespace().collect();
+            match parts.as_slice() {
+                [first, second] => match currency_code(second) {
+                    Some(code) => Decimal::from_str(first).ok().zip(Some(code)),
+                    None => Decimal::from_str(second).ok().zip(currency_code(first)),
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    parsed
+        .map(|(amount, currency)| Money { amount, currency })
+        .ok_or_else(|| {
+            invalid(
+                value,
+                operation,
+                format!(
+                    "Arguments to {} must be amounts of money like \"10.50 USD\"",
+                    operation
+                ),
+            )
+        })
+}
+
+/// The exchange rates of two currencies, for converting between them
+fn rates(
+    ctx: &Context,
+    value: &Value,
+    operation: &str,
+    from: &str,
+    to: &str,
+) -> Result<(Decimal, Decimal), Error> {
+    let rates = ctx.engine.exchange_rates.as_ref().ok_or_else(|| {
+        invalid(
+            value,
+            operation,
+            format!("Cannot convert {} to {} without exchange rates", from, to),
+        )
+    })?;
+    let rate = |currency: &str| {
+        rates.rate(currency).ok_or_else(|| {
+            invalid(
+                value,
+                operation,
+                format!("No exchange rate for {}", currency),
+            )
+        })
+    };
+    Ok((rate(from)?, rate(to)?))
+}
+
+fn overflow(value: &Value, operation: &str) -> Error {
+    invalid(value, operation, "Amount is too large".into())
+}
+
+/// Convert money to another currency
+///
+/// The result isn't rounded, so it may have more decimal places than the
+/// currency uses.
+fn convert(
+    ctx: &Context,
+    value: &Value,
+    operation: &str,
+    money: Money,
+    currency: &str,
+) -> Result<Money, Error> {
+    if money.currency == currency {
+        return Ok(money);
+    }
+    let (from, to) = rates(ctx, value, operation, &money.currency, currency)?;
+    let amount = money
+        .amount
+        .checked_mul(to)
+        .and_then(|amount| amount.checked_div(from))
+        .ok_or_else(|| overflow(value, operation))?;
+    Ok(Money {
+        amount,
+        currency: currency.into(),
+    })
+}
+
+/// Normalize money into an object, or convert it to a currency:
+/// `[money, currency?]`
+pub fn money(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let money = parse_money(items[0], "money")?;
+    match items.get(1) {
+        None => Ok(money.into()),
+        Some(value) => {
+            let currency = value.as_str().and_then(currency_code).ok_or_else(|| {
+                invalid(
+                    value,
+                    "money",
+                    "Second argument to money must be a currency code".into(),
+                )
+            })?;
+            Ok(convert(ctx, items[0], "money", money, &currency)?.into())
+        }
+    }
+}
+
+/// Check that `func` holds for the ordering of each adjacent pair of
+/// amounts
+///
+/// Amounts in different currencies are compared by cross-multiplying with
+/// their rates, which is exact, rather than by converting one of them.
+fn compare<F>(
+    ctx: &Context,
+    operation: &str,
+    items: &[&Value],
+    func: F,
+) -> Result<Value, Error>
+where
+    F: Fn(Ordering) -> bool,
+{
+    let amounts = items
+        .iter()
+        .map(|item| parse_money(item, operation))
+        .collect::<Result<Vec<Money>, Error>>()?;
+    for (idx, pair) in amounts.windows(2).enumerate() {
+        let (a, b) = (&pair[0], &pair[1]);
+        let ordering = match a.currency == b.currency {
+            true => a.amount.cmp(&b.amount),
+            false => {
+                let item = items[idx + 1];
+                let (rate_a, rate_b) =
+                    rates(ctx, item, operation, &a.currency, &b.currency)?;
+                let lhs = a.amount.checked_mul(rate_b);
+                let rhs = b.amount.checked_mul(rate_a);
+                let (lhs, rhs) =
+                    lhs.zip(rhs).ok_or_else(|| overflow(item, operation))?;
+                lhs.cmp(&rhs)
+            }
+        };
+        if !func(ordering) {
+            return Ok(Value::Bool(false));
+        }
+    }
+    Ok(Value::Bool(true))
+}
+
+/// Check amounts are all equal
+pub fn money_eq(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    compare(ctx, "money_eq", items, Ordering::is_eq)
+}
+
+/// Check amounts are in increasing order
+pub fn money_lt(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    compare(ctx, "money_lt", items, Ordering::is_lt)
+}
+
+/// Check amounts are in non-decreasing order
+pub fn money_lte(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    compare(ctx, "money_lte", items, Ordering::is_le)
+}
+
+/// Check amounts are in decreasing order
+pub fn money_gt(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    compare(ctx, "money_gt", items, Ordering::is_gt)
+}
+
+/// Check amounts are in non-increasing order
+pub fn money_gte(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    compare(ctx, "money_gte", items, Ordering::is_ge)
+}
+
+/// Add amounts, in the currency of the first
+pub fn money_add(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let mut total = parse_money(items[0], "money_add")?;
+    for item in &items[1..] {
+        let money = parse_money(item, "money_add")?;
+        let money = convert(ctx, item, "money_add", money, &total.currency)?;
+        total.amount = total
+            .amount
+            .checked_add(money.amount)
+            .ok_or_else(|| overflow(item, "money_add"))?;
+    }
+    Ok(total.into())
+}
+
+/// Subtract the second amount from the first, in the currency of the first
+pub fn money_sub(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let first = parse_money(items[0], "money_sub")?;
+    let second = parse_money(items[1], "money_sub")?;
+    let second = convert(ctx, items[1], "money_sub", second, &first.currency)?;
+    let amount = first
+        .amount
+        .checked_sub(second.amount)
+        .ok_or_else(|| overflow(items[1], "money_sub"))?;
+    Ok(Money { amount, ..first }.into())
+}
+
+/// Multiply an amount by a number: `[money, factor]`
+pub fn money_mul(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let money = parse_money(items[0], "money_mul")?;
+    let factor = parse_decimal(items[1]).ok_or_else(|| {
+        invalid(
+            items[1],
+            "money_mul",
+            "Second argument to money_mul must be a number".into(),
+        )
+    })?;
+    let amount = money
+        .amount
+        .checked_mul(factor)
+        .ok_or_else(|| overflow(items[1], "money_mul"))?;
+    Ok(Money { amount, ..money }.into())
+}
+
+/// Round an amount to some number of decimal places, 2 by default:
+/// `[money, places?]`
+///
+/// Halves are rounded away from zero, as is conventional for money.
+pub fn money_round(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let money = parse_money(items[0], "money_round")?;
+    let places = match items.get(1) {
+        None => 2,
+        Some(value) => {
+            value
+                .as_u64()
+                .filter(|places| *places <= 28)
+                .ok_or_else(|| {
+                    invalid(
+                    value,
+                    "money_round",
+                    "Second argument to money_round must be a number of decimal places"
+                        .into(),
+                )
+                })? as u32
+        }
+    };
+    let amount = money
+        .amount
+        .round_dp_with_strategy(places, RoundingStrategy::MidpointAwayFromZero);
+    Ok(Money { amount, ..money }.into())
+}
+
+#[cfg(test)]
+mod test_money {
+    use super::*;
+
+    fn parse(value: Value) -> Option<(String, String)> {
+        parse_money(&value, "money")
+            .ok()
+            .map(|m| (m.amount.to_string(), m.currency))
+    }
+
+    fn money(amount: &str, currency: &str) -> Option<(String, String)> {
+        Some((amount.into(), currency.into()))
+    }
+
+    #[test]
+    fn test_parse_money() {
+        assert_eq!(parse(json!("10.50 USD")), money("10.50", "USD"));
+        assert_eq!(parse(json!(" usd -3 ")), money("-3", "USD"));
+        assert_eq!(
+            parse(json!({"amount": "10.50", "currency": "eur"})),
+            money("10.50", "EUR")
+        );
+        assert_eq!(
+            parse(json!({"amount": 0.1, "currency": "GBP"})),
+            money("0.1", "GBP")
+        );
+        assert_eq!(
+            parse(json!({"amount": 250, "currency": "JPY"})),
+            money("250", "JPY")
+        );
+        assert_eq!(parse(json!("10.50")), None);
+        assert_eq!(parse(json!("10.50 US")), None);
+        assert_eq!(parse(json!("10.50 USD EUR")), None);
+        assert_eq!(parse(json!("ten USD")), None);
+        assert_eq!(parse(json!({"amount": "10.50"})), None);
+        assert_eq!(parse(json!(10.5)), None);
+    }
+
+    #[test]
+    fn test_exchange_rates() {
+        let rates = ExchangeRates::from_value(&json!({
+            "base": "usd",
+            "rates": {"EUR": "0.92", "JPY": 150},
+        }))
+        .unwrap();
+        assert_eq!(rates.rate("USD"), Some(Decimal::ONE));
+        assert_eq!(rates.rate("EUR"), Decimal::from_str("0.92").ok());
+        assert_eq!(rates.rate("GBP"), None);
+
+        for invalid in &[
+            json!({"rates": {}}),
+            json!({"base": "USD"}),
+            json!({"base": "USD", "rates": {"EUR": 0}}),
+            json!({"base": "USD", "rates": {"EUR": "x"}}),
+            json!({"base": "USD", "rates": {"EURO": 1}}),
+        ] {
+            assert!(ExchangeRates::from_value(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Network Operations
+
+use phf::phf_map;
+use serde_json::Value;
+use std::net::IpAddr;
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::op::{NumParams, Operator};
+
+pub const NET_OPERATOR_MAP: phf::Map<&'static str, Operator> = phf_map! {
+    "in_cidr" => Operator {
+        symbol: "in_cidr",
+        operator: in_cidr,
+        num_params: NumParams::Exactly(2),
+    },
+};
+
+fn invalid(value: &Value, reason: String) -> Error {
+    Error::InvalidArgument {
+        value: value.clone(),
+        operation: "in_cidr".into(),
+        reason,
+    }
+}
+
+/// Parse an address, treating IPv4-mapped IPv6 addresses as IPv4
+fn parse_addr(string: &str) -> Option<IpAddr> {
+    match string.trim().parse::<IpAddr>().ok()? {
+        IpAddr::V6(addr) => {
+            Some(addr.to_ipv4_mapped().map_or(IpAddr::V6(addr), IpAddr::V4))
+        }
+        addr => Some(addr),
+    }
+}
+
+/// A network in CIDR notation, like "10.0.0.0/8" or "2001:db8::/32"
+#[derive(Debug, Clone, Copy)]
+struct Cidr {
+    addr: IpAddr,
+    prefix_len: u32,
+}
+impl Cidr {
+    /// Parse a network, where a bare address is a network of one address
+    fn parse(string: &str) -> Option<Self> {
+        let (addr, prefix_len) = match string.split_once('/') {
+            Some((addr, len)) => (addr, Some(len.trim().parse::<u32>().ok()?)),
+            None => (string, None),
+        };
+        let addr = addr.trim().parse::<IpAddr>().ok()?;
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix_len = prefix_len.unwrap_or(max_len);
+        match prefix_len <= max_len {
+            true => Some(Self { addr, prefix_len }),
+            false => None,
+        }
+    }
+
+    fn contains(&self, addr: &IpAddr) -> bool {
+        match (self.addr, addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len).unwrap_or(0);
+                u32::from(net) & mask == u32::from(*addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len).unwrap_or(0);
+                u128::from(net) & mask == u128::from(*addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn parse_cidr(value: &Value) -> Result<Cidr, Error> {
+    match value {
+        Value::String(s) => Cidr::parse(s)
+            .ok_or_else(|| invalid(value, format!("Invalid CIDR block {:?}", s))),
+        _ => Err(invalid(value, "CIDR blocks must be strings".into())),
+    }
+}
+
+/// Check whether an IP address is in a CIDR block or list of blocks:
+/// `[ip, cidr_or_list]`
+///
+/// Both IPv4 and IPv6 are supported. An address never matches a block of
+/// the other family, except that IPv4-mapped IPv6 addresses like
+/// "::ffff:10.1.2.3" are treated as their IPv4 equivalent. A block without
+/// a prefix length matches only that address.
+pub fn in_cidr(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let (addr_arg, cidr_arg) = (items[0], items[1]);
+    let addr = match addr_arg {
+        Value::String(s) => parse_addr(s)
+            .ok_or_else(|| invalid(addr_arg, format!("Invalid IP address {:?}", s)))?,
+        _ => {
+            return Err(invalid(
+                addr_arg,
+                "First argument to in_cidr must be an IP address string".into(),
+            ))
+        }
+    };
+    let blocks = match cidr_arg {
+        Value::Array(blocks) => blocks.iter().collect(),
+        _ => vec![cidr_arg],
+    };
+    // Parse every block, so that a typo in a list is reported even if an
+    // earlier block matches
+    let blocks = blocks
+        .into_iter()
+        .map(parse_cidr)
+        .collect::<Result<Vec<Cidr>, Error>>()?;
+    Ok(Value::Bool(
+        blocks.iter().any(|block| block.contains(&addr)),
+    ))
+}
+
+#[cfg(test)]
+mod test_cidr {
+    use super::*;
+
+    fn contains(cidr: &str, addr: &str) -> bool {
+        Cidr::parse(cidr)
+            .unwrap()
+            .contains(&parse_addr(addr).unwrap())
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(contains("10.0.0.0/8", "10.255.0.1"));
+        assert!(!contains("10.0.0.0/8", "11.0.0.1"));
+        assert!(contains("0.0.0.0/0", "192.168.1.1"));
+        assert!(contains("192.168.1.1", "192.168.1.1"));
+        assert!(!contains("192.168.1.1", "192.168.1.2"));
+        assert!(contains("10.0.0.0/8", "::ffff:10.1.2.3"));
+        assert!(contains("2001:db8::/32", "2001:db8:1::1"));
+        assert!(!contains("2001:db8::/32", "2001:db9::1"));
+        assert!(contains("::/0", "::1"));
+        assert!(!contains("::/0", "127.0.0.1"));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Cidr::parse("10.0.0.0/33").is_none());
+        assert!(Cidr::parse("::/129").is_none());
+        assert!(Cidr::parse("10.0.0/8").is_none());
+        assert!(Cidr::parse("10.0.0.0/").is_none());
+        assert!(Cidr::parse("example.com/8").is_none());
+    }
+}
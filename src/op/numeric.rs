@@ -1,50 +1,274 @@
 //! Numeric Operations
 
+use std::borrow::Cow;
+
 use serde_json::Value;
 
+use crate::context::Context;
+use crate::engine::{DecimalSeparator, DivisionByZero};
 use crate::error::Error;
 use crate::js_op;
-use crate::value::to_number_value;
+use crate::NULL;
+
+/// Rewrite number strings to use a decimal point, including those in arrays
+fn normalize_decimal(value: &Value, separator: DecimalSeparator) -> Value {
+    match value {
+        Value::String(s) => {
+            Value::String(js_op::normalize_decimal(s, separator).into_owned())
+        }
+        Value::Array(vals) => Value::Array(
+            vals.iter()
+                .map(|v| normalize_decimal(v, separator))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
 
-fn compare<F>(func: F, items: &Vec<&Value>) -> Result<Value, Error>
+/// Call `func` with the arguments normalized per the engine's decimal
+/// separator, so that the `js_op` conversions can read them.
+fn localized<F>(ctx: &Context, items: &[&Value], func: F) -> Result<Value, Error>
+where
+    F: FnOnce(&[&Value]) -> Result<Value, Error>,
+{
+    match ctx.engine.decimal_separator {
+        DecimalSeparator::Point => func(items),
+        separator => {
+            let values: Vec<Value> = items
+                .iter()
+                .map(|v| normalize_decimal(v, separator))
+                .collect();
+            func(&values.iter().collect::<Vec<&Value>>())
+        }
+    }
+}
+
+/// Rewrite a string compared for equality with a number or boolean per the
+/// engine's decimal separator, as `==` would convert it to a number
+///
+/// Strings compared with one another are left as they are, so that they're
+/// still compared as written.
+fn localized_operand<'a>(
+    ctx: &Context,
+    value: &'a Value,
+    other: &Value,
+) -> Cow<'a, Value> {
+    match (ctx.engine.decimal_separator, value, other) {
+        (DecimalSeparator::Point, _, _) => Cow::Borrowed(value),
+        (separator, Value::String(_), Value::Number(_) | Value::Bool(_)) => {
+            Cow::Owned(normalize_decimal(value, separator))
+        }
+        _ => Cow::Borrowed(value),
+    }
+}
+
+/// Whether two values are equal, as by `==`
+fn equal(ctx: &Context, items: &[&Value]) -> bool {
+    let first = localized_operand(ctx, items[0], items[1]);
+    let second = localized_operand(ctx, items[1], items[0]);
+    ctx.engine.comparator.equal(&first, &second)
+}
+
+/// Do == for two values
+pub fn eq(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    Ok(Value::Bool(equal(ctx, items)))
+}
+
+/// Do != for two values
+pub fn ne(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    Ok(Value::Bool(!equal(ctx, items)))
+}
+
+/// Check that `func` holds for each adjacent pair of values, so that e.g.
+/// `{"<": [1, x, y, 10]}` means `1 < x < y < 10`.
+fn compare<F>(func: F, items: &[&Value]) -> Result<Value, Error>
 where
     F: Fn(&Value, &Value) -> bool,
 {
-    if items.len() == 2 {
-        Ok(Value::Bool(func(items[0], items[1])))
-    } else {
-        Ok(Value::Bool(
-            func(items[0], items[1]) && func(items[1], items[2]),
-        ))
+    Ok(Value::Bool(
+        items.windows(2).all(|pair| func(pair[0], pair[1])),
+    ))
+}
+
+/// Do < for two or more values
+pub fn lt(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| {
+        compare(|a, b| ctx.engine.comparator.lt(a, b), items)
+    })
+}
+
+/// Do <= for two or more values
+pub fn lte(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| {
+        compare(|a, b| ctx.engine.comparator.lte(a, b), items)
+    })
+}
+
+/// Do > for two or more values
+pub fn gt(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| {
+        compare(|a, b| ctx.engine.comparator.gt(a, b), items)
+    })
+}
+
+/// Do >= for two or more values
+pub fn gte(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| {
+        compare(|a, b| ctx.engine.comparator.gte(a, b), items)
+    })
+}
+
+/// Treat a single array argument as the list of arguments
+///
+/// This allows e.g. `{"+": {"var": "numbers"}}` to sum an array from the
+/// data. Without spreading, the array would be converted to a string and
+/// parsed as a float, which only ever considers its first element.
+fn spread<'a>(items: &'a [&'a Value]) -> Vec<&'a Value> {
+    match items {
+        [Value::Array(values)] => values.iter().collect(),
+        _ => items.to_vec(),
     }
 }
 
-/// Do < for either 2 or 3 values
-pub fn lt(items: &Vec<&Value>) -> Result<Value, Error> {
-    compare(js_op::abstract_lt, items)
+/// Add values, spreading a single array argument
+pub fn plus(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| {
+        js_op::parse_float_add(&spread(items))
+            .and_then(|sum| ctx.engine.number_value("+", sum, items))
+    })
 }
 
-/// Do <= for either 2 or 3 values
-pub fn lte(items: &Vec<&Value>) -> Result<Value, Error> {
-    compare(js_op::abstract_lte, items)
+/// Multiply values, spreading a single array argument
+pub fn mul(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| multiply(ctx, items))
 }
 
-/// Do > for either 2 or 3 values
-pub fn gt(items: &Vec<&Value>) -> Result<Value, Error> {
-    compare(js_op::abstract_gt, items)
+fn multiply(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let items = spread(items);
+    if items.is_empty() {
+        return Err(Error::InvalidArgument {
+            value: Value::Array(Vec::new()),
+            operation: "*".into(),
+            reason: "Cannot multiply an empty array".into(),
+        });
+    }
+    js_op::parse_float_mul(&items)
+        .and_then(|product| ctx.engine.number_value("*", product, &items))
+}
+
+/// Spread a single array argument, erroring if there is nothing to compare
+fn spread_nonempty<'a>(
+    items: &'a [&'a Value],
+    operation: &str,
+) -> Result<Vec<&'a Value>, Error> {
+    let items = spread(items);
+    if items.is_empty() {
+        return Err(Error::InvalidArgument {
+            value: Value::Array(Vec::new()),
+            operation: operation.into(),
+            reason: format!("Cannot take the {} of an empty array", operation),
+        });
+    }
+    Ok(items)
 }
 
-/// Do >= for either 2 or 3 values
-pub fn gte(items: &Vec<&Value>) -> Result<Value, Error> {
-    compare(js_op::abstract_gte, items)
+/// Get the largest value, spreading a single array argument
+pub fn max(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| {
+        js_op::abstract_max(&spread_nonempty(items, "max")?)
+            .and_then(|max| ctx.engine.number_value("max", max, items))
+    })
+}
+
+/// Get the smallest value, spreading a single array argument
+pub fn min(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| {
+        js_op::abstract_min(&spread_nonempty(items, "min")?)
+            .and_then(|min| ctx.engine.number_value("min", min, items))
+    })
 }
 
 /// Perform subtraction or convert a number to a negative
-pub fn minus(items: &Vec<&Value>) -> Result<Value, Error> {
-    let value = if items.len() == 1 {
-        js_op::to_negative(items[0])?
-    } else {
-        js_op::abstract_minus(items[0], items[1])?
+pub fn minus(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| {
+        let value = if items.len() == 1 {
+            js_op::to_negative(items[0])?
+        } else {
+            js_op::abstract_minus(items[0], items[1])?
+        };
+        ctx.engine.number_value("-", value, items)
+    })
+}
+
+/// Apply a division-like operation, handling a zero divisor or an
+/// overflowing result per the engine
+fn divide<F>(
+    ctx: &Context,
+    symbol: &str,
+    func: F,
+    items: &[&Value],
+) -> Result<Value, Error>
+where
+    F: Fn(&Value, &Value) -> Result<f64, Error>,
+{
+    // Always run the operation first, so that non-numeric arguments are
+    // reported as such, rather than as a division by zero.
+    let result = func(items[0], items[1])?;
+    if result.is_finite() {
+        return ctx.engine.number_value(symbol, result, items);
+    }
+    match ctx.engine.division_by_zero {
+        DivisionByZero::Null => Ok(NULL),
+        DivisionByZero::InfinityAsNull if result.is_infinite() => Ok(NULL),
+        _ if js_op::to_number(items[1]) == Some(0.0) => Err(Error::InvalidArgument {
+            value: items[1].clone(),
+            operation: symbol.into(),
+            reason: "Division by zero".into(),
+        }),
+        _ => ctx.engine.number_value(symbol, result, items),
+    }
+}
+
+/// Perform division
+pub fn div(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| {
+        divide(ctx, "/", js_op::abstract_div, items)
+    })
+}
+
+/// Perform modulo
+pub fn modulo(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| {
+        divide(ctx, "%", js_op::abstract_mod, items)
+    })
+}
+
+/// Explicitly convert a value to a number
+///
+/// Only numbers and strings written as numbers are converted, using the
+/// same parsing as the arithmetic operators (JS' `Number()`). Anything
+/// else, including `null`, booleans, blank strings, and arrays that
+/// JS would coerce, is an error rather than a number.
+pub fn to_number(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    localized(ctx, items, |items| convert(ctx, items))
+}
+
+fn convert(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let num = match items[0] {
+        Value::Number(_) => js_op::to_number(items[0]),
+        Value::String(s) if !is_blank(s) => js_op::str_to_number(s),
+        _ => None,
     };
-    to_number_value(value)
+    num.filter(|num| num.is_finite())
+        .ok_or_else(|| Error::InvalidArgument {
+            value: items[0].clone(),
+            operation: "toNumber".into(),
+            reason: "Could not convert value to number".into(),
+        })
+        .and_then(|num| ctx.engine.number_value("toNumber", num, items))
+}
+
+/// Whether a string is empty once JS' whitespace is trimmed
+fn is_blank(s: &str) -> bool {
+    s.chars().all(|c| c.is_whitespace() || c == '\u{feff}')
 }
@@ -0,0 +1,142 @@
+//! Region Operations
+//!
+//! Countries are ISO 3166-1 alpha-2 codes, like "FR" or "GB", matched
+//! case-insensitively. Regions are the groupings below, which are current
+//! as of this release of the crate; groupings not listed here can be given
+//! as arrays of country codes instead.
+
+use phf::phf_map;
+use serde_json::Value;
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::op::{NumParams, Operator};
+
+pub const REGION_OPERATOR_MAP: phf::Map<&'static str, Operator> = phf_map! {
+    "in_region" => Operator {
+        symbol: "in_region",
+        operator: in_region,
+        num_params: NumParams::Exactly(2),
+    },
+};
+
+/// Member states of the European Union
+const EU: &[&str] = &[
+    "AT", "BE", "BG", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GR", "HR", "HU",
+    "IE", "IT", "LT", "LU", "LV", "MT", "NL", "PL", "PT", "RO", "SE", "SI", "SK",
+];
+
+/// The European Economic Area: the EU, Iceland, Liechtenstein, and Norway
+const EEA: &[&str] = &[
+    "AT", "BE", "BG", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GR", "HR", "HU",
+    "IE", "IS", "IT", "LI", "LT", "LU", "LV", "MT", "NL", "NO", "PL", "PT", "RO", "SE",
+    "SI", "SK",
+];
+
+/// Members of the European Free Trade Association
+const EFTA: &[&str] = &["CH", "IS", "LI", "NO"];
+
+/// Countries fully in the Schengen Area
+const SCHENGEN: &[&str] = &[
+    "AT", "BE", "BG", "CH", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GR", "HR", "HU",
+    "IS", "IT", "LI", "LT", "LU", "LV", "MT", "NL", "NO", "PL", "PT", "RO", "SE", "SI",
+    "SK",
+];
+
+/// EU member states using the euro
+const EUROZONE: &[&str] = &[
+    "AT", "BE", "BG", "CY", "DE", "EE", "ES", "FI", "FR", "GR", "HR", "IE", "IT", "LT",
+    "LU", "LV", "MT", "NL", "PT", "SI", "SK",
+];
+
+/// Regions by their upper case names, with their countries in sorted order
+const REGIONS: phf::Map<&'static str, &'static [&'static str]> = phf_map! {
+    "EU" => EU,
+    "EEA" => EEA,
+    "EFTA" => EFTA,
+    "SCHENGEN" => SCHENGEN,
+    "EUROZONE" => EUROZONE,
+};
+
+fn invalid(value: &Value, reason: String) -> Error {
+    Error::InvalidArgument {
+        value: value.clone(),
+        operation: "in_region".into(),
+        reason,
+    }
+}
+
+/// A country code, in upper case, if the string is shaped like one
+fn country_code(string: &str) -> Option<String> {
+    let string = string.trim();
+    match string.len() == 2 && string.chars().all(|c| c.is_ascii_alphabetic()) {
+        true => Some(string.to_ascii_uppercase()),
+        false => None,
+    }
+}
+
+/// Check whether a region, or a country code standing for itself, includes
+/// a country
+fn includes(value: &Value, country: &str) -> Result<bool, Error> {
+    let string = value.as_str().ok_or_else(|| {
+        invalid(
+            value,
+            "Regions must be region names or country codes".into(),
+        )
+    })?;
+    if let Some(countries) = REGIONS.get(string.trim().to_ascii_uppercase().as_str()) {
+        return Ok(countries.binary_search(&country).is_ok());
+    }
+    match country_code(string) {
+        Some(code) => Ok(code == country),
+        None => Err(invalid(value, format!("Unknown region {:?}", string))),
+    }
+}
+
+/// Check whether a country is in a region, or in any of a list of regions
+/// and countries: `[country, region_or_list]`
+///
+/// Region names, like "EU" and "EEA", are matched case-insensitively.
+pub fn in_region(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let (country_arg, region_arg) = (items[0], items[1]);
+    let country = country_arg.as_str().and_then(country_code).ok_or_else(|| {
+        invalid(
+            country_arg,
+            "First argument to in_region must be a two-letter country code".into(),
+        )
+    })?;
+    let regions = match region_arg {
+        Value::Array(regions) => regions.iter().collect(),
+        _ => vec![region_arg],
+    };
+    // Check every region, so that a typo in a list is reported even if an
+    // earlier region matches
+    let found = regions
+        .into_iter()
+        .map(|region| includes(region, &country))
+        .collect::<Result<Vec<bool>, Error>>()?;
+    Ok(Value::Bool(found.into_iter().any(|found| found)))
+}
+
+#[cfg(test)]
+mod test_region {
+    use super::*;
+
+    #[test]
+    fn test_tables() {
+        for (name, countries) in REGIONS.entries() {
+            assert!(
+                countries.windows(2).all(|pair| pair[0] < pair[1]),
+                "{} isn't sorted",
+                name
+            );
+            assert!(countries
+                .iter()
+                .all(|c| country_code(c).as_deref() == Some(*c)));
+        }
+        assert_eq!(EU.len(), 27);
+        assert!(EU.iter().all(|c| EEA.contains(c)));
+        assert_eq!(EEA.len(), EU.len() + 3);
+        assert!(EUROZONE.iter().all(|c| EU.contains(c)));
+    }
+}
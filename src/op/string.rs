@@ -1,50 +1,77 @@
 //! String Operations
 
 use serde_json::Value;
+use std::borrow::Cow;
 use std::cmp;
 use std::convert::TryInto;
 
+use crate::compare::Comparator;
+use crate::context::Context;
+use crate::engine::CatCoercion;
 use crate::error::Error;
 use crate::js_op;
+use crate::op::array;
+use crate::value::{Evaluated, Parsed};
 use crate::NULL;
 
 /// Concatenate strings.
 ///
 /// Note: the reference implementation just uses JS' builtin string
-/// concatenation with implicit casting, so e.g. `cast("foo", {})`
-/// evaluates to `"foo[object Object]". Here we explicitly require all
-/// arguments to be strings, because the specification explicitly defines
-/// `cat` as a string operation.
-pub fn cat(items: &Vec<&Value>) -> Result<Value, Error> {
-    let mut rv = String::from("");
-    items
-        .into_iter()
-        .map(|i| match i {
-            Value::String(i_string) => Ok(i_string.clone()),
-            _ => Ok(js_op::to_string(i)),
-        })
-        .fold(Ok(&mut rv), |acc: Result<&mut String, Error>, i| {
-            let rv = acc?;
-            rv.push_str(&i?);
-            Ok(rv)
-        })?;
+/// concatenation with implicit casting, so e.g. `cat("foo", {})` evaluates
+/// to `"foo[object Object]"`. The same casting is done here by default, but
+/// the engine's [`CatCoercion`] may serialize objects and arrays as JSON
+/// instead, or reject them.
+///
+/// Arguments are evaluated in turn, and the output is allocated once, with
+/// room for all of them, rather than grown as each one is appended.
+pub fn cat(ctx: &Context, data: &Value, args: &[&Value]) -> Result<Value, Error> {
+    let parsed = Parsed::from_values(args.to_vec(), ctx.engine)?;
+    let mut parts: Vec<Cow<str>> = Vec::with_capacity(parsed.len());
+    for arg in &parsed {
+        parts.push(match arg.evaluate(data, ctx)? {
+            Evaluated::Raw(Value::String(s)) => Cow::Borrowed(s.as_str()),
+            Evaluated::New(Value::String(s)) => Cow::Owned(s),
+            Evaluated::Raw(other) => Cow::Owned(cat_string(ctx, other)?),
+            Evaluated::New(other) => Cow::Owned(cat_string(ctx, &other)?),
+        });
+    }
+    let size = parts.iter().map(|part| part.len()).sum();
+    ctx.engine.check_result_size("cat", size)?;
+    let mut rv = String::with_capacity(size);
+    for part in &parts {
+        rv.push_str(part);
+    }
     Ok(Value::String(rv))
 }
 
+/// Convert a non-string argument to `cat` to a string
+fn cat_string(ctx: &Context, value: &Value) -> Result<String, Error> {
+    match (ctx.engine.cat_coercion, value) {
+        (CatCoercion::Js, _) | (_, Value::Null | Value::Bool(_) | Value::Number(_)) => {
+            Ok(js_op::to_string(value))
+        }
+        (CatCoercion::Json, _) => Ok(value.to_string()),
+        (CatCoercion::Error, _) => Err(Error::InvalidArgument {
+            value: value.clone(),
+            operation: "cat".into(),
+            reason: "Objects and arrays can't be concatenated as strings".into(),
+        }),
+    }
+}
+
 /// Get a substring by index
 ///
 /// Note: the reference implementation casts the first argument to a string,
 /// but since the specification explicitly defines this as a string operation,
 /// the argument types are enforced here to avoid unpredictable behavior.
-pub fn substr(items: &Vec<&Value>) -> Result<Value, Error> {
+pub fn substr(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
     // We can only have 2 or 3 arguments. Number of arguments is validated elsewhere.
     let (string_arg, idx_arg) = (items[0], items[1]);
-    let limit_opt: Option<&Value>;
-    if items.len() > 2 {
-        limit_opt = Some(items[2]);
+    let limit_opt: Option<&Value> = if items.len() > 2 {
+        Some(items[2])
     } else {
-        limit_opt = None;
-    }
+        None
+    };
 
     let string = match string_arg {
         Value::String(s) => s,
@@ -85,7 +112,8 @@ pub fn substr(items: &Vec<&Value>) -> Result<Value, Error> {
                     Err(Error::InvalidArgument {
                         value: limit_arg.clone(),
                         operation: "substr".into(),
-                        reason: "Optional third argument to substr must be an integer".into(),
+                        reason: "Optional third argument to substr must be an integer"
+                            .into(),
                     })
                 }
             }
@@ -99,19 +127,22 @@ pub fn substr(items: &Vec<&Value>) -> Result<Value, Error> {
 
     let string_len = string.len();
 
-    let idx_abs: usize = idx.abs().try_into().map_err(|e| Error::InvalidArgument {
-        value: idx_arg.clone(),
-        operation: "substr".into(),
-        reason: format!(
-            "The number {} is too large to index strings on this system",
-            e
-        ),
-    })?;
+    let idx_abs: usize =
+        idx.unsigned_abs()
+            .try_into()
+            .map_err(|e| Error::InvalidArgument {
+                value: idx_arg.clone(),
+                operation: "substr".into(),
+                reason: format!(
+                    "The number {} is too large to index strings on this system",
+                    e
+                ),
+            })?;
     let start_idx = match idx {
         // If the index is negative it means "number of characters prior to the
         // end of the string from which to start", and corresponds to the string
         // length minus the index.
-        idx if idx < 0 => string_len.checked_sub(idx_abs).unwrap_or(0),
+        idx if idx < 0 => string_len.saturating_sub(idx_abs),
         // A positive index is simply the starting point. Max starting point
         // is the length, which will yield an empty string.
         _ => cmp::min(string_len, idx_abs),
@@ -120,19 +151,22 @@ pub fn substr(items: &Vec<&Value>) -> Result<Value, Error> {
     let end_idx = match limit {
         None => string_len,
         Some(l) => {
-            let limit_abs: usize = l.abs().try_into().map_err(|e| Error::InvalidArgument {
-                value: limit_opt.or(Some(&NULL)).map(|v| v.clone()).unwrap(),
-                operation: "substr".into(),
-                reason: format!(
-                    "The number {} is too large to index strings on this system",
-                    e
-                ),
-            })?;
+            let limit_abs: usize =
+                l.unsigned_abs()
+                    .try_into()
+                    .map_err(|e| Error::InvalidArgument {
+                        value: limit_opt.or(Some(&NULL)).cloned().unwrap(),
+                        operation: "substr".into(),
+                        reason: format!(
+                        "The number {} is too large to index strings on this system",
+                        e
+                    ),
+                    })?;
             match l {
                 // If the limit is negative, it means "characters before the end
                 // at which to stop", corresponding to an index of either 0 or
                 // the length of the string minus the limit.
-                l if l < 0 => string_len.checked_sub(limit_abs).unwrap_or(0),
+                l if l < 0 => string_len.saturating_sub(limit_abs),
                 // A positive limit indicates the number of characters to take,
                 // so it corresponds to an index of the start index plus the
                 // limit (with a maximum value of the string length).
@@ -144,7 +178,7 @@ pub fn substr(items: &Vec<&Value>) -> Result<Value, Error> {
         }
     };
 
-    let count_in_substr = end_idx.checked_sub(start_idx).unwrap_or(0);
+    let count_in_substr = end_idx.saturating_sub(start_idx);
 
     // Iter over our expected count rather than indexing directly to avoid
     // potential panics if any of our math is wrong.
@@ -156,3 +190,81 @@ pub fn substr(items: &Vec<&Value>) -> Result<Value, Error> {
             .collect(),
     ))
 }
+
+/// Explicitly convert a value to a string
+///
+/// Conversion follows JS' `String()`, the same conversion used implicitly
+/// by `cat`, so e.g. `[1, 2]` becomes `"1,2"`.
+pub fn to_string(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    Ok(Value::String(js_op::to_string(items[0])))
+}
+
+/// Get a string argument, erroring with a message that names the operation.
+fn string_arg<'a>(
+    value: &'a Value,
+    operation: &str,
+    position: &str,
+) -> Result<&'a str, Error> {
+    match value {
+        Value::String(s) => Ok(s),
+        _ => Err(Error::InvalidArgument {
+            value: value.clone(),
+            operation: operation.into(),
+            reason: format!("{} argument to {} must be a string", position, operation),
+        }),
+    }
+}
+
+/// Check whether a string starts with a prefix: `[string, prefix]`
+///
+/// Like `substr`, this is strictly a string operation, so both arguments
+/// must be strings.
+pub fn starts_with(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let string = string_arg(items[0], "startsWith", "First")?;
+    let prefix = string_arg(items[1], "startsWith", "Second")?;
+    Ok(Value::Bool(string.starts_with(prefix)))
+}
+
+/// Check whether a string ends with a suffix: `[string, suffix]`
+pub fn ends_with(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let string = string_arg(items[0], "endsWith", "First")?;
+    let suffix = string_arg(items[1], "endsWith", "Second")?;
+    Ok(Value::Bool(string.ends_with(suffix)))
+}
+
+/// Check whether a string contains a substring: `[string, substring]`
+///
+/// Note the argument order is the reverse of `in`, matching the
+/// `startsWith` and `endsWith` operators (and JS' `String.prototype.includes`).
+/// Unlike `in`, arrays are not accepted.
+pub fn contains(_ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    let string = string_arg(items[0], "contains", "First")?;
+    let substring = string_arg(items[1], "contains", "Second")?;
+    Ok(Value::Bool(string.contains(substring)))
+}
+
+/// The engine's comparator, comparing strings without regard to case
+fn case_insensitive(ctx: &Context) -> Comparator {
+    ctx.engine.comparator.case_insensitive(true)
+}
+
+/// Perform case-insensitive abstract equality
+pub fn eq_i(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    Ok(Value::Bool(case_insensitive(ctx).equal(items[0], items[1])))
+}
+
+/// Perform case-insensitive abstract inequality
+pub fn ne_i(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    Ok(Value::Bool(
+        !case_insensitive(ctx).equal(items[0], items[1]),
+    ))
+}
+
+/// Perform case-insensitive containment checks
+///
+/// This follows the same rules as `in`, except that string needles are
+/// matched without regard to case, both as substrings of a string haystack
+/// and as members of an array haystack.
+pub fn in_i(ctx: &Context, items: &[&Value]) -> Result<Value, Error> {
+    array::contains(&case_insensitive(ctx), items[0], items[1])
+}
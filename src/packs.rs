@@ -0,0 +1,222 @@
+//! Operator packs: related operators shipped and tested together
+//!
+//! A pack groups operators that belong together, like those of an optional
+//! feature, with tests of them in the format of the official tests. Packs
+//! are listed by [`Engine::packs()`], and [`Engine::verify_packs()`] runs
+//! each pack's tests against the engine, to check that its operators still
+//! behave as the pack expects once combined with the engine's options and
+//! other operators.
+//!
+//! The operators of each optional feature the crate was built with, like
+//! `money` or `hashing`, form a [`FeaturePack`], which every engine has.
+//! Other packs implement [`OperatorPack`] and are added to an engine with
+//! [`Engine::add_pack()`]:
+//!
+//! ```rust
+//! use jsonlogic_rs::conformance::{parse_cases, Case};
+//! use jsonlogic_rs::packs::OperatorPack;
+//! use jsonlogic_rs::Engine;
+//! use serde_json::{json, Value};
+//!
+//! struct Strings;
+//! impl OperatorPack for Strings {
+//!     fn name(&self) -> &str {
+//!         "strings"
+//!     }
+//!     fn operators(&self) -> Vec<String> {
+//!         vec!["shout".into()]
+//!     }
+//!     fn register(&self, engine: Engine) -> Engine {
+//!         engine.add_operator("shout", |args: &[&Value]| {
+//!             Ok(json!(args[0].as_str().unwrap_or_default().to_uppercase()))
+//!         })
+//!     }
+//!     fn tests(&self) -> Vec<Case> {
+//!         parse_cases(&json!([[{"shout": "hi"}, {}, "HI"]])).unwrap()
+//!     }
+//! }
+//!
+//! let engine = Engine::new().add_pack(Strings);
+//! assert!(engine.packs().contains(&"strings"));
+//! for (name, report) in engine.verify_packs() {
+//!     assert!(report.is_conformant(), "{}: {:?}", name, report.divergences);
+//! }
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::conformance::{parse_cases, Case};
+use crate::engine::Engine;
+use crate::op::FEATURE_OPERATOR_MAPS;
+
+/// A named set of operators, with tests of them
+pub trait OperatorPack: Send + Sync {
+    /// The pack's name, as listed by [`Engine::packs()`]
+    fn name(&self) -> &str;
+
+    /// The operators the pack provides
+    fn operators(&self) -> Vec<String>;
+
+    /// Register the pack's operators on an engine
+    fn register(&self, engine: Engine) -> Engine;
+
+    /// Tests of the pack's operators, run by [`Engine::verify_packs()`]
+    fn tests(&self) -> Vec<Case> {
+        Vec::new()
+    }
+}
+
+/// The built-in operators of an optional feature
+///
+/// Feature packs are part of every engine, so registering one does nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeaturePack {
+    name: &'static str,
+}
+impl OperatorPack for FeaturePack {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn operators(&self) -> Vec<String> {
+        let mut operators: Vec<String> = FEATURE_OPERATOR_MAPS
+            .iter()
+            .filter(|(feature, _)| *feature == self.name)
+            .flat_map(|(_, map)| map.keys().map(|key| key.to_string()))
+            .collect();
+        operators.sort();
+        operators
+    }
+
+    fn register(&self, engine: Engine) -> Engine {
+        engine
+    }
+
+    fn tests(&self) -> Vec<Case> {
+        let tests = serde_json::from_str(feature_tests(self.name))
+            .expect("Bundled pack tests are JSON");
+        parse_cases(&tests).expect("Bundled pack tests are valid")
+    }
+}
+
+/// The packs of the optional features the crate was built with
+pub fn feature_packs() -> Vec<FeaturePack> {
+    FEATURE_OPERATOR_MAPS
+        .iter()
+        .map(|(name, _)| FeaturePack { name })
+        .collect()
+}
+
+/// The bundled tests of a feature's operators
+fn feature_tests(feature: &str) -> &'static str {
+    match feature {
+        #[cfg(feature = "measure")]
+        "measure" => include_str!("../tests/data/packs/measure.json"),
+        #[cfg(feature = "money")]
+        "money" => include_str!("../tests/data/packs/money.json"),
+        #[cfg(feature = "net")]
+        "net" => include_str!("../tests/data/packs/net.json"),
+        #[cfg(feature = "regions")]
+        "regions" => include_str!("../tests/data/packs/regions.json"),
+        #[cfg(feature = "hashing")]
+        "hashing" => include_str!("../tests/data/packs/hashing.json"),
+        _ => "[]",
+    }
+}
+
+/// The packs added to an engine, in the order they were added
+#[derive(Clone, Default)]
+pub(crate) struct Packs(Vec<Arc<dyn OperatorPack>>);
+impl Packs {
+    /// Add a pack, replacing any added earlier with the same name
+    pub(crate) fn add(&mut self, pack: Arc<dyn OperatorPack>) {
+        self.0.retain(|added| added.name() != pack.name());
+        self.0.push(pack);
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &dyn OperatorPack> {
+        self.0.iter().map(|pack| pack.as_ref())
+    }
+}
+impl fmt::Debug for Packs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.iter().map(OperatorPack::name))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test_packs {
+    use super::*;
+    use serde_json::{json, Value};
+
+    struct Doubling {
+        factor: f64,
+    }
+    impl OperatorPack for Doubling {
+        fn name(&self) -> &str {
+            "doubling"
+        }
+        fn operators(&self) -> Vec<String> {
+            vec!["double".into()]
+        }
+        fn register(&self, engine: Engine) -> Engine {
+            let factor = self.factor;
+            engine.add_operator("double", move |args: &[&Value]| {
+                Ok(json!(args[0].as_f64().unwrap_or_default() * factor))
+            })
+        }
+        fn tests(&self) -> Vec<Case> {
+            parse_cases(&json!(["# Doubling", [{"double": 2}, {}, 4.0]])).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_feature_packs() {
+        let engine = Engine::new();
+        let packs = feature_packs();
+        assert_eq!(
+            engine.packs(),
+            packs.iter().map(|pack| pack.name).collect::<Vec<_>>()
+        );
+        for pack in &packs {
+            assert!(!pack.operators().is_empty());
+            assert!(!pack.tests().is_empty(), "{}", pack.name);
+            for operator in pack.operators() {
+                assert!(engine.has_operator(&operator), "{}", operator);
+            }
+        }
+        for (name, report) in engine.verify_packs() {
+            assert!(report.is_conformant(), "{}: {:?}", name, report.divergences);
+        }
+    }
+
+    #[test]
+    fn test_added_packs() {
+        let engine = Engine::new().add_pack(Doubling { factor: 2.0 });
+        assert_eq!(engine.packs().last(), Some(&"doubling"));
+        assert_eq!(
+            engine.apply(&json!({"double": 3}), &json!(null)).unwrap(),
+            json!(6.0)
+        );
+        let (name, report) = engine.verify_packs().pop().unwrap();
+        assert_eq!(name, "doubling");
+        assert_eq!(report.passed, 1);
+
+        // Adding a pack again replaces it, and a broken pack fails its tests
+        let engine = engine.add_pack(Doubling { factor: 3.0 });
+        assert_eq!(
+            engine
+                .packs()
+                .iter()
+                .filter(|name| **name == "doubling")
+                .count(),
+            1
+        );
+        let (_, report) = engine.verify_packs().pop().unwrap();
+        assert!(!report.is_conformant());
+        assert_eq!(format!("{:?}", engine.packs), "[\"doubling\"]");
+    }
+}
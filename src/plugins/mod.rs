@@ -0,0 +1,14 @@
+//! Custom operators loaded at runtime
+//!
+//! Operators registered with [`Engine::add_operator()`](crate::Engine::add_operator)
+//! must be compiled into the host. Plugins provide operators from outside
+//! of it, so they can be added or updated without rebuilding the host, and
+//! are registered on an engine like any other custom operator. Each kind of
+//! plugin is enabled by its own feature.
+
+#[cfg(feature = "native-plugins")]
+pub mod native;
+#[cfg(feature = "script-plugins")]
+pub mod script;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
@@ -0,0 +1,216 @@
+//! Operators provided by native shared libraries
+//!
+//! A plugin is a shared library (a `.so`, `.dylib`, or `.dll`) built from
+//! a Rust crate of type `cdylib` that depends on this crate. It declares a
+//! registration function with [`declare_plugin!`](crate::declare_plugin),
+//! which adds operators to a [`Registrar`] when the plugin is loaded:
+//!
+//! ```rust,ignore
+//! use jsonlogic_rs::plugins::native::Registrar;
+//! use serde_json::{json, Value};
+//!
+//! fn register(registrar: &mut Registrar) {
+//!     registrar.add_operator("double", |args: &[&Value]| {
+//!         Ok(json!(args[0].as_f64().unwrap_or(0.0) * 2.0))
+//!     });
+//! }
+//!
+//! jsonlogic_rs::declare_plugin!(register);
+//! ```
+//!
+//! Unlike [WebAssembly plugins](super::wasm), native plugins are not
+//! sandboxed: they run with all of the host's privileges, and a plugin
+//! that panics or misbehaves can crash or corrupt the host. Rust types are
+//! passed between the host and the plugin, and their layout is only stable
+//! for a single compiler version, so plugins must be built with the same
+//! compiler and the same version of this crate as the host. The version of
+//! this crate is checked when a plugin is loaded, but the compiler version
+//! can't be, which is why loading a plugin is `unsafe`.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::Path;
+use std::sync::Arc;
+
+use libloading::Library;
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::op::CustomOperatorFn;
+
+/// The version of this crate, which plugins must have been built against
+#[doc(hidden)]
+pub const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// The signature of a plugin's registration function
+#[doc(hidden)]
+pub type RegisterFn = fn(&mut Registrar);
+
+/// Collects the operators a plugin provides
+#[derive(Default)]
+pub struct Registrar {
+    operators: Vec<(String, Arc<CustomOperatorFn>)>,
+}
+impl Registrar {
+    /// Add an operator, which works like one registered with
+    /// [`Engine::add_operator()`](crate::Engine::add_operator)
+    pub fn add_operator<F>(&mut self, name: &str, operator: F)
+    where
+        F: Fn(&[&Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.operators.push((name.into(), Arc::new(operator)));
+    }
+}
+
+/// Export a plugin's registration function
+///
+/// The function takes a `&mut Registrar` and adds the plugin's operators
+/// to it. It is exported along with the version of this crate the plugin
+/// was built against.
+#[macro_export]
+macro_rules! declare_plugin {
+    ($register:path) => {
+        #[no_mangle]
+        pub extern "C" fn jsonlogic_plugin_version() -> *const ::std::os::raw::c_char {
+            $crate::plugins::native::VERSION.as_ptr() as *const ::std::os::raw::c_char
+        }
+
+        #[no_mangle]
+        pub fn jsonlogic_plugin_register(
+            registrar: &mut $crate::plugins::native::Registrar,
+        ) {
+            let register: $crate::plugins::native::RegisterFn = $register;
+            register(registrar)
+        }
+    };
+}
+
+/// An operator from a plugin, which keeps the plugin loaded while in use
+struct PluginOperator {
+    // Dropped before the library, since its code is in the library
+    operator: Arc<CustomOperatorFn>,
+    _library: Arc<Library>,
+}
+impl PluginOperator {
+    fn call(&self, args: &[&Value]) -> Result<Value, Error> {
+        (self.operator)(args)
+    }
+}
+
+/// A shared library providing operators
+///
+/// ```rust,no_run
+/// use jsonlogic_rs::plugins::native::NativePlugin;
+/// use jsonlogic_rs::Engine;
+///
+/// // Safety: the plugin was built with the same compiler as this program
+/// let plugin = unsafe { NativePlugin::load("./libmy_operators.so") }.unwrap();
+/// let engine = Engine::new().add_native_plugin(&plugin);
+/// ```
+#[derive(Clone)]
+pub struct NativePlugin {
+    library: Arc<Library>,
+    operators: Vec<(String, Arc<CustomOperatorFn>)>,
+}
+impl NativePlugin {
+    /// Load a plugin and collect its operators
+    ///
+    /// # Safety
+    ///
+    /// The library's initialization code is run, and its registration
+    /// function called, so it must be trusted. It must have been built
+    /// with [`declare_plugin!`](crate::declare_plugin) using the same
+    /// compiler version as the host, and the same version of this crate,
+    /// which is checked.
+    pub unsafe fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let error = |reason: String| Error::PluginError {
+            operator: None,
+            reason: format!("{}: {}", path.display(), reason),
+        };
+        let library = Library::new(path).map_err(|err| error(err.to_string()))?;
+
+        let version = library
+            .get::<extern "C" fn() -> *const c_char>(b"jsonlogic_plugin_version\0")
+            .map_err(|err| error(format!("Not a plugin: {}", err)))?;
+        let version = CStr::from_ptr(version()).to_string_lossy();
+        let expected = &VERSION[..VERSION.len() - 1];
+        if version != expected {
+            return Err(error(format!(
+                "Plugin was built against version {} of jsonlogic-rs, not {}",
+                version, expected
+            )));
+        }
+
+        let register = library
+            .get::<RegisterFn>(b"jsonlogic_plugin_register\0")
+            .map_err(|err| error(format!("Not a plugin: {}", err)))?;
+        let mut registrar = Registrar::default();
+        (*register)(&mut registrar);
+
+        Ok(Self {
+            library: Arc::new(library),
+            operators: registrar.operators,
+        })
+    }
+
+    /// The names of the operators the plugin provides
+    pub fn operators(&self) -> Vec<&str> {
+        self.operators
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// The plugin's operators, as functions of their evaluated arguments
+    pub(crate) fn operator_fns(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            &str,
+            impl Fn(&[&Value]) -> Result<Value, Error> + Send + Sync + 'static,
+        ),
+    > + '_ {
+        self.operators.iter().map(move |(name, operator)| {
+            let loaded = PluginOperator {
+                operator: Arc::clone(operator),
+                _library: Arc::clone(&self.library),
+            };
+            (name.as_str(), move |args: &[&Value]| loaded.call(args))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_native {
+    use super::*;
+
+    #[test]
+    fn test_load_errors() {
+        let err = unsafe { NativePlugin::load("/nonexistent/libplugin.so") };
+        assert!(matches!(
+            err,
+            Err(Error::PluginError { operator: None, .. })
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_not_a_plugin() {
+        let err = unsafe { NativePlugin::load("libc.so.6") }.err().unwrap();
+        assert!(err.to_string().contains("Not a plugin"), "{}", err);
+    }
+
+    #[test]
+    fn test_registrar() {
+        fn register(registrar: &mut Registrar) {
+            registrar.add_operator("one", |_: &[&Value]| Ok(Value::from(1)));
+        }
+        let register: RegisterFn = register;
+        let mut registrar = Registrar::default();
+        register(&mut registrar);
+        assert_eq!(registrar.operators.len(), 1);
+        assert_eq!(registrar.operators[0].0, "one");
+        assert_eq!((registrar.operators[0].1)(&[]).unwrap(), Value::from(1));
+    }
+}
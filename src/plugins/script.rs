@@ -0,0 +1,216 @@
+//! Operators written as Rhai scripts
+//!
+//! [Rhai](https://rhai.rs) is a small scripting language embedded in the
+//! host, so operators can be written without a Rust toolchain. An
+//! operator's script is run with the operator's evaluated arguments in an
+//! array named `args`, and its result is the value of its last
+//! expression. A script may `throw` a message to reject its arguments,
+//! which is reported as an `InvalidArgument` error; other script errors
+//! are reported as a `PluginError`.
+//!
+//! Scripts are sandboxed: they have no access to the filesystem, network,
+//! or host, `eval` is disabled, and `print` and `debug` output is
+//! discarded. The number of operations a script may perform can be limited
+//! with [`ScriptPlugin::max_operations()`].
+
+use std::sync::Arc;
+
+use rhai::{Dynamic, EvalAltResult, Scope, AST};
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// A set of operators written as Rhai scripts
+///
+/// ```rust
+/// use jsonlogic_rs::plugins::script::ScriptPlugin;
+/// use jsonlogic_rs::Engine;
+/// use serde_json::json;
+///
+/// let plugin = ScriptPlugin::new()
+///     .add_operator("clamp", "
+///         let value = args[0];
+///         let low = args[1];
+///         let high = args[2];
+///         if value < low { low } else if value > high { high } else { value }
+///     ")
+///     .unwrap()
+///     .max_operations(10_000);
+///
+/// let engine = Engine::new().add_script_plugin(&plugin);
+/// assert_eq!(
+///     engine.apply(&json!({"clamp": [{"var": "x"}, 0, 10]}), &json!({"x": 12})).unwrap(),
+///     json!(10)
+/// );
+/// ```
+#[derive(Clone, Default)]
+pub struct ScriptPlugin {
+    operators: Vec<(String, Arc<AST>)>,
+    max_operations: Option<u64>,
+}
+impl ScriptPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an operator, compiling its script
+    ///
+    /// Scripts that fail to compile are a `PluginError`.
+    pub fn add_operator(mut self, name: &str, script: &str) -> Result<Self, Error> {
+        let ast = self
+            .runtime()
+            .compile(script)
+            .map_err(|err| Error::PluginError {
+                operator: Some(name.into()),
+                reason: err.to_string(),
+            })?;
+        self.operators.push((name.into(), Arc::new(ast)));
+        Ok(self)
+    }
+
+    /// Limit the number of operations each script run may perform
+    ///
+    /// Scripts exceeding the limit fail with a `PluginError`. Scripts are
+    /// unlimited by default.
+    pub fn max_operations(mut self, operations: u64) -> Self {
+        self.max_operations = Some(operations);
+        self
+    }
+
+    /// The names of the operators the plugin provides
+    pub fn operators(&self) -> Vec<&str> {
+        self.operators
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// The plugin's operators, as functions of their evaluated arguments
+    pub(crate) fn operator_fns(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            &str,
+            impl Fn(&[&Value]) -> Result<Value, Error> + Send + Sync + 'static,
+        ),
+    > + '_ {
+        let runtime = Arc::new(self.runtime());
+        self.operators.iter().map(move |(name, ast)| {
+            let (runtime, ast) = (Arc::clone(&runtime), Arc::clone(ast));
+            let operator = name.clone();
+            let call = move |args: &[&Value]| run(&runtime, &operator, &ast, args);
+            (name.as_str(), call)
+        })
+    }
+
+    /// A sandboxed script engine
+    fn runtime(&self) -> rhai::Engine {
+        let mut runtime = rhai::Engine::new();
+        runtime.disable_symbol("eval");
+        runtime.on_print(|_| {});
+        runtime.on_debug(|_, _, _| {});
+        if let Some(max) = self.max_operations {
+            runtime.set_max_operations(max);
+        }
+        runtime
+    }
+}
+
+/// Run an operator's script
+fn run(
+    runtime: &rhai::Engine,
+    operator: &str,
+    ast: &AST,
+    args: &[&Value],
+) -> Result<Value, Error> {
+    let error = |reason: String| Error::PluginError {
+        operator: Some(operator.into()),
+        reason,
+    };
+    let mut scope = Scope::new();
+    let script_args =
+        rhai::serde::to_dynamic(args).map_err(|err| error(err.to_string()))?;
+    scope.push_dynamic("args", script_args);
+
+    let result: Dynamic =
+        runtime
+            .eval_ast_with_scope(&mut scope, ast)
+            .map_err(|err| match *err {
+                EvalAltResult::ErrorRuntime(reason, _) => Error::InvalidArgument {
+                    value: Value::Array(
+                        args.iter().map(|arg| (*arg).clone()).collect(),
+                    ),
+                    operation: operator.into(),
+                    reason: reason.to_string(),
+                },
+                err => error(err.to_string()),
+            })?;
+    rhai::serde::from_dynamic(&result).map_err(|err| error(err.to_string()))
+}
+
+#[cfg(test)]
+mod test_script {
+    use super::*;
+    use crate::Engine;
+    use serde_json::json;
+
+    #[test]
+    fn test_operators() {
+        let plugin = ScriptPlugin::new()
+            .add_operator("double", "args[0] * 2")
+            .unwrap()
+            .add_operator("describe", r#"#{count: args.len(), first: args[0]}"#)
+            .unwrap()
+            .add_operator(
+                "positive",
+                r#"if args[0] <= 0 { throw "not positive" } args[0]"#,
+            )
+            .unwrap();
+        assert_eq!(plugin.operators(), vec!["double", "describe", "positive"]);
+
+        let engine = Engine::new().add_script_plugin(&plugin);
+        let apply = |rule: Value| engine.apply(&rule, &json!({"a": 2.5}));
+        assert_eq!(apply(json!({"double": 3})).unwrap(), json!(6));
+        assert_eq!(apply(json!({"double": {"var": "a"}})).unwrap(), json!(5.0));
+        assert_eq!(
+            apply(json!({"describe": [[1, "b"], null]})).unwrap(),
+            json!({"count": 2, "first": [1, "b"]})
+        );
+        assert_eq!(apply(json!({"positive": 1})).unwrap(), json!(1));
+        assert!(matches!(
+            apply(json!({"positive": -1})),
+            Err(Error::InvalidArgument { reason, .. }) if reason == "not positive"
+        ));
+        assert!(matches!(
+            apply(json!({"double": "a"})),
+            Err(Error::PluginError { operator: Some(op), .. }) if op == "double"
+        ));
+    }
+
+    #[test]
+    fn test_sandbox() {
+        let plugin = ScriptPlugin::new()
+            .add_operator("spin", "loop {}")
+            .unwrap()
+            .add_operator("chatty", r#"print("hello"); debug("hello"); 1"#)
+            .unwrap()
+            .max_operations(1_000);
+        let engine = Engine::new().add_script_plugin(&plugin);
+        let err = engine
+            .apply(&json!({"spin": []}), &json!(null))
+            .unwrap_err();
+        assert_eq!(err.code(), "plugin_error");
+        assert_eq!(
+            engine.apply(&json!({"chatty": []}), &json!(null)).unwrap(),
+            json!(1)
+        );
+
+        assert!(matches!(
+            ScriptPlugin::new().add_operator("evil", r#"eval("1")"#),
+            Err(Error::PluginError { .. })
+        ));
+        assert!(ScriptPlugin::new()
+            .add_operator("broken", "let = ;")
+            .is_err());
+    }
+}
@@ -0,0 +1,401 @@
+//! Operators provided by WebAssembly modules
+//!
+//! A plugin is a WebAssembly module, run with [wasmtime], whose exported
+//! functions named `op_<name>` become operators named `<name>`. Modules
+//! are sandboxed: they can't import anything from the host, so they have
+//! no access to the filesystem, network, or clock, and each call can be
+//! limited in the fuel (roughly, instructions) it may consume and in the
+//! memory the module may grow to. This makes plugins suitable for running
+//! operators provided by third parties.
+//!
+//! Values are exchanged as UTF-8 JSON in the module's memory. A module
+//! must export:
+//!
+//! - `memory`, its linear memory
+//! - `alloc(len: i32) -> i32`, returning a pointer to `len` bytes that the
+//!   host may write to
+//! - any number of operators, `op_<name>(ptr: i32, len: i32) -> i64`, each
+//!   called with the JSON array of the operator's evaluated arguments at
+//!   `ptr`. An operator returns the pointer to its output in the high 32
+//!   bits of its result and the output's length in the low 32 bits. The
+//!   output is a JSON object, either `{"result": <value>}` or
+//!   `{"error": "<reason>"}`, the latter being reported as an
+//!   `InvalidArgument` error.
+//!
+//! It may also export `dealloc(ptr: i32, len: i32)`, which is then called
+//! to free the arguments and output of each operation.
+//!
+//! [wasmtime]: https://wasmtime.dev
+
+use std::convert::TryFrom;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde_json::Value;
+use wasmtime::{
+    Config, Engine as Runtime, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder, Trap, TypedFunc,
+};
+
+use crate::error::Error;
+
+/// An exported operator function, taking the pointer to and length of its
+/// arguments and returning the packed pointer to and length of its output
+type OperatorFunc = TypedFunc<(i32, i32), i64>;
+
+/// The instantiated module, shared by its operators
+struct Instance {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    dealloc: Option<TypedFunc<(i32, i32), ()>>,
+    fuel: Option<u64>,
+}
+
+/// A WebAssembly module providing operators
+///
+/// ```rust
+/// use jsonlogic_rs::plugins::wasm::WasmPlugin;
+/// use jsonlogic_rs::Engine;
+/// use serde_json::json;
+///
+/// // Modules may be given as WebAssembly binaries or text
+/// let plugin = WasmPlugin::new(r#"(module
+///     (memory (export "memory") 1)
+///     (data (i32.const 0) "{\"result\":42}")
+///     (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+///     ;; The output is at pointer 0, with a length of 13
+///     (func (export "op_answer") (param i32 i32) (result i64) (i64.const 13))
+/// )"#)
+/// .unwrap()
+/// .fuel(1_000_000);
+/// assert_eq!(plugin.operators(), vec!["answer"]);
+///
+/// let engine = Engine::new().add_wasm_plugin(&plugin);
+/// assert_eq!(engine.apply(&json!({"answer": []}), &json!(null)).unwrap(), json!(42));
+/// ```
+#[derive(Clone)]
+pub struct WasmPlugin {
+    instance: Arc<Mutex<Instance>>,
+    operators: Vec<(String, OperatorFunc)>,
+}
+impl WasmPlugin {
+    /// Load a plugin from the bytes of a WebAssembly binary or text module
+    pub fn new(module: impl AsRef<[u8]>) -> Result<Self, Error> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let runtime = Runtime::new(&config).map_err(load_error)?;
+        let module = Module::new(&runtime, module).map_err(load_error)?;
+
+        let mut store = Store::new(&runtime, StoreLimits::default());
+        store.limiter(|limits| limits);
+        store.set_fuel(u64::MAX).map_err(load_error)?;
+        // Nothing is linked, so modules importing anything fail to load
+        let instance = Linker::new(&runtime)
+            .instantiate(&mut store, &module)
+            .map_err(load_error)?;
+
+        let memory = instance.get_memory(&mut store, "memory").ok_or_else(|| {
+            load_error("The module must export its memory as \"memory\"")
+        })?;
+        let alloc = instance
+            .get_typed_func(&mut store, "alloc")
+            .map_err(load_error)?;
+        let dealloc = match module.get_export("dealloc") {
+            Some(_) => Some(
+                instance
+                    .get_typed_func(&mut store, "dealloc")
+                    .map_err(load_error)?,
+            ),
+            None => None,
+        };
+        let mut operators = Vec::new();
+        for export in module.exports() {
+            if let Some(name) = export.name().strip_prefix("op_") {
+                let func = instance
+                    .get_typed_func(&mut store, export.name())
+                    .map_err(load_error)?;
+                operators.push((name.to_string(), func));
+            }
+        }
+
+        Ok(Self {
+            instance: Arc::new(Mutex::new(Instance {
+                store,
+                memory,
+                alloc,
+                dealloc,
+                fuel: None,
+            })),
+            operators,
+        })
+    }
+
+    /// Load a plugin from a WebAssembly binary or text file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let module = std::fs::read(path).map_err(|err| {
+            load_error(format!("Could not read {}: {}", path.display(), err))
+        })?;
+        Self::new(module)
+    }
+
+    /// Limit the fuel each operation may consume
+    ///
+    /// Every WebAssembly instruction consumes fuel, so this bounds the time
+    /// an operation can take. Operations that run out fail with a
+    /// `PluginError`. Operations are unlimited by default.
+    pub fn fuel(self, fuel: u64) -> Self {
+        self.lock().fuel = Some(fuel);
+        self
+    }
+
+    /// Limit the size of the module's memory, in bytes
+    ///
+    /// Attempts to grow the memory past the limit fail, as they would if
+    /// the host were out of memory.
+    pub fn max_memory(self, bytes: usize) -> Self {
+        *self.lock().store.data_mut() =
+            StoreLimitsBuilder::new().memory_size(bytes).build();
+        self
+    }
+
+    /// The names of the operators the plugin provides
+    pub fn operators(&self) -> Vec<&str> {
+        self.operators
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// The plugin's operators, as functions of their evaluated arguments
+    pub(crate) fn operator_fns(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            &str,
+            impl Fn(&[&Value]) -> Result<Value, Error> + Send + Sync + 'static,
+        ),
+    > + '_ {
+        self.operators.iter().map(move |(name, func)| {
+            let instance = Arc::clone(&self.instance);
+            let (operator, func) = (name.clone(), func.clone());
+            let call = move |args: &[&Value]| {
+                let mut instance =
+                    instance.lock().unwrap_or_else(|err| err.into_inner());
+                instance.call(&operator, &func, args)
+            };
+            (name.as_str(), call)
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Instance> {
+        self.instance.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Instance {
+    fn call(
+        &mut self,
+        operator: &str,
+        func: &OperatorFunc,
+        args: &[&Value],
+    ) -> Result<Value, Error> {
+        let error = |reason: String| Error::PluginError {
+            operator: Some(operator.into()),
+            reason,
+        };
+        let trap = |err: wasmtime::Error| match err.downcast_ref::<Trap>() {
+            Some(trap) => error(trap.to_string()),
+            None => error(err.to_string()),
+        };
+
+        self.store
+            .set_fuel(self.fuel.unwrap_or(u64::MAX))
+            .map_err(trap)?;
+        let input = serde_json::to_vec(args).map_err(|err| error(err.to_string()))?;
+        let input_len = i32::try_from(input.len()).map_err(|_| {
+            error("Arguments are too large to pass to the module".into())
+        })?;
+        let input_ptr = self.alloc.call(&mut self.store, input_len).map_err(trap)?;
+        self.memory
+            .write(&mut self.store, input_ptr as u32 as usize, &input)
+            .map_err(|err| error(format!("Could not write arguments: {}", err)))?;
+
+        let packed = func
+            .call(&mut self.store, (input_ptr, input_len))
+            .map_err(trap)?;
+        let (output_ptr, output_len) = ((packed >> 32) as u32, packed as u32);
+        let mut output = vec![0; output_len as usize];
+        self.memory
+            .read(&self.store, output_ptr as usize, &mut output)
+            .map_err(|err| error(format!("Could not read output: {}", err)))?;
+        if let Some(dealloc) = &self.dealloc {
+            dealloc
+                .call(&mut self.store, (input_ptr, input_len))
+                .map_err(trap)?;
+            dealloc
+                .call(&mut self.store, (output_ptr as i32, output_len as i32))
+                .map_err(trap)?;
+        }
+
+        let output: Value = serde_json::from_slice(&output)
+            .map_err(|err| error(format!("Output is not valid JSON: {}", err)))?;
+        match output {
+            Value::Object(mut obj) if obj.len() == 1 => {
+                if let Some(result) = obj.remove("result") {
+                    return Ok(result);
+                }
+                if let Some(Value::String(reason)) = obj.remove("error") {
+                    return Err(Error::InvalidArgument {
+                        value: Value::Array(
+                            args.iter().map(|arg| (*arg).clone()).collect(),
+                        ),
+                        operation: operator.into(),
+                        reason,
+                    });
+                }
+                Err(error(
+                    "Output must be {\"result\": ...} or {\"error\": \"...\"}".into(),
+                ))
+            }
+            _ => Err(error(
+                "Output must be {\"result\": ...} or {\"error\": \"...\"}".into(),
+            )),
+        }
+    }
+}
+
+fn load_error(err: impl ToString) -> Error {
+    Error::PluginError {
+        operator: None,
+        reason: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test_wasm {
+    use super::*;
+    use crate::Engine;
+    use serde_json::json;
+
+    const MODULE: &str = r#"(module
+        (memory (export "memory") 1)
+        (global $heap (mut i32) (i32.const 1024))
+        (data (i32.const 0) "{\"result\":42}")
+        (data (i32.const 16) "{\"error\":\"nope\"}")
+        (data (i32.const 32) "{\"result\":")
+        (data (i32.const 48) "[1, 2]")
+        (func $alloc (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $heap))
+            (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+            (local.get $ptr))
+        (func $pack (param $ptr i32) (param $len i32) (result i64)
+            (i64.or
+                (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                (i64.extend_i32_u (local.get $len))))
+        (func (export "op_answer") (param i32 i32) (result i64)
+            (call $pack (i32.const 0) (i32.const 13)))
+        (func (export "op_fail") (param i32 i32) (result i64)
+            (call $pack (i32.const 16) (i32.const 16)))
+        (func (export "op_bare") (param i32 i32) (result i64)
+            (call $pack (i32.const 48) (i32.const 6)))
+        ;; Wraps the arguments in {"result": ...}
+        (func (export "op_args") (param $ptr i32) (param $len i32) (result i64)
+            (local $out i32)
+            (local.set $out (call $alloc (i32.add (local.get $len) (i32.const 11))))
+            (memory.copy (local.get $out) (i32.const 32) (i32.const 10))
+            (memory.copy
+                (i32.add (local.get $out) (i32.const 10))
+                (local.get $ptr)
+                (local.get $len))
+            (i32.store8
+                (i32.add (i32.add (local.get $out) (i32.const 10)) (local.get $len))
+                (i32.const 125))
+            (call $pack (local.get $out) (i32.add (local.get $len) (i32.const 11))))
+        (func (export "op_spin") (param i32 i32) (result i64)
+            (loop $forever (br $forever))
+            (unreachable))
+        (func (export "op_grow") (param i32 i32) (result i64)
+            (if (i32.eq (memory.grow (i32.const 16)) (i32.const -1))
+                (then (return (call $pack (i32.const 16) (i32.const 16)))))
+            (call $pack (i32.const 0) (i32.const 13)))
+    )"#;
+
+    #[test]
+    fn test_operators() {
+        let plugin = WasmPlugin::new(MODULE).unwrap();
+        let mut names = plugin.operators();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            vec!["answer", "args", "bare", "fail", "grow", "spin"]
+        );
+
+        let engine = Engine::new().add_wasm_plugin(&plugin);
+        let apply = |rule: Value| engine.apply(&rule, &json!({"a": "x"}));
+        assert_eq!(apply(json!({"answer": []})).unwrap(), json!(42));
+        assert_eq!(
+            apply(json!({"args": [1, {"var": "a"}, [true]]})).unwrap(),
+            json!([1, "x", [true]])
+        );
+        assert!(matches!(
+            apply(json!({"fail": []})),
+            Err(Error::InvalidArgument { reason, .. }) if reason == "nope"
+        ));
+        assert!(matches!(
+            apply(json!({"bare": []})),
+            Err(Error::PluginError { operator: Some(op), .. }) if op == "bare"
+        ));
+        // Operators are named without the prefix
+        assert!(!engine.has_operator("op_answer"));
+    }
+
+    #[test]
+    fn test_limits() {
+        let plugin = WasmPlugin::new(MODULE)
+            .unwrap()
+            .fuel(10_000)
+            .max_memory(4 * 65536);
+        let engine = Engine::new().add_wasm_plugin(&plugin);
+        let err = engine
+            .apply(&json!({"spin": []}), &json!(null))
+            .unwrap_err();
+        assert_eq!(err.code(), "plugin_error");
+        // The plugin is still usable after running out of fuel
+        assert_eq!(
+            engine.apply(&json!({"answer": []}), &json!(null)).unwrap(),
+            json!(42)
+        );
+        assert!(engine.apply(&json!({"grow": []}), &json!(null)).is_err());
+
+        let unlimited =
+            Engine::new().add_wasm_plugin(&WasmPlugin::new(MODULE).unwrap());
+        assert_eq!(
+            unlimited.apply(&json!({"grow": []}), &json!(null)).unwrap(),
+            json!(42)
+        );
+    }
+
+    #[test]
+    fn test_load_errors() {
+        for module in [
+            "not a module",
+            // Imports are not provided
+            r#"(module (import "env" "f" (func)) (memory (export "memory") 1))"#,
+            r#"(module (func (export "alloc") (param i32) (result i32) (i32.const 0)))"#,
+            r#"(module (memory (export "memory") 1))"#,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "op_wrong") (param i32) (result i32) (i32.const 0)))"#,
+        ] {
+            assert!(matches!(
+                WasmPlugin::new(module),
+                Err(Error::PluginError { operator: None, .. })
+            ));
+        }
+        assert!(WasmPlugin::from_file("/nonexistent.wasm").is_err());
+    }
+}
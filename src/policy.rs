@@ -0,0 +1,598 @@
+//! Operator permission policies
+//!
+//! A policy restricts which operators a rule may use, which variables it
+//! may read, how deeply nested and how large it may be, and how large its
+//! literal arrays may be. Policies are checked against the whole rule
+//! before it is parsed, so a violation anywhere in the rule, including in
+//! branches that would never be evaluated, causes the rule to be rejected
+//! before any evaluation work is done.
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::op::{self, split_with_escape};
+use crate::schema::{ITERATORS, VARS};
+
+/// The key of `{"var": []}`, which reads the data as a whole
+static WHOLE_DATA: Value = Value::Null;
+
+/// A set of restrictions on the rules an engine will run
+///
+/// ```rust
+/// use jsonlogic_rs::{Engine, Policy};
+/// use serde_json::json;
+///
+/// let engine = Engine::new().policy(Policy::default().deny("log").max_array_len(100));
+/// assert!(engine.apply(&json!({"log": 1}), &json!(null)).is_err());
+/// assert!(engine.apply(&json!({"+": [1, 1]}), &json!(null)).is_ok());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    allowed: Option<HashSet<String>>,
+    denied: HashSet<String>,
+    /// The paths of the allowed variables, as segments
+    allowed_vars: Option<Vec<Vec<String>>>,
+    max_array_len: Option<usize>,
+    max_depth: Option<usize>,
+    max_nodes: Option<usize>,
+}
+impl Policy {
+    /// Permit an operator
+    ///
+    /// Once any operator has been allowed, only allowed operators may be
+    /// used.
+    pub fn allow(mut self, operator: &str) -> Self {
+        self.allowed
+            .get_or_insert_with(HashSet::new)
+            .insert(operator.into());
+        self
+    }
+
+    /// Forbid an operator. Denials take precedence over allowances.
+    pub fn deny(mut self, operator: &str) -> Self {
+        self.denied.insert(operator.into());
+        self
+    }
+
+    /// Permit rules to read a variable from the data, along with anything
+    /// nested in it
+    ///
+    /// Once any variable has been allowed, rules may only read allowed
+    /// variables, so that e.g. a tenant's rules can't read fields beyond
+    /// their scope. Allowing `"user"` permits reading `"user.name"`, but
+    /// allowing `"user.name"` doesn't permit reading `"user"`, nor the data
+    /// as a whole.
+    ///
+    /// Variables given literally, as in `{"var": "user.name"}`, are checked
+    /// with the rest of the policy. Variables computed at runtime are
+    /// checked when they're read, failing with an `InvalidVariable` error.
+    /// Variables read by the expressions of operators like `map` and
+    /// `filter` refer to the items being iterated over, rather than the
+    /// data, so they aren't restricted.
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{Engine, Policy};
+    /// use serde_json::json;
+    ///
+    /// let engine = Engine::new().policy(Policy::default().allow_var("order"));
+    /// let data = json!({"order": {"total": 12}, "user": {"ssn": "123"}});
+    /// assert!(engine.apply(&json!({"var": "order.total"}), &data).is_ok());
+    /// assert!(engine.apply(&json!({"var": "user.ssn"}), &data).is_err());
+    /// assert!(engine
+    ///     .apply(&json!({"var": {"cat": ["user", ".ssn"]}}), &data)
+    ///     .is_err());
+    /// ```
+    pub fn allow_var(mut self, path: &str) -> Self {
+        self.allowed_vars
+            .get_or_insert_with(Vec::new)
+            .push(split_with_escape(path, '.'));
+        self
+    }
+
+    /// Whether rules may read the variable at a path of segments
+    pub(crate) fn allows_var(&self, segments: &[String]) -> bool {
+        match &self.allowed_vars {
+            Some(allowed) => allowed.iter().any(|path| segments.starts_with(path)),
+            None => true,
+        }
+    }
+
+    /// Limit the number of items in any array in the rule
+    pub fn max_array_len(mut self, len: usize) -> Self {
+        self.max_array_len = Some(len);
+        self
+    }
+
+    /// Limit how deeply arrays and objects may be nested in the rule
+    ///
+    /// A scalar rule has a depth of one, `{"var": "a"}` a depth of two, and
+    /// so on.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Limit the total number of JSON values in the rule, counting each
+    /// array, object, and scalar
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = Some(nodes);
+        self
+    }
+
+    /// The policy's rules, for a snapshot
+    pub(crate) fn to_json(&self) -> Value {
+        let sorted = |names: &HashSet<String>| {
+            let mut names: Vec<&String> = names.iter().collect();
+            names.sort();
+            serde_json::json!(names)
+        };
+        serde_json::json!({
+            "allowed": self.allowed.as_ref().map(sorted),
+            "denied": sorted(&self.denied),
+            "allowed_vars": self.allowed_vars,
+            "max_array_len": self.max_array_len,
+            "max_depth": self.max_depth,
+            "max_nodes": self.max_nodes,
+        })
+    }
+
+    /// Read a policy's rules from a snapshot
+    pub(crate) fn from_json(rules: &Value) -> Result<Self, Error> {
+        let invalid = || Error::InvalidData {
+            value: rules.clone(),
+            reason: "Invalid policy in snapshot".into(),
+        };
+        Ok(Self {
+            allowed: optional(rules, "allowed").ok_or_else(invalid)?,
+            denied: optional(rules, "denied")
+                .ok_or_else(invalid)?
+                .unwrap_or_default(),
+            allowed_vars: optional(rules, "allowed_vars").ok_or_else(invalid)?,
+            max_array_len: optional(rules, "max_array_len").ok_or_else(invalid)?,
+            max_depth: optional(rules, "max_depth").ok_or_else(invalid)?,
+            max_nodes: optional(rules, "max_nodes").ok_or_else(invalid)?,
+        })
+    }
+
+    /// Check a rule against the policy
+    pub(crate) fn check(&self, engine: &Engine, rule: &Value) -> Result<(), Error> {
+        let mut walk = Walk::new();
+        self.check_value(engine, rule, &mut walk, None)?;
+        if self.allowed_vars.is_some() {
+            self.check_vars(engine, rule, &mut Walk::new())?;
+        }
+        Ok(())
+    }
+
+    fn check_value(
+        &self,
+        engine: &Engine,
+        value: &Value,
+        walk: &mut Walk,
+        operator: Option<&str>,
+    ) -> Result<(), Error> {
+        walk.nodes += 1;
+        if let Some(max) = self.max_nodes {
+            if walk.nodes > max {
+                return violation(
+                    operator,
+                    &walk.path,
+                    format!("Rule exceeds maximum of {} nodes", max),
+                );
+            }
+        }
+        if let Some(max) = self.max_depth {
+            if walk.depth + 1 > max {
+                return violation(
+                    operator,
+                    &walk.path,
+                    format!("Rule exceeds maximum depth of {}", max),
+                );
+            }
+        }
+
+        match value {
+            Value::Array(items) => {
+                if let Some(max) = self.max_array_len {
+                    if items.len() > max {
+                        return violation(
+                            operator,
+                            &walk.path,
+                            format!(
+                                "Array of length {} exceeds maximum of {}",
+                                items.len(),
+                                max
+                            ),
+                        );
+                    }
+                }
+                items.iter().enumerate().try_for_each(|(idx, item)| {
+                    walk.descend(&idx.to_string(), |walk| {
+                        self.check_value(engine, item, walk, operator)
+                    })
+                })
+            }
+            Value::Object(obj) => {
+                let operator = match obj.iter().next() {
+                    Some((key, _))
+                        if obj.len() == 1 && op::is_operator(engine, key) =>
+                    {
+                        // Aliases are governed by their target operator
+                        // as well as by their own name
+                        let target = op::alias_target(key);
+                        let is_listed = |set: &HashSet<String>| {
+                            set.contains(key) || target.is_some_and(|t| set.contains(t))
+                        };
+                        if is_listed(&self.denied) {
+                            return violation(
+                                Some(key),
+                                &walk.path,
+                                "Operator is denied by policy".into(),
+                            );
+                        }
+                        if let Some(allowed) = &self.allowed {
+                            if !is_listed(allowed) {
+                                return violation(
+                                    Some(key),
+                                    &walk.path,
+                                    "Operator is not allowed by policy".into(),
+                                );
+                            }
+                        }
+                        Some(key.as_str())
+                    }
+                    _ => operator,
+                };
+                obj.iter().try_for_each(|(key, val)| {
+                    walk.descend(key, |walk| {
+                        self.check_value(engine, val, walk, operator)
+                    })
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the variables a rule reads from the data are allowed,
+    /// where they're given literally
+    ///
+    /// The arguments of iterators after the first read from the items being
+    /// iterated over, so they're skipped.
+    fn check_vars(
+        &self,
+        engine: &Engine,
+        value: &Value,
+        walk: &mut Walk,
+    ) -> Result<(), Error> {
+        let (key, args) = match value {
+            Value::Array(items) => {
+                return items.iter().enumerate().try_for_each(|(idx, item)| {
+                    walk.descend(&idx.to_string(), |walk| {
+                        self.check_vars(engine, item, walk)
+                    })
+                })
+            }
+            Value::Object(obj) if obj.len() == 1 => match obj.iter().next() {
+                Some((key, args)) if op::is_operator(engine, key) => (key, args),
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        let operator = op::alias_target(key).unwrap_or(key);
+        for var in read_keys(operator, args) {
+            if let Some(segments) = key_segments(var) {
+                if !self.allows_var(&segments) {
+                    return violation(
+                        Some(key),
+                        &walk.path,
+                        format!("Variable {} is not allowed by policy", var),
+                    );
+                }
+            }
+        }
+        let is_iterator = ITERATORS.iter().any(|(op, _)| *op == operator);
+        walk.descend(key, |walk| match args {
+            Value::Array(items) => {
+                items.iter().enumerate().try_for_each(|(idx, item)| {
+                    // The expression evaluated for each item reads from the
+                    // item rather than the data, but any further arguments,
+                    // like the initial value of `reduce`, read from the data
+                    if is_iterator && idx == 1 {
+                        return Ok(());
+                    }
+                    walk.descend(&idx.to_string(), |walk| {
+                        self.check_vars(engine, item, walk)
+                    })
+                })
+            }
+            _ => self.check_vars(engine, args, walk),
+        })
+    }
+}
+
+/// A violation of the policy, by an operator at a path
+fn violation(operator: Option<&str>, path: &str, reason: String) -> Result<(), Error> {
+    Err(Error::PolicyViolation {
+        operator: operator.map(String::from),
+        path: if path.is_empty() {
+            "/".into()
+        } else {
+            path.into()
+        },
+        reason,
+    })
+}
+
+/// The path segments of a variable key, or `None` if it's computed at
+/// runtime or isn't a valid key
+fn key_segments(key: &Value) -> Option<Vec<String>> {
+    match key {
+        Value::Null => Some(Vec::new()),
+        Value::String(s) => Some(split_with_escape(s, '.')),
+        Value::Number(n) => n.as_i64().map(|i| vec![i.to_string()]),
+        _ => None,
+    }
+}
+
+/// The keys a data operator reads, given its arguments
+///
+/// The null keys skipped by `missing` and friends aren't included, since
+/// they don't read anything.
+fn read_keys<'v>(operator: &str, args: &'v Value) -> Vec<&'v Value> {
+    let items = |value: Option<&'v Value>| match value {
+        Some(Value::Array(keys)) => keys.iter().filter(|key| !key.is_null()).collect(),
+        _ => Vec::new(),
+    };
+    match (operator, args) {
+        (_, Value::Array(args)) if VARS.contains(&operator) => {
+            vec![args.first().unwrap_or(&WHOLE_DATA)]
+        }
+        (_, key) if VARS.contains(&operator) => vec![key],
+        ("missing", Value::Array(keys)) => match keys.first() {
+            Some(Value::Array(_)) => items(keys.first()),
+            _ => keys.iter().filter(|key| !key.is_null()).collect(),
+        },
+        ("missing", key) if !key.is_null() => vec![key],
+        ("missing_some", Value::Array(args)) => items(args.get(1)),
+        ("require", Value::Array(args)) => items(args.first()),
+        _ => Vec::new(),
+    }
+}
+
+/// Read an optional field of a policy in a snapshot, or `None` if it's
+/// of the wrong type
+fn optional<T: serde::de::DeserializeOwned>(
+    rules: &Value,
+    key: &str,
+) -> Option<Option<T>> {
+    match rules.get(key) {
+        None | Some(Value::Null) => Some(None),
+        Some(value) => serde_json::from_value(value.clone()).ok().map(Some),
+    }
+}
+
+/// The state of a walk over a rule
+pub(crate) struct Walk {
+    /// The JSON Pointer to the current value
+    pub(crate) path: String,
+    depth: usize,
+    nodes: usize,
+}
+impl Walk {
+    pub(crate) fn new() -> Self {
+        Self {
+            path: String::new(),
+            depth: 0,
+            nodes: 0,
+        }
+    }
+
+    /// Run `f` one level deeper, with `segment` appended to the path
+    pub(crate) fn descend<T>(
+        &mut self,
+        segment: &str,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let len = self.path.len();
+        self.path.push('/');
+        self.path
+            .push_str(&segment.replace('~', "~0").replace('/', "~1"));
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        self.path.truncate(len);
+        res
+    }
+}
+
+#[cfg(test)]
+mod test_policy {
+    use super::*;
+    use serde_json::json;
+
+    fn violation(policy: Policy, rule: Value) -> (Option<String>, String) {
+        match policy.check(&Engine::new(), &rule) {
+            Err(Error::PolicyViolation { operator, path, .. }) => (operator, path),
+            other => panic!("expected a policy violation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_deny() {
+        let policy = Policy::default().deny("log");
+        assert!(policy.check(&Engine::new(), &json!({"+": [1, 2]})).is_ok());
+        assert_eq!(
+            violation(policy.clone(), json!({"log": 1})),
+            (Some("log".into()), "/".into())
+        );
+        // Lazily evaluated branches are checked too
+        assert_eq!(
+            violation(policy, json!({"if": [false, {"log": 1}, 2]})),
+            (Some("log".into()), "/if/1".into())
+        );
+    }
+
+    #[test]
+    fn test_allow() {
+        let policy = Policy::default().allow("==").allow("var");
+        assert!(policy
+            .check(&Engine::new(), &json!({"==": [{"var": "a"}, 1]}))
+            .is_ok());
+        assert_eq!(
+            violation(policy.clone(), json!({"==": [{"+": [1, 1]}, 2]})),
+            (Some("+".into()), "/==/0".into())
+        );
+        // Objects that are not operations are not restricted
+        assert!(policy
+            .check(&Engine::new(), &json!({"==": [{"a": 1, "b": 2}, 1]}))
+            .is_ok());
+        // Denials take precedence
+        assert_eq!(
+            violation(policy.deny("var"), json!({"var": "a"})),
+            (Some("var".into()), "/".into())
+        );
+    }
+
+    #[test]
+    fn test_aliases() {
+        // Denying an operator denies its aliases
+        assert_eq!(
+            violation(Policy::default().deny("in"), json!({"not_in": [1, [2]]})),
+            (Some("not_in".into()), "/".into())
+        );
+        // Allowing an operator allows its aliases
+        assert!(Policy::default()
+            .allow("in")
+            .check(&Engine::new(), &json!({"not_in": [1, [2]]}))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_allow_var() {
+        let policy = Policy::default().allow_var("user.age").allow_var("items");
+        let check = |rule: Value| policy.check(&Engine::new(), &rule);
+        assert!(check(json!({"<": [{"var": "user.age"}, 18]})).is_ok());
+        assert!(check(json!({"var": ["items.0.price", 0]})).is_ok());
+        assert!(check(json!({"missing": ["items", null]})).is_ok());
+        // Keys computed at runtime are checked when they're read
+        assert!(check(json!({"var": {"cat": ["user", ".ssn"]}})).is_ok());
+        // Expressions iterating over items read from the items
+        assert!(check(json!({"map": [{"var": "items"}, {"var": "price"}]})).is_ok());
+        assert_eq!(
+            violation(policy.clone(), json!({"var": "user"})),
+            (Some("var".into()), "/".into())
+        );
+        assert_eq!(
+            violation(policy.clone(), json!({"var": []})),
+            (Some("var".into()), "/".into())
+        );
+        assert_eq!(
+            violation(
+                policy.clone(),
+                json!({"map": [{"var": "orders"}, {"var": "price"}]})
+            ),
+            (Some("var".into()), "/map/0".into())
+        );
+        assert_eq!(
+            violation(
+                policy.clone(),
+                json!({"if": [true, {"missing_some": [1, ["items", "user.ssn"]]}]})
+            ),
+            (Some("missing_some".into()), "/if/1".into())
+        );
+        // The initial value of `reduce` reads from the data
+        assert!(check(json!({"reduce": [
+            {"var": "items"},
+            {"+": [{"var": "current.price"}, {"var": "accumulator"}]},
+            {"var": "user.age"},
+        ]}))
+        .is_ok());
+        assert_eq!(
+            violation(
+                policy.clone(),
+                json!({"reduce": [[], {"var": "accumulator"}, {"var": "secret"}]})
+            ),
+            (Some("var".into()), "/reduce/2".into())
+        );
+        assert_eq!(
+            violation(policy, json!({"require": [["user.age", "user\\.name"]]})),
+            (Some("require".into()), "/".into())
+        );
+    }
+
+    #[test]
+    fn test_runtime_vars() {
+        let engine = Engine::new().policy(Policy::default().allow_var("items"));
+        let data = json!({"items": [{"price": 2}, {"price": 3}], "secret": 1});
+        let computed = |key: &str| json!({"var": {"cat": [key]}});
+        assert!(engine.apply(&computed("secret"), &data).is_err());
+
+        // Items and accumulators aren't the data at the root
+        let rule = json!({"map": [{"var": "items"}, computed("price")]});
+        assert_eq!(engine.apply(&rule, &data).unwrap(), json!([2, 3]));
+        let rule = json!({"reduce": [
+            {"var": "items"},
+            {"+": [computed("accumulator"), computed("current.price")]},
+            0,
+        ]});
+        assert_eq!(engine.apply(&rule, &data).unwrap(), json!(5));
+
+        // Reads from the data after an iteration are restricted again,
+        // including those nested within the iteration's own arguments
+        let rule = json!({"cat": [
+            {"all": [{"var": "items"}, computed("price")]},
+            computed("secret"),
+        ]});
+        assert!(engine.apply(&rule, &data).is_err());
+        let rule =
+            json!({"map": [{"merge": [{"var": "items"}, computed("secret")]}, 1]});
+        assert!(engine.apply(&rule, &data).is_err());
+        let rule = json!({"reduce": [[], 0, computed("secret")]});
+        assert!(engine.apply(&rule, &data).is_err());
+    }
+
+    #[test]
+    fn test_max_array_len() {
+        let policy = Policy::default().max_array_len(2);
+        assert!(policy.check(&Engine::new(), &json!({"+": [1, 2]})).is_ok());
+        assert_eq!(
+            violation(policy.clone(), json!({"in": [1, [1, 2, 3]]})),
+            (Some("in".into()), "/in/1".into())
+        );
+        assert_eq!(violation(policy, json!([1, 2, 3])), (None, "/".into()));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let policy = Policy::default().max_depth(3);
+        assert!(policy.check(&Engine::new(), &json!(1)).is_ok());
+        assert!(policy.check(&Engine::new(), &json!({"+": [1, 2]})).is_ok());
+        assert_eq!(
+            violation(policy, json!({"+": [1, {"var": "a"}]})),
+            (Some("var".into()), "/+/1/var".into())
+        );
+    }
+
+    #[test]
+    fn test_max_nodes() {
+        let policy = Policy::default().max_nodes(4);
+        // object, array, and two numbers
+        assert!(policy.check(&Engine::new(), &json!({"+": [1, 2]})).is_ok());
+        assert_eq!(
+            violation(policy, json!({"+": [1, 2, 3]})),
+            (Some("+".into()), "/+/2".into())
+        );
+    }
+
+    #[test]
+    fn test_path_escaping() {
+        let policy = Policy::default().deny("var");
+        assert_eq!(
+            violation(policy, json!({"a": 1, "b~/c": {"var": "x"}})),
+            (Some("var".into()), "/b~0~1c".into())
+        );
+    }
+}
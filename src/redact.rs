@@ -0,0 +1,288 @@
+//! Redaction of sensitive data
+//!
+//! Audit records and failure explanations can contain values taken from the
+//! data, whether directly, as in "ssn "123-45-6789" != ...", or derived from
+//! them, as with the result of `{"cat": ["id:", {"var": "ssn"}]}`. Values
+//! chosen for redaction are used as usual while evaluating, but are then
+//! masked wherever they appear in these outputs: strings containing them
+//! have them replaced with `[REDACTED]`, as do numbers equal to them.
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::op::split_with_escape;
+
+/// What redacted values are replaced with
+pub(crate) const MASK: &str = "[REDACTED]";
+
+/// A function choosing values in the data to redact, given their path as
+/// keys and array indices, and the value itself
+pub(crate) type RedactFn = dyn Fn(&[String], &Value) -> bool + Send + Sync;
+
+/// The values an engine redacts
+#[derive(Clone, Default)]
+pub(crate) struct Redaction {
+    /// Paths as segments, where `*` matches any key or index
+    paths: Vec<Vec<String>>,
+    predicate: Option<Arc<RedactFn>>,
+}
+impl fmt::Debug for Redaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redaction")
+            .field("paths", &self.paths)
+            .field("predicate", &self.predicate.is_some())
+            .finish()
+    }
+}
+impl Redaction {
+    pub(crate) fn add_path(&mut self, path: &str) {
+        self.paths.push(split_with_escape(path, '.'));
+    }
+
+    pub(crate) fn set_predicate(&mut self, predicate: Arc<RedactFn>) {
+        self.predicate = Some(predicate);
+    }
+
+    /// Find the sensitive values in some data
+    pub(crate) fn sensitive(&self, data: &Value) -> Sensitive {
+        let mut sensitive = Sensitive::default();
+        if !self.paths.is_empty() || self.predicate.is_some() {
+            self.collect(data, &mut Vec::new(), &mut sensitive);
+        }
+        sensitive
+    }
+
+    fn collect(&self, value: &Value, path: &mut Vec<String>, out: &mut Sensitive) {
+        if self.redacts(path, value) {
+            out.add(value);
+            return;
+        }
+        let children: Box<dyn Iterator<Item = (String, &Value)>> = match value {
+            Value::Array(items) => {
+                Box::new(items.iter().enumerate().map(|(i, v)| (i.to_string(), v)))
+            }
+            Value::Object(obj) => Box::new(obj.iter().map(|(k, v)| (k.clone(), v))),
+            _ => return,
+        };
+        for (segment, child) in children {
+            path.push(segment);
+            self.collect(child, path, out);
+            path.pop();
+        }
+    }
+
+    fn redacts(&self, path: &[String], value: &Value) -> bool {
+        let matches = |redacted: &Vec<String>| {
+            redacted.len() == path.len()
+                && redacted
+                    .iter()
+                    .zip(path)
+                    .all(|(redacted, segment)| redacted == "*" || redacted == segment)
+        };
+        !path.is_empty() && self.paths.iter().any(matches)
+            || self.predicate.as_ref().is_some_and(|p| p(path, value))
+    }
+}
+
+/// The sensitive values found in some data, to be masked in outputs
+#[derive(Debug, Default)]
+pub(crate) struct Sensitive {
+    strings: Vec<String>,
+    numbers: Vec<String>,
+}
+impl Sensitive {
+    /// Add a value, or the strings and numbers within it
+    fn add(&mut self, value: &Value) {
+        match value {
+            Value::String(s) if !s.is_empty() => self.strings.push(s.clone()),
+            Value::Number(n) => self.numbers.push(n.to_string()),
+            Value::Array(items) => items.iter().for_each(|item| self.add(item)),
+            Value::Object(obj) => obj.values().for_each(|val| self.add(val)),
+            _ => {}
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.strings.is_empty() && self.numbers.is_empty()
+    }
+
+    /// Mask sensitive values in text
+    ///
+    /// Numbers are only masked where they aren't part of a longer number
+    /// or word, so that redacting `16` doesn't affect `160`.
+    pub(crate) fn text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for string in &self.strings {
+            text = text.replace(string.as_str(), MASK);
+        }
+        for number in &self.numbers {
+            text = replace_token(&text, number);
+        }
+        text
+    }
+
+    /// Mask sensitive values within a value
+    pub(crate) fn value(&self, value: Value) -> Value {
+        if self.is_empty() {
+            return value;
+        }
+        match value {
+            Value::String(s) => Value::String(self.text(&s)),
+            Value::Number(n) if self.numbers.contains(&n.to_string()) => MASK.into(),
+            Value::Array(items) => {
+                Value::Array(items.into_iter().map(|item| self.value(item)).collect())
+            }
+            Value::Object(obj) => Value::Object(
+                obj.into_iter()
+                    .map(|(key, val)| (key, self.value(val)))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    /// Mask sensitive values in the values and messages of an error
+    pub(crate) fn error(&self, err: Error) -> Error {
+        if self.is_empty() {
+            return err;
+        }
+        match err {
+            Error::InvalidData { value, reason } => Error::InvalidData {
+                value: self.value(value),
+                reason: self.text(&reason),
+            },
+            Error::InvalidOperation { key, reason } => Error::InvalidOperation {
+                key,
+                reason: self.text(&reason),
+            },
+            Error::InvalidVariable { value, reason } => Error::InvalidVariable {
+                value: self.value(value),
+                reason: self.text(&reason),
+            },
+            Error::InvalidVariableKey { value, reason } => Error::InvalidVariableKey {
+                value: self.value(value),
+                reason: self.text(&reason),
+            },
+            Error::InvalidArgument {
+                value,
+                operation,
+                reason,
+            } => Error::InvalidArgument {
+                value: self.value(value),
+                operation,
+                reason: self.text(&reason),
+            },
+            Error::MalformedArgument {
+                operation,
+                index,
+                item,
+                value,
+                expected,
+            } => Error::MalformedArgument {
+                operation,
+                index,
+                item,
+                value: self.value(value),
+                expected,
+            },
+            Error::InvalidVarMap(value) => Error::InvalidVarMap(self.value(value)),
+            Error::UnexpectedError(message) => {
+                Error::UnexpectedError(self.text(&message))
+            }
+            Error::WrongArgumentCount {
+                operator,
+                expected,
+                actual,
+                arguments,
+            } => Error::WrongArgumentCount {
+                operator,
+                expected,
+                actual,
+                arguments: self.text(&arguments),
+            },
+            Error::PluginError { operator, reason } => Error::PluginError {
+                operator,
+                reason: self.text(&reason),
+            },
+            Error::RuleRaised { operator, message } => Error::RuleRaised {
+                operator,
+                message: self.text(&message),
+            },
+            other @ (Error::PolicyViolation { .. } | Error::ResultTooLarge { .. }) => {
+                other
+            }
+        }
+    }
+
+    pub(crate) fn result(&self, result: Result<Value, Error>) -> Result<Value, Error> {
+        match result {
+            Ok(value) => Ok(self.value(value)),
+            Err(err) => Err(self.error(err)),
+        }
+    }
+}
+
+/// Replace a number in text wherever it's not part of a longer token
+fn replace_token(text: &str, number: &str) -> String {
+    let is_token_char = |c: char| c.is_alphanumeric() || c == '.';
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find(number) {
+        let (before, after) = (&rest[..idx], &rest[idx + number.len()..]);
+        let bounded = !out
+            .chars()
+            .chain(before.chars())
+            .last()
+            .is_some_and(is_token_char)
+            && !after.chars().next().is_some_and(is_token_char);
+        out.push_str(before);
+        out.push_str(if bounded { MASK } else { number });
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod test_redact {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sensitive() {
+        let mut redaction = Redaction::default();
+        redaction.add_path("user.ssn");
+        redaction.add_path("cards.*.number");
+        let sensitive = redaction.sensitive(&json!({
+            "user": {"ssn": "123-45-6789", "age": 16},
+            "cards": [{"number": 4111}, {"number": 4242}],
+            "ssn": "unrelated",
+        }));
+        assert_eq!(sensitive.strings, vec!["123-45-6789"]);
+        assert_eq!(sensitive.numbers, vec!["4111", "4242"]);
+
+        assert_eq!(
+            sensitive.text(r#"ssn "123-45-6789" is 4111, 41110, or 4242.5"#),
+            r#"ssn "[REDACTED]" is [REDACTED], 41110, or 4242.5"#
+        );
+        assert_eq!(
+            sensitive.value(json!({"id": "id:123-45-6789", "n": [4242, 16]})),
+            json!({"id": "id:[REDACTED]", "n": ["[REDACTED]", 16]})
+        );
+    }
+
+    #[test]
+    fn test_predicate() {
+        let mut redaction = Redaction::default();
+        redaction.set_predicate(Arc::new(|path: &[String], _: &Value| {
+            path.last().is_some_and(|key| key.ends_with("token"))
+        }));
+        let sensitive =
+            redaction.sensitive(&json!({"auth": {"api_token": "s3cr3t"}, "a": 1}));
+        assert_eq!(sensitive.strings, vec!["s3cr3t"]);
+        assert!(sensitive.numbers.is_empty());
+    }
+}
@@ -0,0 +1,356 @@
+//! Evaluating many named rules against one document
+//!
+//! Validation is often expressed as many small rules rather than one large
+//! one, so that each can be reported on separately. A rule set evaluates
+//! each of its rules against the same data, and an error in one rule is
+//! recorded as that rule's outcome rather than aborting the others.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::js_op::truthy;
+
+/// How serious it is for a rule to fail
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Info,
+    Warning,
+    #[default]
+    Error,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    rule: Value,
+    severity: Severity,
+    weight: f64,
+}
+
+/// A set of named rules, evaluated together
+///
+/// ```rust
+/// use jsonlogic_rs::{Engine, RuleSet, Severity};
+/// use serde_json::json;
+///
+/// let rules = RuleSet::new()
+///     .add("adult", json!({">=": [{"var": "age"}, 18]}))
+///     .add_with_severity("has_email", json!({"!!": {"var": "email"}}), Severity::Warning)
+///     .add("broken", json!({"==": [1]}));
+///
+/// let results = rules.evaluate(&Engine::new(), &json!({"age": 21}));
+/// assert!(results["adult"].passed());
+/// assert!(!results["has_email"].passed());
+/// assert!(results["broken"].result.is_err());
+///
+/// let failed: Vec<&str> = results.failures(Severity::Warning).map(|(name, _)| name).collect();
+/// assert_eq!(failed, vec!["broken", "has_email"]);
+/// assert!(!results.passed());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: BTreeMap<String, Rule>,
+}
+impl RuleSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule whose failure is an error, replacing any rule of the
+    /// same name
+    pub fn add(self, name: &str, rule: Value) -> Self {
+        self.add_with_severity(name, rule, Severity::Error)
+    }
+
+    /// Add a rule with the given severity, replacing any rule of the same
+    /// name
+    pub fn add_with_severity(
+        mut self,
+        name: &str,
+        rule: Value,
+        severity: Severity,
+    ) -> Self {
+        self.rules.insert(
+            name.into(),
+            Rule {
+                rule,
+                severity,
+                weight: 1.0,
+            },
+        );
+        self
+    }
+
+    /// Add a rule which contributes the given weight to the set's score when
+    /// it passes, replacing any rule of the same name
+    ///
+    /// Rules added otherwise have a weight of one. Negative weights can be
+    /// used for rules that should lower the score, like risk factors.
+    pub fn add_weighted(mut self, name: &str, rule: Value, weight: f64) -> Self {
+        self.rules.insert(
+            name.into(),
+            Rule {
+                rule,
+                severity: Severity::Error,
+                weight,
+            },
+        );
+        self
+    }
+
+    /// The names of the rules in the set, in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Check that each rule is valid for an engine without evaluating it,
+    /// returning the errors of those that aren't
+    pub fn validate(&self, engine: &Engine) -> BTreeMap<String, Error> {
+        self.rules
+            .iter()
+            .filter_map(|(name, rule)| {
+                engine
+                    .validate(&rule.rule)
+                    .err()
+                    .map(|err| (name.clone(), err))
+            })
+            .collect()
+    }
+
+    /// Evaluate every rule against the data
+    pub fn evaluate(&self, engine: &Engine, data: &Value) -> RuleSetResults {
+        let outcomes = self
+            .rules
+            .iter()
+            .map(|(name, rule)| {
+                let outcome = RuleOutcome {
+                    severity: rule.severity,
+                    result: engine.apply(&rule.rule, data),
+                };
+                (name.clone(), outcome)
+            })
+            .collect();
+        RuleSetResults { outcomes }
+    }
+
+    /// Evaluate every rule against the data, scoring the weights of those
+    /// that pass
+    ///
+    /// ```rust
+    /// use jsonlogic_rs::{Engine, RuleSet};
+    /// use serde_json::json;
+    ///
+    /// let rules = RuleSet::new()
+    ///     .add_weighted("enterprise", json!({">": [{"var": "employees"}, 1000]}), 30.0)
+    ///     .add_weighted("visited_pricing", json!({"var": "visited_pricing"}), 10.0)
+    ///     .add_weighted("free_email", json!({"var": "free_email"}), -20.0);
+    ///
+    /// let score = rules.score(
+    ///     &Engine::new(),
+    ///     &json!({"employees": 5000, "visited_pricing": true, "free_email": false}),
+    /// );
+    /// assert_eq!(score.total, 40.0);
+    /// assert_eq!(score.contributions["free_email"], 0.0);
+    /// ```
+    pub fn score(&self, engine: &Engine, data: &Value) -> Score {
+        let mut score = Score::default();
+        for (name, outcome) in self.evaluate(engine, data).outcomes {
+            let contribution = if outcome.passed() {
+                self.rules[&name].weight
+            } else {
+                0.0
+            };
+            score.total += contribution;
+            if let Err(err) = outcome.result {
+                score.errors.insert(name.clone(), err);
+            }
+            score.contributions.insert(name, contribution);
+        }
+        score
+    }
+}
+
+/// The outcome of evaluating one rule of a set
+#[derive(Debug)]
+pub struct RuleOutcome {
+    pub severity: Severity,
+    /// The rule's result, or the error evaluating it
+    pub result: Result<Value, Error>,
+}
+impl RuleOutcome {
+    /// Whether the rule evaluated to a truthy value
+    pub fn passed(&self) -> bool {
+        matches!(&self.result, Ok(value) if truthy(value))
+    }
+}
+/// Serialized as an object with either a `result` or an `error`
+#[cfg(feature = "serde")]
+impl serde::Serialize for RuleOutcome {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("severity", &self.severity)?;
+        map.serialize_entry("passed", &self.passed())?;
+        crate::audit::serialize_result(&mut map, &self.result)?;
+        map.end()
+    }
+}
+
+/// The outcomes of evaluating a rule set, by rule name
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RuleSetResults {
+    pub outcomes: BTreeMap<String, RuleOutcome>,
+}
+impl RuleSetResults {
+    pub fn get(&self, name: &str) -> Option<&RuleOutcome> {
+        self.outcomes.get(name)
+    }
+
+    /// Whether every rule whose failure is an error passed
+    pub fn passed(&self) -> bool {
+        self.failures(Severity::Error).next().is_none()
+    }
+
+    /// The rules of at least the given severity that didn't pass, because
+    /// they were falsy or couldn't be evaluated
+    pub fn failures(
+        &self,
+        severity: Severity,
+    ) -> impl Iterator<Item = (&str, &RuleOutcome)> {
+        self.outcomes
+            .iter()
+            .filter(move |(_, outcome)| {
+                outcome.severity >= severity && !outcome.passed()
+            })
+            .map(|(name, outcome)| (name.as_str(), outcome))
+    }
+
+    /// The rules that couldn't be evaluated, with their errors
+    pub fn errors(&self) -> impl Iterator<Item = (&str, &Error)> {
+        self.outcomes.iter().filter_map(|(name, outcome)| {
+            outcome
+                .result
+                .as_ref()
+                .err()
+                .map(|err| (name.as_str(), err))
+        })
+    }
+}
+/// The score of a rule set for some data
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Score {
+    /// The sum of the contributions of all rules
+    pub total: f64,
+    /// Each rule's weight if it passed, or zero if it didn't
+    pub contributions: BTreeMap<String, f64>,
+    /// The rules that couldn't be evaluated, which contribute nothing
+    pub errors: BTreeMap<String, Error>,
+}
+
+impl std::ops::Index<&str> for RuleSetResults {
+    type Output = RuleOutcome;
+
+    fn index(&self, name: &str) -> &RuleOutcome {
+        &self.outcomes[name]
+    }
+}
+
+#[cfg(test)]
+mod test_rule_set {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_evaluate() {
+        let rules = RuleSet::new()
+            .add("positive", json!({">": [{"var": "x"}, 0]}))
+            .add("small", json!({"<": [{"var": "x"}, 10]}))
+            .add_with_severity(
+                "even",
+                json!({"==": [{"%": [{"var": "x"}, 2]}, 0]}),
+                Severity::Info,
+            )
+            .add("broken", json!({"+": [1, 2]}))
+            .add("broken", json!({"==": [1]}));
+        assert_eq!(rules.len(), 4);
+        assert_eq!(
+            rules.names().collect::<Vec<_>>(),
+            vec!["broken", "even", "positive", "small"]
+        );
+
+        let results = rules.evaluate(&Engine::new(), &json!({"x": 3}));
+        assert_eq!(results.outcomes.len(), 4);
+        assert!(results["positive"].passed());
+        assert!(results["small"].passed());
+        assert!(!results["even"].passed());
+        assert_eq!(results["even"].result.as_ref().unwrap(), &json!(false));
+        assert!(results.get("missing").is_none());
+
+        let errors: Vec<&str> = results.errors().map(|(name, _)| name).collect();
+        assert_eq!(errors, vec!["broken"]);
+        let failed = |severity| {
+            results
+                .failures(severity)
+                .map(|(name, _)| name)
+                .collect::<Vec<&str>>()
+        };
+        assert_eq!(failed(Severity::Error), vec!["broken"]);
+        assert_eq!(failed(Severity::Info), vec!["broken", "even"]);
+        assert!(!results.passed());
+    }
+
+    #[test]
+    fn test_score() {
+        let rules = RuleSet::new()
+            .add("any", json!({"var": "a"}))
+            .add_weighted("big", json!({">": [{"var": "a"}, 10]}), 2.5)
+            .add_weighted("negative", json!({"<": [{"var": "a"}, 0]}), -4.0)
+            .add_weighted("broken", json!({"==": [1]}), 100.0);
+        let score = |data| rules.score(&Engine::new(), &data);
+
+        let high = score(json!({"a": 20}));
+        assert_eq!(high.total, 3.5);
+        assert_eq!(high.contributions["any"], 1.0);
+        assert_eq!(high.contributions["big"], 2.5);
+        assert_eq!(high.contributions["negative"], 0.0);
+        assert_eq!(high.contributions["broken"], 0.0);
+        assert_eq!(high.errors.keys().collect::<Vec<_>>(), vec!["broken"]);
+
+        assert_eq!(score(json!({"a": -1})).total, -3.0);
+        assert_eq!(score(json!({"a": 0})).total, 0.0);
+        assert_eq!(
+            RuleSet::new().score(&Engine::new(), &json!(null)).total,
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let rules = RuleSet::new()
+            .add("ok", json!({"var": "a"}))
+            .add("bad", json!({"==": [1]}));
+        let errors = rules.validate(&Engine::new());
+        assert_eq!(errors.keys().collect::<Vec<_>>(), vec!["bad"]);
+        assert!(RuleSet::new()
+            .evaluate(&Engine::new(), &json!(null))
+            .passed());
+    }
+}
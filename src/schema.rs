@@ -0,0 +1,420 @@
+//! Data schema inference
+//!
+//! Inference walks a rule without evaluating it, collecting the data paths
+//! read by `var`, `missing`, `missing_some`, and `require`, along with the types that
+//! the operators they're passed to imply. Inference is necessarily
+//! approximate: paths computed at runtime can't be known, and JsonLogic's
+//! implicit type conversions mean an implied type is what the rule's author
+//! most likely intended rather than the only type that would work.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{json, Map, Value};
+
+use crate::engine::Engine;
+use crate::op;
+
+const NUMBER: &[&str] = &["number"];
+const STRING: &[&str] = &["string"];
+const BOOLEAN: &[&str] = &["boolean"];
+const ARRAY: &[&str] = &["array"];
+const NUMBERS: &[&str] = &["array", "number"];
+const CONTAINER: &[&str] = &["array", "string"];
+const STRING_OR_NUMBER: &[&str] = &["number", "string"];
+
+/// Operators that read a single variable
+pub(crate) const VARS: &[&str] = &["var", "var_str", "var_num", "var_bool"];
+
+/// Operators that iterate over an array given as their first argument, with
+/// the name under which each item is available to their other arguments
+/// (`None` if the item is the data itself)
+pub(crate) const ITERATORS: &[(&str, Option<&str>)] = &[
+    ("map", None),
+    ("filter", None),
+    ("all", None),
+    ("some", None),
+    ("none", None),
+    ("max_by", None),
+    ("min_by", None),
+    ("take_while", None),
+    ("first", None),
+    ("reduce", Some("current")),
+];
+
+/// The types implied for an operator's argument
+fn implied_types(
+    key: &str,
+    idx: usize,
+    num_args: usize,
+) -> Option<&'static [&'static str]> {
+    match (key, idx) {
+        // A single argument to these is spread if it's an array
+        ("+" | "*" | "max" | "min", _) if num_args == 1 => Some(NUMBERS),
+        ("+" | "-" | "*" | "/" | "%" | "<" | "<=" | ">" | ">=" | "max" | "min", _) => {
+            Some(NUMBER)
+        }
+        ("cat" | "startsWith" | "endsWith" | "contains", _) => Some(STRING),
+        (
+            "measure" | "measure_eq" | "measure_lt" | "measure_lte" | "measure_gt"
+            | "measure_gte",
+            _,
+        ) => Some(STRING),
+        ("in_cidr" | "in_region", 0) => Some(STRING),
+        ("sha256" | "md5" | "xxh64" | "bucket" | "luhn_valid" | "checksum", 0) => {
+            Some(STRING_OR_NUMBER)
+        }
+        ("checksum", 1) => Some(STRING),
+        ("substr", 0) => Some(STRING),
+        ("substr", _) => Some(NUMBER),
+        ("bucket", 1) => Some(NUMBER),
+        ("get", 1) => Some(STRING_OR_NUMBER),
+        ("flatten" | "is_sorted" | "all_distinct", 0) => Some(ARRAY),
+        ("flatten", 1) => Some(NUMBER),
+        ("take", 0) => Some(ARRAY),
+        ("take", 1) => Some(NUMBER),
+        ("in" | "in_i" | "not_in" | "not_in_i", 1) => Some(CONTAINER),
+        ("contains_all" | "contains_any", 0) => Some(CONTAINER),
+        _ => ITERATORS
+            .iter()
+            .find(|(op, _)| *op == key)
+            .filter(|_| idx == 0)
+            .map(|_| ARRAY),
+    }
+}
+
+/// The schema of a value, accumulated over all references to it
+#[derive(Debug, Default)]
+struct Schema {
+    types: BTreeSet<&'static str>,
+    properties: BTreeMap<String, Schema>,
+    items: Option<Box<Schema>>,
+}
+impl Schema {
+    /// Get the schema at a path of segments, creating it if needed
+    fn at<'s>(&mut self, path: impl IntoIterator<Item = &'s str>) -> &mut Schema {
+        path.into_iter().fold(self, |schema, segment| {
+            // Numeric segments index into arrays
+            if !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+                schema.items.get_or_insert_with(Default::default)
+            } else {
+                schema.properties.entry(segment.into()).or_default()
+            }
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        let mut rv = Map::new();
+        let mut types = self.types.clone();
+        if !self.properties.is_empty() {
+            types.insert("object");
+            rv.insert(
+                "properties".into(),
+                Value::Object(
+                    self.properties
+                        .iter()
+                        .map(|(key, schema)| (key.clone(), schema.to_json()))
+                        .collect(),
+                ),
+            );
+        }
+        if let Some(items) = &self.items {
+            types.insert("array");
+            rv.insert("items".into(), items.to_json());
+        }
+        match types.len() {
+            0 => {}
+            1 => {
+                rv.insert("type".into(), json!(types.iter().next()));
+            }
+            _ => {
+                rv.insert("type".into(), json!(types));
+            }
+        }
+        Value::Object(rv)
+    }
+}
+
+/// Infer a JSON Schema describing the data a rule expects
+///
+/// The schema lists each data path the rule reads, typed according to the
+/// operators that use it. Since a missing value is usually treated as
+/// `null` rather than an error, no properties are marked as required.
+///
+/// ```rust
+/// use jsonlogic_rs::infer_schema;
+/// use serde_json::json;
+///
+/// let schema = infer_schema(&json!({"<": [{"var": "person.age"}, 18]}));
+/// assert_eq!(
+///     schema["properties"]["person"]["properties"]["age"],
+///     json!({"type": "number"})
+/// );
+/// ```
+pub fn infer_schema(rule: &Value) -> Value {
+    let mut inference = Inference {
+        engine: Engine::new(),
+    };
+    let mut root = Schema::default();
+    inference.visit(rule, &mut root, None, None);
+    let mut schema = match root.to_json() {
+        Value::Object(schema) => schema,
+        _ => Map::new(),
+    };
+    schema.insert(
+        "$schema".into(),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    // The data is always an object unless the rule only indexes into it
+    schema.entry("type").or_insert_with(|| json!("object"));
+    Value::Object(schema)
+}
+
+struct Inference {
+    engine: Engine,
+}
+impl Inference {
+    /// Visit a value, recording the data it references in `scope`.
+    ///
+    /// `implied` is the set of types implied for the value by the operator
+    /// it is passed to. If `prefix` is set, only paths starting with that
+    /// segment are recorded, with the segment removed.
+    fn visit(
+        &mut self,
+        value: &Value,
+        scope: &mut Schema,
+        implied: Option<&[&'static str]>,
+        prefix: Option<&str>,
+    ) {
+        let (key, args) = match op::split_operation(&self.engine, value) {
+            Some(operation) => operation,
+            None => {
+                if let Value::Array(items) = value {
+                    items
+                        .iter()
+                        .for_each(|item| self.visit(item, scope, None, prefix));
+                }
+                return;
+            }
+        };
+        let key = op::alias_target(key).unwrap_or(key);
+
+        match key {
+            _ if VARS.contains(&key) => {
+                // Typed variables are checked, so their type takes precedence
+                let implied = match key {
+                    "var_str" => Some(STRING),
+                    "var_num" => Some(NUMBER),
+                    "var_bool" => Some(BOOLEAN),
+                    _ => implied,
+                };
+                if let Some(path) = args.first().and_then(|arg| literal_path(arg)) {
+                    if let Some(schema) = resolve(scope, &path, prefix) {
+                        schema.types.extend(implied.unwrap_or_default());
+                    }
+                }
+            }
+            "missing" => {
+                let keys = match args.first() {
+                    Some(Value::Array(keys)) => keys.iter().collect(),
+                    _ => args.clone(),
+                };
+                self.record_paths(&keys, scope, prefix);
+            }
+            "require" => {
+                if let Some(Value::Array(keys)) = args.first() {
+                    self.record_paths(&keys.iter().collect::<Vec<_>>(), scope, prefix);
+                }
+            }
+            "missing_some" => {
+                if let Some(Value::Array(keys)) = args.get(1) {
+                    self.record_paths(&keys.iter().collect::<Vec<_>>(), scope, prefix);
+                }
+            }
+            _ => {}
+        }
+        if op::is_data_operator(key) {
+            // Arguments that are themselves operations, e.g. a computed
+            // key, may still reference data
+            args.iter()
+                .for_each(|arg| self.visit(arg, scope, None, prefix));
+            return;
+        }
+
+        let iterator = ITERATORS.iter().find(|(op, _)| *op == key);
+        for (idx, arg) in args.iter().enumerate() {
+            let types = implied_types(key, idx, args.len());
+            match iterator {
+                // The other arguments of an iterator see the items of the
+                // array as their data
+                Some((_, item_prefix)) if idx > 0 => {
+                    let items_path = op::split_operation(&self.engine, args[0])
+                        .filter(|(key, _)| VARS.contains(key))
+                        .and_then(|(_, var_args)| {
+                            var_args.first().and_then(|k| literal_path(k))
+                        });
+                    let items =
+                        items_path.and_then(|path| resolve(scope, &path, prefix));
+                    match items {
+                        Some(items) => {
+                            let item_scope =
+                                items.items.get_or_insert_with(Default::default);
+                            self.visit(arg, item_scope, types, *item_prefix)
+                        }
+                        // Data referenced by items computed at runtime can't be
+                        // placed in the schema
+                        None => {
+                            self.visit(arg, &mut Schema::default(), types, *item_prefix)
+                        }
+                    }
+                }
+                _ => self.visit(arg, scope, types, prefix),
+            }
+        }
+    }
+
+    /// Record keys, as given to `missing`, as present in the schema
+    fn record_paths(
+        &mut self,
+        keys: &[&Value],
+        scope: &mut Schema,
+        prefix: Option<&str>,
+    ) {
+        for key in keys {
+            match literal_path(key) {
+                Some(path) => {
+                    resolve(scope, &path, prefix);
+                }
+                None => self.visit(key, scope, None, prefix),
+            }
+        }
+    }
+}
+
+/// The path segments of a literal variable key
+///
+/// Keys given as arrays (`{"var": ["a", default]}`) use their first item.
+/// Returns `None` for keys computed at runtime and for the empty key,
+/// which refers to the data as a whole.
+fn literal_path(key: &Value) -> Option<Vec<String>> {
+    match key {
+        Value::Array(items) => items.first().and_then(literal_path),
+        Value::String(s) if !s.is_empty() => {
+            Some(s.split('.').map(String::from).collect())
+        }
+        Value::Number(n) => Some(vec![n.to_string()]),
+        _ => None,
+    }
+}
+
+/// Find the schema for a path in a scope, removing the required prefix
+fn resolve<'s>(
+    scope: &'s mut Schema,
+    path: &[String],
+    prefix: Option<&str>,
+) -> Option<&'s mut Schema> {
+    let path = match prefix {
+        Some(prefix) if path.first().map(String::as_str) == Some(prefix) => &path[1..],
+        Some(_) => return None,
+        None => path,
+    };
+    Some(scope.at(path.iter().map(String::as_str)))
+}
+
+#[cfg(test)]
+mod test_schema {
+    use super::*;
+
+    #[test]
+    fn test_nested_paths() {
+        let rule = json!({"and": [
+            {"<": [{"var": "person.age"}, 18]},
+            {"in": [{"var": "person.country"}, ["US", "CA"]]},
+            {"startsWith": [{"var": ["person.name", ""]}, "A"]},
+            {"var": "flags.0"},
+            {"var_bool": "active"},
+        ]});
+        assert_eq!(
+            infer_schema(&rule),
+            json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "type": "object",
+                "properties": {
+                    "active": {"type": "boolean"},
+                    "flags": {"type": "array", "items": {}},
+                    "person": {
+                        "type": "object",
+                        "properties": {
+                            "age": {"type": "number"},
+                            "country": {},
+                            "name": {"type": "string"},
+                        },
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_conflicting_types() {
+        let rule = json!({"or": [
+            {"==": [{"cat": [{"var": "a"}, "x"]}, "1x"]},
+            {">": [{"var": "a"}, 1]},
+            {"in": ["x", {"var": "b"}]},
+        ]});
+        let schema = infer_schema(&rule);
+        assert_eq!(
+            schema["properties"]["a"],
+            json!({"type": ["number", "string"]})
+        );
+        assert_eq!(
+            schema["properties"]["b"],
+            json!({"type": ["array", "string"]})
+        );
+    }
+
+    #[test]
+    fn test_iterators() {
+        let rule = json!({"and": [
+            {"all": [{"var": "items"}, {">": [{"var": "price"}, 0]}]},
+            {"reduce": [
+                {"var": "items"},
+                {"+": [{"var": "accumulator"}, {"var": "current.qty"}]},
+                0,
+            ]},
+        ]});
+        assert_eq!(
+            infer_schema(&rule)["properties"]["items"],
+            json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "price": {"type": "number"},
+                        "qty": {"type": "number"},
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing() {
+        let rule = json!({"if": [
+            {"missing": ["a", "b.c"]},
+            {"missing_some": [1, ["d", "e"]]},
+            {"require": [["h"]]},
+            {"var": {"cat": ["f", "g"]}},
+        ]});
+        let properties = &infer_schema(&rule)["properties"];
+        assert_eq!(
+            properties,
+            &json!({
+                "a": {},
+                "b": {"type": "object", "properties": {"c": {}}},
+                "d": {},
+                "e": {},
+                "h": {},
+            })
+        );
+    }
+}
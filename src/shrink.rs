@@ -0,0 +1,315 @@
+//! Shrinking failing cases to minimal reproductions
+//!
+//! Given a rule and data that produce an error or an unexpected result, a
+//! shrink repeatedly tries smaller versions of them, keeping each one that
+//! still produces the same outcome, until no smaller version does. Rules
+//! are made smaller by replacing operations with operations among their
+//! arguments and by removing arguments and the items of arrays, and data by
+//! removing the keys of objects and the items of arrays.
+//!
+//! Operations are never replaced by literals, since a literal can trivially
+//! reproduce any result: an unexpected `true` from a rule is reproduced by
+//! the rule `true`, which says nothing about why.
+
+use serde_json::Value;
+
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::op;
+
+/// A rule and data shrunk by [`Engine::shrink()`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shrunk {
+    pub rule: Value,
+    pub data: Value,
+}
+
+/// A key or index within a value
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// A way of making a value smaller
+#[derive(Debug, Clone)]
+enum Step {
+    /// Remove the child of the value at a path
+    Remove(Vec<Segment>, Segment),
+    /// Replace the value at a path with its descendant at a further path
+    Hoist(Vec<Segment>, Vec<Segment>),
+}
+impl Step {
+    fn apply(&self, value: &Value) -> Value {
+        let mut value = value.clone();
+        match self {
+            Step::Remove(path, child) => match (get_mut(&mut value, path), child) {
+                (Value::Array(items), Segment::Index(idx)) => {
+                    items.remove(*idx);
+                }
+                (Value::Object(obj), Segment::Key(key)) => {
+                    obj.remove(key);
+                }
+                _ => unreachable!("Steps are found from the value they apply to"),
+            },
+            Step::Hoist(path, descendant) => {
+                let node = get_mut(&mut value, path);
+                *node = get_mut(node, descendant).take();
+            }
+        }
+        value
+    }
+}
+
+fn get_mut<'v>(value: &'v mut Value, path: &[Segment]) -> &'v mut Value {
+    path.iter().fold(value, |value, segment| match segment {
+        Segment::Key(key) => &mut value[key.as_str()],
+        Segment::Index(idx) => &mut value[*idx],
+    })
+}
+
+fn child_path(path: &[Segment], segment: Segment) -> Vec<Segment> {
+    let mut path = path.to_vec();
+    path.push(segment);
+    path
+}
+
+/// The keys or indices and values of the children of an object or array
+fn children(value: &Value) -> Vec<(Segment, &Value)> {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| (Segment::Index(idx), item))
+            .collect(),
+        Value::Object(obj) => obj
+            .iter()
+            .map(|(key, val)| (Segment::Key(key.clone()), val))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The ways of making the objects and arrays within data smaller, outermost
+/// first
+fn data_steps(value: &Value, path: &mut Vec<Segment>, out: &mut Vec<Step>) {
+    let children = children(value);
+    for (segment, _) in &children {
+        out.push(Step::Remove(path.clone(), segment.clone()));
+    }
+    for (segment, child) in children {
+        path.push(segment);
+        data_steps(child, path, out);
+        path.pop();
+    }
+}
+
+/// The ways of making a rule smaller, outermost first
+fn rule_steps(
+    engine: &Engine,
+    value: &Value,
+    path: &mut Vec<Segment>,
+    out: &mut Vec<Step>,
+) {
+    let (key, args) = match value {
+        Value::Object(obj) if op::split_operation(engine, value).is_some() => {
+            obj.iter().next().expect("Operations have a key")
+        }
+        _ => return literal_steps(engine, value, path, out),
+    };
+    let args: Vec<(Vec<Segment>, &Value)> = match args {
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| {
+                (vec![Segment::Key(key.clone()), Segment::Index(idx)], item)
+            })
+            .collect(),
+        _ => vec![(vec![Segment::Key(key.clone())], args)],
+    };
+    for (arg_path, arg) in &args {
+        if op::split_operation(engine, arg).is_some() {
+            out.push(Step::Hoist(path.clone(), arg_path.clone()));
+        }
+    }
+    // A non-array argument can't be removed, but operators taking one can
+    // be hoisted out of
+    for (arg_path, _) in &args {
+        if let [key, idx] = arg_path.as_slice() {
+            out.push(Step::Remove(child_path(path, key.clone()), idx.clone()));
+        }
+    }
+    for (arg_path, arg) in args {
+        let len = path.len();
+        path.extend(arg_path);
+        rule_steps(engine, arg, path, out);
+        path.truncate(len);
+    }
+}
+
+/// The ways of making a literal within a rule smaller, which may contain
+/// further operations
+fn literal_steps(
+    engine: &Engine,
+    value: &Value,
+    path: &mut Vec<Segment>,
+    out: &mut Vec<Step>,
+) {
+    let children = children(value);
+    for (segment, _) in &children {
+        out.push(Step::Remove(path.clone(), segment.clone()));
+    }
+    for (segment, child) in children {
+        path.push(segment);
+        rule_steps(engine, child, path, out);
+        path.pop();
+    }
+}
+
+/// Apply steps to a value while they keep it interesting, returning whether
+/// any were applied
+fn reduce<S, K>(value: &mut Value, steps: S, mut keep: K) -> bool
+where
+    S: Fn(&Value) -> Vec<Step>,
+    K: FnMut(&Value) -> bool,
+{
+    let mut changed = false;
+    let mut all_steps = steps(value);
+    // After a step is applied, those before it have already been tried, so
+    // the search continues from the same position
+    let mut idx = 0;
+    while let Some(step) = all_steps.get(idx) {
+        let candidate = step.apply(value);
+        if keep(&candidate) {
+            *value = candidate;
+            changed = true;
+            all_steps = steps(value);
+        } else {
+            idx += 1;
+        }
+    }
+    changed
+}
+
+/// Whether two results are the same outcome for the purposes of shrinking
+///
+/// Errors are compared by their code and operator, since their messages
+/// often include values that change as the data is shrunk.
+pub(crate) fn same_outcome(
+    expected: &Result<Value, Error>,
+    actual: &Result<Value, Error>,
+) -> bool {
+    match (expected, actual) {
+        (Ok(expected), Ok(actual)) => expected == actual,
+        (Err(expected), Err(actual)) => {
+            let operator = |err: &Error| err.to_json()["details"]["operator"].clone();
+            expected.code() == actual.code() && operator(expected) == operator(actual)
+        }
+        _ => false,
+    }
+}
+
+/// Shrink a rule and data while their results remain interesting
+pub(crate) fn shrink<F>(
+    engine: &Engine,
+    rule: &Value,
+    data: &Value,
+    mut interesting: F,
+) -> Shrunk
+where
+    F: FnMut(&Result<Value, Error>) -> bool,
+{
+    let (mut rule, mut data) = (rule.clone(), data.clone());
+    let mut test = |rule: &Value, data: &Value| interesting(&engine.apply(rule, data));
+    if !test(&rule, &data) {
+        return Shrunk { rule, data };
+    }
+    loop {
+        let rule_steps = |rule: &Value| {
+            let mut steps = Vec::new();
+            rule_steps(engine, rule, &mut Vec::new(), &mut steps);
+            steps
+        };
+        let data_steps = |data: &Value| {
+            let mut steps = Vec::new();
+            data_steps(data, &mut Vec::new(), &mut steps);
+            steps
+        };
+        // Shrinking the rule can leave parts of the data unused, and
+        // shrinking the data can make parts of the rule irrelevant, so both
+        // are shrunk until neither changes
+        let rule_changed = reduce(&mut rule, rule_steps, |rule| test(rule, &data));
+        let data_changed = reduce(&mut data, data_steps, |data| test(&rule, data));
+        if !(rule_changed || data_changed) {
+            return Shrunk { rule, data };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_shrink {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_shrink_error() {
+        let rule = json!({"and": [
+            {"==": [{"var": "plan"}, "gold"]},
+            {"if": [
+                {"var": "active"},
+                {"<": [100, {"/": [{"var": "price"}, {"var": "qty"}]}]},
+                "inactive",
+            ]},
+            {"in": ["x", ["a", "b", "x"]]},
+        ]});
+        let data = json!({
+            "plan": "gold",
+            "active": true,
+            "price": 500,
+            "qty": 0,
+            "notes": ["a", "b"],
+        });
+        let shrunk = Engine::new().shrink(&rule, &data);
+        assert_eq!(
+            shrunk.rule,
+            json!({"/": [{"var": "price"}, {"var": "qty"}]})
+        );
+        assert_eq!(shrunk.data, json!({}));
+    }
+
+    #[test]
+    fn test_shrink_result() {
+        let rule = json!({"some": [
+            {"var": "items"},
+            {"and": [{">": [{"var": "qty"}, 0]}, {"in": [{"var": "sku"}, ["A1", "B2"]]}]},
+        ]});
+        let data = json!({"items": [
+            {"sku": "A1", "qty": 0},
+            {"sku": "C3", "qty": 2, "note": "gift"},
+            {"sku": "B2", "qty": 1},
+        ], "customer": {"id": 7}});
+        let engine = Engine::new();
+        assert_eq!(engine.apply(&rule, &data).unwrap(), json!(true));
+
+        // The shrunk rule is still true for the shrunk data, though not
+        // necessarily for the same reason
+        let shrunk = engine.shrink(&rule, &data);
+        assert_eq!(
+            shrunk.rule,
+            json!({"some": [{"var": "items"}, {"var": "qty"}]})
+        );
+        assert_eq!(shrunk.data, json!({"items": [{"qty": 1}]}));
+        assert_eq!(
+            engine.apply(&shrunk.rule, &shrunk.data).unwrap(),
+            json!(true)
+        );
+    }
+
+    #[test]
+    fn test_uninteresting() {
+        let rule = json!({"+": [1, {"var": "a"}]});
+        let data = json!({"a": 2});
+        let shrunk = Engine::new().shrink_by(&rule, &data, |result| result.is_err());
+        assert_eq!(shrunk, Shrunk { rule, data });
+    }
+}
@@ -0,0 +1,396 @@
+//! Evaluation snapshots, for reproducible bug reports
+//!
+//! A snapshot records everything needed to evaluate a rule again: the rule,
+//! the data, and the engine's options, along with what the evaluation
+//! produced and a trace of how it got there. It's a single JSON value, so
+//! it can be attached to a bug report or saved from a failing request, and
+//! [`replay()`] re-runs it to check whether the outcome is reproduced.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "rule": {"if": [{"<": [{"var": "age"}, 18]}, "minor", "adult"]},
+//!   "data": {"age": 21},
+//!   "options": {"division_by_zero": "error", "number_output": "integer", ...},
+//!   "result": "adult",
+//!   "trace": {"operations": 3, "max_depth": 3, "data_lookups": 1, "branch": 2}
+//! }
+//! ```
+//!
+//! Evaluations that fail have an `"error"`, as rendered by
+//! [`Error::to_json()`], in place of the `"result"`.
+//!
+//! Custom operators can't be serialized, so only their names are recorded.
+//! Snapshots using them must be replayed with [`replay_with()`], given an
+//! engine that has them. If the engine redacts values from the data, they
+//! are masked in the snapshot as well, in which case the outcome may not be
+//! reproduced.
+
+use serde_json::{json, Value};
+
+use crate::compare::Comparator;
+use crate::context::Context;
+use crate::engine::{
+    CatCoercion, Compatibility, DecimalSeparator, DivisionByZero, Engine, NumberOutput,
+    NumericKeys,
+};
+use crate::error::Error;
+#[cfg(feature = "money")]
+use crate::op::ExchangeRates;
+use crate::policy::Policy;
+
+/// The version of the snapshot format written by [`snapshot()`]
+pub const VERSION: u64 = 1;
+
+/// Evaluate a rule, recording the evaluation as a snapshot
+///
+/// [`Engine::snapshot()`] does the same.
+pub fn snapshot(engine: &Engine, rule: &Value, data: &Value) -> Value {
+    let ctx = Context::new(engine);
+    let result = engine.apply_in(&ctx, rule, data);
+    let stats = ctx.stats(Default::default());
+
+    let sensitive = engine.redaction.sensitive(data);
+    let mut rv = json!({
+        "version": VERSION,
+        "rule": rule,
+        "data": sensitive.value(data.clone()),
+        "options": options(engine),
+    });
+    match sensitive.result(result) {
+        Ok(value) => rv["result"] = value,
+        Err(err) => rv["error"] = err.to_json(),
+    }
+    // Elapsed time is left out, since it isn't reproducible
+    rv["trace"] = json!({
+        "operations": stats.operations,
+        "max_depth": stats.max_depth,
+        "data_lookups": stats.data_lookups,
+        "branch": ctx.branch(),
+    });
+    rv
+}
+
+/// The outcome of replaying a snapshot
+#[derive(Debug)]
+pub struct Replay {
+    /// The recorded outcome, as an object with either a `"result"` or an
+    /// `"error"`
+    pub recorded: Value,
+    /// The result of evaluating the rule again
+    pub result: Result<Value, Error>,
+}
+impl Replay {
+    /// Whether the rule evaluated to the recorded result, or failed with
+    /// the recorded error
+    pub fn reproduced(&self) -> bool {
+        outcome(&self.result) == self.recorded
+    }
+}
+
+/// Re-run the evaluation recorded in a snapshot
+///
+/// ```rust
+/// use jsonlogic_rs::snapshot::{replay, snapshot};
+/// use jsonlogic_rs::{DivisionByZero, Engine};
+/// use serde_json::json;
+///
+/// let engine = Engine::new().division_by_zero(DivisionByZero::Null);
+/// let recorded = snapshot(&engine, &json!({"/": [{"var": "a"}, 0]}), &json!({"a": 1}));
+/// assert_eq!(recorded["result"], json!(null));
+///
+/// // The snapshot can be saved and replayed elsewhere, with the same options
+/// let replayed = replay(&recorded).unwrap();
+/// assert!(replayed.reproduced());
+/// assert_eq!(replayed.result.unwrap(), json!(null));
+/// ```
+pub fn replay(snapshot: &Value) -> Result<Replay, Error> {
+    replay_with(&Engine::new(), snapshot)
+}
+
+/// Re-run the evaluation recorded in a snapshot, with the custom operators
+/// of an engine
+///
+/// The engine's options are replaced by those recorded in the snapshot. It
+/// must have every custom operator the snapshot names.
+pub fn replay_with(engine: &Engine, snapshot: &Value) -> Result<Replay, Error> {
+    let invalid = |reason: String| Error::InvalidData {
+        value: snapshot.clone(),
+        reason,
+    };
+    match snapshot.get("version").and_then(Value::as_u64) {
+        Some(VERSION) => {}
+        Some(version) => {
+            return Err(invalid(format!("Unsupported snapshot version {}", version)))
+        }
+        None => return Err(invalid("Snapshots must have a version".into())),
+    }
+    let field = |key: &str| {
+        snapshot
+            .get(key)
+            .ok_or_else(|| invalid(format!("Snapshots must have a {:?}", key)))
+    };
+    let (rule, data) = (field("rule")?, field("data")?);
+    let recorded = match (snapshot.get("result"), snapshot.get("error")) {
+        (Some(result), None) => json!({"result": result}),
+        (None, Some(error)) => json!({"error": error}),
+        _ => {
+            return Err(invalid(
+                "Snapshots must have either a \"result\" or an \"error\"".into(),
+            ))
+        }
+    };
+    let engine = configure(engine.clone(), field("options")?)?;
+    Ok(Replay {
+        recorded,
+        result: engine.apply(rule, data),
+    })
+}
+
+/// A result in the form it's recorded in a snapshot
+fn outcome(result: &Result<Value, Error>) -> Value {
+    match result {
+        Ok(value) => json!({"result": value}),
+        Err(err) => json!({"error": err.to_json()}),
+    }
+}
+
+/// The options of an engine that affect evaluation
+fn options(engine: &Engine) -> Value {
+    let mut custom_operators: Vec<&String> = engine.custom_operators.keys().collect();
+    custom_operators.sort();
+    #[allow(unused_mut)]
+    let mut rv = json!({
+        "division_by_zero": match engine.division_by_zero {
+            DivisionByZero::Error => "error",
+            DivisionByZero::Null => "null",
+            DivisionByZero::InfinityAsNull => "infinity_as_null",
+        },
+        "decimal_separator": match engine.decimal_separator {
+            DecimalSeparator::Point => "point",
+            DecimalSeparator::Comma => "comma",
+        },
+        "number_output": match engine.number_output {
+            NumberOutput::Integer => "integer",
+            NumberOutput::Float => "float",
+            NumberOutput::Preserve => "preserve",
+        },
+        "numeric_keys": match engine.numeric_keys {
+            NumericKeys::Exact => "exact",
+            NumericKeys::ExactFirst => "exact_first",
+            NumericKeys::NumberFirst => "number_first",
+        },
+        "compatibility": match engine.compatibility {
+            Compatibility::Spec => "spec",
+            Compatibility::Reference => "reference",
+        },
+        "cat_coercion": match engine.cat_coercion {
+            CatCoercion::Js => "js",
+            CatCoercion::Json => "json",
+            CatCoercion::Error => "error",
+        },
+        "evaluate_objects": engine.evaluate_objects,
+        "pure_only": engine.pure_only,
+        "max_result_size": engine.max_result_size,
+        "comparator": engine.comparator.to_json(),
+        "policy": engine.policy.as_ref().map(Policy::to_json),
+        "custom_operators": custom_operators,
+        "namespaces": engine.namespaces,
+    });
+    #[cfg(feature = "money")]
+    if let Some(rates) = &engine.exchange_rates {
+        rv["exchange_rates"] = rates.to_value();
+    }
+    rv
+}
+
+/// Apply the options recorded in a snapshot to an engine
+fn configure(mut engine: Engine, options: &Value) -> Result<Engine, Error> {
+    let invalid = |key: &str| Error::InvalidData {
+        value: options.clone(),
+        reason: format!("Invalid {:?} option in snapshot", key),
+    };
+    let choice = |key: &str| match options.get(key) {
+        None => Ok(None),
+        Some(Value::String(choice)) => Ok(Some(choice.as_str())),
+        Some(_) => Err(invalid(key)),
+    };
+    let flag = |key: &str| match options.get(key) {
+        None => Ok(false),
+        Some(flag) => flag.as_bool().ok_or_else(|| invalid(key)),
+    };
+
+    engine.division_by_zero = match choice("division_by_zero")? {
+        None | Some("error") => DivisionByZero::Error,
+        Some("null") => DivisionByZero::Null,
+        Some("infinity_as_null") => DivisionByZero::InfinityAsNull,
+        Some(_) => return Err(invalid("division_by_zero")),
+    };
+    engine.decimal_separator = match choice("decimal_separator")? {
+        None | Some("point") => DecimalSeparator::Point,
+        Some("comma") => DecimalSeparator::Comma,
+        Some(_) => return Err(invalid("decimal_separator")),
+    };
+    engine.number_output = match choice("number_output")? {
+        None | Some("integer") => NumberOutput::Integer,
+        Some("float") => NumberOutput::Float,
+        Some("preserve") => NumberOutput::Preserve,
+        Some(_) => return Err(invalid("number_output")),
+    };
+    engine.numeric_keys = match choice("numeric_keys")? {
+        None | Some("exact") => NumericKeys::Exact,
+        Some("exact_first") => NumericKeys::ExactFirst,
+        Some("number_first") => NumericKeys::NumberFirst,
+        Some(_) => return Err(invalid("numeric_keys")),
+    };
+    engine.compatibility = match choice("compatibility")? {
+        None | Some("spec") => Compatibility::Spec,
+        Some("reference") => Compatibility::Reference,
+        Some(_) => return Err(invalid("compatibility")),
+    };
+    engine.cat_coercion = match choice("cat_coercion")? {
+        None | Some("js") => CatCoercion::Js,
+        Some("json") => CatCoercion::Json,
+        Some("error") => CatCoercion::Error,
+        Some(_) => return Err(invalid("cat_coercion")),
+    };
+    engine.evaluate_objects = flag("evaluate_objects")?;
+    engine.pure_only = flag("pure_only")?;
+    engine.max_result_size = match options.get("max_result_size") {
+        None | Some(Value::Null) => None,
+        Some(size) => {
+            Some(size.as_u64().ok_or_else(|| invalid("max_result_size"))? as usize)
+        }
+    };
+    engine.comparator = match options.get("comparator") {
+        None => Comparator::default(),
+        Some(comparator) => Comparator::from_json(comparator),
+    };
+    engine.policy = match options.get("policy") {
+        None | Some(Value::Null) => None,
+        Some(policy) => Some(Policy::from_json(policy)?),
+    };
+    #[cfg(feature = "money")]
+    {
+        engine.exchange_rates = match options.get("exchange_rates") {
+            None | Some(Value::Null) => None,
+            Some(rates) => Some(ExchangeRates::from_value(rates)?),
+        };
+    }
+
+    let namespaces: Vec<&str> = match options.get("namespaces") {
+        None => Vec::new(),
+        Some(names) => names
+            .as_array()
+            .and_then(|names| names.iter().map(Value::as_str).collect())
+            .ok_or_else(|| invalid("namespaces"))?,
+    };
+    engine = namespaces.into_iter().fold(engine, |engine, namespace| {
+        engine.register_namespace(namespace)
+    });
+
+    let custom_operators = match options.get("custom_operators") {
+        None => Vec::new(),
+        Some(names) => names
+            .as_array()
+            .and_then(|names| names.iter().map(Value::as_str).collect())
+            .ok_or_else(|| invalid("custom_operators"))?,
+    };
+    if let Some(missing) = custom_operators
+        .into_iter()
+        .find(|name| !engine.custom_operators.contains_key(*name))
+    {
+        return Err(Error::InvalidData {
+            value: options.clone(),
+            reason: format!(
+                "The snapshot uses the custom operator {:?}, which the engine \
+                doesn't have",
+                missing
+            ),
+        });
+    }
+    Ok(engine)
+}
+
+#[cfg(test)]
+mod test_snapshot {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let engine = Engine::new()
+            .number_output(NumberOutput::Float)
+            .comparator(Comparator::default().case_insensitive(true))
+            .policy(Policy::default().deny("log").max_depth(8));
+        let rule = json!({"if": [
+            {"==": [{"var": "plan"}, "GOLD"]}, {"*": [{"var": "price"}, 0.5]},
+            {"var": "price"},
+        ]});
+        let recorded = snapshot(&engine, &rule, &json!({"plan": "gold", "price": 8}));
+        assert_eq!(recorded["result"], json!(4.0));
+        assert_eq!(recorded["options"]["number_output"], "float");
+        assert_eq!(recorded["options"]["policy"]["denied"], json!(["log"]));
+        assert_eq!(recorded["trace"]["branch"], 1);
+        assert_eq!(recorded["trace"]["data_lookups"], 2);
+
+        // Replaying with a default engine uses the recorded options
+        let replayed = replay(&recorded).unwrap();
+        assert!(replayed.reproduced());
+        assert_eq!(replayed.result.unwrap(), json!(4.0));
+        let text = serde_json::to_string(&recorded).unwrap();
+        assert!(replay(&serde_json::from_str(&text).unwrap())
+            .unwrap()
+            .reproduced());
+
+        // Edited snapshots are replayed as edited
+        let mut edited = recorded;
+        edited["data"]["plan"] = json!("silver");
+        let replayed = replay(&edited).unwrap();
+        assert!(!replayed.reproduced());
+        assert_eq!(replayed.result.unwrap(), json!(8));
+    }
+
+    #[test]
+    fn test_errors() {
+        let recorded = snapshot(&Engine::new(), &json!({"/": [1, 0]}), &json!(null));
+        assert_eq!(recorded["error"]["code"], "invalid_argument");
+        assert!(recorded.get("result").is_none());
+        assert!(replay(&recorded).unwrap().reproduced());
+
+        let mut edited = recorded.clone();
+        edited["options"]["division_by_zero"] = json!("null");
+        assert_eq!(replay(&edited).unwrap().result.unwrap(), json!(null));
+        edited["options"]["division_by_zero"] = json!("infinity_as_null");
+        assert_eq!(replay(&edited).unwrap().result.unwrap(), json!(null));
+
+        let mut future = recorded.clone();
+        future["version"] = json!(2);
+        assert!(matches!(replay(&future), Err(Error::InvalidData { .. })));
+        let mut bad = recorded;
+        bad["options"]["number_output"] = json!("roman");
+        assert!(matches!(replay(&bad), Err(Error::InvalidData { .. })));
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        let engine = Engine::new().add_operator("double", |args: &[&Value]| {
+            Ok(json!(args[0].as_f64().unwrap_or_default() * 2.0))
+        });
+        let recorded = snapshot(&engine, &json!({"double": 4}), &json!(null));
+        assert_eq!(recorded["options"]["custom_operators"], json!(["double"]));
+        assert!(matches!(replay(&recorded), Err(Error::InvalidData { .. })));
+        assert!(replay_with(&engine, &recorded).unwrap().reproduced());
+    }
+
+    #[test]
+    fn test_redacted() {
+        let engine = Engine::new().redact("ssn");
+        let recorded = snapshot(
+            &engine,
+            &json!({"cat": ["id:", {"var": "ssn"}]}),
+            &json!({"ssn": "123-45-6789"}),
+        );
+        assert_eq!(recorded["data"], json!({"ssn": "[REDACTED]"}));
+        assert_eq!(recorded["result"], "id:[REDACTED]");
+    }
+}
@@ -0,0 +1,120 @@
+//! Data sources
+//!
+//! Rules usually read from a `serde_json::Value`, but the data operators
+//! (`var`, `missing`, and friends) only ever need the values at particular
+//! paths. A [`DataSource`] provides just those, so that rules can be
+//! evaluated against e.g. a database row or a protobuf message without
+//! first building a JSON document from it.
+
+use std::borrow::Cow;
+use std::convert::TryInto;
+
+use serde_json::Value;
+
+/// A source of data for rules to read
+///
+/// Paths are the keys of a `var` split on unescaped dots, so
+/// `{"var": "user.tags.0"}` reads `["user", "tags", "0"]`. Numeric keys
+/// such as `{"var": 1}` are passed as their string representation.
+///
+/// ```rust
+/// use std::borrow::Cow;
+/// use std::collections::HashMap;
+///
+/// use jsonlogic_rs::{DataSource, Engine};
+/// use serde_json::{json, Value};
+///
+/// struct Row(HashMap<&'static str, i64>);
+///
+/// impl DataSource for Row {
+///     fn get_path(&self, segments: &[&str]) -> Option<Cow<'_, Value>> {
+///         match segments {
+///             [column] => self.0.get(column).map(|v| Cow::Owned(json!(v))),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let row = Row(vec![("age", 42)].into_iter().collect());
+/// let rule = json!({">": [{"var": "age"}, 18]});
+/// assert_eq!(Engine::new().apply_source(&rule, &row).unwrap(), json!(true));
+/// ```
+pub trait DataSource {
+    /// Get the value at a path, or `None` if there is no value there
+    ///
+    /// An empty path refers to the data as a whole, which is requested by
+    /// e.g. `{"var": ""}`.
+    fn get_path(&self, segments: &[&str]) -> Option<Cow<'_, Value>>;
+}
+
+/// A get operation that supports negative indexes
+fn get<T>(slice: &[T], idx: i64) -> Option<&T> {
+    let vec_len = slice.len();
+    let usize_idx: usize = idx.unsigned_abs().try_into().ok()?;
+
+    let adjusted_idx = if idx >= 0 {
+        usize_idx
+    } else {
+        vec_len.checked_sub(usize_idx)?
+    };
+
+    slice.get(adjusted_idx)
+}
+
+/// Get the child of a value at a single key
+fn child<'v>(value: &'v Value, segment: &str) -> Option<Cow<'v, Value>> {
+    match value {
+        Value::Object(map) => map.get(segment).map(Cow::Borrowed),
+        Value::Array(arr) => segment
+            .parse::<i64>()
+            .ok()
+            .and_then(|i| get(arr, i))
+            .map(Cow::Borrowed),
+        Value::String(s) => {
+            let s_chars: Vec<char> = s.chars().collect();
+            segment
+                .parse::<i64>()
+                .ok()
+                .and_then(|i| get(&s_chars, i))
+                .map(|c| Cow::Owned(Value::String(c.to_string())))
+        }
+        _ => None,
+    }
+}
+
+/// JSON values are traversed by object key, or by index for arrays and
+/// strings (as arrays of characters). Negative indexes count back from
+/// the end.
+impl DataSource for Value {
+    fn get_path(&self, segments: &[&str]) -> Option<Cow<'_, Value>> {
+        segments
+            .iter()
+            .try_fold(Cow::Borrowed(self), |acc, segment| match acc {
+                Cow::Borrowed(value) => child(value, segment),
+                Cow::Owned(value) => {
+                    child(&value, segment).map(|v| Cow::Owned(v.into_owned()))
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod test_value_source {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_get_path() {
+        let data = json!({"a": {"b": [1, 2, {"c": "xyz"}]}, "1": "one"});
+        let get = |path: &[&str]| data.get_path(path).map(Cow::into_owned);
+        assert_eq!(get(&[]), Some(data.clone()));
+        assert_eq!(get(&["a", "b", "0"]), Some(json!(1)));
+        assert_eq!(get(&["a", "b", "-1", "c"]), Some(json!("xyz")));
+        assert_eq!(get(&["a", "b", "2", "c", "1"]), Some(json!("y")));
+        assert_eq!(get(&["a", "b", "2", "c", "1", "0"]), Some(json!("y")));
+        assert_eq!(get(&["1"]), Some(json!("one")));
+        assert_eq!(get(&["a", "x"]), None);
+        assert_eq!(get(&["a", "b", "x"]), None);
+        assert_eq!(get(&["a", "b", "0", "c"]), None);
+    }
+}
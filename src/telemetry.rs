@@ -0,0 +1,116 @@
+//! Optional instrumentation
+//!
+//! With the `metrics` feature enabled, evaluation reports counters through
+//! the [`metrics`](https://docs.rs/metrics) facade, to be collected by
+//! whichever recorder (e.g. a Prometheus exporter) the application installs:
+//!
+//! - `jsonlogic_operations_total`, labelled by `operator`, counts each
+//!   operator invocation
+//! - `jsonlogic_errors_total`, labelled by `code` (see `Error::code()`),
+//!   counts rules that failed to parse or evaluate
+//!
+//! With the `tracing` feature enabled, each application of a rule is
+//! wrapped in a `jsonlogic.apply` span, recording the rule's `rule_hash()`, with
+//! `jsonlogic.parse` and `jsonlogic.evaluate` child spans. The evaluate span
+//! records the operator count and nesting depth once evaluation finishes.
+//!
+//! Without the features, these functions are empty and compile away.
+
+use serde_json::Value;
+
+use crate::context::Context;
+use crate::error::Error;
+
+/// Count an invocation of an operator
+#[cfg(feature = "metrics")]
+pub fn record_operation(symbol: &str) {
+    metrics::counter!("jsonlogic_operations_total", "operator" => symbol.to_owned())
+        .increment(1);
+}
+
+/// Count an invocation of an operator
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub fn record_operation(_symbol: &str) {}
+
+/// Count a failed rule by error code
+#[cfg(feature = "metrics")]
+pub fn record_error(err: &Error) {
+    metrics::counter!("jsonlogic_errors_total", "code" => err.code()).increment(1);
+}
+
+/// Count a failed rule by error code
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub fn record_error(_err: &Error) {}
+
+/// A tracing span, exited when dropped
+#[cfg(feature = "tracing")]
+pub struct Span(tracing::span::EnteredSpan);
+
+/// A tracing span, exited when dropped
+#[cfg(not(feature = "tracing"))]
+pub struct Span;
+
+#[cfg(feature = "tracing")]
+impl Span {
+    /// Enter a span covering the application of a rule
+    pub fn apply(rule: &Value) -> Self {
+        let span =
+            tracing::debug_span!("jsonlogic.apply", rule_hash = tracing::field::Empty);
+        // Only pay for hashing the rule if someone is listening
+        if !span.is_disabled() {
+            span.record("rule_hash", crate::audit::rule_hash(rule).as_str());
+        }
+        Self(span.entered())
+    }
+
+    /// Enter a span covering the parsing of a rule
+    pub fn parse() -> Self {
+        Self(tracing::debug_span!("jsonlogic.parse").entered())
+    }
+
+    /// Enter a span covering the evaluation of a parsed rule
+    pub fn evaluate() -> Self {
+        Self(
+            tracing::debug_span!(
+                "jsonlogic.evaluate",
+                operations = tracing::field::Empty,
+                max_depth = tracing::field::Empty,
+            )
+            .entered(),
+        )
+    }
+
+    /// Record the counts gathered during evaluation
+    pub fn record_counts(&self, ctx: &Context) {
+        let stats = ctx.stats(Default::default());
+        self.0.record("operations", stats.operations);
+        self.0.record("max_depth", stats.max_depth as u64);
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    /// Enter a span covering the application of a rule
+    #[inline(always)]
+    pub fn apply(_rule: &Value) -> Self {
+        Self
+    }
+
+    /// Enter a span covering the parsing of a rule
+    #[inline(always)]
+    pub fn parse() -> Self {
+        Self
+    }
+
+    /// Enter a span covering the evaluation of a parsed rule
+    #[inline(always)]
+    pub fn evaluate() -> Self {
+        Self
+    }
+
+    /// Record the counts gathered during evaluation
+    #[inline(always)]
+    pub fn record_counts(&self, _ctx: &Context) {}
+}
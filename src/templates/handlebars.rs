@@ -0,0 +1,80 @@
+//! Handlebars helpers
+//!
+//! [`register()`] adds a `jsonlogic` helper, which takes the name of a
+//! stored rule or a rule itself, and optionally the data to evaluate it
+//! against, which is otherwise the template's data. Its result can be
+//! rendered, or used by other helpers as a subexpression, as in a
+//! conditional block:
+//!
+//! ```rust
+//! use handlebars::Handlebars;
+//! use jsonlogic_rs::templates::{self, TemplateRules};
+//! use jsonlogic_rs::Engine;
+//! use serde_json::json;
+//!
+//! let rules = TemplateRules::new(Engine::new())
+//!     .add("adult", json!({">=": [{"var": "age"}, 18]}));
+//! let mut registry = Handlebars::new();
+//! templates::handlebars::register(&mut registry, rules);
+//!
+//! let template = r#"{{#if (jsonlogic "adult")}}Welcome{{else}}Sorry{{/if}}, {{name}}"#;
+//! assert_eq!(
+//!     registry
+//!         .render_template(template, &json!({"name": "Ada", "age": 36}))
+//!         .unwrap(),
+//!     "Welcome, Ada"
+//! );
+//! ```
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, RenderContext, RenderError,
+    RenderErrorReason, ScopedJson,
+};
+
+use super::{TemplateRules, NAME};
+
+impl HelperDef for TemplateRules {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let rule = h
+            .param(0)
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex(NAME, 0))?;
+        let data = h.param(1).map_or(ctx.data(), |data| data.value());
+        self.apply(rule.value(), data)
+            .map(ScopedJson::Derived)
+            .map_err(|err| RenderErrorReason::NestedError(Box::new(err)).into())
+    }
+}
+
+/// Register rules with a registry as the `jsonlogic` helper
+pub fn register(registry: &mut Handlebars, rules: TemplateRules) {
+    registry.register_helper(NAME, Box::new(rules));
+}
+
+#[cfg(test)]
+mod test_handlebars {
+    use super::*;
+    use crate::Engine;
+    use serde_json::json;
+
+    #[test]
+    fn test_helper() {
+        let mut registry = Handlebars::new();
+        register(
+            &mut registry,
+            TemplateRules::new(Engine::new())
+                .add("total", json!({"+": [{"var": "a"}, 1]})),
+        );
+        let render = |template: &str| {
+            registry.render_template(template, &json!({"a": 1, "b": {"a": 5}}))
+        };
+        assert_eq!(render("{{jsonlogic \"total\"}}").unwrap(), "2");
+        assert_eq!(render("{{jsonlogic \"total\" b}}").unwrap(), "6");
+        assert!(render("{{#if (jsonlogic \"unknown\")}}x{{/if}}").is_err());
+    }
+}
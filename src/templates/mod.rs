@@ -0,0 +1,82 @@
+//! Evaluating rules from templates
+//!
+//! Templates that render conditionally often repeat logic that already
+//! exists as rules, e.g. showing a notice only to customers who are
+//! eligible for an offer. [`TemplateRules`] holds rules by name, so that
+//! templates can refer to the stored rules instead, and is registered with
+//! a template engine by the module for it. Each template engine is enabled
+//! by its own feature.
+//!
+//! Templates call rules as `jsonlogic`, giving either the name of a stored
+//! rule or a rule itself, along with the data to evaluate it against.
+
+#[cfg(feature = "handlebars")]
+pub mod handlebars;
+#[cfg(feature = "tera")]
+pub mod tera;
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::engine::Engine;
+use crate::error::Error;
+
+/// The name templates call rules by
+pub const NAME: &str = "jsonlogic";
+
+/// Named rules for templates, with the engine to evaluate them
+#[derive(Debug, Clone, Default)]
+pub struct TemplateRules {
+    engine: Engine,
+    rules: HashMap<String, Value>,
+}
+impl TemplateRules {
+    /// Create an empty set of rules, evaluated with `engine`
+    pub fn new(engine: Engine) -> Self {
+        Self {
+            engine,
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Add a rule, replacing any rule of the same name
+    pub fn add(mut self, name: &str, rule: Value) -> Self {
+        self.rules.insert(name.into(), rule);
+        self
+    }
+
+    /// Evaluate a stored rule, if given its name, or else a rule itself
+    pub fn apply(&self, rule: &Value, data: &Value) -> Result<Value, Error> {
+        match rule {
+            Value::String(name) => match self.rules.get(name) {
+                Some(rule) => self.engine.apply(rule, data),
+                None => Err(Error::InvalidArgument {
+                    value: rule.clone(),
+                    operation: NAME.into(),
+                    reason: "No rule with this name has been added".into(),
+                }),
+            },
+            rule => self.engine.apply(rule, data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_templates {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply() {
+        let rules =
+            TemplateRules::default().add("adult", json!({">=": [{"var": "age"}, 18]}));
+        let data = json!({"age": 21});
+        assert_eq!(rules.apply(&json!("adult"), &data).unwrap(), json!(true));
+        assert_eq!(
+            rules.apply(&json!({"var": "age"}), &data).unwrap(),
+            json!(21)
+        );
+        assert!(rules.apply(&json!("minor"), &data).is_err());
+    }
+}
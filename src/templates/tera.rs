@@ -0,0 +1,88 @@
+//! Tera functions and tests
+//!
+//! [`register()`] adds a `jsonlogic` function, taking the name of a stored
+//! rule or a rule itself as `rule`, and the data to evaluate it against as
+//! `data`, and a `jsonlogic` test, which checks whether a rule is truthy
+//! for the value being tested. Tera doesn't give functions or tests the
+//! template's context, so data is always passed explicitly.
+//!
+//! ```rust
+//! use jsonlogic_rs::templates::{self, TemplateRules};
+//! use jsonlogic_rs::Engine;
+//! use serde_json::json;
+//! use tera::{Context, Tera};
+//!
+//! let rules = TemplateRules::new(Engine::new())
+//!     .add("adult", json!({">=": [{"var": "age"}, 18]}));
+//! let mut tera = Tera::default();
+//! templates::tera::register(&mut tera, rules);
+//!
+//! let template = r#"{% if user is jsonlogic("adult") %}Welcome{% else %}Sorry{% endif %}"#;
+//! let context = Context::from_value(json!({"user": {"age": 36}})).unwrap();
+//! assert_eq!(tera.render_str(template, &context).unwrap(), "Welcome");
+//! ```
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tera::{Function, Tera, Test};
+
+use super::{TemplateRules, NAME};
+use crate::js_op::truthy;
+
+impl Function for TemplateRules {
+    fn call(&self, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        let rule = args.get("rule").ok_or_else(|| {
+            tera::Error::msg("`jsonlogic` requires a `rule` argument")
+        })?;
+        let data = args.get("data").unwrap_or(&Value::Null);
+        self.apply(rule, data)
+            .map_err(|err| tera::Error::call_function(NAME, err))
+    }
+}
+
+impl Test for TemplateRules {
+    fn test(&self, value: Option<&Value>, args: &[Value]) -> tera::Result<bool> {
+        let rule = args
+            .first()
+            .ok_or_else(|| tera::Error::msg("`jsonlogic` requires a rule"))?;
+        self.apply(rule, value.unwrap_or(&Value::Null))
+            .map(|result| truthy(&result))
+            .map_err(|err| tera::Error::call_test(NAME, err))
+    }
+}
+
+/// Register rules with Tera as the `jsonlogic` function and test
+pub fn register(tera: &mut Tera, rules: TemplateRules) {
+    tera.register_tester(NAME, rules.clone());
+    tera.register_function(NAME, rules);
+}
+
+#[cfg(test)]
+mod test_tera {
+    use super::*;
+    use crate::Engine;
+    use serde_json::json;
+    use tera::Context;
+
+    #[test]
+    fn test_function() {
+        let mut tera = Tera::default();
+        register(
+            &mut tera,
+            TemplateRules::new(Engine::new())
+                .add("total", json!({"+": [{"var": "a"}, 1]})),
+        );
+        let context = Context::from_value(json!({"order": {"a": 1}})).unwrap();
+        let mut render = |template: &str| tera.render_str(template, &context);
+        assert_eq!(
+            render(r#"{{ jsonlogic(rule="total", data=order) }}"#).unwrap(),
+            "2"
+        );
+        assert_eq!(
+            render(r#"{% if order is jsonlogic("total") %}yes{% endif %}"#).unwrap(),
+            "yes"
+        );
+        assert!(render(r#"{{ jsonlogic(rule="unknown") }}"#).is_err());
+    }
+}
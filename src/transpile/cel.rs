@@ -0,0 +1,398 @@
+//! Translating rules into CEL expressions
+//!
+//! The supported subset is the inverse of
+//! [`import::cel`](crate::import::cel): literals, `var`s, logic,
+//! comparisons, `in`, arithmetic, `cat`, the `startsWith`, `endsWith`, and
+//! `contains` string operators, `toNumber` and `toString`, and the `all`,
+//! `some`, `none`, `filter`, and `map` array operators.
+
+use serde_json::Value;
+
+use super::unsupported;
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::import::cel::is_identifier;
+use crate::op;
+
+const TARGET: &str = "CEL";
+
+// Operator precedence, from loosest to tightest
+const TERNARY: u8 = 1;
+const OR: u8 = 2;
+const AND: u8 = 3;
+const RELATION: u8 = 4;
+const ADDITIVE: u8 = 5;
+const MULTIPLICATIVE: u8 = 6;
+const UNARY: u8 = 7;
+const MEMBER: u8 = 8;
+
+/// Translate a rule into a CEL expression
+///
+/// CEL is strictly typed, so while `===` and `==` both become `==`, a rule
+/// like `{"==": [{"var": "age"}, "18"]}` that relies on JsonLogic's type
+/// coercion won't behave the same. Likewise, `cat` becomes `+`, which only
+/// concatenates strings. Inside `all`, `some`, `none`, `filter`, and `map`,
+/// the current item is named `x`, or `y` and `z` in nested operations.
+///
+/// ```rust
+/// use jsonlogic_rs::transpile::cel::to_cel;
+/// use serde_json::json;
+///
+/// let rule = json!({"and": [
+///     {">=": [{"var": "user.age"}, 18]},
+///     {"some": [{"var": "user.roles"}, {"==": [{"var": ""}, "admin"]}]},
+/// ]});
+/// assert_eq!(
+///     to_cel(&rule).unwrap(),
+///     r#"user.age >= 18 && user.roles.exists(x, x == "admin")"#
+/// );
+/// ```
+pub fn to_cel(rule: &Value) -> Result<String, Error> {
+    let engine = Engine::new();
+    engine.parse(rule)?;
+    let translator = Translator { engine };
+    translator.expr(rule, 0).map(|(expression, _)| expression)
+}
+
+fn invalid(value: &Value, reason: &str) -> Error {
+    Error::InvalidArgument {
+        value: value.clone(),
+        operation: TARGET.into(),
+        reason: reason.into(),
+    }
+}
+
+/// The name of the current item in a macro nested `depth` deep
+fn item_name(depth: usize) -> String {
+    match depth {
+        1 => "x".into(),
+        2 => "y".into(),
+        3 => "z".into(),
+        depth => format!("x{}", depth),
+    }
+}
+
+/// An expression and the precedence of its outermost operator
+type Expression = (String, u8);
+
+/// Parenthesize an expression if it binds more loosely than required
+fn wrap((expression, precedence): Expression, min_precedence: u8) -> String {
+    match precedence < min_precedence {
+        true => format!("({})", expression),
+        false => expression,
+    }
+}
+
+struct Translator {
+    engine: Engine,
+}
+impl Translator {
+    /// Translate a value, `depth` macros deep
+    fn expr(&self, value: &Value, depth: usize) -> Result<Expression, Error> {
+        let (key, args) = match value {
+            Value::Null | Value::Bool(_) | Value::String(_) => {
+                return Ok((value.to_string(), MEMBER))
+            }
+            Value::Number(n) => {
+                let precedence = match n.as_f64() {
+                    Some(f) if f < 0.0 => UNARY,
+                    _ => MEMBER,
+                };
+                return Ok((n.to_string(), precedence));
+            }
+            Value::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| Ok(self.expr(item, depth)?.0))
+                    .collect::<Result<Vec<String>, Error>>()?;
+                return Ok((format!("[{}]", items.join(", ")), MEMBER));
+            }
+            Value::Object(_) => match op::split_operation(&self.engine, value) {
+                Some(split) => split,
+                None => {
+                    return Err(invalid(value, "Object literals are not supported"))
+                }
+            },
+        };
+
+        let binary = |operator: &str, precedence: u8| -> Result<Expression, Error> {
+            match args.as_slice() {
+                [left, right] => Ok((
+                    format!(
+                        "{} {} {}",
+                        wrap(self.expr(left, depth)?, precedence),
+                        operator,
+                        wrap(self.expr(right, depth)?, precedence + 1),
+                    ),
+                    precedence,
+                )),
+                _ => Err(invalid(value, "Expected two arguments")),
+            }
+        };
+        let chain = |operator: &str, precedence: u8| -> Result<Expression, Error> {
+            if args.len() < 2 {
+                return Err(invalid(value, "Expected at least two arguments"));
+            }
+            let operands = args
+                .iter()
+                .enumerate()
+                .map(|(idx, arg)| {
+                    let min = if idx == 0 { precedence } else { precedence + 1 };
+                    Ok(wrap(self.expr(arg, depth)?, min))
+                })
+                .collect::<Result<Vec<String>, Error>>()?;
+            Ok((operands.join(&format!(" {} ", operator)), precedence))
+        };
+        let unary = |operator: &str| -> Result<Expression, Error> {
+            match args.as_slice() {
+                [arg] => Ok((
+                    format!("{}{}", operator, wrap(self.expr(arg, depth)?, UNARY)),
+                    UNARY,
+                )),
+                _ => Err(invalid(value, "Expected one argument")),
+            }
+        };
+        let call = |function: &str| -> Result<Expression, Error> {
+            match args.as_slice() {
+                [arg] => Ok((
+                    format!("{}({})", function, self.expr(arg, depth)?.0),
+                    MEMBER,
+                )),
+                _ => Err(invalid(value, "Expected one argument")),
+            }
+        };
+        let method = |name: &str| -> Result<Expression, Error> {
+            match args.as_slice() {
+                [receiver, arg] => Ok((
+                    format!(
+                        "{}.{}({})",
+                        wrap(self.expr(receiver, depth)?, MEMBER),
+                        name,
+                        self.expr(arg, depth)?.0
+                    ),
+                    MEMBER,
+                )),
+                _ => Err(invalid(value, "Expected two arguments")),
+            }
+        };
+        let macro_call = |name: &str| -> Result<Expression, Error> {
+            match args.as_slice() {
+                [items, body] => Ok((
+                    format!(
+                        "{}.{}({}, {})",
+                        wrap(self.expr(items, depth)?, MEMBER),
+                        name,
+                        item_name(depth + 1),
+                        self.expr(body, depth + 1)?.0
+                    ),
+                    MEMBER,
+                )),
+                _ => Err(invalid(value, "Expected two arguments")),
+            }
+        };
+
+        match key {
+            "var" => self.var(&args, value, depth),
+            "==" | "===" => binary("==", RELATION),
+            "!=" | "!==" => binary("!=", RELATION),
+            "<" | "<=" if args.len() == 3 => {
+                let comparisons = [
+                    binary_expr(key, args[0], args[1]),
+                    binary_expr(key, args[1], args[2]),
+                ];
+                let operands = comparisons
+                    .iter()
+                    .map(|comparison| Ok(wrap(self.expr(comparison, depth)?, AND)))
+                    .collect::<Result<Vec<String>, Error>>()?;
+                Ok((operands.join(" && "), AND))
+            }
+            "<" | "<=" | ">" | ">=" => binary(key, RELATION),
+            "in" => match args.as_slice() {
+                [needle, Value::String(_)] => {
+                    let haystack = self.expr(args[1], depth)?;
+                    let needle = self.expr(needle, depth)?.0;
+                    Ok((format!("{}.contains({})", haystack.0, needle), MEMBER))
+                }
+                _ => binary("in", RELATION),
+            },
+            "not_in" => {
+                let in_ = binary_expr("in", args[0], args[1]);
+                Ok((format!("!({})", self.expr(&in_, depth)?.0), UNARY))
+            }
+            "and" => chain("&&", AND),
+            "or" => chain("||", OR),
+            "!" => unary("!"),
+            "!!" => unary("!!"),
+            "if" | "?:" => self.ternary(&args, depth),
+            "+" => chain("+", ADDITIVE),
+            "cat" => chain("+", ADDITIVE),
+            "-" if args.len() == 1 => unary("-"),
+            "-" => binary("-", ADDITIVE),
+            "*" => chain("*", MULTIPLICATIVE),
+            "/" | "%" => binary(key, MULTIPLICATIVE),
+            "toNumber" => call("double"),
+            "toString" => call("string"),
+            "startsWith" | "endsWith" | "contains" => method(key),
+            "all" => macro_call("all"),
+            "some" => macro_call("exists"),
+            "none" => {
+                let (expression, _) = macro_call("exists")?;
+                Ok((format!("!{}", expression), UNARY))
+            }
+            "filter" | "map" => macro_call(key),
+            key => Err(unsupported(key, TARGET)),
+        }
+    }
+
+    fn var(
+        &self,
+        args: &[&Value],
+        value: &Value,
+        depth: usize,
+    ) -> Result<Expression, Error> {
+        let key = match args {
+            [key] => *key,
+            _ => return Err(invalid(value, "Defaults for var are not supported")),
+        };
+        let segments = match key {
+            Value::String(s) => op::split_with_escape(s, '.'),
+            Value::Number(n) if n.is_u64() => vec![n.to_string()],
+            _ => return Err(invalid(key, "Key cannot be used as a CEL variable")),
+        };
+        let mut segments = segments.iter();
+        let mut expression = match depth {
+            0 => match segments.next() {
+                Some(name) if is_identifier(name) => name.clone(),
+                _ => {
+                    return Err(invalid(
+                        key,
+                        "Variables must start with a CEL identifier",
+                    ))
+                }
+            },
+            depth => item_name(depth),
+        };
+        for segment in segments {
+            if is_identifier(segment) {
+                expression.push('.');
+                expression.push_str(segment);
+            } else if segment.parse::<u64>().is_ok() {
+                expression.push_str(&format!("[{}]", segment));
+            } else {
+                expression.push_str(&format!("[{}]", Value::String(segment.clone())));
+            }
+        }
+        Ok((expression, MEMBER))
+    }
+
+    fn ternary(&self, args: &[&Value], depth: usize) -> Result<Expression, Error> {
+        match args {
+            [] => Ok(("null".into(), MEMBER)),
+            [result] => self.expr(result, depth),
+            [condition, then, rest @ ..] => {
+                let otherwise = match rest {
+                    [] => ("null".into(), MEMBER),
+                    rest => self.ternary(rest, depth)?,
+                };
+                Ok((
+                    format!(
+                        "{} ? {} : {}",
+                        wrap(self.expr(condition, depth)?, OR),
+                        wrap(self.expr(then, depth)?, OR),
+                        wrap(otherwise, TERNARY)
+                    ),
+                    TERNARY,
+                ))
+            }
+        }
+    }
+}
+
+fn binary_expr(operator: &str, left: &Value, right: &Value) -> Value {
+    serde_json::json!({ operator: [left, right] })
+}
+
+#[cfg(test)]
+mod test_cel {
+    use super::*;
+    use crate::import::cel::from_cel;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_cel() {
+        let cases = vec![
+            (json!(null), "null"),
+            (json!([1, "a", -2.5]), r#"[1, "a", -2.5]"#),
+            (json!({"var": "a.b.0.c d"}), r#"a.b[0]["c d"]"#),
+            (
+                json!({"or": [{"and": [{"var": "a"}, {"var": "b"}]}, {"!": {"var": "c"}}]}),
+                "a && b || !c",
+            ),
+            (
+                json!({"and": [{"or": [{"var": "a"}, {"var": "b"}]}, {"!!": {"var": "c"}}]}),
+                "(a || b) && !!c",
+            ),
+            (
+                json!({"-": [{"var": "a"}, {"-": [{"var": "b"}, 1]}]}),
+                "a - (b - 1)",
+            ),
+            (json!({"*": [{"+": [1, 2]}, -3]}), "(1 + 2) * -3"),
+            (json!({"-": {"+": [1, 2]}}), "-(1 + 2)"),
+            (json!({"<": [0, {"var": "x"}, 10]}), "0 < x && x < 10"),
+            (json!({"in": ["a", {"var": "tags"}]}), r#""a" in tags"#),
+            (json!({"in": [{"var": "s"}, "abc"]}), r#""abc".contains(s)"#),
+            (json!({"not_in": [1, [2]]}), "!(1 in [2])"),
+            (
+                json!({"if": [{"var": "a"}, 1, {"var": "b"}, {"if": [true, 2, 3]}, 4]}),
+                "a ? 1 : b ? (true ? 2 : 3) : 4",
+            ),
+            (json!({"if": [{"var": "a"}, 1]}), "a ? 1 : null"),
+            (
+                json!({"cat": ["Hello, ", {"toString": {"var": "n"}}]}),
+                r#""Hello, " + string(n)"#,
+            ),
+            (
+                json!({"none": [{"var": "xs"}, {"all": [{"var": "ys"}, {"var": "ok"}]}]}),
+                "!xs.exists(x, x.ys.all(y, y.ok))",
+            ),
+            (
+                json!({"startsWith": [{"var": "name"}, "A"]}),
+                r#"name.startsWith("A")"#,
+            ),
+        ];
+        for (rule, expected) in cases {
+            assert_eq!(to_cel(&rule).unwrap(), expected, "{}", rule);
+        }
+    }
+
+    #[test]
+    fn test_unsupported() {
+        for rule in &[
+            json!({"var": ["a", 1]}),
+            json!({"var": ""}),
+            json!({"var": "1a"}),
+            json!({"merge": [[1], [2]]}),
+            json!({"a": 1}),
+        ] {
+            assert!(to_cel(rule).is_err(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rules = vec![
+            json!({"and": [
+                {">=": [{"var": "user.age"}, 18]},
+                {"!==": [{"var": "user.country"}, "DE"]},
+                {"or": [{"var": "a"}, {"!!": {"var": "b"}}]},
+            ]}),
+            json!({"if": [{"<": [{"var": "x"}, 0]}, "negative", {"===": [{"var": "x"}, 0]}, "zero", "positive"]}),
+            json!({"-": [{"*": [{"var": "a"}, 2, 3]}, {"/": [{"var": "b"}, {"%": [4, 3]}]}]}),
+            json!({"filter": [{"var": "items"}, {"some": [{"var": "tags"}, {"in": [{"var": ""}, ["x", "y"]]}]}]}),
+        ];
+        for rule in rules {
+            let cel = to_cel(&rule).unwrap();
+            assert_eq!(from_cel(&cel).unwrap(), rule, "{}", cel);
+        }
+    }
+}
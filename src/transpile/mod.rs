@@ -0,0 +1,50 @@
+//! Translating rules into other query languages
+//!
+//! Only a subset of rules can be translated, since most targets can't
+//! express everything JsonLogic can. Rules outside the subset are rejected
+//! with an error naming the unsupported operation, rather than translated
+//! into something that might behave differently.
+
+use serde_json::Value;
+
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::op;
+
+#[cfg(feature = "cel")]
+pub mod cel;
+pub mod mongo;
+#[cfg(feature = "polars")]
+pub mod polars;
+
+/// Split a rule into its operator and arguments, resolving aliases to the
+/// operator they refer to
+///
+/// Returns `None` for values that aren't operations.
+fn split<'v>(engine: &Engine, rule: &'v Value) -> Option<(&'v str, Vec<&'v Value>)> {
+    op::split_operation(engine, rule)
+        .map(|(key, args)| (op::alias_target(key).unwrap_or(key), args))
+}
+
+/// Get the path segments for the key of a `var`
+///
+/// Returns `None` for keys referring to the data as a whole and for
+/// negative indexes, which have no equivalent in other languages.
+fn var_path(key: &Value) -> Option<Vec<String>> {
+    let segments = match key {
+        Value::String(s) => op::split_with_escape(s, '.'),
+        Value::Number(n) => vec![n.as_u64()?.to_string()],
+        _ => return None,
+    };
+    match segments.is_empty() {
+        true => None,
+        false => Some(segments),
+    }
+}
+
+fn unsupported(key: &str, target: &str) -> Error {
+    Error::InvalidOperation {
+        key: key.into(),
+        reason: format!("Operation cannot be translated to {}", target),
+    }
+}
@@ -0,0 +1,302 @@
+//! Translating rules into MongoDB query filters
+//!
+//! The supported subset is the conditions a query filter can express:
+//! comparisons of fields read with `var` to literals or to each other,
+//! `in` with an array of literals, and `and`, `or`, `!`, and `!!` of those.
+
+use serde_json::{json, Map, Value};
+
+use super::{split, unsupported, var_path};
+use crate::engine::Engine;
+use crate::error::Error;
+
+const TARGET: &str = "MongoDB";
+
+/// Translate a rule into a MongoDB query filter document
+///
+/// The filter is returned as JSON, which can be converted to BSON with e.g.
+/// `bson::to_document()`. MongoDB compares values strictly by type, so `==`
+/// and `===` both become `$eq`, and e.g. `{"==": [{"var": "age"}, "18"]}`
+/// doesn't match a numeric `age`. As in JsonLogic, a missing field is equal
+/// to `null`.
+///
+/// ```rust
+/// use jsonlogic_rs::transpile::mongo::to_filter;
+/// use serde_json::json;
+///
+/// let rule = json!({"and": [
+///     {">=": [{"var": "age"}, 18]},
+///     {"in": [{"var": "address.country"}, ["US", "CA"]]},
+/// ]});
+/// assert_eq!(
+///     to_filter(&rule).unwrap(),
+///     json!({"$and": [
+///         {"age": {"$gte": 18}},
+///         {"address.country": {"$in": ["US", "CA"]}},
+///     ]})
+/// );
+/// ```
+pub fn to_filter(rule: &Value) -> Result<Value, Error> {
+    let engine = Engine::new();
+    engine.parse(rule)?;
+    Translator { engine }.filter(rule)
+}
+
+fn invalid(value: &Value, reason: &str) -> Error {
+    Error::InvalidArgument {
+        value: value.clone(),
+        operation: TARGET.into(),
+        reason: reason.into(),
+    }
+}
+
+/// The MongoDB operator for a comparison, and its mirror image for when
+/// the field is on the right, as in `{"<": [18, {"var": "age"}]}`
+fn comparison_operator(key: &str) -> Option<(&'static str, &'static str)> {
+    match key {
+        "==" | "===" => Some(("$eq", "$eq")),
+        "!=" | "!==" => Some(("$ne", "$ne")),
+        "<" => Some(("$lt", "$gt")),
+        "<=" => Some(("$lte", "$gte")),
+        ">" => Some(("$gt", "$lt")),
+        ">=" => Some(("$gte", "$lte")),
+        _ => None,
+    }
+}
+
+/// An argument to a comparison
+enum Operand {
+    Field(String),
+    Literal(Value),
+}
+
+fn field_filter(field: String, operator: &str, literal: Value) -> Value {
+    let mut condition = Map::new();
+    condition.insert(operator.into(), literal);
+    let mut filter = Map::new();
+    filter.insert(field, Value::Object(condition));
+    Value::Object(filter)
+}
+
+struct Translator {
+    engine: Engine,
+}
+impl Translator {
+    /// Get the dotted field path read by a `var`
+    fn field(&self, args: &[&Value], value: &Value) -> Result<String, Error> {
+        let key = match args {
+            [key] => key,
+            _ => return Err(invalid(value, "Defaults for var are not supported")),
+        };
+        let segments = var_path(key)
+            .filter(|segments| {
+                segments
+                    .iter()
+                    .all(|s| !s.is_empty() && !s.contains('.') && !s.starts_with('$'))
+            })
+            .ok_or_else(|| {
+                invalid(key, "Key cannot be used as a MongoDB field path")
+            })?;
+        Ok(segments.join("."))
+    }
+
+    fn operand(&self, value: &Value) -> Result<Operand, Error> {
+        match value {
+            Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {
+                Ok(Operand::Literal(value.clone()))
+            }
+            Value::Object(_) => match split(&self.engine, value) {
+                Some(("var", args)) => Ok(Operand::Field(self.field(&args, value)?)),
+                Some((key, _)) => Err(unsupported(key, TARGET)),
+                None => Err(invalid(value, "Object literals are not supported")),
+            },
+            Value::Array(_) => Err(invalid(
+                value,
+                "Arrays are only supported as the second argument to in",
+            )),
+        }
+    }
+
+    fn compare(&self, key: &str, left: &Value, right: &Value) -> Result<Value, Error> {
+        let (operator, mirrored) =
+            comparison_operator(key).ok_or_else(|| unsupported(key, TARGET))?;
+        let ordered = !matches!(key, "==" | "===" | "!=" | "!==");
+        let check_literal = |literal: Value| match literal {
+            Value::Null if ordered => Err(invalid(
+                &literal,
+                "null cannot be compared with <, <=, >, or >=",
+            )),
+            _ => Ok(literal),
+        };
+        match (self.operand(left)?, self.operand(right)?) {
+            (Operand::Field(field), Operand::Literal(literal)) => {
+                Ok(field_filter(field, operator, check_literal(literal)?))
+            }
+            (Operand::Literal(literal), Operand::Field(field)) => {
+                Ok(field_filter(field, mirrored, check_literal(literal)?))
+            }
+            (Operand::Field(a), Operand::Field(b)) => Ok(json!({
+                "$expr": {operator: [format!("${}", a), format!("${}", b)]}
+            })),
+            (Operand::Literal(_), Operand::Literal(_)) => Err(invalid(
+                right,
+                "Comparisons must have a var on at least one side",
+            )),
+        }
+    }
+
+    fn filters(&self, args: &[&Value]) -> Result<Vec<Value>, Error> {
+        args.iter().map(|arg| self.filter(arg)).collect()
+    }
+
+    /// Combine filters with `$and` or `$or`, unless there's only one
+    fn combine(&self, operator: &str, mut filters: Vec<Value>) -> Value {
+        match filters.len() {
+            1 => filters.remove(0),
+            _ => json!({ operator: filters }),
+        }
+    }
+
+    fn operation(&self, key: &str, args: &[&Value]) -> Result<Value, Error> {
+        match (key, args) {
+            ("and", [_, ..]) => Ok(self.combine("$and", self.filters(args)?)),
+            ("or", [_, ..]) => Ok(self.combine("$or", self.filters(args)?)),
+            ("!", [arg]) => Ok(json!({"$nor": [self.filter(arg)?]})),
+            ("!!", [arg]) => self.filter(arg),
+            ("in", [needle, haystack @ Value::Array(items)]) => {
+                let field = match self.operand(needle)? {
+                    Operand::Field(field) => field,
+                    Operand::Literal(_) => {
+                        return Err(invalid(
+                            needle,
+                            "The first argument to in must be a var",
+                        ))
+                    }
+                };
+                if items.iter().any(|item| item.is_array() || item.is_object()) {
+                    return Err(invalid(haystack, "Arrays must contain only literals"));
+                }
+                Ok(field_filter(field, "$in", (*haystack).clone()))
+            }
+            (_, [left, right]) => self.compare(key, left, right),
+            // Chained comparisons, as in `{"<": [0, {"var": "x"}, 10]}`
+            (_, [_, _, ..]) if comparison_operator(key).is_some() => {
+                let filters = args
+                    .windows(2)
+                    .map(|pair| self.compare(key, pair[0], pair[1]))
+                    .collect::<Result<Vec<Value>, Error>>()?;
+                Ok(self.combine("$and", filters))
+            }
+            _ => Err(unsupported(key, TARGET)),
+        }
+    }
+
+    fn filter(&self, value: &Value) -> Result<Value, Error> {
+        match value {
+            Value::Bool(true) => Ok(json!({})),
+            Value::Bool(false) => Ok(json!({"$expr": false})),
+            Value::Object(_) => match split(&self.engine, value) {
+                Some((key, args)) => self.operation(key, &args),
+                None => Err(invalid(value, "Object literals are not supported")),
+            },
+            _ => Err(invalid(
+                value,
+                "Only conditions can be translated to MongoDB filters",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_mongo {
+    use super::*;
+
+    fn filter(rule: Value) -> Value {
+        to_filter(&rule).unwrap()
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(
+            filter(json!({"==": [{"var": "status"}, "active"]})),
+            json!({"status": {"$eq": "active"}})
+        );
+        assert_eq!(
+            filter(json!({"<": [18, {"var": "age"}]})),
+            json!({"age": {"$gt": 18}})
+        );
+        assert_eq!(
+            filter(json!({"<=": [0, {"var": "score"}, 1.5]})),
+            json!({"$and": [{"score": {"$gte": 0}}, {"score": {"$lte": 1.5}}]})
+        );
+        assert_eq!(
+            filter(json!({"!==": [{"var": "deleted_at"}, null]})),
+            json!({"deleted_at": {"$ne": null}})
+        );
+        assert_eq!(
+            filter(json!({">": [{"var": "spent"}, {"var": "budget.total"}]})),
+            json!({"$expr": {"$gt": ["$spent", "$budget.total"]}})
+        );
+        assert_eq!(
+            filter(json!({"==": [{"var": "tags.0"}, "new"]})),
+            json!({"tags.0": {"$eq": "new"}})
+        );
+    }
+
+    #[test]
+    fn test_logic() {
+        assert_eq!(
+            filter(json!({"or": [
+                {"!": {"in": [{"var": "country"}, ["US", "CA"]]}},
+                {"!!": [{"===": [{"var": "vip"}, true]}]},
+            ]})),
+            json!({"$or": [
+                {"$nor": [{"country": {"$in": ["US", "CA"]}}]},
+                {"vip": {"$eq": true}},
+            ]})
+        );
+        assert_eq!(
+            filter(json!({"and": [{"==": [{"var": "a"}, 1]}]})),
+            json!({"a": {"$eq": 1}})
+        );
+        assert_eq!(filter(json!(true)), json!({}));
+        assert_eq!(filter(json!(false)), json!({"$expr": false}));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let err = |rule: Value| to_filter(&rule).unwrap_err();
+        assert!(matches!(
+            err(json!({"<": [{"+": [{"var": "a"}, 1]}, 5]})),
+            Error::InvalidOperation { key, .. } if key == "+"
+        ));
+        assert!(matches!(
+            err(json!({"==": [{"var": ["a", 0]}, 0]})),
+            Error::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            err(json!({"==": [{"var": "a\\.b"}, 0]})),
+            Error::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            err(json!({"==": [{"var": "$where"}, 0]})),
+            Error::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            err(json!({"<": [{"var": "a"}, null]})),
+            Error::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            err(json!({"==": [1, 1]})),
+            Error::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            err(json!({"in": ["a", {"var": "tags"}]})),
+            Error::InvalidOperation { .. }
+        ));
+        assert!(matches!(
+            err(json!({"var": "a"})),
+            Error::InvalidOperation { .. }
+        ));
+    }
+}
@@ -0,0 +1,308 @@
+//! Translating rules into Polars expressions
+//!
+//! The supported subset covers what's typically needed to filter or derive
+//! columns: `var` of top-level columns (with an optional default),
+//! comparisons, `and`, `or`, `!`, `!!`, `if`, `+`, `-`, `*`, `/`, `max`,
+//! `min`, and `in` with an array of literals.
+
+use polars::prelude::{col, lit, when, DataType, Expr, NamedFrom, Null, Series};
+use serde_json::Value;
+
+use super::{split, unsupported, var_path};
+use crate::engine::Engine;
+use crate::error::Error;
+
+const TARGET: &str = "Polars";
+
+/// Translate a rule into a Polars expression
+///
+/// The expression follows Polars' semantics rather than JsonLogic's where
+/// they differ. In particular, values aren't coerced between types, so
+/// e.g. `{"==": [{"var": "age"}, "18"]}` fails if `age` is numeric. The
+/// operands of `and`, `or`, `!`, `!!`, and the conditions of `if` are cast
+/// to booleans, which treats numbers as true when non-zero, and the results
+/// of `and` and `or` are booleans rather than one of the operands. Nulls
+/// propagate as in Polars, and division by zero gives infinity or NaN.
+///
+/// ```rust
+/// use jsonlogic_rs::transpile::polars::to_expr;
+/// use polars::prelude::*;
+/// use serde_json::json;
+///
+/// let df = df!("age" => [17, 30, 45], "country" => ["US", "US", "CA"]).unwrap();
+/// let rule = json!({"and": [
+///     {">=": [{"var": "age"}, 18]},
+///     {"in": [{"var": "country"}, ["US", "MX"]]},
+/// ]});
+/// let adults = df.lazy().filter(to_expr(&rule).unwrap()).collect().unwrap();
+/// assert_eq!(adults.height(), 1);
+/// ```
+pub fn to_expr(rule: &Value) -> Result<Expr, Error> {
+    let engine = Engine::new();
+    engine.parse(rule)?;
+    Translator { engine }.expr(rule)
+}
+
+/// Get the column name for the key of a `var`, if it refers to a single
+/// top-level column
+fn column_name(key: &Value) -> Option<String> {
+    match var_path(key)?.as_slice() {
+        [name] => Some(name.clone()),
+        _ => None,
+    }
+}
+
+fn invalid(value: &Value, reason: &str) -> Error {
+    Error::InvalidArgument {
+        value: value.clone(),
+        operation: TARGET.into(),
+        reason: reason.into(),
+    }
+}
+
+/// Build a series from an array of literals of a single type
+fn series(value: &Value, items: &[Value]) -> Result<Series, Error> {
+    let name = "literal".into();
+    if let Some(ints) = items.iter().map(Value::as_i64).collect::<Option<Vec<_>>>() {
+        return Ok(Series::new(name, ints));
+    }
+    if let Some(floats) = items.iter().map(Value::as_f64).collect::<Option<Vec<_>>>() {
+        return Ok(Series::new(name, floats));
+    }
+    if let Some(strings) = items.iter().map(Value::as_str).collect::<Option<Vec<_>>>() {
+        return Ok(Series::new(name, strings));
+    }
+    if let Some(bools) = items.iter().map(Value::as_bool).collect::<Option<Vec<_>>>() {
+        return Ok(Series::new(name, bools));
+    }
+    Err(invalid(
+        value,
+        "Arrays must contain only numbers, only strings, or only booleans",
+    ))
+}
+
+struct Translator {
+    engine: Engine,
+}
+impl Translator {
+    fn exprs(&self, args: &[&Value]) -> Result<Vec<Expr>, Error> {
+        args.iter().map(|arg| self.expr(arg)).collect()
+    }
+
+    fn boolean(&self, arg: &Value) -> Result<Expr, Error> {
+        Ok(self.expr(arg)?.cast(DataType::Boolean))
+    }
+
+    /// Chain comparisons of adjacent arguments, as for `{"<": [0, x, 10]}`
+    fn compare<F>(&self, args: &[&Value], func: F) -> Result<Expr, Error>
+    where
+        F: Fn(Expr, Expr) -> Expr,
+    {
+        let exprs = self.exprs(args)?;
+        Ok(exprs
+            .windows(2)
+            .map(|pair| func(pair[0].clone(), pair[1].clone()))
+            .reduce(Expr::and)
+            .unwrap_or_else(|| lit(true)))
+    }
+
+    fn fold<F>(&self, key: &str, args: &[&Value], func: F) -> Result<Expr, Error>
+    where
+        F: Fn(Expr, Expr) -> Expr,
+    {
+        self.exprs(args)?
+            .into_iter()
+            .reduce(func)
+            .ok_or_else(|| unsupported(key, TARGET))
+    }
+
+    /// Translate `if`, with each condition followed by its result and an
+    /// optional final result if no condition holds
+    fn conditional(&self, args: &[&Value]) -> Result<Expr, Error> {
+        match args {
+            [] => Ok(lit(Null {})),
+            [otherwise] => self.expr(otherwise),
+            [condition, then, rest @ ..] => Ok(when(self.boolean(condition)?)
+                .then(self.expr(then)?)
+                .otherwise(self.conditional(rest)?)),
+        }
+    }
+
+    fn operation(&self, key: &str, args: &[&Value]) -> Result<Expr, Error> {
+        match (key, args) {
+            ("var", [name]) | ("var", [name, _]) => {
+                let column = column_name(name).map(col).ok_or_else(|| {
+                    invalid(name, "Only top-level columns can be read with var")
+                })?;
+                match args.get(1) {
+                    Some(default) => Ok(column.fill_null(self.expr(default)?)),
+                    None => Ok(column),
+                }
+            }
+            ("==", [a, b]) | ("===", [a, b]) => Ok(self.expr(a)?.eq(self.expr(b)?)),
+            ("!=", [a, b]) | ("!==", [a, b]) => Ok(self.expr(a)?.neq(self.expr(b)?)),
+            ("<", _) => self.compare(args, Expr::lt),
+            ("<=", _) => self.compare(args, Expr::lt_eq),
+            (">", _) => self.compare(args, Expr::gt),
+            (">=", _) => self.compare(args, Expr::gt_eq),
+            ("and", _) | ("or", _) => {
+                let exprs =
+                    args.iter()
+                        .map(|arg| self.boolean(arg))
+                        .collect::<Result<Vec<Expr>, Error>>()?;
+                let func = match key {
+                    "and" => Expr::and,
+                    _ => Expr::or,
+                };
+                exprs
+                    .into_iter()
+                    .reduce(func)
+                    .ok_or_else(|| unsupported(key, TARGET))
+            }
+            ("!", [arg]) => Ok(self.boolean(arg)?.not()),
+            ("!!", [arg]) => self.boolean(arg),
+            ("if", _) | ("?:", _) => self.conditional(args),
+            ("+", [arg]) => Ok(self.expr(arg)?.cast(DataType::Float64)),
+            ("+", _) => self.fold(key, args, |a, b| a + b),
+            ("*", _) => self.fold(key, args, |a, b| a * b),
+            ("-", [arg]) => Ok(-self.expr(arg)?),
+            ("-", [a, b]) => Ok(self.expr(a)? - self.expr(b)?),
+            ("/", [a, b]) => Ok(self.expr(a)?.cast(DataType::Float64)
+                / self.expr(b)?.cast(DataType::Float64)),
+            ("max", _) => self.fold(key, args, |a, b| {
+                when(a.clone().gt_eq(b.clone())).then(a).otherwise(b)
+            }),
+            ("min", _) => self.fold(key, args, |a, b| {
+                when(a.clone().lt_eq(b.clone())).then(a).otherwise(b)
+            }),
+            ("in", [needle, haystack @ Value::Array(items)]) => {
+                Ok(self.expr(needle)?.is_in(lit(series(haystack, items)?)))
+            }
+            _ => Err(unsupported(key, TARGET)),
+        }
+    }
+
+    fn expr(&self, value: &Value) -> Result<Expr, Error> {
+        match value {
+            Value::Null => Ok(lit(Null {})),
+            Value::Bool(b) => Ok(lit(*b)),
+            Value::Number(n) => match n.as_i64() {
+                Some(i) => Ok(lit(i)),
+                None => Ok(lit(n.as_f64().unwrap_or(f64::NAN))),
+            },
+            Value::String(s) => Ok(lit(s.as_str())),
+            Value::Array(_) => Err(invalid(
+                value,
+                "Arrays are only supported as the second argument to in",
+            )),
+            Value::Object(_) => match split(&self.engine, value) {
+                Some((key, args)) => self.operation(key, &args),
+                None => Err(invalid(value, "Object literals are not supported")),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_polars {
+    use super::*;
+    use polars::prelude::{df, DataFrame, IntoLazy};
+    use serde_json::json;
+
+    fn data() -> DataFrame {
+        df!(
+            "age" => [17, 30, 45, 64],
+            "score" => [0.5, 2.5, 1.0, 3.0],
+            "country" => ["US", "CA", "US", "MX"],
+            "active" => [true, false, true, true],
+            "nickname" => [Some("al"), None, Some("jo"), None],
+        )
+        .unwrap()
+    }
+
+    /// The values of the `age` column in rows matching a rule
+    fn matching_ages(rule: Value) -> Vec<i32> {
+        let expr = to_expr(&rule).unwrap();
+        let df = data().lazy().filter(expr).collect().unwrap();
+        df.column("age")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(
+            matching_ages(json!({">=": [{"var": "age"}, 30]})),
+            vec![30, 45, 64]
+        );
+        assert_eq!(
+            matching_ages(json!({"<": [18, {"var": "age"}, 50]})),
+            vec![30, 45]
+        );
+        assert_eq!(
+            matching_ages(json!({"and": [
+                {"==": [{"var": "country"}, "US"]},
+                {"var": "active"},
+            ]})),
+            vec![17, 45]
+        );
+        assert_eq!(
+            matching_ages(json!({"or": [
+                {"!": {"var": "active"}},
+                {"in": [{"var": "country"}, ["MX"]]},
+            ]})),
+            vec![30, 64]
+        );
+        assert_eq!(
+            matching_ages(json!({"==": [{"var": ["nickname", "?"]}, "?"]})),
+            vec![30, 64]
+        );
+        assert_eq!(
+            matching_ages(json!({">": [
+                {"if": [{"<": [{"var": "age"}, 40]}, {"*": [{"var": "score"}, 2]}, 0]},
+                1.5
+            ]})),
+            vec![30]
+        );
+        assert_eq!(
+            matching_ages(json!({"?:": [{"<": [{"var": "age"}, 40]}, true, false]})),
+            matching_ages(json!({"<": [{"var": "age"}, 40]}))
+        );
+        assert_eq!(
+            matching_ages(json!({"===": [{"max": [{"var": "score"}, 2]}, 2]})),
+            vec![17, 45]
+        );
+        assert_eq!(
+            matching_ages(json!({"<": [{"/": [{"-": [{"var": "age"}, 15]}, 10]}, 1]})),
+            vec![17]
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let err = |rule: Value| to_expr(&rule).unwrap_err();
+        assert!(matches!(
+            err(json!({"cat": ["a", "b"]})),
+            Error::InvalidOperation { key, .. } if key == "cat"
+        ));
+        assert!(matches!(
+            err(json!({"var": "user.age"})),
+            Error::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            err(json!({"in": ["a", {"var": "country"}]})),
+            Error::InvalidOperation { .. }
+        ));
+        assert!(matches!(
+            err(json!({"in": [1, [1, "a"]]})),
+            Error::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            err(json!({"==": [1]})),
+            Error::WrongArgumentCount { .. }
+        ));
+    }
+}
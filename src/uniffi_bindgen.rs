@@ -0,0 +1,7 @@
+//! Generate foreign language bindings for the `uniffi` feature.
+//!
+//! See the "Kotlin, Swift, and Python via UniFFI" section of the README.
+
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
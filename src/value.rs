@@ -1,7 +1,12 @@
-use serde_json::{Number, Value};
+use std::borrow::Cow;
 
+use serde_json::{Map, Number, Value};
+
+use crate::context::Context;
+use crate::engine::Engine;
 use crate::error::Error;
-use crate::op::{DataOperation, LazyOperation, Operation};
+use crate::op::{CustomOperation, DataOperation, LazyOperation, Operation};
+use crate::telemetry;
 use crate::Parser;
 
 /// A Parsed JSON value
@@ -9,50 +14,140 @@ use crate::Parser;
 /// Parsed values are one of:
 ///   - An operation whose arguments are eagerly evaluated
 ///   - An operation whose arguments are lazily evaluated
+///   - A user-provided operation registered on the engine
+///   - An array containing rules, whose items are evaluated
+///   - An object whose values are evaluated, if the engine evaluates objects
 ///   - A raw value: a non-rule, raw JSON value
 #[derive(Debug)]
 pub enum Parsed<'a> {
     Operation(Operation<'a>),
-    LazyOperation(LazyOperation<'a>),
+    LazyOperation(LazyOperation),
     DataOperation(DataOperation<'a>),
+    CustomOperation(CustomOperation<'a>),
+    Array(Array<'a>),
+    Template(Template<'a>),
     Raw(Raw<'a>),
 }
 impl<'a> Parsed<'a> {
     /// Recursively parse a value
-    pub fn from_value(value: &'a Value) -> Result<Self, Error> {
-        Operation::from_value(value)?
-            .map(Self::Operation)
-            // .or(Operation::from_value(value)?.map(Self::Operation))
-            .or(LazyOperation::from_value(value)?.map(Self::LazyOperation))
-            .or(DataOperation::from_value(value)?.map(Self::DataOperation))
-            .or(Raw::from_value(value)?.map(Self::Raw))
+    ///
+    /// Each kind of value is tried in turn, stopping at the first that
+    /// matches, so that e.g. an operation's arguments aren't also parsed as
+    /// the members of a template.
+    pub fn from_value(value: &'a Value, engine: &Engine) -> Result<Self, Error> {
+        if let Some(op) = Operation::from_value(value, engine)? {
+            return Ok(Self::Operation(op));
+        }
+        if let Some(op) = LazyOperation::from_value(value, engine)? {
+            return Ok(Self::LazyOperation(op));
+        }
+        if let Some(op) = DataOperation::from_value(value, engine)? {
+            return Ok(Self::DataOperation(op));
+        }
+        if let Some(op) = CustomOperation::from_value(value, engine)? {
+            return Ok(Self::CustomOperation(op));
+        }
+        if let Some(array) = Array::from_value(value, engine)? {
+            return Ok(Self::Array(array));
+        }
+        if let Some(template) = Template::from_value(value, engine)? {
+            return Ok(Self::Template(template));
+        }
+        Raw::from_value(value, engine)?
+            .map(Self::Raw)
             .ok_or_else(|| {
                 Error::UnexpectedError(format!("Failed to parse Value {:?}", value))
             })
     }
 
-    pub fn from_values(values: Vec<&'a Value>) -> Result<Vec<Self>, Error> {
+    pub fn from_values(
+        values: Vec<&'a Value>,
+        engine: &Engine,
+    ) -> Result<Vec<Self>, Error> {
         values
             .into_iter()
-            .map(Self::from_value)
+            .map(|value| Self::from_value(value, engine))
             .collect::<Result<Vec<Self>, Error>>()
     }
 
-    pub fn evaluate(&self, data: &'a Value) -> Result<Evaluated, Error> {
+    /// The operator symbol, if this is an operation
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            Self::Operation(op) => Some(op.symbol()),
+            Self::LazyOperation(op) => Some(op.symbol()),
+            Self::DataOperation(op) => Some(op.symbol()),
+            Self::CustomOperation(op) => Some(op.symbol()),
+            Self::Array(_) | Self::Template(_) | Self::Raw(_) => None,
+        }
+    }
+
+    /// Copy any literals borrowed from the rule, so that the parsed rule
+    /// no longer borrows it
+    pub fn into_owned(self) -> ParsedOwned {
+        match self {
+            Self::Operation(op) => Parsed::Operation(op.into_owned()),
+            Self::LazyOperation(op) => Parsed::LazyOperation(op),
+            Self::DataOperation(op) => Parsed::DataOperation(op.into_owned()),
+            Self::CustomOperation(op) => Parsed::CustomOperation(op.into_owned()),
+            Self::Array(array) => Parsed::Array(array.into_owned()),
+            Self::Template(template) => Parsed::Template(template.into_owned()),
+            Self::Raw(raw) => Parsed::Raw(raw.into_owned()),
+        }
+    }
+
+    pub fn evaluate(
+        &self,
+        data: &Value,
+        ctx: &Context,
+    ) -> Result<Evaluated<'_>, Error> {
         match self {
-            Self::Operation(op) => op.evaluate(data),
-            Self::LazyOperation(op) => op.evaluate(data),
-            Self::DataOperation(op) => op.evaluate(data),
-            Self::Raw(val) => val.evaluate(data),
+            Self::Operation(op) => operate(op.symbol(), ctx, || op.evaluate(data, ctx)),
+            Self::LazyOperation(op) => {
+                operate(op.symbol(), ctx, || op.evaluate(data, ctx))
+            }
+            Self::DataOperation(op) => {
+                operate(op.symbol(), ctx, || op.evaluate(data, ctx))
+            }
+            Self::CustomOperation(op) => {
+                operate(op.symbol(), ctx, || op.evaluate(data, ctx))
+            }
+            Self::Array(array) => ctx.in_container(|| array.evaluate(data, ctx)),
+            Self::Template(template) => {
+                ctx.in_container(|| template.evaluate(data, ctx))
+            }
+            Self::Raw(val) => val.evaluate(data, ctx),
         }
     }
 }
+
+/// Evaluate an operator, counting it in the context and telemetry
+fn operate<'a>(
+    symbol: &str,
+    ctx: &Context,
+    evaluate: impl FnOnce() -> Result<Evaluated<'a>, Error>,
+) -> Result<Evaluated<'a>, Error> {
+    telemetry::record_operation(symbol);
+    ctx.enter();
+    let result = evaluate();
+    ctx.exit();
+    result
+}
+
+/// A parsed rule that owns all of its literals
+///
+/// Operators are always `'static`, so the only borrows in a parse tree are
+/// of raw values in the rule, which [`Parsed::into_owned()`] copies.
+pub type ParsedOwned = Parsed<'static>;
+
 impl From<Parsed<'_>> for Value {
     fn from(item: Parsed) -> Value {
         match item {
             Parsed::Operation(op) => Value::from(op),
             Parsed::LazyOperation(op) => Value::from(op),
             Parsed::DataOperation(op) => Value::from(op),
+            Parsed::CustomOperation(op) => Value::from(op),
+            Parsed::Array(array) => Value::from(array),
+            Parsed::Template(template) => Value::from(template),
             Parsed::Raw(raw) => Value::from(raw),
         }
     }
@@ -64,19 +159,129 @@ impl From<Parsed<'_>> for Value {
 /// be of any valid JSON type.
 #[derive(Debug)]
 pub struct Raw<'a> {
-    value: &'a Value,
+    value: Cow<'a, Value>,
+}
+impl Raw<'_> {
+    pub fn into_owned(self) -> Raw<'static> {
+        Raw {
+            value: Cow::Owned(self.value.into_owned()),
+        }
+    }
 }
 impl<'a> Parser<'a> for Raw<'a> {
-    fn from_value(value: &'a Value) -> Result<Option<Self>, Error> {
-        Ok(Some(Self { value }))
+    fn from_value(value: &'a Value, _engine: &Engine) -> Result<Option<Self>, Error> {
+        Ok(Some(Self {
+            value: Cow::Borrowed(value),
+        }))
     }
-    fn evaluate(&self, _data: &Value) -> Result<Evaluated, Error> {
-        Ok(Evaluated::Raw(self.value))
+    fn evaluate(&self, _data: &Value, _ctx: &Context) -> Result<Evaluated<'_>, Error> {
+        Ok(Evaluated::Raw(&self.value))
     }
 }
 impl From<Raw<'_>> for Value {
     fn from(raw: Raw) -> Self {
-        raw.value.clone()
+        raw.value.into_owned()
+    }
+}
+
+/// An array whose items are evaluated, producing an array of the results
+///
+/// As in the reference implementation, e.g. `[{"var": "a"}, {"var": "b"}]`
+/// evaluates to the values of `a` and `b`. Arrays that don't contain any
+/// rules are used as they are.
+#[derive(Debug)]
+pub struct Array<'a> {
+    items: Vec<Parsed<'a>>,
+}
+impl Array<'_> {
+    pub fn into_owned(self) -> Array<'static> {
+        Array {
+            items: self.items.into_iter().map(Parsed::into_owned).collect(),
+        }
+    }
+}
+impl<'a> Parser<'a> for Array<'a> {
+    fn from_value(value: &'a Value, engine: &Engine) -> Result<Option<Self>, Error> {
+        let items = match value {
+            Value::Array(items) => Parsed::from_values(items.iter().collect(), engine)?,
+            _ => return Ok(None),
+        };
+        match items.iter().all(|parsed| matches!(parsed, Parsed::Raw(_))) {
+            true => Ok(None),
+            false => Ok(Some(Self { items })),
+        }
+    }
+
+    fn evaluate(&self, data: &Value, ctx: &Context) -> Result<Evaluated<'_>, Error> {
+        self.items
+            .iter()
+            .map(|parsed| Ok(parsed.evaluate(data, ctx)?.into()))
+            .collect::<Result<Vec<Value>, Error>>()
+            .map(|items| Evaluated::New(Value::Array(items)))
+    }
+}
+impl From<Array<'_>> for Value {
+    fn from(array: Array) -> Self {
+        Value::Array(array.items.into_iter().map(Value::from).collect())
+    }
+}
+
+/// An object whose values are evaluated, producing an object of the results
+///
+/// Objects are only parsed as templates with `Engine::evaluate_objects()`,
+/// and only if they contain an operation, so that objects of literals are
+/// used as they are.
+#[derive(Debug)]
+pub struct Template<'a> {
+    members: Vec<(String, Parsed<'a>)>,
+}
+impl Template<'_> {
+    pub fn into_owned(self) -> Template<'static> {
+        Template {
+            members: self
+                .members
+                .into_iter()
+                .map(|(key, parsed)| (key, parsed.into_owned()))
+                .collect(),
+        }
+    }
+}
+impl<'a> Parser<'a> for Template<'a> {
+    fn from_value(value: &'a Value, engine: &Engine) -> Result<Option<Self>, Error> {
+        let obj = match value {
+            Value::Object(obj) if engine.evaluate_objects => obj,
+            _ => return Ok(None),
+        };
+        let members = obj
+            .iter()
+            .map(|(key, val)| Ok((key.clone(), Parsed::from_value(val, engine)?)))
+            .collect::<Result<Vec<(String, Parsed)>, Error>>()?;
+        match members
+            .iter()
+            .all(|(_, parsed)| matches!(parsed, Parsed::Raw(_)))
+        {
+            true => Ok(None),
+            false => Ok(Some(Self { members })),
+        }
+    }
+
+    fn evaluate(&self, data: &Value, ctx: &Context) -> Result<Evaluated<'_>, Error> {
+        self.members
+            .iter()
+            .map(|(key, parsed)| Ok((key.clone(), parsed.evaluate(data, ctx)?.into())))
+            .collect::<Result<Map<String, Value>, Error>>()
+            .map(|obj| Evaluated::New(Value::Object(obj)))
+    }
+}
+impl From<Template<'_>> for Value {
+    fn from(template: Template) -> Self {
+        Value::Object(
+            template
+                .members
+                .into_iter()
+                .map(|(key, parsed)| (key, Value::from(parsed)))
+                .collect(),
+        )
     }
 }
 
@@ -101,16 +306,22 @@ impl From<Evaluated<'_>> for Value {
 }
 
 pub fn to_number_value(number: f64) -> Result<Value, Error> {
-    if number.fract() == 0.0 {
+    // Whole numbers are represented as integers where possible. Outside of
+    // the i64 range, casting would saturate, so fall back to a float.
+    if number.fract() == 0.0 && number >= i64::MIN as f64 && number < i64::MAX as f64 {
         Ok(Value::Number(Number::from(number as i64)))
     } else {
-        Number::from_f64(number)
-            .ok_or_else(|| {
-                Error::UnexpectedError(format!(
-                    "Could not make JSON number from result {:?}",
-                    number
-                ))
-            })
-            .map(Value::Number)
+        to_float_value(number)
     }
 }
+
+pub fn to_float_value(number: f64) -> Result<Value, Error> {
+    Number::from_f64(number)
+        .ok_or_else(|| {
+            Error::UnexpectedError(format!(
+                "Could not make JSON number from result {:?}",
+                number
+            ))
+        })
+        .map(Value::Number)
+}
@@ -0,0 +1,49 @@
+//! A minimal command interface for running rules in a WASI sandbox
+//!
+//! Reads a JSON object of the form `{"logic": <rule>, "data": <data>}` from
+//! stdin and writes `{"result": <value>}` to stdout. On failure, writes
+//! `{"error": {"code": ..., "message": ..., "details": ...}}` to stdout, in
+//! the format of `Error::to_json()`, and exits with a non-zero status.
+//!
+//! This has no dependencies beyond the library itself, so it builds for
+//! `wasm32-wasip1` and can be run by any WASI runtime, e.g. from a host
+//! that embeds `wasmtime` (see `examples/wasmtime-host`).
+
+use std::io;
+use std::io::Read;
+use std::process;
+
+use serde_json::{json, Value};
+
+/// An error with the input itself, rather than with the rule
+fn input_error(message: String) -> Value {
+    json!({"code": "invalid_input", "message": message, "details": {}})
+}
+
+fn run() -> Result<Value, Value> {
+    let mut input = String::new();
+    io::stdin()
+        .lock()
+        .read_to_string(&mut input)
+        .map_err(|err| input_error(format!("Could not read stdin: {}", err)))?;
+
+    let request: Value = serde_json::from_str(&input).map_err(|err| {
+        input_error(format!("Could not parse input as JSON: {}", err))
+    })?;
+    let logic = request
+        .get("logic")
+        .ok_or_else(|| input_error("Input is missing the \"logic\" key".into()))?;
+    let data = request.get("data").unwrap_or(&Value::Null);
+
+    jsonlogic_rs::apply(logic, data).map_err(|err| err.to_json())
+}
+
+fn main() {
+    match run() {
+        Ok(result) => println!("{}", json!({ "result": result })),
+        Err(err) => {
+            println!("{}", json!({ "error": err }));
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Check that embedders can name the types they need from the crate root
+
+use jsonlogic_rs::{
+    CompiledRule, DecimalSeparator, DivisionByZero, Engine, Error, LintKind,
+    LintWarning, NumParams, NumberOutput, Stats,
+};
+use serde_json::{json, Value};
+
+#[test]
+fn match_typed_errors() {
+    let engine: Engine = Engine::new()
+        .division_by_zero(DivisionByZero::Null)
+        .decimal_separator(DecimalSeparator::Point)
+        .number_output(NumberOutput::Integer);
+    let err = engine.apply(&json!({"==": [1]}), &json!(null)).unwrap_err();
+    match err {
+        Error::WrongArgumentCount {
+            operator,
+            expected: NumParams::Exactly(2),
+            actual: 1,
+            ..
+        } => assert_eq!(operator, "=="),
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn name_result_types() {
+    let engine = Engine::new();
+    let rule = json!({"<": [1, 2]});
+    let compiled: CompiledRule = engine.compile(&rule).unwrap();
+    assert_eq!(compiled.apply(&json!(null)).unwrap(), json!(true));
+
+    let (result, stats): (Value, Stats) =
+        engine.apply_with_stats(&rule, &json!(null)).unwrap();
+    assert_eq!(result, json!(true));
+    assert_eq!(stats.operations, 1);
+
+    let warnings: Vec<LintWarning> = engine.lint(&rule);
+    assert_eq!(warnings[0].kind, LintKind::ConstantComparison);
+}
@@ -0,0 +1,130 @@
+//! Tests for column-wise evaluation of Arrow record batches
+//!
+//! These tests will only run if the "arrow" feature is active.
+
+#[cfg(feature = "arrow")]
+use std::sync::Arc;
+
+#[cfg(feature = "arrow")]
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, Float64Array, Int32Array, RecordBatch, StringArray,
+};
+#[cfg(feature = "arrow")]
+use jsonlogic_rs::Engine;
+#[cfg(feature = "arrow")]
+use serde_json::{json, Value};
+
+#[cfg(feature = "arrow")]
+fn batch() -> RecordBatch {
+    RecordBatch::try_from_iter(vec![
+        (
+            "age",
+            Arc::new(Int32Array::from(vec![17, 30, 45, 64])) as ArrayRef,
+        ),
+        (
+            "score",
+            Arc::new(Float64Array::from(vec![0.5, 2.5, 1.0, 3.0])) as ArrayRef,
+        ),
+        (
+            "country",
+            Arc::new(StringArray::from(vec!["US", "CA", "US", "MX"])) as ArrayRef,
+        ),
+        (
+            "active",
+            Arc::new(BooleanArray::from(vec![true, false, true, true])) as ArrayRef,
+        ),
+        (
+            "nickname",
+            Arc::new(StringArray::from(vec![Some("al"), None, Some("jo"), None]))
+                as ArrayRef,
+        ),
+    ])
+    .unwrap()
+}
+
+/// The same batch as JSON rows, for comparison with `Engine::apply()`
+#[cfg(feature = "arrow")]
+fn rows() -> Vec<Value> {
+    vec![
+        json!({"age": 17, "score": 0.5, "country": "US", "active": true, "nickname": "al"}),
+        json!({"age": 30, "score": 2.5, "country": "CA", "active": false, "nickname": null}),
+        json!({"age": 45, "score": 1, "country": "US", "active": true, "nickname": "jo"}),
+        json!({"age": 64, "score": 3, "country": "MX", "active": true, "nickname": null}),
+    ]
+}
+
+#[cfg(feature = "arrow")]
+fn mask(rule: &Value) -> Vec<bool> {
+    let result = Engine::new().evaluate_batch(rule, &batch()).unwrap();
+    assert_eq!(result.null_count(), 0);
+    result.values().iter().collect()
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_column_wise() {
+    assert_eq!(
+        mask(&json!({">=": [{"var": "age"}, 30]})),
+        vec![false, true, true, true]
+    );
+    assert_eq!(
+        mask(&json!({"<": [{"var": "age"}, {"var": "score"}]})),
+        vec![false, false, false, false]
+    );
+    assert_eq!(
+        mask(&json!({"and": [
+            {"==": [{"var": "country"}, "US"]},
+            {"!": {"<": [18, {"var": "age"}]}},
+        ]})),
+        vec![true, false, false, false]
+    );
+    assert_eq!(
+        mask(&json!({"or": [{"var": "active"}, {"!==": ["CA", {"var": "country"}]}]})),
+        vec![true, false, true, true]
+    );
+    assert_eq!(mask(&json!(true)), vec![true; 4]);
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_matches_row_wise() {
+    let rules = vec![
+        json!({">=": [{"var": "age"}, 30]}),
+        json!({"==": [{"var": "score"}, 1]}),
+        json!({"!=": [{"var": "country"}, "US"]}),
+        json!({"==": [{"var": "active"}, false]}),
+        json!({"!!": [{"var": "active"}]}),
+        // Falls back to row-wise evaluation
+        json!({">": [{"var": "age"}, "18"]}),
+        json!({"<": [{"var": "country"}, "N"]}),
+        json!({"in": [{"var": "country"}, ["CA", "MX"]]}),
+        json!({"==": [{"var": "nickname"}, null]}),
+        json!({"!": {"var": "nickname"}}),
+        json!({"and": [{"var": "age"}, {"cat": [{"var": "country"}, "!"]}]}),
+        json!({"or": [{">": [{"var": "score"}, 2]}, {"in": ["o", {"var": "nickname"}]}]}),
+        json!({"if": [{"var": "active"}, {"var": "age"}, 0]}),
+        json!({"var": "missing_column"}),
+    ];
+    let engine = Engine::new();
+    for rule in rules {
+        let expected: Vec<bool> = rows()
+            .iter()
+            .map(|row| match engine.apply(&rule, row).unwrap() {
+                Value::Bool(b) => b,
+                other => {
+                    engine.apply(&json!({"!!": [other]}), &json!(null)).unwrap()
+                        == json!(true)
+                }
+            })
+            .collect();
+        assert_eq!(mask(&rule), expected, "{}", rule);
+    }
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_invalid_rule() {
+    let rule = json!({"==": [{"var": "age"}]});
+    assert!(Engine::new().apply(&rule, &json!(null)).is_err());
+    assert!(Engine::new().evaluate_batch(&rule, &batch()).is_err());
+}
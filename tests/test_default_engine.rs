@@ -0,0 +1,37 @@
+//! Check that the free functions use the default engine once it's set
+//!
+//! The default engine is global to the process, so this is kept apart from
+//! the other tests, which expect default options.
+
+use jsonlogic_rs::{default_engine, set_default_engine, Engine, Error};
+use serde_json::{json, Value};
+
+#[test]
+fn set_at_startup() {
+    let engine = Engine::new().add_operator("double", |args: &[&Value]| {
+        Ok(json!(args[0].as_f64().unwrap_or_default() * 2.0))
+    });
+    set_default_engine(engine).unwrap();
+    assert!(default_engine().has_operator("double"));
+
+    let rule = json!({"double": {"var": "a"}});
+    assert_eq!(
+        jsonlogic_rs::apply(&rule, &json!({"a": 2})).unwrap(),
+        json!(4.0)
+    );
+    assert!(jsonlogic_rs::lint(&rule).is_empty());
+
+    // Evaluations elsewhere in the process can't change it
+    assert!(set_default_engine(Engine::new()).is_err());
+    let threads: Vec<_> = (0..4)
+        .map(|n| {
+            std::thread::spawn(move || {
+                jsonlogic_rs::apply(&json!({"double": n}), &json!(null))
+            })
+        })
+        .collect();
+    for (n, thread) in threads.into_iter().enumerate() {
+        let result: Result<Value, Error> = thread.join().unwrap();
+        assert_eq!(result.unwrap(), json!(n as f64 * 2.0));
+    }
+}
@@ -0,0 +1,75 @@
+//! Tests for the metrics integration
+//!
+//! These tests will only run if the "metrics" feature is active.
+
+#[cfg(feature = "metrics")]
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+#[cfg(feature = "metrics")]
+use metrics_util::{CompositeKey, MetricKind};
+#[cfg(feature = "metrics")]
+use serde_json::json;
+
+/// Get the value of a counter with the given name and label
+#[cfg(feature = "metrics")]
+fn counter<U, D>(
+    snapshot: &[(CompositeKey, U, D, DebugValue)],
+    name: &str,
+    label: (&str, &str),
+) -> u64 {
+    snapshot
+        .iter()
+        .find_map(|(key, _, _, value)| {
+            let key_matches = key.kind() == MetricKind::Counter
+                && key.key().name() == name
+                && key
+                    .key()
+                    .labels()
+                    .any(|l| l.key() == label.0 && l.value() == label.1);
+            match (key_matches, value) {
+                (true, DebugValue::Counter(count)) => Some(*count),
+                _ => None,
+            }
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn test_counters() {
+    let recorder = DebuggingRecorder::new();
+    metrics::with_local_recorder(&recorder, || {
+        jsonlogic_rs::apply(
+            &json!({"+": [1, {"var": "a"}, {"var": "b"}]}),
+            &json!({"a": 1, "b": 2}),
+        )
+        .unwrap();
+        jsonlogic_rs::apply(&json!({"not_in": [1, [2]]}), &json!(null)).unwrap();
+        jsonlogic_rs::apply(&json!({"/": [1, 0]}), &json!(null)).unwrap_err();
+    });
+    let snapshot = recorder.snapshotter().snapshot().into_vec();
+
+    assert_eq!(
+        counter(&snapshot, "jsonlogic_operations_total", ("operator", "+")),
+        1
+    );
+    assert_eq!(
+        counter(&snapshot, "jsonlogic_operations_total", ("operator", "var")),
+        2
+    );
+    assert_eq!(
+        counter(
+            &snapshot,
+            "jsonlogic_operations_total",
+            ("operator", "not_in")
+        ),
+        1
+    );
+    assert_eq!(
+        counter(
+            &snapshot,
+            "jsonlogic_errors_total",
+            ("code", "invalid_argument")
+        ),
+        1
+    );
+}
@@ -1,10 +1,15 @@
 """Test the python distribution."""
 
+import io
 import json
+import sys
+import tempfile
 import typing as t
+from contextlib import redirect_stdout
 from pathlib import Path
 
 import jsonlogic_rs
+from jsonlogic_rs.cli import main as cli_main
 
 
 TEST_FILE = Path(__file__).parent / "data/tests.json"
@@ -34,5 +39,93 @@ def run_tests() -> None:
         assert result == case.exp, f"Failed test case {idx}: {case}"
 
 
+def run_file_tests() -> None:
+    """Assert that rules and data can be read from paths and files."""
+    logic = {"===": [{"var": "a"}, 7]}
+    with tempfile.TemporaryDirectory() as tmp:
+        logic_path = Path(tmp) / "logic.json"
+        data_path = Path(tmp) / "data.json"
+        logic_path.write_text(json.dumps(logic))
+        data_path.write_text(json.dumps({"a": 7}))
+
+        assert jsonlogic_rs.apply_file(logic_path, data_path) is True
+        assert jsonlogic_rs.apply_file(str(logic_path), str(data_path)) is True
+        assert jsonlogic_rs.apply_file(logic_path) is False
+        with open(logic_path) as logic_f, open(data_path, "rb") as data_f:
+            assert jsonlogic_rs.apply_file(logic_f, data_f) is True
+    assert jsonlogic_rs.apply_file(io.StringIO('{"+": [1, 2]}')) == 3
+
+
+def run_cli_tests() -> None:
+    """Assert that the console script behaves like the Rust CLI."""
+    out = io.StringIO()
+    with redirect_stdout(out):
+        code = cli_main(['{"===": [{"var": "a"}, "foo"]}', '{"a": "foo"}'])
+    assert code == 0
+    assert json.loads(out.getvalue()) is True
+
+    stdin = sys.stdin
+    sys.stdin = io.StringIO('{"a": 2}')
+    out = io.StringIO()
+    try:
+        with redirect_stdout(out):
+            code = cli_main(['{"+": [1, {"var": "a"}]}'])
+    finally:
+        sys.stdin = stdin
+    assert code == 0
+    assert json.loads(out.getvalue()) == 3
+
+    assert cli_main(["not json", "null"]) == 1
+    assert cli_main(['{"==": [1]}', "null"]) == 1
+
+
+def run_error_tests() -> None:
+    """Assert that errors carry a machine-readable code and details."""
+    try:
+        jsonlogic_rs.apply({"==": [1]})
+    except jsonlogic_rs.JsonLogicError as exc:
+        assert exc.code == "wrong_argument_count"
+        assert exc.details["operator"] == "=="
+        assert exc.to_json()["message"] == str(exc)
+    else:
+        raise AssertionError("Expected a JsonLogicError")
+
+
+def run_engine_tests() -> None:
+    """Assert that engines evaluate rules with custom operations."""
+    engine = jsonlogic_rs.Engine()
+    engine.add_operation("between", lambda x, lo, hi: lo <= x <= hi)
+    engine.add_operation("join", lambda *parts: "-".join(map(str, parts)))
+    assert engine.apply({"between": [{"var": "a"}, 1, 10]}, {"a": 5}) is True
+    assert engine.apply({"between": [{"var": "a"}, 1, 10]}, {"a": 50}) is False
+    assert engine.apply({"join": ["a", {"+": [1, 1]}]}) == "a-2"
+
+    # Built-in operations still work, and take precedence
+    engine.add_operation("+", lambda *args: "overridden")
+    assert engine.apply({"+": [1, 2]}) == 3
+    for case in load_tests()[:20]:
+        assert engine.apply(case.logic, case.data) == case.exp
+
+    def fail(*args):
+        raise RuntimeError("nope")
+
+    engine.add_operation("fail", fail)
+    try:
+        engine.apply({"fail": []})
+    except jsonlogic_rs.JsonLogicError as exc:
+        assert exc.code == "plugin_error"
+        assert exc.details["operator"] == "fail"
+        assert "RuntimeError: nope" in exc.details["reason"]
+    else:
+        raise AssertionError("Expected a JsonLogicError")
+
+    # Unregistered operations are just data, as with apply()
+    assert jsonlogic_rs.Engine().apply({"between": [1]}) == {"between": [1]}
+
+
 if __name__ == "__main__":
     run_tests()
+    run_file_tests()
+    run_cli_tests()
+    run_error_tests()
+    run_engine_tests()
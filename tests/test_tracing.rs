@@ -0,0 +1,84 @@
+//! Tests for the tracing instrumentation
+//!
+//! These tests will only run if the "tracing" feature is active.
+
+#[cfg(feature = "tracing")]
+use std::collections::HashMap;
+#[cfg(feature = "tracing")]
+use std::fmt;
+#[cfg(feature = "tracing")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "tracing")]
+use serde_json::json;
+#[cfg(feature = "tracing")]
+use tracing::field::{Field, Visit};
+#[cfg(feature = "tracing")]
+use tracing::span::{Attributes, Id, Record};
+#[cfg(feature = "tracing")]
+use tracing::Subscriber;
+#[cfg(feature = "tracing")]
+use tracing_subscriber::layer::{Context, SubscriberExt};
+#[cfg(feature = "tracing")]
+use tracing_subscriber::registry::LookupSpan;
+#[cfg(feature = "tracing")]
+use tracing_subscriber::Layer;
+
+/// Span fields recorded so far, by span name
+#[cfg(feature = "tracing")]
+type Spans = Arc<Mutex<HashMap<String, HashMap<String, String>>>>;
+
+/// A layer collecting the fields recorded on each span
+#[cfg(feature = "tracing")]
+struct Collect(Spans);
+
+#[cfg(feature = "tracing")]
+struct Fields<'a>(&'a mut HashMap<String, String>);
+
+#[cfg(feature = "tracing")]
+impl Visit for Fields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().into(), format!("{:?}", value));
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<S> Layer<S> for Collect
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        let mut spans = self.0.lock().unwrap();
+        let fields = spans.entry(attrs.metadata().name().into()).or_default();
+        attrs.record(&mut Fields(fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let name = ctx.span(id).unwrap().name();
+        let mut spans = self.0.lock().unwrap();
+        values.record(&mut Fields(spans.entry(name.into()).or_default()));
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_spans() {
+    let spans = Spans::default();
+    let subscriber = tracing_subscriber::registry().with(Collect(Arc::clone(&spans)));
+    tracing::subscriber::with_default(subscriber, || {
+        jsonlogic_rs::apply(&json!({"+": [1, {"var": "a"}]}), &json!({"a": 1}))
+            .unwrap();
+    });
+
+    let spans = spans.lock().unwrap();
+    let apply = &spans["jsonlogic.apply"];
+    assert_eq!(apply["rule_hash"].len(), 16);
+    assert!(spans.contains_key("jsonlogic.parse"));
+    let evaluate = &spans["jsonlogic.evaluate"];
+    assert_eq!(evaluate["operations"], "2");
+    assert_eq!(evaluate["max_depth"], "2");
+}
@@ -0,0 +1,52 @@
+//! Tests for the WASI command interface
+//!
+//! These tests will only run if the "wasi" feature is active. They run the
+//! command natively, since its behavior does not depend on the target.
+
+#[cfg(feature = "wasi")]
+use std::io::Write;
+#[cfg(feature = "wasi")]
+use std::process::{Command, Stdio};
+
+#[cfg(feature = "wasi")]
+use serde_json::{json, Value};
+
+#[cfg(feature = "wasi")]
+fn run(input: &str) -> (bool, Value) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_jsonlogic-wasi"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Could not spawn jsonlogic-wasi");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = serde_json::from_slice(&output.stdout).unwrap();
+    (output.status.success(), stdout)
+}
+
+#[cfg(feature = "wasi")]
+#[test]
+fn test_wasi_command() {
+    assert_eq!(
+        run(r#"{"logic": {"+": [1, {"var": "a"}]}, "data": {"a": 2}}"#),
+        (true, json!({"result": 3}))
+    );
+    assert_eq!(
+        run(r#"{"logic": {"var": "a"}}"#),
+        (true, json!({"result": null}))
+    );
+
+    let (success, output) = run(r#"{"logic": {"==": [1]}}"#);
+    assert!(!success);
+    assert_eq!(output["error"]["code"], "wrong_argument_count");
+    assert!(output["error"]["message"].is_string());
+
+    let (success, output) = run("not json");
+    assert!(!success);
+    assert_eq!(output["error"]["code"], "invalid_input");
+}
@@ -47,8 +47,46 @@ const run_tests = (cases) => {
     }
 };
 
+// Compiled rule handles should give the same results as apply()
+const run_handle_tests = (cases) => {
+    const no_comments = cases.filter(i => typeof i !== "string");
+    for (c of no_comments) {
+        const handle = jsonlogic.compile(c[0]);
+        let res;
+        try {
+            res = handle.apply(c[1]);
+        }
+        catch (e) {
+            console.log("Handle test errored!");
+            console.log(`  Error: ${e}}`);
+            print_case(c);
+            process.exit(2);
+        }
+        finally {
+            handle.free();
+        }
+
+        if (JSON.stringify(res) !== JSON.stringify(c[2])) {
+            console.log("Failed handle test!")
+            print_case(c, res)
+            process.exit(1);
+        }
+    }
+
+    // A handle can be applied repeatedly
+    const handle = jsonlogic.compile({"<": [{"var": "a"}, 10]});
+    const results = [1, 20, 5].map(a => handle.apply({a}));
+    handle.free();
+    if (JSON.stringify(results) !== JSON.stringify([true, false, true])) {
+        console.log(`Failed repeated handle test: ${JSON.stringify(results)}`);
+        process.exit(1);
+    }
+};
+
 const main = () => {
-    run_tests(load_test_json());
+    const cases = load_test_json();
+    run_tests(cases);
+    run_handle_tests(cases);
 };
 
 main();